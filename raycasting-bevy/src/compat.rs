@@ -0,0 +1,164 @@
+use bevy::asset::RenderAssetUsages;
+use bevy::ecs::observer::IntoObserver;
+use bevy::ecs::system::ScheduleSystem;
+use bevy::prelude::*;
+
+pub use bevy::ecs::lifecycle::Remove;
+pub use bevy::ecs::message::{Message, MessageReader, MessageWriter};
+pub use bevy::ecs::observer::On;
+pub use bevy::mesh::{Indices, MeshVertexAttribute, PrimitiveTopology, VertexFormat};
+
+// The parts of Bevy whose names or signatures changed between releases: setting up an app, messages, meshes
+// and input. The plugins and the demo use them only through this module, so following a new release only changes
+// the bodies and re-exports below. Targets Bevy 0.19.
+//
+// Components which changed their shape, like sprites, cameras and UI nodes (required components since 0.15),
+// as well as asset loaders and diagnostics, are used directly, as an adapter for them would have to wrap most of Bevy.
+
+/// Setting up an app. Since 0.11, plugins are added with `add_plugins` and systems with `add_systems` and the
+/// schedule (`Startup` or `Update`) they run in.
+pub trait AppCompat {
+    fn plugin(&mut self, plugin: impl Plugin) -> &mut Self;
+
+    /// Add systems which run once, before the first frame.
+    fn startup_systems<M>(&mut self, systems: impl IntoScheduleConfigs<ScheduleSystem, M>) -> &mut Self;
+
+    /// Add systems which run every frame.
+    fn update_systems<M>(&mut self, systems: impl IntoScheduleConfigs<ScheduleSystem, M>) -> &mut Self;
+
+    /// Register a message, so systems can read and write it. Since 0.17, buffered events are messages
+    /// and registered with `add_message`.
+    fn message<M: Message>(&mut self) -> &mut Self;
+
+    /// Add a system which runs whenever its event is triggered, like when a component is removed. Since 0.17,
+    /// it takes `On<E>` instead of `Trigger<E>`, and the component events are called `Add`, `Remove` and so on.
+    fn observer<M>(&mut self, observer: impl IntoObserver<M>) -> &mut Self;
+}
+
+impl AppCompat for App {
+    fn plugin(&mut self, plugin: impl Plugin) -> &mut Self {
+        self.add_plugins(plugin)
+    }
+
+    fn startup_systems<M>(&mut self, systems: impl IntoScheduleConfigs<ScheduleSystem, M>) -> &mut Self {
+        self.add_systems(Startup, systems)
+    }
+
+    fn update_systems<M>(&mut self, systems: impl IntoScheduleConfigs<ScheduleSystem, M>) -> &mut Self {
+        self.add_systems(Update, systems)
+    }
+
+    fn message<M: Message>(&mut self) -> &mut Self {
+        self.add_message::<M>()
+    }
+
+    fn observer<M>(&mut self, observer: impl IntoObserver<M>) -> &mut Self {
+        self.add_observer(observer)
+    }
+}
+
+/// Reading messages. Since 0.12, the reader returns its unread messages with `read` instead of `iter`.
+pub trait ReadMessages<M: Message> {
+    fn messages(&mut self) -> impl Iterator<Item=&M>;
+}
+
+impl<M: Message> ReadMessages<M> for MessageReader<'_, '_, M> {
+    fn messages(&mut self) -> impl Iterator<Item=&M> {
+        self.read()
+    }
+}
+
+/// Reading the entities which lost a component. Since 0.12, `RemovedComponents` returns them with `read` instead of `iter`.
+pub trait ReadRemoved {
+    fn removed(&mut self) -> impl Iterator<Item=Entity>;
+}
+
+impl<T: Component> ReadRemoved for RemovedComponents<'_, '_, T> {
+    fn removed(&mut self) -> impl Iterator<Item=Entity> {
+        self.read()
+    }
+}
+
+/// Writing messages. Since 0.17, the writer sends them with `write` instead of `send`.
+pub trait WriteMessages<M: Message> {
+    fn emit(&mut self, message: M);
+
+    fn emit_all(&mut self, messages: impl IntoIterator<Item=M>);
+}
+
+impl<M: Message> WriteMessages<M> for MessageWriter<'_, M> {
+    fn emit(&mut self, message: M) {
+        self.write(message);
+    }
+
+    fn emit_all(&mut self, messages: impl IntoIterator<Item=M>) {
+        self.write_batch(messages);
+    }
+}
+
+/// Create an empty mesh. Since 0.13, this also takes the render asset usages, the mesh is kept in both worlds,
+/// as the plugins update their meshes.
+pub fn new_mesh(topology: PrimitiveTopology) -> Mesh {
+    Mesh::new(topology, RenderAssetUsages::default())
+}
+
+/// Set the indices of a mesh. Since 0.13, this is `insert_indices`.
+pub fn set_mesh_indices(
+    mesh: &mut Mesh,
+    indices: Indices,
+) {
+    mesh.insert_indices(indices);
+}
+
+/// The component holding the mesh of an entity spawned with [colored_mesh]
+pub type MeshComponent = Mesh2d;
+
+/// The component holding the material of an entity spawned with [colored_mesh]
+pub type MaterialComponent = MeshMaterial2d<ColorMaterial>;
+
+/// The components drawing a 2D mesh with a color material. Since 0.15, these are the `Mesh2d` and `MeshMaterial2d`
+/// components instead of a `MaterialMesh2dBundle`.
+pub fn colored_mesh(
+    mesh: Handle<Mesh>,
+    material: Handle<ColorMaterial>,
+) -> impl Bundle {
+    (Mesh2d(mesh), MeshMaterial2d(material))
+}
+
+/// Return the handle of the mesh in the component.
+pub fn mesh_handle(mesh: &MeshComponent) -> &Handle<Mesh> {
+    &mesh.0
+}
+
+/// Return the handle of the material in the component.
+pub fn material_handle(material: &MaterialComponent) -> &Handle<ColorMaterial> {
+    &material.0
+}
+
+/// The pressed keys. Since 0.13, this is `ButtonInput` instead of `Input`.
+pub type Keys = ButtonInput<KeyCode>;
+
+/// The pressed mouse buttons. Since 0.13, this is `ButtonInput` instead of `Input`.
+pub type MouseButtons = ButtonInput<MouseButton>;
+
+/// The keys the plugins and the demo react to. Since 0.13, the key codes follow the physical layout of the
+/// keyboard, with names like `KeyC` or `ArrowLeft`.
+pub mod keys {
+    use bevy::prelude::KeyCode;
+
+    pub const C: KeyCode = KeyCode::KeyC;
+    pub const D: KeyCode = KeyCode::KeyD;
+    pub const F: KeyCode = KeyCode::KeyF;
+    pub const G: KeyCode = KeyCode::KeyG;
+    pub const O: KeyCode = KeyCode::KeyO;
+    pub const R: KeyCode = KeyCode::KeyR;
+    pub const X: KeyCode = KeyCode::KeyX;
+    pub const LEFT: KeyCode = KeyCode::ArrowLeft;
+    pub const RIGHT: KeyCode = KeyCode::ArrowRight;
+}
+
+/// Return the positions of the left and the right stick of the gamepad. Since 0.15, every gamepad is an entity
+/// with a `Gamepad` component holding its input.
+pub fn gamepad_sticks(gamepad: &Gamepad) -> (Vec2, Vec2) {
+    (gamepad.left_stick(), gamepad.right_stick())
+}
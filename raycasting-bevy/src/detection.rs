@@ -0,0 +1,252 @@
+use std::collections::{HashMap, HashSet};
+use bevy::prelude::*;
+use raycasting_core::batch::{batch_query, PolygonBatch};
+use raycasting_core::config::{RaycastConfig, SymmetryPolicy};
+use raycasting_core::raycasting::calculate_intersection_points;
+use raycasting_core::visibility_events::{polygon_contains, VisibilityTracker};
+use crate::compat::{AppCompat, Message, MessageReader, MessageWriter, ReadMessages, ReadRemoved, WriteMessages};
+use crate::occluders::{QueryOccluders, SceneOccluders, SceneOccludersPlugin};
+
+/// Lets [Viewer] entities detect [Detectable] entities behind the [SceneOccluders] and remember where they saw them.
+pub struct DetectionPlugin;
+
+impl Plugin for DetectionPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<SceneOccludersPlugin>() {
+            app.plugin(SceneOccludersPlugin);
+        }
+
+        app
+            .message::<Spotted>()
+            .message::<Lost>()
+            .register_type::<Viewer>()
+            .register_type::<Detectable>()
+            .register_type::<VisionMemory>()
+            .register_type::<Sighting>()
+            .register_type::<HashMap<Entity, Sighting>>()
+            .update_systems((
+                detect.in_set(QueryOccluders),
+                remember.after(detect)
+            ))
+        ;
+    }
+}
+
+/// Something which looks around, like a guard. Add a [VisionMemory] to remember what it saw.
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Viewer {
+    pub config: RaycastConfig,
+    #[reflect(ignore)]
+    tracker: VisibilityTracker<Entity>,
+}
+
+/// Something which can be seen by viewers, like the player.
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Detectable;
+
+/// Sent when a viewer starts seeing a detectable entity.
+#[derive(Message)]
+pub struct Spotted {
+    pub viewer: Entity,
+    pub target: Entity,
+}
+
+/// Sent when a viewer stops seeing a detectable entity.
+#[derive(Message)]
+pub struct Lost {
+    pub viewer: Entity,
+    pub target: Entity,
+}
+
+/// Where and when a viewer last saw a detectable entity.
+#[derive(Copy, Clone, Debug, PartialEq, Reflect)]
+pub struct Sighting {
+    pub position: Vec2,
+    /// Seconds since startup
+    pub time: f32,
+}
+
+/// Remembers the last known position of every detectable entity a viewer has seen, for an AI to search there.
+///
+/// While an entity is visible, its sighting follows it every frame. Once it is lost or despawned, the sighting
+/// stays where it was last seen until the entity is spotted again or [VisionMemory::forget] is called.
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct VisionMemory {
+    /// Follows from the spotted and lost events of the next frame
+    #[reflect(ignore)]
+    visible: HashSet<Entity>,
+    sightings: HashMap<Entity, Sighting>,
+}
+
+impl VisionMemory {
+    pub fn last_seen(&self, target: Entity) -> Option<Sighting> {
+        self.sightings.get(&target).copied()
+    }
+
+    /// Return true if the viewer sees the target right now.
+    pub fn is_visible(&self, target: Entity) -> bool {
+        self.visible.contains(&target)
+    }
+
+    /// Return every remembered entity with its last sighting, in no particular order.
+    pub fn sightings(&self) -> impl Iterator<Item=(Entity, Sighting)> + '_ {
+        self.sightings.iter().map(|(target, sighting)| (*target, *sighting))
+    }
+
+    /// Forget the sighting of the target, like after searching its last known position.
+    /// A visible target is remembered again in the next frame.
+    pub fn forget(&mut self, target: Entity) {
+        self.sightings.remove(&target);
+    }
+}
+
+/// The queries of all viewers, kept between frames to reuse their memory
+#[derive(Default)]
+struct DetectionBatch {
+    origins: Vec<Vec2>,
+    configs: Vec<RaycastConfig>,
+    polygons: PolygonBatch,
+}
+
+/// The polygons of all viewers are computed in one batch, reusing the memory of the last frame. Nothing is
+/// recomputed unless the occluders changed, a viewer changed or moved, or a detectable moved or disappeared.
+fn detect(
+    mut batch: Local<DetectionBatch>,
+    occluders: Res<SceneOccluders>,
+    mut viewers: Query<(Entity, Ref<GlobalTransform>, &mut Viewer)>,
+    detectables: Query<(Entity, Ref<GlobalTransform>), With<Detectable>>,
+    mut removed: RemovedComponents<Detectable>,
+    mut spotted: MessageWriter<Spotted>,
+    mut lost: MessageWriter<Lost>,
+) {
+    // the removed detectables must be read every frame, so they do not pile up until the next change
+    let disappeared = removed.removed().count() > 0;
+    let moved = viewers.iter_mut().any(|(_, transform, viewer)| transform.is_changed() || viewer.is_changed())
+        || detectables.iter().any(|(_, transform)| transform.is_changed());
+
+    if viewers.is_empty() || !(occluders.is_changed() || moved || disappeared) {
+        return;
+    }
+
+    let DetectionBatch { origins, configs, polygons } = &mut *batch;
+    origins.clear();
+    configs.clear();
+
+    for (_, transform, viewer) in &viewers {
+        origins.push(transform.translation().truncate());
+        configs.push(viewer.config.clone());
+    }
+
+    batch_query(origins, configs, occluders.0.occluders(), polygons);
+
+    for (index, (viewer_entity, _, mut viewer)) in viewers.iter_mut().enumerate() {
+        let origin = origins[index];
+        let polygon = polygons.polygon(index);
+        // a symmetric viewer only sees targets which could see it as well
+        let seen_back = |target: Vec2| match viewer.config.symmetry_policy {
+            SymmetryPolicy::Asymmetric => true,
+            SymmetryPolicy::Symmetric => polygon_contains(target, &calculate_intersection_points(target, occluders.0.occluders(), &viewer.config), origin)
+        };
+        let visible = detectables
+            .iter()
+            .filter(|(target, _)| *target != viewer_entity)
+            .map(|(target, target_transform)| (target, target_transform.translation().truncate()))
+            .filter(|(_, position)| polygon_contains(origin, polygon, *position) && seen_back(*position))
+            .map(|(target, _)| target)
+            .collect::<Vec<_>>();
+        // updating the tracker is no change of the viewer
+        let changes = viewer.bypass_change_detection().tracker.update(visible);
+
+        spotted.emit_all(changes.became_visible.into_iter().map(|target| Spotted { viewer: viewer_entity, target }));
+        lost.emit_all(changes.became_hidden.into_iter().map(|target| Lost { viewer: viewer_entity, target }));
+    }
+}
+
+fn remember(
+    time: Res<Time>,
+    mut spotted: MessageReader<Spotted>,
+    mut lost: MessageReader<Lost>,
+    mut memories: Query<&mut VisionMemory>,
+    detectables: Query<&GlobalTransform, With<Detectable>>,
+) {
+    for event in spotted.messages() {
+        if let Ok(mut memory) = memories.get_mut(event.viewer) {
+            memory.visible.insert(event.target);
+        }
+    }
+
+    for event in lost.messages() {
+        if let Ok(mut memory) = memories.get_mut(event.viewer) {
+            memory.visible.remove(&event.target);
+        }
+    }
+
+    let now = time.elapsed_secs();
+
+    for mut memory in &mut memories {
+        let memory = &mut *memory;
+        // despawned targets can neither be seen nor be lost anymore
+        memory.visible.retain(|target| detectables.contains(*target));
+
+        for target in &memory.visible {
+            if let Ok(transform) = detectables.get(*target) {
+                memory.sightings.insert(*target, Sighting { position: transform.translation().truncate(), time: now });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+    use raycasting_core::occluder::Occluder;
+    use raycasting_core::occluder_set::OccluderSet;
+    use raycasting_core::raycasting::Segment;
+    use crate::compat::AppCompat;
+    use crate::detection::{Detectable, DetectionPlugin, Viewer, VisionMemory};
+    use crate::occluders::{DEFAULT_CELL_SIZE, SceneOccluders};
+
+    #[test]
+    fn viewers_remember_targets_and_forget_despawned_ones() {
+        let mut app = App::new();
+        app
+            .init_resource::<Time>()
+            .plugin(DetectionPlugin)
+            .insert_resource(SceneOccluders(OccluderSet::new([
+                (-100.0, -100.0, 100.0, -100.0),
+                (100.0, -100.0, 100.0, 100.0),
+                (100.0, 100.0, -100.0, 100.0),
+                (-100.0, 100.0, -100.0, -100.0),
+            ].map(|(x0, y0, x1, y1)| Occluder::new(Segment::from_coords(x0, y0, x1, y1))).to_vec(), DEFAULT_CELL_SIZE)))
+        ;
+
+        let viewer = app.world_mut().spawn((GlobalTransform::default(), Viewer::default(), VisionMemory::default())).id();
+        let target = app.world_mut().spawn((GlobalTransform::from_translation(Vec3::new(50.0, 20.0, 0.0)), Detectable)).id();
+        app.update();
+
+        let memory = app.world().get::<VisionMemory>(viewer).unwrap();
+        assert!(memory.is_visible(target));
+        assert_eq!(memory.last_seen(target).map(|sighting| sighting.position), Some(Vec2::new(50.0, 20.0)));
+
+        // a wall between them hides the target, but its sighting stays
+        let wall = app.world_mut().resource_mut::<SceneOccluders>().0.push(Occluder::new(Segment::from_coords(25.0, -50.0, 25.0, 50.0)));
+        app.update();
+        app.update();
+
+        let memory = app.world().get::<VisionMemory>(viewer).unwrap();
+        assert!(!memory.is_visible(target));
+        assert!(memory.last_seen(target).is_some());
+
+        app.world_mut().resource_mut::<SceneOccluders>().0.remove(wall);
+        app.update();
+        app.update();
+        assert!(app.world().get::<VisionMemory>(viewer).unwrap().is_visible(target));
+
+        app.world_mut().despawn(target);
+        app.update();
+        assert!(!app.world().get::<VisionMemory>(viewer).unwrap().is_visible(target));
+    }
+}
@@ -0,0 +1,8 @@
+pub mod compat;
+pub mod detection;
+pub mod line_of_sight;
+pub mod lit;
+pub mod occluders;
+#[cfg(feature = "prototype_lyon")]
+pub mod prototype_lyon;
+pub mod reflect;
@@ -0,0 +1,256 @@
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy::platform::time::Instant;
+use bevy::prelude::*;
+use raycasting_core::config::RaycastConfig;
+use raycasting_core::flicker::{Flicker, FlickerTracker};
+use raycasting_core::intensity::{IntensityProfile, light_triangles, LitTriangle};
+use raycasting_core::raycasting::{calculate_intersection_points, Triangle};
+use raycasting_core::view_distance::limit_view_distance;
+use raycasting_core::visibility_polygon::VisibilityPolygon;
+use crate::compat::{AppCompat, colored_mesh, Indices, material_handle, MaterialComponent, new_mesh, PrimitiveTopology, set_mesh_indices};
+use crate::occluders::{QueryOccluders, SceneOccluders, SceneOccludersPlugin};
+
+/// Time it took to compute the line of sight, in milliseconds
+pub const LOS_COMPUTE_TIME: DiagnosticPath = DiagnosticPath::const_new("los_compute_time");
+/// Number of occluders considered by the line of sight computation
+pub const LOS_OCCLUDER_COUNT: DiagnosticPath = DiagnosticPath::const_new("los_occluder_count");
+/// Number of vertices of the line of sight polygon
+pub const LOS_VERTEX_COUNT: DiagnosticPath = DiagnosticPath::const_new("los_vertex_count");
+
+/// Measurements the diagnostics of the line of sight average over
+const DIAGNOSTIC_HISTORY: usize = 20;
+
+/// Computes the [LineOfSight] from the [LosOrigin] against the [SceneOccluders] and draws it. The origin follows
+/// the [LosTarget], which the app moves, like with the cursor.
+pub struct LineOfSightPlugin;
+
+impl Plugin for LineOfSightPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<SceneOccludersPlugin>() {
+            app.plugin(SceneOccludersPlugin);
+        }
+
+        app
+            .init_resource::<LineOfSight>()
+            .register_type::<LineOfSight>()
+            .init_resource::<LosTarget>()
+            .init_resource::<LosOrigin>()
+            .init_resource::<LosSettings>()
+            .register_type::<LosTarget>()
+            .register_type::<LosOrigin>()
+            .register_type::<LosSettings>()
+            .init_resource::<LosFlicker>()
+            .register_diagnostic(Diagnostic::new(LOS_COMPUTE_TIME).with_suffix("ms").with_max_history_length(DIAGNOSTIC_HISTORY))
+            .register_diagnostic(Diagnostic::new(LOS_OCCLUDER_COUNT).with_max_history_length(DIAGNOSTIC_HISTORY))
+            .register_diagnostic(Diagnostic::new(LOS_VERTEX_COUNT).with_max_history_length(DIAGNOSTIC_HISTORY))
+            .update_systems((
+                update_los_origin,
+                update_los.after(update_los_origin).in_set(QueryOccluders),
+                spawn_los_triangles.after(update_los),
+                flicker_los_intensity.after(spawn_los_triangles)
+            ))
+        ;
+    }
+}
+
+/// Largest angle (radians) between two points of the line of sight if it is shaped by an intensity profile or a radius
+const INTENSITY_STEP: f32 = 0.05;
+
+/// Opacity of the line of sight before flickering
+const LOS_ALPHA: f32 = 0.5;
+
+/// Distance below which a smoothed origin jumps onto the target, so it comes to rest and stops recomputing
+const SMOOTHING_REST_DISTANCE: f32 = 0.01;
+
+/// The area seen from the [LosOrigin], limited to the radius of the [LosSettings], and the triangles drawing it.
+#[derive(Default, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct LineOfSight {
+    polygon: VisibilityPolygon,
+    /// A fan around the origin, shaded by the intensity profile of the settings
+    #[reflect(ignore)]
+    triangles: Vec<LitTriangle>,
+}
+
+impl LineOfSight {
+    /// Return the line of sight as a polygon, which has no points before it was computed.
+    pub fn polygon(&self) -> &VisibilityPolygon {
+        &self.polygon
+    }
+
+    /// Return the triangles covering the line of sight.
+    pub fn triangles(&self) -> impl Iterator<Item=&Triangle> {
+        self.triangles.iter().map(|lit| &lit.triangle)
+    }
+}
+
+/// Settings of the line of sight computation. Changing them recomputes the line of sight.
+#[derive(Default, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct LosSettings {
+    pub config: RaycastConfig,
+    /// Shade the line of sight like a flashlight facing the given angle (radians) instead of uniformly.
+    pub intensity_profile: Option<(IntensityProfile, f32)>,
+    /// Limit the line of sight to this distance, like the light of a torch.
+    pub radius: Option<f32>,
+    /// Let the intensity and radius of the line of sight flicker.
+    pub flicker: Option<Flicker>,
+    /// Let the origin follow the target smoothly instead of jumping to it, approaching it exponentially at the given
+    /// rate per second (like 10, higher follows faster), so the polygon shimmers less while the target is moved.
+    pub smoothing: Option<f32>,
+    /// Snap the origin to the nearest multiple of this size, like the centers of the tiles of a map, so algorithm
+    /// variants can be compared from exactly the same origins.
+    pub snap_to_grid: Option<f32>,
+}
+
+/// The point the origin of the line of sight follows, like the cursor.
+#[derive(Default, Deref, DerefMut, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct LosTarget(pub Vec2);
+
+/// The origin of the line of sight, which follows the [LosTarget] as configured in the [LosSettings].
+#[derive(Default, Deref, DerefMut, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct LosOrigin(Vec2);
+
+/// The radius the line of sight was last computed with, if it flickers.
+#[derive(Default, Resource)]
+struct LosFlicker(FlickerTracker);
+
+#[derive(Component)]
+struct LosTriangle;
+
+fn update_los_origin(
+    time: Res<Time>,
+    target: Res<LosTarget>,
+    settings: Res<LosSettings>,
+    mut origin: ResMut<LosOrigin>,
+) {
+    let target = match settings.snap_to_grid {
+        Some(size) => (**target / size).round() * size,
+        None => **target
+    };
+    let next = match settings.smoothing {
+        // frame rate independent exponential smoothing
+        Some(rate) if target.distance(**origin) > SMOOTHING_REST_DISTANCE => origin.lerp(target, 1.0 - (-rate * time.delta_secs()).exp()),
+        _ => target
+    };
+
+    if next != **origin {
+        **origin = next
+    }
+}
+
+fn update_los(
+    time: Res<Time>,
+    mut diagnostics: Diagnostics,
+    origin: Res<LosOrigin>,
+    mut line_of_sight: ResMut<LineOfSight>,
+    mut tracker: ResMut<LosFlicker>,
+    settings: Res<LosSettings>,
+    occluders: Res<SceneOccluders>,
+) {
+    let radius = match (settings.radius, settings.flicker) {
+        (Some(radius), Some(flicker)) => Some(flicker.radius(radius, time.elapsed_secs())),
+        (radius, _) => radius
+    };
+    let flickered = match (radius, settings.flicker) {
+        (Some(radius), Some(flicker)) => tracker.0.update(radius, flicker.threshold).is_some(),
+        _ => false
+    };
+
+    if !flickered && !origin.is_changed() && !settings.is_changed() && !occluders.is_changed() {
+        return;
+    }
+
+    // the line of sight is recomputed with the current radius, so later flickering starts from it
+    if let Some(radius) = radius {
+        tracker.0.invalidate();
+        tracker.0.update(radius, 0.0);
+    }
+
+    let origin = **origin;
+    let start = Instant::now();
+    let points = calculate_intersection_points(origin, occluders.0.occluders(), &settings.config);
+    let compute_time = start.elapsed();
+
+    diagnostics.add_measurement(&LOS_COMPUTE_TIME, || compute_time.as_secs_f64() * 1000.0);
+    diagnostics.add_measurement(&LOS_OCCLUDER_COUNT, || occluders.0.len() as f64);
+    diagnostics.add_measurement(&LOS_VERTEX_COUNT, || points.len() as f64);
+
+    let points = match radius {
+        Some(radius) => limit_view_distance(origin, &points, |_| radius, INTENSITY_STEP),
+        None => points
+    };
+    let triangles = match &settings.intensity_profile {
+        Some((profile, facing)) => light_triangles(origin, &points, *facing, |angle| profile.intensity(angle), INTENSITY_STEP),
+        None => (0..points.len())
+            .map(|i| LitTriangle {
+                triangle: Triangle::new(origin, points[i], points[(i + 1) % points.len()]),
+                intensities: [1.0; 3],
+            })
+            .collect()
+    };
+
+    *line_of_sight = LineOfSight {
+        polygon: VisibilityPolygon::new(origin, points),
+        triangles,
+    };
+}
+
+fn spawn_los_triangles(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    line_of_sight: Res<LineOfSight>,
+    los_triangles: Query<Entity, With<LosTriangle>>,
+) {
+    if !line_of_sight.is_changed() {
+        return;
+    }
+
+    for e in &los_triangles {
+        commands.entity(e).despawn();
+    }
+
+    let color = Color::srgba(1.0, 1.0, 1.0, LOS_ALPHA);
+
+    for triangle in line_of_sight.triangles.iter() {
+        commands.spawn((
+            LosTriangle,
+            colored_mesh(meshes.add(create_triangle(*triangle)), materials.add(ColorMaterial::from(color))),
+            Transform::from_translation(Vec3::new(0.0, 0.0, 1.0)),
+        ));
+    }
+}
+
+/// Change the intensity of the line of sight every frame if it flickers, without recomputing it.
+fn flicker_los_intensity(
+    time: Res<Time>,
+    settings: Res<LosSettings>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    los_triangles: Query<&MaterialComponent, With<LosTriangle>>,
+) {
+    let Some(flicker) = settings.flicker else {
+        return;
+    };
+    let intensity = flicker.intensity(time.elapsed_secs());
+
+    for component in &los_triangles {
+        if let Some(mut material) = materials.get_mut(material_handle(component)) {
+            material.color.set_alpha(LOS_ALPHA * intensity);
+        }
+    }
+}
+
+fn create_triangle(lit: LitTriangle) -> Mesh {
+    let triangle = lit.triangle;
+    let mut mesh = new_mesh(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        vec![triangle.a.extend(0.0).to_array(), triangle.b.extend(0.0).to_array(), triangle.c.extend(0.0).to_array()],
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, lit.intensities.map(|intensity| [1.0, 1.0, 1.0, intensity]).to_vec());
+    set_mesh_indices(&mut mesh, Indices::U32(vec![0, 1, 2]));
+    mesh
+}
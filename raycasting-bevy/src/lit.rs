@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+use raycasting_core::aabb::Aabb;
+use raycasting_core::visibility_polygon::{lit_fraction, VisibilityPolygon};
+use crate::compat::AppCompat;
+use crate::line_of_sight::LineOfSight;
+use crate::occluders::QueryOccluders;
+
+/// Points along each axis of a shape which are tested for light, if it is partially lit
+const LIT_SAMPLES: usize = 8;
+
+/// Computes the [LitFraction] of every entity with a [LitQuery] from the line of sight and every [LitPolygon], like to
+/// skip updating entities in darkness or to shade partially lit ones.
+pub struct LitPlugin;
+
+impl Plugin for LitPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_type::<LitQuery>()
+            .register_type::<LitFraction>()
+            .register_type::<LitPolygon>()
+            .update_systems(update_lit_fractions.after(QueryOccluders))
+        ;
+    }
+}
+
+/// Asks for the fraction of the shape of an entity which is lit, given as a box relative to its transform.
+/// Rotated and scaled entities use the bounds of the transformed box.
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct LitQuery {
+    pub shape: Aabb,
+}
+
+/// A visibility polygon which lights the shapes of [LitQuery] entities besides the line of sight, like the area of a
+/// light or a vision cone. The app computes it and keeps it up to date.
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct LitPolygon(pub VisibilityPolygon);
+
+/// The fraction (0 to 1) of the shape of a [LitQuery] which lies inside the line of sight or any [LitPolygon].
+#[derive(Component, Copy, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct LitFraction(pub f32);
+
+fn update_lit_fractions(
+    mut commands: Commands,
+    line_of_sight: Option<Res<LineOfSight>>,
+    lit_polygons: Query<&LitPolygon>,
+    mut query: Query<(Entity, &LitQuery, &GlobalTransform, Option<&mut LitFraction>)>,
+) {
+    let polygons = line_of_sight
+        .map(|line_of_sight| line_of_sight.polygon().clone())
+        .filter(|polygon| !polygon.points().is_empty())
+        .into_iter()
+        .chain(lit_polygons.iter().map(|polygon| polygon.0.clone()))
+        .collect::<Vec<_>>();
+
+    for (entity, lit_query, transform, fraction) in &mut query {
+        let corners = lit_query.shape.corners().map(|corner| transform.transform_point(corner.extend(0.0)).truncate());
+        let Some(bounds) = Aabb::from_points(corners) else {
+            continue;
+        };
+        let lit = LitFraction(lit_fraction(&bounds, &polygons, LIT_SAMPLES));
+
+        match fraction {
+            // only touch changed fractions, so Changed<LitFraction> finds the entities whose light changed
+            Some(mut fraction) => {
+                fraction.set_if_neq(lit);
+            }
+            None => {
+                commands.entity(entity).insert(lit);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+    use raycasting_core::aabb::Aabb;
+    use raycasting_core::visibility_polygon::VisibilityPolygon;
+    use crate::compat::AppCompat;
+    use crate::lit::{LitFraction, LitPlugin, LitPolygon, LitQuery};
+
+    #[test]
+    fn any_lit_polygon_lights_the_shapes() {
+        let mut app = App::new();
+        app.plugin(LitPlugin);
+
+        let square = |center: Vec2| VisibilityPolygon::new(center, [(-10.0, -10.0), (10.0, -10.0), (10.0, 10.0), (-10.0, 10.0)]
+            .map(|(x, y)| center + Vec2::new(x, y))
+            .to_vec());
+        let shape = Aabb::new(Vec2::splat(-1.0), Vec2::splat(1.0));
+        let inside = app.world_mut().spawn((GlobalTransform::from_translation(Vec3::new(5.0, 5.0, 0.0)), LitQuery { shape })).id();
+        let border = app.world_mut().spawn((GlobalTransform::from_translation(Vec3::new(40.0, 0.0, 0.0)), LitQuery { shape })).id();
+        let outside = app.world_mut().spawn((GlobalTransform::from_translation(Vec3::new(100.0, 0.0, 0.0)), LitQuery { shape })).id();
+        app.world_mut().spawn(LitPolygon(square(Vec2::ZERO)));
+        app.world_mut().spawn(LitPolygon(square(Vec2::new(50.0, 0.0))));
+        app.update();
+
+        let fraction = |entity: Entity| app.world().get::<LitFraction>(entity).copied();
+        assert_eq!(fraction(inside), Some(LitFraction(1.0)));
+        assert_eq!(fraction(border), Some(LitFraction(0.5)));
+        assert_eq!(fraction(outside), Some(LitFraction(0.0)));
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use bevy::prelude::*;
+use raycasting_core::occluder::{Occluder, OccluderId};
+use raycasting_core::occluder_set::OccluderSet;
+use raycasting_core::transform::Transform2;
+use crate::compat::{AppCompat, ReadRemoved};
+
+/// The cell size of the index of the default [SceneOccluders], in the order of the tiles of a typical map
+pub const DEFAULT_CELL_SIZE: f32 = 32.0;
+
+/// Keeps the [EntityOccluders] of every entity in the [SceneOccluders]. The plugins querying the occluders add it
+/// if the app did not.
+pub struct SceneOccludersPlugin;
+
+impl Plugin for SceneOccludersPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<SceneOccluders>()
+            .register_type::<SceneOccluders>()
+            .register_type::<EntityOccluders>()
+            .update_systems(sync_entity_occluders.before(QueryOccluders))
+        ;
+    }
+}
+
+/// The occluders every plugin of this crate queries. The app keeps them up to date, like when a map is loaded
+/// or a door opens, and only changes them if they actually changed, as every change recomputes the queries.
+///
+/// The [EntityOccluders] of entities are added to the set and follow their entities, so change the set in place
+/// instead of replacing it, which would leave their ids pointing at other occluders. Apps whose occluders are much
+/// longer or shorter than [DEFAULT_CELL_SIZE] insert a set with a matching cell size before adding the plugins.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct SceneOccluders(pub OccluderSet);
+
+impl Default for SceneOccluders {
+    fn default() -> Self {
+        SceneOccluders(OccluderSet::new(vec![], DEFAULT_CELL_SIZE))
+    }
+}
+
+/// The occluders of an entity relative to its transform, like the outline of a moving crate. They are part of the
+/// [SceneOccluders] until the component is removed or the entity despawned.
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct EntityOccluders(pub Vec<Occluder>);
+
+/// The systems which query the [SceneOccluders]. Systems changing them should run before, so the queries
+/// see the change in the same frame.
+#[derive(SystemSet, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct QueryOccluders;
+
+/// Add, move and remove the [EntityOccluders] in the [SceneOccluders]. Only entities which moved or whose
+/// occluders changed touch the set, so the queries are not recomputed while everything stands still.
+fn sync_entity_occluders(
+    mut ids: Local<HashMap<Entity, Vec<OccluderId>>>,
+    mut occluders: ResMut<SceneOccluders>,
+    entities: Query<(Entity, Ref<EntityOccluders>, Ref<GlobalTransform>)>,
+    mut removed: RemovedComponents<EntityOccluders>,
+) {
+    for entity in removed.removed() {
+        for id in ids.remove(&entity).unwrap_or_default() {
+            occluders.0.remove(id);
+        }
+    }
+
+    for (entity, entity_occluders, transform) in &entities {
+        if !entity_occluders.is_changed() && !transform.is_changed() {
+            continue;
+        }
+
+        let (scale, rotation, translation) = transform.to_scale_rotation_translation();
+        let (angle, _, _) = rotation.to_euler(EulerRot::ZYX);
+        let transform = Transform2::from_scale_rotation_translation(scale.truncate(), angle, translation.truncate());
+        let placed = entity_occluders.0.iter().map(|occluder| transform.apply_to_occluder(*occluder));
+        let entity_ids = ids.entry(entity).or_default();
+
+        if entity_ids.len() == entity_occluders.0.len() {
+            entity_ids.iter().zip(placed).for_each(|(id, occluder)| {
+                occluders.0.set(*id, occluder);
+            });
+        } else {
+            entity_ids.drain(..).for_each(|id| {
+                occluders.0.remove(id);
+            });
+            entity_ids.extend(placed.map(|occluder| occluders.0.push(occluder)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+    use raycasting_core::occluder::Occluder;
+    use raycasting_core::raycasting::Segment;
+    use crate::compat::AppCompat;
+    use crate::occluders::{EntityOccluders, SceneOccluders, SceneOccludersPlugin};
+
+    #[test]
+    fn entity_occluders_follow_their_entity() {
+        let mut app = App::new();
+        app.plugin(SceneOccludersPlugin);
+
+        let segments = |app: &App| {
+            let occluders = &app.world().resource::<SceneOccluders>().0;
+            occluders.ids().map(|id| occluders.get(id).unwrap().segment).collect::<Vec<_>>()
+        };
+        let entity = app.world_mut().spawn((
+            GlobalTransform::from_translation(Vec3::new(10.0, 0.0, 0.0)),
+            EntityOccluders(vec![Occluder::new(Segment::from_coords(0.0, -1.0, 0.0, 1.0))]),
+        )).id();
+        app.update();
+        assert_eq!(segments(&app), vec![Segment::from_coords(10.0, -1.0, 10.0, 1.0)]);
+
+        // standing still does not touch the set
+        let revision = app.world().resource::<SceneOccluders>().0.revision();
+        app.update();
+        assert_eq!(app.world().resource::<SceneOccluders>().0.revision(), revision);
+
+        *app.world_mut().get_mut::<GlobalTransform>(entity).unwrap() = GlobalTransform::from_translation(Vec3::new(0.0, 5.0, 0.0));
+        app.update();
+        assert_eq!(segments(&app), vec![Segment::from_coords(0.0, 4.0, 0.0, 6.0)]);
+
+        app.world_mut().get_mut::<EntityOccluders>(entity).unwrap().0.push(Occluder::new(Segment::from_coords(-1.0, 0.0, 1.0, 0.0)));
+        app.update();
+        assert_eq!(segments(&app).len(), 2);
+
+        app.world_mut().despawn(entity);
+        app.update();
+        assert!(segments(&app).is_empty());
+    }
+}
@@ -0,0 +1,64 @@
+use bevy_prototype_lyon::prelude::*;
+use raycasting_core::occluder::Occluder;
+use raycasting_core::occluder_set::OccluderSet;
+use raycasting_core::visibility_polygon::VisibilityPolygon;
+
+/// Return a closed path along the border of the polygon, to draw it with bevy_prototype_lyon, like
+/// filled with a [Fill] or outlined with a [Stroke] by a [ShapeBuilder].
+pub fn polygon_path(polygon: &VisibilityPolygon) -> ShapePath {
+    let Some((first, rest)) = polygon.points().split_first() else {
+        return ShapePath::new();
+    };
+
+    rest
+        .iter()
+        .fold(ShapePath::new().move_to(*first), |path, point| path.line_to(*point))
+        .close()
+}
+
+/// Return a path with a line for every occluder of the set, see [occluders_path]. Removed occluders are left out.
+pub fn occluder_set_path(set: &OccluderSet) -> ShapePath {
+    let occluders = set.ids().filter_map(|id| set.get(id).copied()).collect::<Vec<_>>();
+    occluders_path(&occluders)
+}
+
+/// Return a path with a separate line for every occluder, to draw them with a [Stroke].
+pub fn occluders_path(occluders: &[Occluder]) -> ShapePath {
+    occluders
+        .iter()
+        .fold(ShapePath::new(), |path, occluder| path.move_to(occluder.segment.a()).line_to(occluder.segment.b()))
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+    use bevy_prototype_lyon::prelude::*;
+    use bevy_prototype_lyon::prelude::tess::path::PathEvent;
+    use raycasting_core::occluder::Occluder;
+    use raycasting_core::occluder_set::OccluderSet;
+    use raycasting_core::raycasting::Segment;
+    use raycasting_core::visibility_polygon::VisibilityPolygon;
+    use crate::prototype_lyon::{occluder_set_path, polygon_path};
+
+    #[test]
+    fn paths_follow_polygons_and_occluders() {
+        let polygon = VisibilityPolygon::new(Vec2::ZERO, vec![Vec2::new(-1.0, -1.0), Vec2::new(1.0, -1.0), Vec2::new(0.0, 1.0)]);
+        // the paths are only built into lyon paths by the shape
+        let events = |path: &ShapePath| ShapeBuilder::with(path).stroke(Stroke::color(Color::WHITE)).build().path.iter().collect::<Vec<_>>();
+        let polygon_events = events(&polygon_path(&polygon));
+
+        assert_eq!(polygon_events.len(), 4);
+        assert!(matches!(polygon_events.last(), Some(PathEvent::End { close: true, .. })));
+
+        let mut set = OccluderSet::new(vec![
+            Occluder::new(Segment::from_coords(0.0, 0.0, 10.0, 0.0)),
+            Occluder::new(Segment::from_coords(0.0, 5.0, 10.0, 5.0)),
+        ], 10.0);
+        let begins = |set: &OccluderSet| events(&occluder_set_path(set)).iter().filter(|event| matches!(event, PathEvent::Begin { .. })).count();
+
+        assert_eq!(begins(&set), 2);
+        set.remove(set.id(0).unwrap());
+        assert_eq!(begins(&set), 1);
+        assert!(events(&polygon_path(&VisibilityPolygon::default())).is_empty());
+    }
+}
@@ -0,0 +1,48 @@
+use bevy::prelude::*;
+use raycasting_core::aabb::Aabb;
+use raycasting_core::cone::ViewCone;
+use raycasting_core::config::{Algorithm, CoordinateSystem, MissPolicy, PeekPolicy, PolygonFormat, RaycastConfig, SelfHitPolicy, SymmetryPolicy, Winding};
+use raycasting_core::flicker::Flicker;
+use raycasting_core::groups::GroupId;
+use raycasting_core::intensity::IntensityProfile;
+use raycasting_core::occluder::{Material, Occluder, OccluderHeight, OccluderId, OccluderKind};
+use raycasting_core::raycasting::Segment;
+use raycasting_core::visibility_polygon::VisibilityPolygon;
+
+/// Registers the types the components and resources of the plugins are built from, so inspectors can edit
+/// them and scenes can store them. The plugins register their components and resources themselves.
+pub struct ReflectTypesPlugin;
+
+impl Plugin for ReflectTypesPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_type::<Aabb>()
+            .register_type::<Segment>()
+            .register_type::<Occluder>()
+            .register_type::<OccluderId>()
+            .register_type::<OccluderKind>()
+            .register_type::<OccluderHeight>()
+            .register_type::<Material>()
+            .register_type::<GroupId>()
+            .register_type::<Option<GroupId>>()
+            .register_type::<RaycastConfig>()
+            .register_type::<Algorithm>()
+            .register_type::<PeekPolicy>()
+            .register_type::<SymmetryPolicy>()
+            .register_type::<CoordinateSystem>()
+            .register_type::<Winding>()
+            .register_type::<PolygonFormat>()
+            .register_type::<MissPolicy>()
+            .register_type::<SelfHitPolicy>()
+            .register_type::<VisibilityPolygon>()
+            .register_type::<ViewCone>()
+            .register_type::<IntensityProfile>()
+            .register_type::<Option<(IntensityProfile, f32)>>()
+            .register_type::<Flicker>()
+            .register_type::<Option<Flicker>>()
+            .register_type::<Vec<Occluder>>()
+            .register_type::<Option<f32>>()
+            .register_type::<Option<usize>>()
+        ;
+    }
+}
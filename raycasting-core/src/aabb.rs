@@ -0,0 +1,88 @@
+use glam::*;
+
+/// An axis aligned bounding box.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub struct Aabb {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Aabb {
+    pub const fn new(min: Vec2, max: Vec2) -> Self {
+        Aabb { min, max }
+    }
+
+    pub fn from_center(center: Vec2, half_extents: Vec2) -> Self {
+        Aabb::new(center - half_extents, center + half_extents)
+    }
+
+    /// Return the smallest box containing all points, or None if there are none.
+    pub fn from_points(points: impl IntoIterator<Item=Vec2>) -> Option<Self> {
+        points
+            .into_iter()
+            .map(|point| Aabb::new(point, point))
+            .reduce(|bounds, point| Aabb::new(bounds.min.min(point.min), bounds.max.max(point.max)))
+    }
+
+    pub fn center(&self) -> Vec2 {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn half_extents(&self) -> Vec2 {
+        (self.max - self.min) / 2.0
+    }
+
+    /// Return the corners in counterclockwise order, starting at the lower left one.
+    pub fn corners(&self) -> [Vec2; 4] {
+        [
+            self.min,
+            Vec2::new(self.max.x, self.min.y),
+            self.max,
+            Vec2::new(self.min.x, self.max.y),
+        ]
+    }
+
+    /// Return true if the point lies inside this box or on its border.
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.max).all() && other.min.cmple(self.max).all()
+    }
+
+    /// Clip the segment from a to b against this box (Liang–Barsky) and return the part inside it, if any.
+    pub fn clip_segment(&self, a: Vec2, b: Vec2) -> Option<(Vec2, Vec2)> {
+        let d = b - a;
+        let mut t_min: f32 = 0.0;
+        let mut t_max: f32 = 1.0;
+
+        for (p, q) in [
+            (-d.x, a.x - self.min.x),
+            (d.x, self.max.x - a.x),
+            (-d.y, a.y - self.min.y),
+            (d.y, self.max.y - a.y),
+        ] {
+            if p == 0.0 {
+                if q < 0.0 {
+                    return None;
+                }
+            } else {
+                let t = q / p;
+
+                if p < 0.0 {
+                    t_min = t_min.max(t);
+                } else {
+                    t_max = t_max.min(t);
+                }
+            }
+        }
+
+        if t_min > t_max {
+            return None;
+        }
+
+        Some((a + d * t_min, a + d * t_max))
+    }
+}
@@ -0,0 +1,126 @@
+use glam::*;
+use crate::config::RaycastConfig;
+use crate::occluder::Occluder;
+use crate::raycasting::{EndpointHit, GrazingRay, polygon_in};
+use crate::stats::RaycastStats;
+use crate::visibility_polygon::VisibilityPolygon;
+
+/// Number of elements a [Buffer] holds without allocating, which covers the endpoints of common scenes
+#[cfg(feature = "smallvec")]
+pub const INLINE_CAPACITY: usize = 64;
+
+/// Storage for the temporaries of a query. With the smallvec feature, small queries keep them on the stack.
+#[cfg(feature = "smallvec")]
+pub(crate) type Buffer<T> = smallvec::SmallVec<[T; INLINE_CAPACITY]>;
+#[cfg(not(feature = "smallvec"))]
+pub(crate) type Buffer<T> = Vec<T>;
+
+/// The temporaries of the naive algorithm, which can be kept between queries, see
+/// [crate::raycasting::calculate_intersection_points_in]. Once the buffers have grown to the size of the scene,
+/// repeated queries only allocate their result, and queries with [QueryContext::query_in] not even that.
+#[derive(Default)]
+pub struct QueryArena {
+    pub(crate) endpoints: Buffer<Vec2>,
+    pub(crate) hits: Buffer<EndpointHit>,
+    pub(crate) grazing_rays: Buffer<GrazingRay>,
+    pub(crate) grazing_hits: Buffer<Vec2>,
+    /// The points of the last visibility polygon
+    pub(crate) polygon: Vec<Vec2>,
+}
+
+impl QueryArena {
+    /// Empty all buffers, keeping their memory.
+    pub(crate) fn clear(&mut self) {
+        self.endpoints.clear();
+        self.hits.clear();
+        self.grazing_rays.clear();
+        self.grazing_hits.clear();
+        self.polygon.clear();
+    }
+}
+
+/// The occluders and config shared by many queries, like all queries of a frame.
+#[derive(Copy, Clone, Debug)]
+pub struct QueryContext<'a> {
+    pub occluders: &'a [Occluder],
+    pub config: &'a RaycastConfig,
+}
+
+impl<'a> QueryContext<'a> {
+    pub fn new(
+        occluders: &'a [Occluder],
+        config: &'a RaycastConfig,
+    ) -> Self {
+        QueryContext { occluders, config }
+    }
+
+    /// Compute the visibility polygon around the origin, like [crate::raycasting::calculate_intersection_points],
+    /// but keep its points in the arena instead of returning a vector.
+    ///
+    /// The polygon borrows the arena until it is dropped, and the next query overwrites it. So a caller running many
+    /// queries per frame with the same arena only allocates while the buffers grow, see [PolygonRef::to_polygon]
+    /// to keep a polygon for longer.
+    pub fn query_in<'arena>(
+        &self,
+        arena: &'arena mut QueryArena,
+        origin: Vec2,
+    ) -> PolygonRef<'arena> {
+        polygon_in(origin, self.occluders, self.config, arena, &mut RaycastStats::default());
+        PolygonRef { origin, points: &arena.polygon }
+    }
+}
+
+/// A visibility polygon whose points are kept in a [QueryArena], see [QueryContext::query_in].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PolygonRef<'arena> {
+    origin: Vec2,
+    points: &'arena [Vec2],
+}
+
+impl<'arena> PolygonRef<'arena> {
+    pub fn origin(&self) -> Vec2 {
+        self.origin
+    }
+
+    /// Return the points of the polygon, ordered by angle.
+    pub fn points(&self) -> &'arena [Vec2] {
+        self.points
+    }
+
+    /// Copy the points into an owned polygon, which outlives the arena.
+    pub fn to_polygon(&self) -> VisibilityPolygon {
+        VisibilityPolygon::new(self.origin, self.points.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use crate::arena::{QueryArena, QueryContext};
+    use crate::config::{Algorithm, CoordinateSystem, RaycastConfig};
+    use crate::pathological::pathological_scenes;
+    use crate::raycasting::calculate_intersection_points;
+
+    #[test]
+    fn queries_in_an_arena_match_owned_queries() {
+        let mut arena = QueryArena::default();
+
+        for scene in pathological_scenes() {
+            [
+                RaycastConfig::default(),
+                RaycastConfig::default().with_algorithm(Algorithm::Sweep),
+                RaycastConfig::default().with_coordinate_system(CoordinateSystem::YDown),
+            ].into_iter().for_each(|config| {
+                let context = QueryContext::new(&scene.occluders, &config);
+
+                [scene.origin, scene.origin + Vec2::new(0.5, 0.25)].into_iter().for_each(|origin| {
+                    let expected = calculate_intersection_points(origin, &scene.occluders, &config);
+                    let polygon = context.query_in(&mut arena, origin);
+
+                    assert_eq!(polygon.points(), expected.as_slice(), "{}", scene.name);
+                    assert_eq!(polygon.to_polygon().points(), expected.as_slice(), "{}", scene.name);
+                });
+            });
+        }
+    }
+}
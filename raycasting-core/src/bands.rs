@@ -0,0 +1,96 @@
+use glam::*;
+use crate::config::RaycastConfig;
+use crate::occluder::Occluder;
+use crate::raycasting::polygon_points;
+use crate::view_distance::limit_view_distance;
+use crate::visibility_polygon::VisibilityPolygon;
+
+/// Settings of [visibility_bands].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BandSettings {
+    /// The exact polygon reaches up to this distance from the origin
+    pub near_radius: f32,
+    /// The approximated polygon reaches up to this distance from the origin
+    pub far_radius: f32,
+    /// Largest angle (radians) between two points of the near polygon where it follows the near radius
+    pub max_step: f32,
+    /// Number of uniform angles the far polygon is sampled at
+    pub far_resolution: usize,
+}
+
+impl Default for BandSettings {
+    fn default() -> Self {
+        BandSettings {
+            near_radius: 200.0,
+            far_radius: 1000.0,
+            max_step: 0.05,
+            far_resolution: 64,
+        }
+    }
+}
+
+/// The visibility polygon of an origin split into a detailed near band and a cheap far band, see [visibility_bands].
+/// Both polygons are ordered counterclockwise and not closed, regardless of the [crate::config::PolygonFormat].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VisibilityBands {
+    /// The exact polygon, cut off at the near radius
+    pub near: Vec<Vec2>,
+    /// The polygon sampled at the far resolution (see [VisibilityPolygon::resample]) and cut off at the far radius.
+    /// It covers the near band as well, so renderers draw it beyond the near radius only.
+    pub far: Vec<Vec2>,
+}
+
+/// Compute the visibility polygon of the origin once and return it as two bands: exact up to the near radius,
+/// for detailed shadows around the player, and approximated up to the far radius, for cheap silhouettes in the
+/// distance. The far polygon has a fixed number of points, so it misses gaps narrower than its resolution.
+pub fn visibility_bands(
+    origin: Vec2,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+    settings: &BandSettings,
+) -> VisibilityBands {
+    let points = polygon_points(origin, occluders, config);
+    let near = limit_view_distance(origin, &points, |_| settings.near_radius, settings.max_step);
+    let far = VisibilityPolygon::new(origin, points)
+        .resample(settings.far_resolution)
+        .into_iter()
+        .map(|point| origin + (point - origin).clamp_length_max(settings.far_radius))
+        .collect();
+
+    VisibilityBands { near, far }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use crate::bands::{BandSettings, visibility_bands};
+    use crate::config::RaycastConfig;
+    use crate::occluders;
+    use crate::visibility_polygon::VisibilityPolygon;
+
+    #[test]
+    fn bands_are_exact_near_and_approximated_far() {
+        // a long hall with a pillar near the origin
+        let occluders = occluders![
+            (-500.0, -100.0, 500.0, -100.0),
+            (500.0, -100.0, 500.0, -1.0),
+            (500.0, 1.0, 500.0, 100.0),
+            (500.0, 100.0, -500.0, 100.0),
+            (-500.0, 100.0, -500.0, -100.0),
+            (20.0, -5.0, 20.0, 5.0),
+        ];
+        let settings = BandSettings { near_radius: 50.0, far_radius: 400.0, max_step: 0.05, far_resolution: 32 };
+        let bands = visibility_bands(Vec2::ZERO, &occluders, &RaycastConfig::default(), &settings);
+
+        assert!(bands.near.iter().all(|point| point.length() <= 50.001));
+        assert!(bands.far.iter().all(|point| point.length() <= 400.001));
+        assert_eq!(bands.far.len(), 32);
+
+        // the shadow of the pillar is exact near the origin
+        let near = VisibilityPolygon::new(Vec2::ZERO, bands.near.clone());
+        assert!((near.raycast(0.0).unwrap() - 20.0).abs() < 0.001);
+        // between two steps the near radius is followed by a chord
+        assert!((near.raycast(0.3).unwrap() - 50.0).abs() < 0.1);
+        assert!(near.contains(Vec2::new(19.0, 4.0)) && !near.contains(Vec2::new(21.0, 4.0)));
+    }
+}
@@ -0,0 +1,104 @@
+use glam::*;
+use crate::config::RaycastConfig;
+use crate::occluder::Occluder;
+use crate::raycasting::calculate_intersection_points;
+
+/// The visibility polygons of a batch of queries, with the points of all polygons in one buffer.
+///
+/// Keep a batch around and pass it to [batch_query] every frame: it is cleared, but keeps its memory,
+/// so after the first frames, storing the results needs no allocations.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PolygonBatch {
+    points: Vec<Vec2>,
+    /// The index in points behind the last point of every polygon
+    ends: Vec<usize>,
+}
+
+impl PolygonBatch {
+    /// Remove all polygons, but keep the memory.
+    pub fn clear(&mut self) {
+        self.points.clear();
+        self.ends.clear();
+    }
+
+    /// Return the number of polygons.
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    /// Return the points of the polygon of the query with the given index, ordered by angle.
+    /// Panics if there is no such query.
+    pub fn polygon(&self, index: usize) -> &[Vec2] {
+        let start = if index == 0 { 0 } else { self.ends[index - 1] };
+        &self.points[start..self.ends[index]]
+    }
+
+    /// Return the polygons in the order of the queries.
+    pub fn polygons(&self) -> impl Iterator<Item=&[Vec2]> + '_ {
+        (0..self.len()).map(|index| self.polygon(index))
+    }
+
+    /// Return the points of all polygons, one after another.
+    pub fn points(&self) -> &[Vec2] {
+        &self.points
+    }
+
+    fn push(&mut self, points: impl IntoIterator<Item=Vec2>) {
+        self.points.extend(points);
+        self.ends.push(self.points.len());
+    }
+}
+
+/// Compute the visibility polygons (like [calculate_intersection_points]) of all origins and write them into
+/// the batch, replacing what it held before. Either give one config per origin, or a single one for all of them.
+///
+/// This suits ECS systems, which can collect the origins of all their entities into a slice, run the batch
+/// and read the polygon of every entity by its index, without keeping a vector for every polygon.
+pub fn batch_query(
+    origins: &[Vec2],
+    configs: &[RaycastConfig],
+    occluders: &[Occluder],
+    out: &mut PolygonBatch,
+) {
+    assert!(configs.len() == origins.len() || configs.len() == 1, "expected one config per origin or a single one, got {} for {} origins", configs.len(), origins.len());
+    out.clear();
+
+    for (index, origin) in origins.iter().enumerate() {
+        let config = &configs[index.min(configs.len() - 1)];
+        out.push(calculate_intersection_points(*origin, occluders, config));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use crate::batch::{batch_query, PolygonBatch};
+    use crate::config::RaycastConfig;
+    use crate::pathological::pathological_scenes;
+    use crate::raycasting::calculate_intersection_points;
+
+    #[test]
+    fn batch_query_matches_single_queries() {
+        let mut batch = PolygonBatch::default();
+
+        for scene in pathological_scenes() {
+            let origins = [scene.origin, scene.origin + Vec2::new(0.5, 0.25), scene.origin - Vec2::new(0.25, 0.5)];
+            let configs = [RaycastConfig::default(), RaycastConfig::layers(0), RaycastConfig::default()];
+
+            batch_query(&origins, &configs, &scene.occluders, &mut batch);
+            assert_eq!(batch.len(), 3);
+            origins.iter().zip(&configs).enumerate().for_each(|(index, (origin, config))| {
+                assert_eq!(batch.polygon(index), calculate_intersection_points(*origin, &scene.occluders, config).as_slice());
+            });
+
+            batch_query(&origins[..2], &[RaycastConfig::default()], &scene.occluders, &mut batch);
+            assert_eq!(batch.polygons().count(), 2);
+            assert_eq!(batch.points().len(), batch.polygons().map(<[Vec2]>::len).sum::<usize>());
+            assert_eq!(batch.polygon(1), calculate_intersection_points(origins[1], &scene.occluders, &RaycastConfig::default()).as_slice());
+        }
+    }
+}
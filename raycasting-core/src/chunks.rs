@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use glam::*;
+use crate::aabb::Aabb;
+use crate::config::{MissPolicy, RaycastConfig, SelfHitPolicy};
+use crate::occluder::{Occluder, OccluderId};
+use crate::occluder_set::OccluderSet;
+use crate::raycasting::{calculate_intersection_points, Segment};
+
+/// The position of a chunk in the grid of chunks. Chunk (0, 0) spans from the world origin to (chunk size, chunk size).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct ChunkCoord {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl ChunkCoord {
+    pub fn new(x: i32, y: i32) -> Self {
+        ChunkCoord { x, y }
+    }
+}
+
+/// The chunks loaded and unloaded by [ChunkedWorld::stream].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StreamingChanges {
+    pub loaded: Vec<ChunkCoord>,
+    pub unloaded: Vec<ChunkCoord>,
+}
+
+/// The occluders of a world too large to keep in memory at once, split into square chunks of a fixed size.
+/// Chunks are loaded and unloaded as the camera moves, and queries span all loaded chunks around them,
+/// regardless of the borders between them.
+///
+/// Every chunk only holds the parts of occluders inside of it, see [partition]. Occluders crossing a border
+/// are split there, which does not change the visibility polygon.
+///
+/// The occluders of all loaded chunks live in one [OccluderSet], so their ids stay the same while their chunk
+/// is loaded and become stale once it is unloaded.
+#[derive(Clone, Debug)]
+pub struct ChunkedWorld {
+    chunk_size: f32,
+    occluders: OccluderSet,
+    chunks: HashMap<ChunkCoord, Vec<OccluderId>>,
+}
+
+impl ChunkedWorld {
+    pub fn new(chunk_size: f32) -> Self {
+        ChunkedWorld {
+            chunk_size,
+            occluders: OccluderSet::new(Vec::new(), chunk_size),
+            chunks: HashMap::new(),
+        }
+    }
+
+    pub fn chunk_size(&self) -> f32 {
+        self.chunk_size
+    }
+
+    /// Return the chunk containing the given point. Points on a border belong to the chunk above or right of it.
+    pub fn chunk_of(&self, point: Vec2) -> ChunkCoord {
+        let coord = (point / self.chunk_size).floor();
+        ChunkCoord::new(coord.x as i32, coord.y as i32)
+    }
+
+    pub fn chunk_bounds(&self, coord: ChunkCoord) -> Aabb {
+        let min = Vec2::new(coord.x as f32, coord.y as f32) * self.chunk_size;
+        Aabb::new(min, min + Vec2::splat(self.chunk_size))
+    }
+
+    /// Return every chunk overlapping the given box, loaded or not.
+    pub fn chunks_in(&self, bounds: Aabb) -> impl Iterator<Item=ChunkCoord> {
+        let (min, max) = (self.chunk_of(bounds.min), self.chunk_of(bounds.max));
+        (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| ChunkCoord::new(x, y)))
+    }
+
+    pub fn is_loaded(&self, coord: ChunkCoord) -> bool {
+        self.chunks.contains_key(&coord)
+    }
+
+    /// Return every loaded chunk, in no particular order.
+    pub fn loaded_chunks(&self) -> impl Iterator<Item=ChunkCoord> + '_ {
+        self.chunks.keys().copied()
+    }
+
+    /// Load a chunk with the given occluders, replacing the ones it had, and return their ids. They should lie
+    /// inside the chunk, like the ones [partition] returns for it.
+    pub fn load(
+        &mut self,
+        coord: ChunkCoord,
+        occluders: Vec<Occluder>,
+    ) -> Vec<OccluderId> {
+        self.unload(coord);
+        let ids = occluders.into_iter().map(|occluder| self.occluders.push(occluder)).collect::<Vec<_>>();
+        self.chunks.insert(coord, ids.clone());
+        ids
+    }
+
+    /// Unload a chunk and return its occluders, if it was loaded.
+    pub fn unload(&mut self, coord: ChunkCoord) -> Option<Vec<Occluder>> {
+        let ids = self.chunks.remove(&coord)?;
+        Some(ids.into_iter().filter_map(|id| self.occluders.remove(id)).collect())
+    }
+
+    /// Return the occluder with the given id, or None if its chunk was unloaded since.
+    pub fn get(&self, id: OccluderId) -> Option<&Occluder> {
+        self.occluders.get(id)
+    }
+
+    /// Load every chunk within the given distance of the center which is not loaded yet, with the occluders
+    /// returned by the loader, and unload every chunk which is further than the distance plus one chunk away.
+    /// The extra chunk keeps chunks from being loaded and unloaded over and over when the camera moves
+    /// back and forth over a border.
+    pub fn stream(
+        &mut self,
+        center: Vec2,
+        distance: f32,
+        mut loader: impl FnMut(ChunkCoord) -> Vec<Occluder>,
+    ) -> StreamingChanges {
+        let mut changes = StreamingChanges::default();
+        let keep_distance = distance + self.chunk_size;
+
+        changes.unloaded = self
+            .loaded_chunks()
+            .filter(|coord| self.distance_to_chunk(center, *coord) > keep_distance)
+            .collect();
+        changes.unloaded.sort();
+        changes.unloaded.iter().for_each(|coord| { self.unload(*coord); });
+
+        changes.loaded = self
+            .chunks_in(Aabb::from_center(center, Vec2::splat(distance)))
+            .filter(|coord| !self.is_loaded(*coord) && self.distance_to_chunk(center, *coord) <= distance)
+            .collect();
+        changes.loaded.iter().for_each(|coord| { self.load(*coord, loader(*coord)); });
+
+        changes
+    }
+
+    fn distance_to_chunk(&self, point: Vec2, coord: ChunkCoord) -> f32 {
+        let bounds = self.chunk_bounds(coord);
+        point.distance(point.clamp(bounds.min, bounds.max))
+    }
+
+    /// Return the ids of the occluders of all loaded chunks overlapping the given box, in the order of the chunks.
+    pub fn ids_in(&self, bounds: Aabb) -> Vec<OccluderId> {
+        self.chunks_in(bounds)
+            .filter_map(|coord| self.chunks.get(&coord))
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    /// Return the occluders of all loaded chunks overlapping the given box, in the order of the chunks.
+    pub fn occluders_in(&self, bounds: Aabb) -> Vec<Occluder> {
+        self.ids_in(bounds)
+            .into_iter()
+            .filter_map(|id| self.occluders.get(id))
+            .copied()
+            .collect()
+    }
+
+    /// Return the visibility polygon of the origin (like [calculate_intersection_points]), considering the occluders
+    /// of all loaded chunks within the given distance of it. Occluder ids in the config are the ones returned by
+    /// [ChunkedWorld::load].
+    ///
+    /// The occluders further away are not considered, so rays which hit nothing end at the distance, or earlier
+    /// if the config has a smaller [MissPolicy::MaxDistance].
+    pub fn intersection_points(
+        &self,
+        origin: Vec2,
+        distance: f32,
+        config: &RaycastConfig,
+    ) -> Vec<Vec2> {
+        let ids = self.ids_in(Aabb::from_center(origin, Vec2::splat(distance)));
+        let occluders = ids.iter().filter_map(|id| self.occluders.get(*id)).copied().collect::<Vec<_>>();
+
+        // the ids in the config refer to the world, not to the occluders around the origin
+        let self_hit_policy = match config.self_hit_policy {
+            SelfHitPolicy::ExcludeOccluder(excluded) => ids
+                .iter()
+                .position(|id| *id == excluded)
+                .map(|index| SelfHitPolicy::ExcludeOccluder(OccluderId::new(index)))
+                .unwrap_or(SelfHitPolicy::None),
+            policy => policy
+        };
+        let miss_policy = match config.miss_policy {
+            MissPolicy::MaxDistance(max_distance) => MissPolicy::MaxDistance(max_distance.min(distance)),
+            _ => MissPolicy::MaxDistance(distance)
+        };
+
+        calculate_intersection_points(origin, &occluders, &config.clone().with_self_hit_policy(self_hit_policy).with_miss_policy(miss_policy))
+    }
+}
+
+/// Split the given occluders into the chunks of the given size. Occluders crossing the border of a chunk are cut there,
+/// and occluders lying on a border belong to the chunk above or right of it, so no part is in two chunks.
+pub fn partition(
+    occluders: &[Occluder],
+    chunk_size: f32,
+) -> HashMap<ChunkCoord, Vec<Occluder>> {
+    let world = ChunkedWorld::new(chunk_size);
+    let mut chunks = HashMap::<ChunkCoord, Vec<Occluder>>::new();
+
+    for occluder in occluders {
+        let [a, b] = occluder.segment.points();
+
+        for coord in world.chunks_in(Aabb::new(a.min(b), a.max(b))) {
+            let Some((start, end)) = world.chunk_bounds(coord).clip_segment(a, b) else {
+                continue;
+            };
+
+            if start != end && world.chunk_of((start + end) / 2.0) == coord {
+                chunks.entry(coord).or_default().push(Occluder { segment: Segment::new(start, end), ..*occluder });
+            }
+        }
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use crate::aabb::Aabb;
+    use crate::chunks::{ChunkCoord, ChunkedWorld, partition};
+    use crate::config::{RaycastConfig, SelfHitPolicy};
+    use crate::occluder::Occluder;
+    use crate::raycasting::{calculate_intersection_points, Segment};
+    use crate::scene::Scene;
+
+    #[test]
+    fn partition_cuts_occluders_at_chunk_borders() {
+        let occluders = [
+            Segment::from_coords(5.0, 5.0, 25.0, 5.0),
+            Segment::from_coords(-5.0, 10.0, 5.0, 10.0),
+            // on the border between two chunks
+            Segment::from_coords(12.0, 10.0, 18.0, 10.0),
+        ].into_iter().map(Occluder::new).collect::<Vec<_>>();
+
+        let chunks = partition(&occluders, 10.0);
+        let in_chunk = |x: i32, y: i32| chunks
+            .get(&ChunkCoord::new(x, y))
+            .map(|occluders| occluders.iter().map(|occluder| occluder.segment.points()).collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        assert_eq!(in_chunk(0, 0), vec![[Vec2::new(5.0, 5.0), Vec2::new(10.0, 5.0)]]);
+        assert_eq!(in_chunk(1, 0), vec![[Vec2::new(10.0, 5.0), Vec2::new(20.0, 5.0)]]);
+        assert_eq!(in_chunk(2, 0), vec![[Vec2::new(20.0, 5.0), Vec2::new(25.0, 5.0)]]);
+        assert_eq!(in_chunk(-1, 1), vec![[Vec2::new(-5.0, 10.0), Vec2::new(0.0, 10.0)]]);
+        assert_eq!(in_chunk(0, 1), vec![[Vec2::new(0.0, 10.0), Vec2::new(5.0, 10.0)]]);
+        assert_eq!(in_chunk(1, 1), vec![[Vec2::new(12.0, 10.0), Vec2::new(18.0, 10.0)]]);
+        assert_eq!(chunks.values().map(Vec::len).sum::<usize>(), 6);
+    }
+
+    #[test]
+    fn queries_span_chunk_borders() {
+        let occluders = Scene::parse("##########\n#........#\n#..#.....#\n#......#.#\n#.##.....#\n##########\n").unwrap().occluders(10.0);
+        let chunks = partition(&occluders, 25.0);
+        let mut world = ChunkedWorld::new(25.0);
+        chunks.iter().for_each(|(coord, occluders)| { world.load(*coord, occluders.clone()); });
+
+        let area = |points: &[Vec2]| (0..points.len()).map(|i| points[i].perp_dot(points[(i + 1) % points.len()])).sum::<f32>() / 2.0;
+        let config = RaycastConfig::default();
+
+        [Vec2::new(15.0, 15.0), Vec2::new(55.0, 35.0), Vec2::new(45.0, 25.0)].into_iter().for_each(|origin| {
+            let expected = calculate_intersection_points(origin, &occluders, &config);
+            let chunked = world.intersection_points(origin, 200.0, &config);
+            assert!((area(&expected) - area(&chunked)).abs() < 0.01, "{origin}");
+        });
+    }
+
+    #[test]
+    fn queries_end_at_the_distance_and_keep_world_ids() {
+        let mut world = ChunkedWorld::new(10.0);
+        world.load(ChunkCoord::new(0, 0), vec![Occluder::new(Segment::from_coords(2.0, 8.0, 8.0, 8.0))]);
+        let wall = world.load(ChunkCoord::new(1, 0), vec![Occluder::new(Segment::from_coords(12.0, 2.0, 12.0, 8.0))])[0];
+
+        // nothing encloses the origin, but no ray reaches further than the distance
+        let points = world.intersection_points(Vec2::new(5.0, 5.0), 30.0, &RaycastConfig::default());
+        assert!(!points.is_empty());
+        assert!(points.iter().all(|point| point.distance(Vec2::new(5.0, 5.0)) <= 30.001));
+
+        // the id of the wall stays valid, even though other chunks change
+        world.unload(ChunkCoord::new(0, 0));
+        world.load(ChunkCoord::new(0, 0), vec![Occluder::new(Segment::from_coords(2.0, 2.0, 8.0, 2.0))]);
+        assert_eq!(world.get(wall).map(|occluder| occluder.segment), Some(Segment::from_coords(12.0, 2.0, 12.0, 8.0)));
+
+        // the wall hides the other chunk, unless it is excluded
+        let origin = Vec2::new(14.0, 5.0);
+        let sees_corner = |config: &RaycastConfig| world.intersection_points(origin, 30.0, config).contains(&Vec2::new(8.0, 2.0));
+        assert!(!sees_corner(&RaycastConfig::default()));
+        assert!(sees_corner(&RaycastConfig::default().with_self_hit_policy(SelfHitPolicy::ExcludeOccluder(wall))));
+
+        world.unload(ChunkCoord::new(1, 0));
+        assert_eq!(world.get(wall), None);
+    }
+
+    #[test]
+    fn streaming_loads_and_unloads_chunks_around_the_center() {
+        let mut world = ChunkedWorld::new(10.0);
+        let mut requested = Vec::new();
+
+        let changes = world.stream(Vec2::new(5.0, 5.0), 8.0, |coord| {
+            requested.push(coord);
+            vec![Occluder::new(Segment::from_coords(1.0, 1.0, 2.0, 2.0))]
+        });
+
+        assert_eq!(changes.loaded.len(), 9);
+        assert_eq!(requested, changes.loaded);
+        assert!(changes.unloaded.is_empty());
+        assert_eq!(world.occluders_in(Aabb::from_center(Vec2::new(5.0, 5.0), Vec2::splat(8.0))).len(), 9);
+
+        // moving a bit keeps everything loaded
+        let changes = world.stream(Vec2::new(12.0, 5.0), 8.0, |_| vec![]);
+        assert_eq!(changes.loaded, vec![ChunkCoord::new(2, 0)]);
+        assert!(changes.unloaded.is_empty());
+
+        // moving far unloads the chunks left behind
+        let changes = world.stream(Vec2::new(45.0, 5.0), 8.0, |_| vec![]);
+        assert_eq!(changes.unloaded.len(), 9);
+        assert!(!world.is_loaded(ChunkCoord::new(0, 0)));
+        assert!(world.is_loaded(ChunkCoord::new(4, 0)));
+    }
+}
@@ -0,0 +1,80 @@
+use parry2d::math::{Isometry, Point};
+use parry2d::shape::SharedShape;
+use crate::visibility_polygon::VisibilityPolygon;
+
+/// The kind of shape [VisibilityPolygon::to_collider] builds.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum ColliderKind {
+    /// A compound of the convex pieces of the polygon (see [VisibilityPolygon::convex_pieces]), which covers
+    /// the whole lit area, like for a trigger zone which detects everything inside of the light.
+    #[default]
+    ConvexDecomposition,
+    /// The closed border of the polygon, which only detects things touching it.
+    Polyline,
+}
+
+impl VisibilityPolygon {
+    /// Build a parry2d shape (also used by rapier) from this polygon, in the same coordinates as its points.
+    /// Return None if the polygon has no area.
+    pub fn to_collider(&self, kind: ColliderKind) -> Option<SharedShape> {
+        let to_point = |point: &glam::Vec2| Point::new(point.x, point.y);
+
+        match kind {
+            ColliderKind::ConvexDecomposition => {
+                let shapes = self
+                    .convex_pieces()
+                    .iter()
+                    .filter_map(|piece| SharedShape::convex_polyline(piece.iter().map(to_point).collect()))
+                    .map(|shape| (Isometry::identity(), shape))
+                    .collect::<Vec<_>>();
+
+                (!shapes.is_empty()).then(|| SharedShape::compound(shapes))
+            }
+            ColliderKind::Polyline => {
+                let count = self.points().len() as u32;
+                let indices = (0..count).map(|i| [i, (i + 1) % count]).collect();
+
+                (count >= 3).then(|| SharedShape::polyline(self.points().iter().map(to_point).collect(), Some(indices)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use parry2d::math::{Isometry, Point};
+    use crate::collider::ColliderKind;
+    use crate::visibility_polygon::VisibilityPolygon;
+
+    #[test]
+    fn colliders_cover_the_lit_area() {
+        // a square room with a shadow behind a pillar on the right
+        let polygon = VisibilityPolygon::new(Vec2::ZERO, vec![
+            Vec2::new(-10.0, -10.0),
+            Vec2::new(10.0, -10.0),
+            Vec2::new(10.0, -2.0),
+            Vec2::new(5.0, -1.0),
+            Vec2::new(5.0, 1.0),
+            Vec2::new(10.0, 2.0),
+            Vec2::new(10.0, 10.0),
+            Vec2::new(-10.0, 10.0),
+        ]);
+        let area = polygon.to_collider(ColliderKind::ConvexDecomposition).unwrap();
+        let border = polygon.to_collider(ColliderKind::Polyline).unwrap();
+
+        [
+            (Vec2::new(-5.0, 5.0), true),
+            (Vec2::new(7.0, -3.0), true),
+            // in the shadow
+            (Vec2::new(8.0, 0.0), false),
+            (Vec2::new(20.0, 0.0), false),
+        ].into_iter().for_each(|(point, inside)| {
+            assert_eq!(area.contains_point(&Isometry::identity(), &Point::new(point.x, point.y)), inside, "{point}");
+            assert_eq!(polygon.contains(point), inside, "{point}");
+        });
+
+        assert!(border.distance_to_point(&Isometry::identity(), &Point::new(8.0, 0.0), true) > 0.5);
+        assert!(VisibilityPolygon::default().to_collider(ColliderKind::ConvexDecomposition).is_none());
+    }
+}
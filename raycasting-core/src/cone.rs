@@ -0,0 +1,327 @@
+use glam::*;
+use crate::aabb::Aabb;
+use crate::config::{CoordinateSystem, PolygonFormat, RaycastConfig};
+use crate::occluder::Occluder;
+use crate::raycasting::{calculate_angle, collect_endpoints, endpoint_rays, find_nearest_intersection, grazing_rays, Ray, resolve_grazing_hits, resolve_hits, Segment, sort_by_angle, Triangle};
+use crate::stats::RaycastStats;
+
+/// A field of view, limited to the directions at most half_angle (radians, below PI) away from the direction.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub struct ViewCone {
+    pub origin: Vec2,
+    pub direction: Vec2,
+    pub half_angle: f32,
+}
+
+impl ViewCone {
+    pub fn new(
+        origin: Vec2,
+        direction: Vec2,
+        half_angle: f32,
+    ) -> Self {
+        ViewCone {
+            origin,
+            direction,
+            half_angle,
+        }
+    }
+
+    /// Return the signed angle (radians) from the cone direction to the point.
+    fn relative_angle(&self, point: Vec2) -> f32 {
+        self.direction.angle_to(point - self.origin)
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        self.relative_angle(point).abs() <= self.half_angle
+    }
+
+    /// Return true if any part of the segment lies inside the cone, at any distance. Segments touching the origin do.
+    pub fn overlaps_segment(&self, segment: Segment) -> bool {
+        let [a, b] = segment.points();
+
+        if a == self.origin || b == self.origin || self.contains(a) || self.contains(b) {
+            return true;
+        }
+
+        // with both endpoints outside, the segment can only pass through the cone by crossing one of its borders
+        let length = a.distance(self.origin).max(b.distance(self.origin)) * 2.0 + 1.0;
+        [-self.half_angle, self.half_angle]
+            .map(|angle| Vec2::from_angle(angle).rotate(self.direction.normalize()))
+            .into_iter()
+            .any(|border| Segment::new(self.origin, self.origin + border * length).intersects(segment))
+    }
+
+    /// Return true if any part of the box lies inside the cone.
+    pub fn overlaps_aabb(&self, aabb: &Aabb) -> bool {
+        let corners = aabb.corners();
+
+        aabb.contains(self.origin) || (0..4).any(|i| self.overlaps_segment(Segment::new(corners[i], corners[(i + 1) % 4])))
+    }
+}
+
+/// Return the visibility polygon of the cone: the origin, followed by the hits in the cone ordered from the
+/// clockwise to the counterclockwise border. Borders which hit nothing are handled by the [crate::config::MissPolicy],
+/// and the points are returned in the [PolygonFormat] of the config, see [PolygonFormat::apply_to_cone].
+pub fn cone_intersection_points(
+    cone: &ViewCone,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+) -> Vec<Vec2> {
+    ConeTracker::default().update(cone, occluders, config, &mut RaycastStats::default())
+}
+
+/// A triangle of a cone polygon, with the angular offset (radians, counterclockwise) from the cone direction at each
+/// of its points, so shading can fade the cone towards its borders like peripheral vision.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ConeTriangle {
+    pub triangle: Triangle,
+    /// The offsets at a, b and c
+    pub offsets: [f32; 3],
+}
+
+/// Return the polygon of the cone (like the result of [cone_intersection_points]) as a triangle fan around the
+/// origin, with the angular offset from the cone direction at every point.
+///
+/// As the offsets are interpolated linearly across the triangles, the edges of the polygon are split into steps of
+/// at most max_step radians. The point at the origin gets the offset in the middle of its triangle, like in
+/// [crate::intensity::light_triangles].
+pub fn cone_triangles(
+    cone: &ViewCone,
+    points: &[Vec2],
+    max_step: f32,
+) -> Vec<ConeTriangle> {
+    let origin = cone.origin;
+    let offset = |direction: Vec2| cone.direction.angle_to(direction);
+
+    points
+        .windows(2)
+        .skip(1)
+        .flat_map(|pair| {
+            let (b, c) = (pair[0], pair[1]);
+            let angle = (b - origin).angle_to(c - origin).abs();
+            let steps = if max_step > 0.0 && angle.is_finite() { (angle / max_step).ceil().max(1.0) as usize } else { 1 };
+
+            (0..steps).map(move |step| (b.lerp(c, step as f32 / steps as f32), b.lerp(c, (step + 1) as f32 / steps as f32)))
+        })
+        .map(|(b, c)| ConeTriangle {
+            triangle: Triangle::new(origin, b, c),
+            offsets: [
+                offset((b - origin).normalize_or_zero() + (c - origin).normalize_or_zero()),
+                offset(b - origin),
+                offset(c - origin),
+            ],
+        })
+        .collect()
+}
+
+/// Computes the visibility polygon of a cone which rotates a bit every frame, like the view of a guard.
+///
+/// The hits of the rays towards endpoints only depend on the origin, not on the cone direction. So the tracker
+/// keeps them, and after a rotation, it only casts rays towards the endpoints which entered the cone, together
+/// with the two borders. Moving the origin or changing the config starts from scratch. The tracker cannot
+/// see if the occluders changed, so call [ConeTracker::invalidate] in this case.
+#[derive(Clone, Debug, Default)]
+pub struct ConeTracker {
+    origin: Option<Vec2>,
+    config: RaycastConfig,
+    /// All endpoints of the considered occluders, ordered by angle
+    endpoints: Vec<Vec2>,
+    angles: Vec<f32>,
+    /// The points each endpoint ray added to the polygon, once it was cast
+    hits: Vec<Option<Vec<Vec2>>>,
+}
+
+impl ConeTracker {
+    /// Forget all hits, so the next update recomputes the whole polygon.
+    pub fn invalidate(&mut self) {
+        self.origin = None
+    }
+
+    /// Return the visibility polygon of the cone, like [cone_intersection_points].
+    pub fn update(
+        &mut self,
+        cone: &ViewCone,
+        occluders: &[Occluder],
+        config: &RaycastConfig,
+        stats: &mut RaycastStats,
+    ) -> Vec<Vec2> {
+        if config.coordinate_system != CoordinateSystem::YUp {
+            let coordinate_system = config.coordinate_system;
+            let cone = ViewCone::new(coordinate_system.to_y_up(cone.origin), coordinate_system.to_y_up(cone.direction), cone.half_angle);
+            let occluders = occluders.iter().map(|occluder| occluder.to_y_up(coordinate_system)).collect::<Vec<_>>();
+            let config = config.clone().with_coordinate_system(CoordinateSystem::YUp);
+
+            return self.update(&cone, &occluders, &config, stats)
+                .into_iter()
+                .map(|point| coordinate_system.to_y_up(point))
+                .collect();
+        }
+
+        if config.format != PolygonFormat::default() {
+            let mut points = self.update(cone, occluders, &config.clone().with_format(PolygonFormat::default()), stats);
+            config.format.apply_to_cone(cone, &mut points);
+            return points;
+        }
+
+        if self.origin != Some(cone.origin) || self.config != *config {
+            self.reset(cone.origin, occluders, config);
+        }
+
+        let mut points = Vec::new();
+
+        for index in self.indices_in(cone) {
+            let hits = self.hits[index].get_or_insert_with(|| {
+                let hits = resolve_hits(endpoint_rays(cone.origin, &self.endpoints[index..=index]), occluders, config, stats);
+                let grazing_hits = resolve_grazing_hits(grazing_rays(&hits, occluders, config), occluders, config, stats);
+                hits.into_iter().map(|hit| hit.point).chain(grazing_hits).collect()
+            });
+
+            points.extend(hits.iter().filter(|point| cone.contains(**point)));
+        }
+
+        points.sort_by(|p0, p1| cone.relative_angle(*p0).total_cmp(&cone.relative_angle(*p1)));
+
+        let mut border = |angle: f32| {
+            let direction = Vec2::from_angle(angle).rotate(cone.direction);
+
+            match find_nearest_intersection(Ray::new(cone.origin, direction), occluders, config, stats) {
+                Some((point, _)) => Some(point),
+                None => config.miss_policy.far_point(cone.origin, direction)
+            }
+        };
+        let start = border(-cone.half_angle);
+        let end = border(cone.half_angle);
+
+        [cone.origin].into_iter()
+            .chain(start)
+            .chain(points)
+            .chain(end)
+            .collect()
+    }
+
+    fn reset(
+        &mut self,
+        origin: Vec2,
+        occluders: &[Occluder],
+        config: &RaycastConfig,
+    ) {
+        self.origin = Some(origin);
+        self.config = config.clone();
+        self.endpoints = collect_endpoints(origin, occluders, config);
+        sort_by_angle(origin, &mut self.endpoints);
+        self.angles = self.endpoints.iter().map(|endpoint| calculate_angle(origin, *endpoint)).collect();
+        self.hits = vec![None; self.endpoints.len()];
+    }
+
+    /// Return the indices of the endpoints inside the cone, ordered from its clockwise to its counterclockwise border.
+    fn indices_in(&self, cone: &ViewCone) -> Vec<usize> {
+        let center = calculate_angle(Vec2::ZERO, cone.direction);
+        let half_angle = cone.half_angle.to_degrees();
+        let (start, end) = (center - half_angle, center + half_angle);
+        let range = |from: f32, to: f32| self.angles.partition_point(|angle| *angle < from)..self.angles.partition_point(|angle| *angle <= to);
+
+        // the angles run from -180 to 180 degrees, so a cone across the negative x axis covers two ranges
+        if start < -180.0 {
+            range(start + 360.0, 180.0).chain(range(-180.0, end)).collect()
+        } else if end > 180.0 {
+            range(start, 180.0).chain(range(-180.0, end - 360.0)).collect()
+        } else {
+            range(start, end).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+    use std::fs;
+    use std::path::PathBuf;
+    use glam::*;
+    use crate::aabb::Aabb;
+    use crate::cone::{cone_intersection_points, cone_triangles, ConeTracker, ViewCone};
+    use crate::config::RaycastConfig;
+    use crate::occluder::Occluder;
+    use crate::pathological::pathological_scenes;
+    use crate::raycasting::Segment;
+    use crate::scene::Scene;
+    use crate::stats::RaycastStats;
+
+    #[test]
+    fn rotating_cone_matches_full_recomputation() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures").join("scenes").join("example.map");
+        let occluders = Scene::parse(&fs::read_to_string(path).unwrap()).unwrap().occluders(32.0);
+        let config = RaycastConfig::default();
+        let origin = Vec2::new(300.0, 300.0);
+        let mut tracker = ConeTracker::default();
+        let mut incremental_rays = 0;
+        let mut full_rays = 0;
+
+        // a full turn in steps of 3 degrees, crossing the negative x axis
+        for step in 0..120 {
+            let cone = ViewCone::new(origin, Vec2::from_angle((step * 3) as f32 * PI / 180.0), PI / 6.0);
+
+            let mut stats = RaycastStats::default();
+            let points = tracker.update(&cone, &occluders, &config, &mut stats);
+            incremental_rays += stats.rays_cast;
+
+            let mut stats = RaycastStats::default();
+            let expected = ConeTracker::default().update(&cone, &occluders, &config, &mut stats);
+            full_rays += stats.rays_cast;
+
+            assert_eq!(points, expected, "step {step}");
+            assert_eq!(points, cone_intersection_points(&cone, &occluders, &config));
+        }
+
+        assert!(incremental_rays * 3 < full_rays, "{incremental_rays} rays are not much less than {full_rays}");
+    }
+
+    #[test]
+    fn cone_only_contains_points_inside() {
+        let occluders = pathological_scenes().into_iter().find(|scene| scene.name == "t_junction").unwrap().occluders;
+        let cone = ViewCone::new(Vec2::new(-30.0, 0.0), Vec2::NEG_Y, PI / 4.0);
+        let points = cone_intersection_points(&cone, &occluders, &RaycastConfig::default());
+
+        assert_eq!(points[0], cone.origin);
+        assert!(points.len() >= 3);
+        points.iter().skip(1).for_each(|point| assert!(cone.relative_angle(*point).abs() <= cone.half_angle + 0.0001, "{point}"));
+    }
+
+    #[test]
+    fn cone_triangles_carry_the_offset_from_the_direction() {
+        let occluders = [Segment::from_coords(10.0, -20.0, 10.0, 20.0)].into_iter().map(Occluder::new).collect::<Vec<_>>();
+        let cone = ViewCone::new(Vec2::ZERO, Vec2::X, PI / 4.0);
+        let points = cone_intersection_points(&cone, &occluders, &RaycastConfig::default());
+        let triangles = cone_triangles(&cone, &points, 0.1);
+
+        // the cone is split into steps of at most 0.1 radians
+        assert!(triangles.len() >= 16);
+        assert!(triangles.iter().all(|cone_triangle| cone_triangle.triangle.area() > 0.0));
+        triangles.iter().for_each(|cone_triangle| {
+            let [_, b, c] = cone_triangle.offsets;
+            assert!(b < c && b >= -PI / 4.0 - 0.0001 && c <= PI / 4.0 + 0.0001, "{cone_triangle:?}");
+        });
+        assert!((triangles[0].offsets[1] + PI / 4.0).abs() < 0.0001);
+        assert!((triangles.last().unwrap().offsets[2] - PI / 4.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn cone_overlaps_segments_inside_or_across_it() {
+        let cone = ViewCone::new(Vec2::ZERO, Vec2::X, PI / 4.0);
+
+        [
+            (Segment::from_coords(10.0, -1.0, 10.0, 1.0), true),
+            // across the whole cone, with both endpoints outside
+            (Segment::from_coords(10.0, -20.0, 10.0, 20.0), true),
+            // across one border
+            (Segment::from_coords(5.0, 2.0, 5.0, 20.0), true),
+            (Segment::from_coords(0.0, 0.0, -10.0, 0.0), true),
+            (Segment::from_coords(-10.0, -20.0, -10.0, 20.0), false),
+            (Segment::from_coords(1.0, 2.0, 1.0, 20.0), false),
+        ].into_iter().for_each(|(segment, expected)| assert_eq!(cone.overlaps_segment(segment), expected, "{segment}"));
+
+        assert!(cone.overlaps_aabb(&Aabb::new(Vec2::new(-1.0, -1.0), Vec2::new(1.0, 1.0))));
+        assert!(cone.overlaps_aabb(&Aabb::new(Vec2::new(5.0, -100.0), Vec2::new(6.0, 100.0))));
+        assert!(!cone.overlaps_aabb(&Aabb::new(Vec2::new(-6.0, -100.0), Vec2::new(-5.0, 100.0))));
+    }
+}
@@ -0,0 +1,389 @@
+use glam::*;
+use crate::aabb::Aabb;
+use crate::cone::ViewCone;
+use crate::occluder::{ALL_LAYERS, Occluder, OccluderId};
+use crate::visibility_polygon::VisibilityPolygon;
+
+/// Settings of a single raycast query.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub struct RaycastConfig {
+    pub self_hit_policy: SelfHitPolicy,
+    /// Only occluders on at least one of these layers block rays.
+    pub layers: u32,
+    /// Occluders in these groups are ignored, see [crate::groups::OccluderGroups::mask].
+    pub disabled_groups: u64,
+    pub algorithm: Algorithm,
+    pub peek_policy: PeekPolicy,
+    pub miss_policy: MissPolicy,
+    pub coordinate_system: CoordinateSystem,
+    /// How the points of [crate::raycasting::calculate_intersection_points] and of the cone queries (see
+    /// [PolygonFormat::apply_to_cone]) are returned. Queries building on the polygon, like
+    /// [crate::raycasting::raycast_with_config], ignore it.
+    pub format: PolygonFormat,
+    pub symmetry_policy: SymmetryPolicy,
+    /// The elevation of the observer. If set, only occluders which block at this elevation are considered,
+    /// see [crate::occluder::Occluder::with_z_range].
+    pub height: Option<f32>,
+    /// Cast at most this many rays towards endpoints, to bound the cost of a query over huge or user generated
+    /// scenes, see [crate::raycasting::limit_endpoints]. Each of them may add up to two rays beside its endpoint.
+    /// Only the naive algorithm casts rays, the sweep ignores the budget.
+    pub max_rays: Option<usize>,
+    /// Cast this many rays at uniform angles in addition to the endpoint rays, see [crate::raycasting::uniform_rays].
+    /// Together with [RaycastConfig::max_rays], this trades exactness for a stable cost: no budget and no uniform
+    /// rays is exact, a budget of 0 with uniform rays samples the scene at a fixed cost, but misses gaps thinner
+    /// than the angle between two rays. Only the naive algorithm casts rays, the sweep ignores it.
+    pub uniform_rays: usize,
+}
+
+impl Default for RaycastConfig {
+    fn default() -> Self {
+        Self {
+            self_hit_policy: SelfHitPolicy::default(),
+            layers: ALL_LAYERS,
+            disabled_groups: 0,
+            algorithm: Algorithm::default(),
+            peek_policy: PeekPolicy::default(),
+            miss_policy: MissPolicy::default(),
+            coordinate_system: CoordinateSystem::default(),
+            format: PolygonFormat::default(),
+            symmetry_policy: SymmetryPolicy::default(),
+            height: None,
+            max_rays: None,
+            uniform_rays: 0,
+        }
+    }
+}
+
+impl RaycastConfig {
+    /// Create a config which only considers occluders on the given layers.
+    pub fn layers(layers: u32) -> Self {
+        RaycastConfig::default().with_layers(layers)
+    }
+
+    pub fn with_layers(mut self, layers: u32) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    pub fn with_self_hit_policy(mut self, self_hit_policy: SelfHitPolicy) -> Self {
+        self.self_hit_policy = self_hit_policy;
+        self
+    }
+
+    /// Ignore the occluders in the groups of the given mask, in addition to the already disabled ones.
+    pub fn without_groups(mut self, groups: u64) -> Self {
+        self.disabled_groups |= groups;
+        self
+    }
+
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    pub fn with_peek_policy(mut self, peek_policy: PeekPolicy) -> Self {
+        self.peek_policy = peek_policy;
+        self
+    }
+
+    pub fn with_miss_policy(mut self, miss_policy: MissPolicy) -> Self {
+        self.miss_policy = miss_policy;
+        self
+    }
+
+    pub fn with_coordinate_system(mut self, coordinate_system: CoordinateSystem) -> Self {
+        self.coordinate_system = coordinate_system;
+        self
+    }
+
+    pub fn with_format(mut self, format: PolygonFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_symmetry_policy(mut self, symmetry_policy: SymmetryPolicy) -> Self {
+        self.symmetry_policy = symmetry_policy;
+        self
+    }
+
+    /// Look from the given elevation, so a 2.5D game can share one set of occluders between its floors.
+    pub fn with_height(mut self, height: f32) -> Self {
+        self.height = Some(height);
+        self
+    }
+
+    /// Cast at most the given number of rays towards endpoints, see [RaycastConfig::max_rays].
+    pub fn with_max_rays(mut self, max_rays: usize) -> Self {
+        self.max_rays = Some(max_rays);
+        self
+    }
+
+    /// Cast the given number of rays at uniform angles in addition to the endpoint rays, see [RaycastConfig::uniform_rays].
+    pub fn with_uniform_rays(mut self, uniform_rays: usize) -> Self {
+        self.uniform_rays = uniform_rays;
+        self
+    }
+
+    /// Return the algorithm to use for a query over the given number of occluders.
+    pub fn resolve_algorithm(&self, occluder_count: usize) -> Algorithm {
+        match self.algorithm {
+            Algorithm::Auto if occluder_count < SWEEP_THRESHOLD
+                || matches!(self.self_hit_policy, SelfHitPolicy::IgnoreNear(_))
+                || self.peek_policy == PeekPolicy::Peek
+                || self.max_rays.is_some()
+                || self.uniform_rays > 0 => Algorithm::Naive,
+            Algorithm::Auto => Algorithm::Sweep,
+            algorithm => algorithm
+        }
+    }
+
+    /// Return true if the given occluder takes part in a query with this config.
+    /// Degenerate occluders (see [crate::raycasting::Segment::validate]) never do, so they cannot spoil a query.
+    pub fn considers(&self, id: OccluderId, occluder: &Occluder) -> bool {
+        occluder.segment.validate().is_ok()
+            && occluder.is_on_layers(self.layers)
+            && occluder.is_enabled(self.disabled_groups)
+            && self.self_hit_policy.allows_occluder(id)
+            && self.height.map(|height| occluder.blocks_at(height)).unwrap_or(true)
+    }
+}
+
+/// From this number of occluders on, [Algorithm::Auto] uses the sweep.
+pub const SWEEP_THRESHOLD: usize = 128;
+
+/// The algorithm which computes the visibility polygon.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub enum Algorithm {
+    /// The sweep for large inputs, the naive algorithm for small ones, if hits near the origin must be ignored,
+    /// if the view may peek through corners, if the rays are limited by [RaycastConfig::max_rays] or if
+    /// [RaycastConfig::uniform_rays] are cast.
+    #[default]
+    Auto,
+    /// Cast a ray to every endpoint and test it against every occluder. O(n²), but simple and battle tested.
+    Naive,
+    /// Rotate a ray around the origin and keep the occluders it crosses ordered by distance. O(n log n),
+    /// see [crate::sweep::sweep_intersection_points]. The view always stops at corners, regardless of the [PeekPolicy].
+    Sweep,
+}
+
+/// Decides whether the view passes a point where occluders from both sides of the ray meet, like the touching
+/// corners of two diagonally adjacent wall tiles (see [crate::raycasting::EndpointKind::Both]).
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub enum PeekPolicy {
+    /// The view stops at such points.
+    #[default]
+    Block,
+    /// The view continues right through such points. As the gap has no width, this only adds a spike of
+    /// zero width to the polygon, which reaches whatever lies behind the point.
+    Peek,
+}
+
+/// Decides whether point to point visibility checks (see [crate::visibility_events::can_see]) may be asymmetric.
+///
+/// The visibility polygon only approximates the visible area: rays pass corners at a small angle and the
+/// [PeekPolicy] treats touching corners differently depending on the side they are seen from. So a point may
+/// lie in the polygon of another one, but not the other way around. The grid queries in [crate::tiles] and
+/// [crate::shadowcasting] are always symmetric between floor tiles.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub enum SymmetryPolicy {
+    /// A target is visible if it lies in the visibility polygon of the viewer.
+    #[default]
+    Asymmetric,
+    /// A target is visible if it lies in the visibility polygon of the viewer and the viewer lies in the
+    /// visibility polygon of the target. This costs a second query.
+    Symmetric,
+}
+
+/// The direction of the y axis in the coordinates of occluders, origins and results.
+///
+/// Everything which depends on the orientation follows the coordinate system: with [CoordinateSystem::YDown],
+/// results are ordered counterclockwise as seen on a screen and positive angles rotate counterclockwise as
+/// seen on a screen. Internally, queries convert the occluders to y up and the results back, which costs a copy
+/// of the occluders per query.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub enum CoordinateSystem {
+    /// y grows upwards, like in Bevy and in maths
+    #[default]
+    YUp,
+    /// y grows downwards, like on screens and in images
+    YDown,
+}
+
+impl CoordinateSystem {
+    /// Convert a point between this coordinate system and [CoordinateSystem::YUp], in both directions.
+    pub fn to_y_up(self, point: Vec2) -> Vec2 {
+        match self {
+            CoordinateSystem::YUp => point,
+            CoordinateSystem::YDown => Vec2::new(point.x, -point.y)
+        }
+    }
+}
+
+/// The order in which the points of a visibility polygon are returned. Renderers and physics engines disagree
+/// about this, so it can be chosen instead of post processing every result.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub enum Winding {
+    /// Ordered by ascending angle, as seen in the [CoordinateSystem] of the query
+    #[default]
+    CounterClockwise,
+    /// Ordered by descending angle
+    Clockwise,
+}
+
+/// How the points of a visibility polygon are returned.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub struct PolygonFormat {
+    pub winding: Winding,
+    /// If true, the first point is repeated at the end.
+    pub closed: bool,
+    /// If set, the boundary is resampled at this many uniform angles, so the polygon always has exactly this
+    /// many points (plus the repeated one if closed), see [crate::visibility_polygon::VisibilityPolygon::resample].
+    /// The result is no longer exact, but can be streamed into buffers of a fixed size or interpolated between frames.
+    pub resolution: Option<usize>,
+}
+
+impl PolygonFormat {
+    pub fn new(winding: Winding, closed: bool) -> Self {
+        PolygonFormat { winding, closed, resolution: None }
+    }
+
+    /// Resample the boundary at the given number of uniform angles, see [PolygonFormat::resolution].
+    pub fn with_resolution(mut self, resolution: usize) -> Self {
+        self.resolution = Some(resolution);
+        self
+    }
+
+    /// Bring the points of a polygon around the origin, ordered counterclockwise and not closed, into this format.
+    pub fn apply(&self, origin: Vec2, points: &mut Vec<Vec2>) {
+        if let Some(resolution) = self.resolution {
+            *points = VisibilityPolygon::new(origin, std::mem::take(points)).resample(resolution);
+        }
+
+        if self.winding == Winding::Clockwise {
+            points.reverse();
+        }
+
+        if self.closed {
+            if let Some(first) = points.first() {
+                points.push(*first);
+            }
+        }
+    }
+
+    /// Bring the points of a cone polygon, the origin of the cone followed by the points ordered from its clockwise
+    /// to its counterclockwise border, into this format. The origin stays first: the resolution resamples the
+    /// boundary at uniform angles from border to border, the winding reverses the points after the origin,
+    /// and a closed polygon repeats the origin.
+    pub fn apply_to_cone(&self, cone: &ViewCone, points: &mut Vec<Vec2>) {
+        if let Some(resolution) = self.resolution {
+            let polygon = VisibilityPolygon::new(cone.origin, std::mem::take(points));
+            let direction = cone.direction.y.atan2(cone.direction.x);
+            let step = if resolution > 1 { 2.0 * cone.half_angle / (resolution - 1) as f32 } else { 0.0 };
+            let first = if resolution > 1 { direction - cone.half_angle } else { direction };
+
+            *points = [cone.origin].into_iter()
+                .chain((0..resolution)
+                    .map(|i| first + step * i as f32)
+                    .map(|angle| cone.origin + Vec2::from_angle(angle) * polygon.raycast(angle).unwrap_or(0.0)))
+                .collect();
+        }
+
+        if self.winding == Winding::Clockwise && points.len() > 1 {
+            points[1..].reverse();
+        }
+
+        if self.closed {
+            if let Some(first) = points.first() {
+                points.push(*first);
+            }
+        }
+    }
+}
+
+/// The distance of the points [MissPolicy::Unbounded] places, far beyond any scene.
+pub const UNBOUNDED_DISTANCE: f32 = 1.0e6;
+
+/// Decides what happens with a ray which hits no occluder, which happens in scenes without bounds.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub enum MissPolicy {
+    /// The ray adds no point. In an open scene, this cuts off the parts of the polygon which reach into the open.
+    #[default]
+    Drop,
+    /// The ray ends at the given distance from the origin.
+    MaxDistance(f32),
+    /// The ray ends where it leaves the given bounds. Rays from origins outside the bounds add no point.
+    Bounds(Aabb),
+    /// The ray ends at [UNBOUNDED_DISTANCE], which marks the sectors next to it as open, see [MissPolicy::is_unbounded].
+    Unbounded,
+}
+
+impl MissPolicy {
+    /// Return the point where a ray from the origin in the given direction which hit nothing ends, if it adds one.
+    pub fn far_point(
+        &self,
+        origin: Vec2,
+        direction: Vec2,
+    ) -> Option<Vec2> {
+        let direction = direction.normalize_or_zero();
+
+        match self {
+            MissPolicy::Drop => None,
+            MissPolicy::MaxDistance(distance) => Some(origin + direction * *distance),
+            MissPolicy::Bounds(bounds) => {
+                let farthest_corner = bounds.corners().into_iter().map(|corner| corner.distance(origin)).fold(0.0, f32::max);
+                bounds.clip_segment(origin, origin + direction * (farthest_corner + 1.0)).map(|(_, exit)| exit)
+            }
+            MissPolicy::Unbounded => Some(origin + direction * UNBOUNDED_DISTANCE)
+        }
+    }
+
+    /// Return true if the point of a visibility polygon around the origin was placed by [MissPolicy::Unbounded].
+    pub fn is_unbounded(origin: Vec2, point: Vec2) -> bool {
+        origin.distance(point) >= UNBOUNDED_DISTANCE * 0.999
+    }
+}
+
+/// Decides which hits are ignored when the origin of a query lies on (or right next to) an occluder,
+/// like a torch mounted on a wall.
+///
+/// Without such a policy, every ray starting on the wall hits it at distance 0, which collapses the
+/// resulting polygon to the origin.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub enum SelfHitPolicy {
+    /// Every hit counts, even at distance 0.
+    #[default]
+    None,
+    /// Ignore hits which are closer to the origin than the given epsilon.
+    IgnoreNear(f32),
+    /// Ignore the given occluder completely. It neither blocks rays nor casts rays to its endpoints.
+    ///
+    /// Queries over slices identify their occluders by ids of generation 0 (see [OccluderId::new]), so an id of
+    /// another generation excludes nothing there. Queries of [crate::occluder_set::OccluderSet] take the ids of the
+    /// set instead and ignore stale ones.
+    ExcludeOccluder(OccluderId),
+}
+
+impl SelfHitPolicy {
+    /// Return true if the given occluder can be hit at all under this policy.
+    /// The whole id is compared, so an id with another generation does not exclude the occluder.
+    pub fn allows_occluder(&self, id: OccluderId) -> bool {
+        !matches!(self, SelfHitPolicy::ExcludeOccluder(excluded) if *excluded == id)
+    }
+
+    /// Return true if a hit at the given distance from the origin counts under this policy.
+    pub fn allows_distance(&self, distance: f32) -> bool {
+        match self {
+            SelfHitPolicy::IgnoreNear(epsilon) => distance >= *epsilon,
+            _ => true
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use glam::*;
+use crate::aabb::Aabb;
+use crate::occluder::Occluder;
+
+/// The lit and shadowed parts of some bounds under a directional light, as quads.
+/// Their winding depends on the direction of the light.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DirectionalLighting {
+    pub lit: Vec<[Vec2; 4]>,
+    pub shadow: Vec<[Vec2; 4]>,
+}
+
+/// Light the bounds with parallel rays travelling in the given direction, like the sun.
+///
+/// The bounds are cut into slabs parallel to the direction, one between every two neighbouring
+/// occluder endpoints (and bounds corners). Inside a slab, the order of the occluders along the direction
+/// does not change, so everything before the first occluder is lit and everything after it is in shadow.
+///
+/// Occluders crossing each other are not supported and may produce wrong shadows.
+pub fn raycast_directional(
+    direction: Vec2,
+    bounds: Aabb,
+    occluders: &[Occluder],
+) -> DirectionalLighting {
+    let mut lighting = DirectionalLighting::default();
+    let direction = match direction.try_normalize() {
+        Some(direction) => direction,
+        None => return lighting
+    };
+    let normal = direction.perp();
+
+    let segments = occluders
+        .iter()
+        .filter_map(|occluder| {
+            let [a, b] = occluder.segment.points();
+            bounds.clip_segment(a, b)
+        })
+        .collect::<Vec<_>>();
+
+    let mut offsets = segments
+        .iter()
+        .flat_map(|(a, b)| [a.dot(normal), b.dot(normal)])
+        .chain(bounds.corners().map(|corner| corner.dot(normal)))
+        .collect::<Vec<_>>();
+    offsets.sort_by(f32::total_cmp);
+    offsets.dedup();
+
+    let half_length = (bounds.max - bounds.min).length();
+    let center_along = bounds.center().dot(direction);
+    // where the line with the given offset enters and leaves the bounds
+    let crossing = |offset: f32| {
+        let middle = normal * offset + direction * center_along;
+        bounds.clip_segment(middle - direction * half_length, middle + direction * half_length)
+    };
+    // where the line with the given offset crosses the line through a and b
+    let hit = |offset: f32, (a, b): (Vec2, Vec2)| {
+        let (offset_a, offset_b) = (a.dot(normal), b.dot(normal));
+        a.lerp(b, (offset - offset_a) / (offset_b - offset_a))
+    };
+
+    for pair in offsets.windows(2) {
+        let (offset_0, offset_1) = (pair[0], pair[1]);
+        let middle = (offset_0 + offset_1) / 2.0;
+
+        let (enter_0, exit_0) = match crossing(offset_0) {
+            Some(crossing) => crossing,
+            None => continue
+        };
+        let (enter_1, exit_1) = match crossing(offset_1) {
+            Some(crossing) => crossing,
+            None => continue
+        };
+
+        let first = segments
+            .iter()
+            .filter(|(a, b)| {
+                let (offset_a, offset_b) = (a.dot(normal), b.dot(normal));
+                offset_a.min(offset_b) < middle && middle < offset_a.max(offset_b)
+            })
+            .min_by(|s0, s1| hit(middle, **s0).dot(direction).total_cmp(&hit(middle, **s1).dot(direction)));
+
+        match first {
+            Some(segment) => {
+                let (hit_0, hit_1) = (hit(offset_0, *segment), hit(offset_1, *segment));
+                lighting.lit.push([enter_0, hit_0, hit_1, enter_1]);
+                lighting.shadow.push([hit_0, exit_0, exit_1, hit_1]);
+            }
+            None => lighting.lit.push([enter_0, exit_0, exit_1, enter_1])
+        }
+    }
+
+    lighting
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use crate::aabb::Aabb;
+    use crate::directional::raycast_directional;
+    use crate::occluder::Occluder;
+    use crate::raycasting::Segment;
+
+    fn area(quads: &[[Vec2; 4]]) -> f32 {
+        quads
+            .iter()
+            .map(|quad| (0..4).map(|i| quad[i].perp_dot(quad[(i + 1) % 4])).sum::<f32>().abs() / 2.0)
+            .sum()
+    }
+
+    #[test]
+    fn raycast_directional_works() {
+        let bounds = Aabb::new(Vec2::ZERO, Vec2::new(10.0, 10.0));
+        let occluders = [Occluder::new(Segment::from_coords(2.0, 5.0, 4.0, 5.0))];
+
+        [
+            (Vec2::new(0.0, -1.0), 10.0),
+            (Vec2::new(1.0, -1.0), 10.0),
+            (Vec2::new(0.0, 1.0), 10.0),
+            // parallel to the occluder
+            (Vec2::new(1.0, 0.0), 0.0),
+        ].into_iter().for_each(|(direction, shadow)| {
+            let lighting = raycast_directional(direction, bounds, &occluders);
+
+            assert!((area(&lighting.shadow) - shadow).abs() < 0.001, "{direction}: shadow {}", area(&lighting.shadow));
+            assert!((area(&lighting.lit) - (100.0 - shadow)).abs() < 0.001, "{direction}: lit {}", area(&lighting.lit));
+        });
+
+        let lighting = raycast_directional(Vec2::new(0.0, -1.0), bounds, &[]);
+        assert!(lighting.shadow.is_empty());
+        assert!((area(&lighting.lit) - 100.0).abs() < 0.001);
+    }
+}
@@ -0,0 +1,104 @@
+use glam::*;
+use crate::occluder_set::OccluderSet;
+use crate::raster::Grid;
+
+/// The distance to the nearest occluder, sampled at the centers of a grid of square cells.
+///
+/// Coarse, but cheap to query every frame, for example to steer units away from walls.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DistanceField {
+    grid: Grid<f32>,
+    /// The revision of the occluders the field was sampled from
+    revision: u64,
+}
+
+impl DistanceField {
+    /// Sample the distance to the occluders of the set at every cell center.
+    /// If the set is empty, every distance is infinite.
+    pub fn sample(
+        occluders: &OccluderSet,
+        origin: Vec2,
+        cell_size: f32,
+        width: usize,
+        height: usize,
+    ) -> Self {
+        let mut grid = Grid::new(origin, cell_size, width, height);
+        grid.update(|center, distance| *distance = occluders
+            .closest_point(center)
+            .map(|closest| closest.distance)
+            .unwrap_or(f32::INFINITY));
+
+        DistanceField {
+            grid,
+            revision: occluders.revision(),
+        }
+    }
+
+    /// Return true if the field was sampled from the current state of the given occluders, so it does not need to be sampled again.
+    pub fn is_valid_for(&self, occluders: &OccluderSet) -> bool {
+        self.revision == occluders.revision()
+    }
+
+    /// Return the sampled distances, one per cell.
+    pub fn grid(&self) -> &Grid<f32> {
+        &self.grid
+    }
+
+    /// Return the distance at the given world position, interpolated bilinearly between the
+    /// surrounding cell centers. Positions outside the field are clamped to its border.
+    pub fn distance_at(&self, point: Vec2) -> f32 {
+        let grid = &self.grid;
+
+        if grid.width() == 0 || grid.height() == 0 {
+            return f32::INFINITY;
+        }
+
+        let max = Vec2::new(grid.width() as f32 - 1.0, grid.height() as f32 - 1.0);
+        let cell = ((point - grid.origin()) / grid.cell_size() - 0.5).clamp(Vec2::ZERO, max);
+        let (x0, y0) = (cell.x as usize, cell.y as usize);
+        let (x1, y1) = ((x0 + 1).min(grid.width() - 1), (y0 + 1).min(grid.height() - 1));
+        let fraction = cell - Vec2::new(x0 as f32, y0 as f32);
+
+        let value = |x: usize, y: usize| grid.get(x, y).unwrap_or(f32::INFINITY);
+        let bottom = value(x0, y0) + (value(x1, y0) - value(x0, y0)) * fraction.x;
+        let top = value(x0, y1) + (value(x1, y1) - value(x0, y1)) * fraction.x;
+
+        bottom + (top - bottom) * fraction.y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use crate::distance_field::DistanceField;
+    use crate::occluder::Occluder;
+    use crate::occluder_set::OccluderSet;
+    use crate::raycasting::Segment;
+
+    #[test]
+    fn sample_works() {
+        // a single wall along the y axis
+        let set = OccluderSet::new(vec![Occluder::new(Segment::from_coords(0.0, -100.0, 0.0, 100.0))], 10.0);
+        let field = DistanceField::sample(&set, Vec2::ZERO, 2.0, 4, 3);
+
+        for y in 0..3 {
+            for x in 0..4 {
+                assert_eq!(field.grid().get(x, y), Some(x as f32 * 2.0 + 1.0), "cell ({x}, {y})")
+            }
+        }
+        assert_eq!(field.grid().get(4, 0), None);
+
+        [
+            (Vec2::new(1.0, 1.0), 1.0),
+            (Vec2::new(2.0, 3.0), 2.0),
+            (Vec2::new(6.5, 5.0), 6.5),
+            (Vec2::new(-10.0, 1.0), 1.0),
+            (Vec2::new(100.0, 1.0), 7.0),
+        ].into_iter().for_each(|(point, expected)| assert_eq!(field.distance_at(point), expected, "{point}"));
+
+        let mut changed = set.snapshot();
+        assert!(field.is_valid_for(&changed));
+        changed.push(Occluder::new(Segment::from_coords(5.0, -100.0, 5.0, 100.0)));
+        assert!(!field.is_valid_for(&changed));
+    }
+}
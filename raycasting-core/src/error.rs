@@ -0,0 +1,153 @@
+use glam::*;
+use thiserror::Error;
+use crate::config::{MissPolicy, RaycastConfig, SelfHitPolicy};
+use crate::occluder::{Occluder, OccluderId};
+use crate::raycasting::{calculate_intersection_points, SegmentError};
+use crate::scene::SceneError;
+
+/// Everything which can make a query, or one of the APIs around it, fail instead of returning a useful result.
+#[derive(Debug, Error)]
+pub enum RaycastError {
+    /// The occluder has non-finite coordinates or no length
+    #[error("occluder {} is degenerate", id.index)]
+    DegenerateSegment {
+        id: OccluderId,
+        #[source]
+        error: SegmentError,
+    },
+    /// No occluder takes part in the query and misses are dropped, so the polygon would be empty
+    #[error("no occluder takes part in the query")]
+    EmptyScene,
+    /// The origin is not finite or lies outside of the bounds of [MissPolicy::Bounds]
+    #[error("the origin {origin} lies out of bounds")]
+    OriginOutOfBounds { origin: Vec2 },
+    /// The origin lies on an occluder which blocks it, so the polygon would collapse to the origin.
+    /// A [SelfHitPolicy] avoids this.
+    #[error("the origin {origin} lies on occluder {}", id.index)]
+    OriginInsideOccluder { origin: Vec2, id: OccluderId },
+    /// The query produced a non-finite point, even though its input was valid
+    #[error("the query produced the non-finite point {point}")]
+    NumericalFailure { point: Vec2 },
+    /// A segment could not be created, see [Segment::try_new](crate::raycasting::Segment::try_new)
+    #[error("the segment is degenerate")]
+    Segment(#[from] SegmentError),
+    /// A scene could not be parsed, see [Scene::parse](crate::scene::Scene::parse)
+    #[error("the scene could not be parsed")]
+    Scene(#[from] SceneError),
+    /// A file, like a recording (see [replay](crate::replay::replay)), could not be read or written
+    #[error("the file could not be read or written")]
+    Io(#[from] std::io::Error),
+    /// A recording contains data which no [QueryRecorder](crate::replay::QueryRecorder) writes
+    #[error("the recording is invalid: {reason}")]
+    InvalidRecording { reason: String },
+    /// The outline of a polygon could not be tessellated, see
+    /// [VisibilityPolygon::stroke_outline](crate::visibility_polygon::VisibilityPolygon::stroke_outline)
+    #[cfg(feature = "lyon")]
+    #[error("the outline could not be tessellated")]
+    Tessellation(#[from] lyon_tessellation::TessellationError),
+}
+
+/// Check that a query from the origin gives a useful result, instead of silently returning a collapsed,
+/// empty or non-finite polygon.
+pub fn validate_query(
+    origin: Vec2,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+) -> Result<(), RaycastError> {
+    let bounded = match config.miss_policy {
+        MissPolicy::Bounds(bounds) => bounds.contains(origin),
+        _ => true
+    };
+
+    if !origin.is_finite() || !bounded {
+        return Err(RaycastError::OriginOutOfBounds { origin });
+    }
+
+    for (id, occluder) in occluders.iter().enumerate() {
+        if let Err(error) = occluder.segment.validate() {
+            return Err(RaycastError::DegenerateSegment { id: OccluderId::new(id), error });
+        }
+    }
+
+    let mut considered = occluders
+        .iter()
+        .enumerate()
+        .filter(|(id, occluder)| config.considers(OccluderId::new(*id), occluder))
+        .peekable();
+
+    if considered.peek().is_none() && config.miss_policy == MissPolicy::Drop {
+        return Err(RaycastError::EmptyScene);
+    }
+
+    // ignoring near hits keeps the polygon from collapsing, and excluded occluders are not considered anyway
+    if !matches!(config.self_hit_policy, SelfHitPolicy::IgnoreNear(_)) {
+        if let Some((id, _)) = considered.find(|(_, occluder)| occluder.segment.contains_point(origin)) {
+            return Err(RaycastError::OriginInsideOccluder { origin, id: OccluderId::new(id) });
+        }
+    }
+
+    Ok(())
+}
+
+/// Like [calculate_intersection_points], but fail if the query is invalid (see [validate_query]) or its result is not finite.
+pub fn try_calculate_intersection_points(
+    origin: Vec2,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+) -> Result<Vec<Vec2>, RaycastError> {
+    validate_query(origin, occluders, config)?;
+    let points = calculate_intersection_points(origin, occluders, config);
+
+    match points.iter().find(|point| !point.is_finite()) {
+        Some(point) => Err(RaycastError::NumericalFailure { point: *point }),
+        None => Ok(points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use crate::aabb::Aabb;
+    use crate::config::{MissPolicy, RaycastConfig, SelfHitPolicy};
+    use crate::error::{RaycastError, try_calculate_intersection_points};
+    use crate::occluder::{Occluder, OccluderId};
+    use crate::raycasting::{calculate_intersection_points, Segment, SegmentError};
+
+    #[test]
+    fn invalid_queries_fail() {
+        let wall = Occluder::new(Segment::from_coords(-10.0, 10.0, 10.0, 10.0));
+        let config = RaycastConfig::default();
+
+        [
+            (Vec2::ZERO, vec![wall], config.clone(), None),
+            (Vec2::new(f32::NAN, 0.0), vec![wall], config.clone(), Some(RaycastError::OriginOutOfBounds { origin: Vec2::new(f32::NAN, 0.0) })),
+            (
+                Vec2::new(100.0, 0.0),
+                vec![wall],
+                config.clone().with_miss_policy(MissPolicy::Bounds(Aabb::new(Vec2::splat(-50.0), Vec2::splat(50.0)))),
+                Some(RaycastError::OriginOutOfBounds { origin: Vec2::new(100.0, 0.0) }),
+            ),
+            (
+                Vec2::ZERO,
+                vec![wall, Occluder::new(Segment::from_coords(1.0, 1.0, 1.0, 1.0))],
+                config.clone(),
+                Some(RaycastError::DegenerateSegment { id: OccluderId::new(1), error: SegmentError::ZeroLength }),
+            ),
+            (Vec2::ZERO, vec![wall], RaycastConfig::layers(0), Some(RaycastError::EmptyScene)),
+            (Vec2::ZERO, vec![], config.clone().with_miss_policy(MissPolicy::MaxDistance(100.0)), None),
+            (Vec2::new(0.0, 10.0), vec![wall], config.clone(), Some(RaycastError::OriginInsideOccluder { origin: Vec2::new(0.0, 10.0), id: OccluderId::new(0) })),
+            (Vec2::new(0.0, 10.0), vec![wall, wall], config.clone().with_self_hit_policy(SelfHitPolicy::ExcludeOccluder(OccluderId::new(0))), Some(RaycastError::OriginInsideOccluder { origin: Vec2::new(0.0, 10.0), id: OccluderId::new(1) })),
+            (Vec2::new(0.0, 10.0), vec![wall, Occluder::new(Segment::from_coords(-10.0, 20.0, 10.0, 20.0))], config.clone().with_self_hit_policy(SelfHitPolicy::ExcludeOccluder(OccluderId::new(0))), None),
+        ].into_iter().for_each(|(origin, occluders, config, expected)| {
+            let result = try_calculate_intersection_points(origin, &occluders, &config);
+
+            match expected {
+                // NaN origins cannot be compared, so only the kind of the error is checked
+                Some(RaycastError::OriginOutOfBounds { .. }) => assert!(matches!(result, Err(RaycastError::OriginOutOfBounds { .. })), "{origin}: {result:?}"),
+                // io errors are not comparable, so neither is the error itself
+                Some(error) => assert_eq!(result.map_err(|error| format!("{error:?}")), Err(format!("{error:?}")), "{origin}"),
+                None => assert_eq!(result.ok(), Some(calculate_intersection_points(origin, &occluders, &config)), "{origin}")
+            }
+        });
+    }
+}
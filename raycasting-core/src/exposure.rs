@@ -0,0 +1,193 @@
+use glam::*;
+use crate::config::RaycastConfig;
+use crate::occluder::{Occluder, OccluderHeight, OccluderId};
+use crate::raycasting::Segment;
+
+/// Something with an extent which can be partially hidden, like a unit behind low cover.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Target {
+    Circle {
+        center: Vec2,
+        radius: f32,
+    },
+    Segment(Segment),
+}
+
+impl Target {
+    /// Return the outline of the target as seen from the viewer: the segment between the tangent points for a
+    /// circle, the segment itself otherwise. Returns None if the viewer is inside the circle.
+    pub fn silhouette(&self, viewer: Vec2) -> Option<Segment> {
+        match *self {
+            Target::Circle { center, radius } => {
+                let to_viewer = viewer - center;
+                let distance = to_viewer.length();
+
+                if distance <= radius {
+                    return None;
+                }
+
+                let angle = (radius / distance).acos();
+                let direction = to_viewer / distance;
+
+                Some(Segment::new(
+                    center + Vec2::from_angle(-angle).rotate(direction) * radius,
+                    center + Vec2::from_angle(angle).rotate(direction) * radius,
+                ))
+            }
+            Target::Segment(segment) => Some(segment)
+        }
+    }
+}
+
+/// Return which fraction of the target's silhouette the viewer can see, from 0 (fully hidden) to 1 (fully exposed).
+///
+/// The silhouette is split into the given number of equal parts (at least one), and each part counts as visible
+/// if the line from the viewer to its center touches no occluder considered by the config. A viewer inside
+/// a circle target sees all of it.
+pub fn exposure(
+    viewer: Vec2,
+    target: &Target,
+    samples: usize,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+) -> f32 {
+    let Some(silhouette) = target.silhouette(viewer) else {
+        return 1.0;
+    };
+
+    let samples = samples.max(1);
+    let visible = sample_points(silhouette, samples)
+        .filter(|point| blockers(viewer, *point, occluders, config).next().is_none())
+        .count();
+
+    visible as f32 / samples as f32
+}
+
+/// How well a target is protected from a shooter, see [evaluate_cover]. Better cover compares greater.
+#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Cover {
+    None,
+    Half,
+    Full,
+}
+
+/// The number of points on the silhouette of the target [evaluate_cover] casts rays to.
+pub const COVER_SAMPLES: usize = 3;
+
+/// Return the cover of the target against the shooter, based on the rays from the shooter to points along the
+/// silhouette of the target (its corners and its center, see [Target::silhouette]).
+///
+/// The target has full cover if every ray hits an occluder of [OccluderHeight::Full]. It has half cover if
+/// every ray hits some occluder, but some of them only hit [OccluderHeight::Low] ones. If any ray reaches
+/// the target, it has no cover, like a unit flanked behind a low wall.
+pub fn evaluate_cover(
+    shooter: Vec2,
+    target: &Target,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+) -> Cover {
+    let Some(silhouette) = target.silhouette(shooter) else {
+        return Cover::None;
+    };
+
+    (0..COVER_SAMPLES)
+        .map(|i| silhouette.a().lerp(silhouette.b(), i as f32 / (COVER_SAMPLES - 1) as f32))
+        .map(|point| blockers(shooter, point, occluders, config)
+            .map(|occluder| match occluder.height {
+                OccluderHeight::Full => Cover::Full,
+                OccluderHeight::Low => Cover::Half
+            })
+            .max()
+            .unwrap_or(Cover::None))
+        .min()
+        .unwrap_or(Cover::None)
+}
+
+/// Return the centers of the given number of equal parts of the silhouette.
+fn sample_points(
+    silhouette: Segment,
+    samples: usize,
+) -> impl Iterator<Item=Vec2> {
+    let [a, b] = silhouette.points();
+    (0..samples).map(move |i| a.lerp(b, (i as f32 + 0.5) / samples as f32))
+}
+
+/// Return the occluders considered by the config which touch the line from the viewer to the point.
+fn blockers<'a>(
+    viewer: Vec2,
+    point: Vec2,
+    occluders: &'a [Occluder],
+    config: &'a RaycastConfig,
+) -> impl Iterator<Item=&'a Occluder> {
+    let sight = Segment::new(viewer, point);
+
+    occluders
+        .iter()
+        .enumerate()
+        .filter(move |(id, occluder)| config.considers(OccluderId::new(*id), occluder) && sight.intersects(occluder.segment))
+        .map(|(_, occluder)| occluder)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use crate::config::RaycastConfig;
+    use crate::exposure::{Cover, evaluate_cover, exposure, Target};
+    use crate::occluder::{Occluder, OccluderHeight};
+    use crate::raycasting::Segment;
+
+    #[test]
+    fn circle_silhouette_touches_the_tangents() {
+        let target = Target::Circle { center: Vec2::new(10.0, 0.0), radius: 5.0 };
+        let [a, b] = target.silhouette(Vec2::ZERO).unwrap().points();
+
+        // the tangent points are at right angles to the lines from the viewer
+        assert!(a.dot(a - Vec2::new(10.0, 0.0)).abs() < 0.001);
+        assert!(b.dot(b - Vec2::new(10.0, 0.0)).abs() < 0.001);
+        assert!((a.distance(b) - 2.0 * 5.0 * 75.0_f32.sqrt() / 10.0).abs() < 0.001);
+        assert!(target.silhouette(Vec2::new(12.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn exposure_works() {
+        let half_cover = [Occluder::new(Segment::from_coords(5.0, 0.0, 5.0, 10.0))];
+        let full_cover = [Occluder::new(Segment::from_coords(5.0, -10.0, 5.0, 10.0))];
+        let config = RaycastConfig::default();
+        let wall = Target::Segment(Segment::from_coords(10.0, -5.0, 10.0, 5.0));
+        let circle = Target::Circle { center: Vec2::new(10.0, 0.0), radius: 2.0 };
+
+        [
+            ("wall", &wall, &[][..], 1.0),
+            ("wall in half cover", &wall, &half_cover[..], 0.5),
+            ("wall in full cover", &wall, &full_cover[..], 0.0),
+            ("circle", &circle, &[][..], 1.0),
+            ("circle in full cover", &circle, &full_cover[..], 0.0),
+        ].into_iter().for_each(|(name, target, occluders, expected)| assert_eq!(exposure(Vec2::ZERO, target, 10, occluders, &config), expected, "{name}"));
+
+        // occluders on other layers do not count
+        assert_eq!(exposure(Vec2::ZERO, &wall, 10, &[full_cover[0].with_layers(2)], &RaycastConfig::layers(1)), 1.0);
+    }
+
+    #[test]
+    fn evaluate_cover_works() {
+        let wall = Occluder::new(Segment::from_coords(5.0, -10.0, 5.0, 10.0));
+        let low_wall = wall.with_height(OccluderHeight::Low);
+        let short_wall = Occluder::new(Segment::from_coords(5.0, -10.0, 5.0, 0.0));
+        let config = RaycastConfig::default();
+        let target = Target::Circle { center: Vec2::new(10.0, 0.0), radius: 2.0 };
+
+        [
+            ("nothing", vec![], Cover::None),
+            ("wall", vec![wall], Cover::Full),
+            ("low wall", vec![low_wall], Cover::Half),
+            ("wall behind low wall", vec![low_wall, wall], Cover::Full),
+            ("partial wall", vec![short_wall], Cover::None),
+            ("partial wall and low wall", vec![short_wall, low_wall], Cover::Half),
+        ].into_iter().for_each(|(name, occluders, expected)| assert_eq!(evaluate_cover(Vec2::ZERO, &target, &occluders, &config), expected, "{name}"));
+
+        // the wall hides the center of the target and most of it, but not its upper corner
+        let target = Target::Segment(Segment::from_coords(10.0, -5.0, 10.0, 5.0));
+        let corner_wall = Occluder::new(Segment::from_coords(5.0, -10.0, 5.0, 2.0));
+        assert_eq!(evaluate_cover(Vec2::ZERO, &target, &[corner_wall], &config), Cover::None);
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+use std::f32::consts::{PI, TAU};
+use glam::*;
+use crate::aabb::Aabb;
+use crate::chunks::{ChunkCoord, ChunkedWorld};
+use crate::config::RaycastConfig;
+use crate::occluder::Occluder;
+use crate::raycasting::{polygon_points, Ray, Segment};
+use crate::tiles::tiles_on_ray;
+use crate::view_distance::limit_view_distance;
+
+/// The outline of the occluders of a chunk, seen from far away in a few evenly spread directions.
+///
+/// For every direction, the occluders are projected onto the line across it. The projections are merged into
+/// intervals, each with the smallest depth along the direction of the occluders in it. A ray from far away
+/// in about this direction is blocked by the chunk if it passes through one of the intervals.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Horizon {
+    center: Vec2,
+    silhouettes: Vec<Vec<Silhouette>>,
+}
+
+/// An interval of offsets across a direction of a [Horizon] covered by occluders, relative to its center.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Silhouette {
+    start: f32,
+    end: f32,
+    depth: f32,
+}
+
+impl Horizon {
+    /// Compute the horizon of the given occluders around the center for the given number of directions.
+    pub fn new(
+        center: Vec2,
+        occluders: &[Occluder],
+        directions: usize,
+    ) -> Self {
+        let silhouettes = (0..directions)
+            .map(|index| {
+                let along = Vec2::from_angle(index as f32 * TAU / directions as f32);
+                let across = along.perp();
+                let mut projections = occluders
+                    .iter()
+                    .map(|occluder| {
+                        let [a, b] = occluder.segment.points().map(|point| point - center);
+                        Silhouette {
+                            start: a.dot(across).min(b.dot(across)),
+                            end: a.dot(across).max(b.dot(across)),
+                            depth: a.dot(along).min(b.dot(along)),
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                projections.sort_by(|s, t| s.start.total_cmp(&t.start));
+
+                let mut merged: Vec<Silhouette> = Vec::new();
+
+                for projection in projections {
+                    match merged.last_mut() {
+                        Some(last) if projection.start <= last.end => {
+                            last.end = last.end.max(projection.end);
+                            last.depth = last.depth.min(projection.depth);
+                        }
+                        _ => merged.push(projection)
+                    }
+                }
+
+                merged
+            })
+            .collect();
+
+        Horizon { center, silhouettes }
+    }
+
+    /// Return the distance at which the ray is blocked by this horizon, if it is. This is only a good
+    /// approximation if the origin of the ray is far away from the center.
+    pub fn blocks(&self, ray: Ray) -> Option<f32> {
+        let directions = self.silhouettes.len();
+
+        if directions == 0 {
+            return None;
+        }
+
+        let direction = ray.direction().normalize_or_zero();
+        let index = (direction.y.atan2(direction.x).rem_euclid(TAU) / TAU * directions as f32).round() as usize % directions;
+        let along = Vec2::from_angle(index as f32 * TAU / directions as f32);
+        let offset = (ray.origin() - self.center).dot(along.perp());
+
+        self.silhouettes[index]
+            .iter()
+            .find(|silhouette| silhouette.start <= offset && offset <= silhouette.end)
+            .map(|silhouette| (self.center - ray.origin()).dot(along) + silhouette.depth)
+    }
+}
+
+/// The horizons of every chunk of a world, which are small enough to keep all of them in memory,
+/// even while most chunks are unloaded.
+#[derive(Clone, Debug)]
+pub struct FarField {
+    chunk_size: f32,
+    horizons: HashMap<ChunkCoord, Horizon>,
+}
+
+impl FarField {
+    /// Compute the horizons of the given chunks, like the ones returned by [crate::chunks::partition].
+    pub fn new(
+        chunks: &HashMap<ChunkCoord, Vec<Occluder>>,
+        chunk_size: f32,
+        directions: usize,
+    ) -> Self {
+        let world = ChunkedWorld::new(chunk_size);
+        let horizons = chunks
+            .iter()
+            .map(|(coord, occluders)| (*coord, Horizon::new(world.chunk_bounds(*coord).center(), occluders, directions)))
+            .collect();
+
+        FarField { chunk_size, horizons }
+    }
+
+    /// Replace the horizon of a chunk, like after its occluders changed.
+    pub fn insert(&mut self, coord: ChunkCoord, horizon: Horizon) {
+        self.horizons.insert(coord, horizon);
+    }
+
+    pub fn remove(&mut self, coord: ChunkCoord) -> Option<Horizon> {
+        self.horizons.remove(&coord)
+    }
+
+    /// Return the distance at which the first horizon behind the given distance blocks the ray,
+    /// or None if none does before the ray is further than max_distance away from its origin.
+    pub fn blocks(
+        &self,
+        ray: Ray,
+        min_distance: f32,
+        max_distance: f32,
+    ) -> Option<f32> {
+        let world = ChunkedWorld::new(self.chunk_size);
+
+        for (x, y) in tiles_on_ray(ray, self.chunk_size) {
+            let coord = ChunkCoord::new(x, y);
+            let bounds = world.chunk_bounds(coord);
+
+            if ray.origin().distance(ray.origin().clamp(bounds.min, bounds.max)) > max_distance {
+                return None;
+            }
+
+            let distance = self.horizons.get(&coord).and_then(|horizon| horizon.blocks(ray));
+
+            if let Some(distance) = distance.filter(|distance| *distance >= min_distance) {
+                return Some(distance.min(max_distance));
+            }
+        }
+
+        None
+    }
+}
+
+/// Settings of [far_field_intersection_points].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FarFieldSettings {
+    /// Distance up to which the visibility polygon is exact
+    pub near: f32,
+    /// Distance at which rays which were not blocked end
+    pub far: f32,
+    /// Largest angle (radians) between two rays into the far field
+    pub max_step: f32,
+}
+
+impl Default for FarFieldSettings {
+    fn default() -> Self {
+        FarFieldSettings {
+            near: 500.0,
+            far: 5000.0,
+            max_step: 0.02,
+        }
+    }
+}
+
+/// Return the visibility polygon of the origin in a huge world, which is exact within the near distance and
+/// approximated by the horizons of the far field beyond it.
+///
+/// The exact polygon only considers the occluders of the loaded chunks around the origin (see
+/// [ChunkedWorld::occluders_in]), enclosed by a ring at the near distance. Where it reaches the ring, rays
+/// in steps of the max step continue into the far field and end at the first horizon which blocks them.
+/// This way, the cost of a query only depends on the near and far distances, not on the size of the world.
+/// The horizons consider every occluder, regardless of the config.
+pub fn far_field_intersection_points(
+    origin: Vec2,
+    world: &ChunkedWorld,
+    far_field: &FarField,
+    config: &RaycastConfig,
+    settings: &FarFieldSettings,
+) -> Vec<Vec2> {
+    let near = settings.near;
+    let sides = ((TAU / settings.max_step).ceil() as usize).max(3);
+    let corner = |i: usize| origin + Vec2::from_angle(i as f32 * TAU / sides as f32) * near / (PI / sides as f32).cos();
+    let mut occluders = world.occluders_in(Aabb::from_center(origin, Vec2::splat(near)));
+    occluders.extend((0..sides).map(|i| Occluder::new(Segment::new(corner(i), corner(i + 1)))));
+    let points = polygon_points(origin, &occluders, config);
+
+    limit_view_distance(origin, &points, |_| near, settings.max_step)
+        .into_iter()
+        .map(|point| {
+            let direction = point - origin;
+
+            if direction.length() < near * 0.999 {
+                return point;
+            }
+
+            let direction = direction.normalize();
+            let distance = far_field.blocks(Ray::new(origin, direction), near, settings.far).unwrap_or(settings.far);
+            origin + direction * distance
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+    use glam::*;
+    use crate::chunks::{ChunkCoord, ChunkedWorld, partition};
+    use crate::far_field::{far_field_intersection_points, FarField, FarFieldSettings, Horizon};
+    use crate::config::RaycastConfig;
+    use crate::occluder::Occluder;
+    use crate::raycasting::{Ray, Segment};
+
+    #[test]
+    fn horizon_blocks_rays_through_its_silhouette() {
+        let occluders = [
+            Segment::from_coords(-5.0, -5.0, -5.0, 0.0),
+            Segment::from_coords(-5.0, 2.0, -5.0, 4.0),
+        ].into_iter().map(Occluder::new).collect::<Vec<_>>();
+        let horizon = Horizon::new(Vec2::ZERO, &occluders, 8);
+
+        [
+            (Vec2::new(-100.0, -2.0), Vec2::X, Some(95.0)),
+            (Vec2::new(-100.0, 3.0), Vec2::X, Some(95.0)),
+            // through the gap
+            (Vec2::new(-100.0, 1.0), Vec2::X, None),
+            (Vec2::new(-100.0, 10.0), Vec2::X, None),
+            // from the other side, the silhouette lies further away
+            (Vec2::new(100.0, 3.0), Vec2::NEG_X, Some(105.0)),
+            (Vec2::new(100.0, 1.0), Vec2::NEG_X, None),
+        ].into_iter().for_each(|(origin, direction, expected)| {
+            let distance = horizon.blocks(Ray::new(origin, direction));
+            assert!(distance.is_some() == expected.is_some(), "{origin}");
+            assert!((distance.unwrap_or(0.0) - expected.unwrap_or(0.0)).abs() < 0.001, "{origin}");
+        });
+    }
+
+    #[test]
+    fn far_field_extends_the_exact_near_field() {
+        // a long corridor of pillars along the x axis, and one close pillar
+        let mut occluders = (1..100)
+            .map(|i| Occluder::new(Segment::from_coords(i as f32 * 100.0, 20.0, i as f32 * 100.0, 40.0)))
+            .collect::<Vec<_>>();
+        occluders.push(Occluder::new(Segment::from_coords(-30.0, -10.0, -30.0, 10.0)));
+        occluders.push(Occluder::new(Segment::from_coords(5000.0, -50.0, 5000.0, 10.0)));
+
+        let chunks = partition(&occluders, 100.0);
+        let far_field = FarField::new(&chunks, 100.0, 64);
+        let mut world = ChunkedWorld::new(100.0);
+        world.stream(Vec2::ZERO, 200.0, |coord: ChunkCoord| chunks.get(&coord).cloned().unwrap_or_default());
+
+        let settings = FarFieldSettings { near: 200.0, far: 8000.0, max_step: PI / 64.0 };
+        let points = far_field_intersection_points(Vec2::ZERO, &world, &far_field, &RaycastConfig::default(), &settings);
+
+        // the close pillar is exact
+        assert!(points.iter().any(|point| point.distance(Vec2::new(-30.0, 10.0)) < 0.001));
+        // along the x axis, the far pillar ends the view
+        let along_x = points.iter().find(|point| point.y.abs() < 0.001 && point.x > 0.0).unwrap();
+        assert!((along_x.x - 5000.0).abs() < 1.0, "{along_x}");
+        // other directions reach the far distance
+        assert!(points.iter().any(|point| (point.length() - 8000.0).abs() < 1.0));
+        assert!(points.iter().all(|point| point.length() <= 8000.001));
+    }
+}
@@ -0,0 +1,142 @@
+use glam::*;
+
+/// Noise driven changes of the intensity and radius of a light over time, like the flame of a torch.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub struct Flicker {
+    /// Largest change of the intensity, as a fraction of it
+    pub intensity_amplitude: f32,
+    /// Largest change of the radius, as a fraction of it
+    pub radius_amplitude: f32,
+    /// Number of random values the noise passes per second. Higher values flicker faster.
+    pub frequency: f32,
+    /// Smallest change of the radius which recomputes the geometry of the light, see [FlickerTracker]
+    pub threshold: f32,
+    /// Lights with different seeds flicker differently
+    pub seed: u32,
+}
+
+impl Default for Flicker {
+    fn default() -> Self {
+        Flicker {
+            intensity_amplitude: 0.2,
+            radius_amplitude: 0.05,
+            frequency: 8.0,
+            threshold: 1.0,
+            seed: 0,
+        }
+    }
+}
+
+impl Flicker {
+    pub fn with_seed(mut self, seed: u32) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Return the factor to multiply the intensity of the light with at the given time (seconds).
+    pub fn intensity(&self, time: f32) -> f32 {
+        1.0 + self.intensity_amplitude * noise(time * self.frequency, self.seed)
+    }
+
+    /// Return the radius of the light with the given radius at the given time (seconds).
+    pub fn radius(&self, radius: f32, time: f32) -> f32 {
+        radius * (1.0 + self.radius_amplitude * noise(time * self.frequency, self.seed.wrapping_add(1)))
+    }
+}
+
+/// Keeps the radius the geometry of a flickering light was last computed with, so the geometry is only
+/// recomputed if the radius changed noticeably. The intensity can change every frame, as it does not change
+/// the geometry.
+#[derive(Clone, Debug, Default)]
+pub struct FlickerTracker {
+    radius: Option<f32>,
+}
+
+impl FlickerTracker {
+    /// Forget the last radius, so the next update returns the new one. Call this if the geometry is recomputed
+    /// for another reason, like a moving light.
+    pub fn invalidate(&mut self) {
+        self.radius = None
+    }
+
+    /// Return the radius to recompute the geometry with if it differs from the last one by more than the
+    /// threshold, or None if the last geometry is still good enough.
+    pub fn update(
+        &mut self,
+        radius: f32,
+        threshold: f32,
+    ) -> Option<f32> {
+        match self.radius {
+            Some(last) if (radius - last).abs() <= threshold => None,
+            _ => {
+                self.radius = Some(radius);
+                Some(radius)
+            }
+        }
+    }
+}
+
+/// Return smooth value noise from -1 to 1, which passes a new random value at every integer.
+fn noise(x: f32, seed: u32) -> f32 {
+    let cell = x.floor();
+    let fraction = x - cell;
+    let smooth = fraction * fraction * (3.0 - 2.0 * fraction);
+    let (start, end) = (random_value(cell as i32, seed), random_value(cell as i32 + 1, seed));
+
+    start + (end - start) * smooth
+}
+
+/// Return a random value from -1 to 1 for the given integer, always the same for the same integer and seed.
+fn random_value(i: i32, seed: u32) -> f32 {
+    let mut hash = (i as u32).wrapping_mul(0x9E37_79B9) ^ seed.wrapping_mul(0x85EB_CA6B);
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x7FEB_352D);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x846C_A68B);
+    hash ^= hash >> 16;
+
+    hash as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::flicker::{Flicker, FlickerTracker, noise};
+
+    #[test]
+    fn noise_is_smooth_and_bounded() {
+        let values = (0..1000).map(|i| noise(i as f32 * 0.01 - 5.0, 3)).collect::<Vec<_>>();
+
+        assert!(values.iter().all(|value| (-1.0..=1.0).contains(value)));
+        assert!(values.windows(2).all(|pair| (pair[0] - pair[1]).abs() < 0.05));
+        assert!(values.iter().any(|value| *value > 0.2) && values.iter().any(|value| *value < -0.2));
+        assert_eq!(noise(1.5, 3), noise(1.5, 3));
+        assert_ne!(noise(1.5, 3), noise(1.5, 4));
+    }
+
+    #[test]
+    fn flicker_stays_within_its_amplitudes() {
+        let flicker = Flicker::default().with_seed(7);
+
+        (0..500).map(|i| i as f32 * 0.013).for_each(|time| {
+            assert!((flicker.intensity(time) - 1.0).abs() <= flicker.intensity_amplitude + 0.0001);
+            assert!((flicker.radius(100.0, time) - 100.0).abs() <= 100.0 * flicker.radius_amplitude + 0.0001);
+        });
+    }
+
+    #[test]
+    fn tracker_only_reports_noticeable_changes() {
+        let mut tracker = FlickerTracker::default();
+
+        [
+            (100.0, Some(100.0)),
+            (100.5, None),
+            (99.2, None),
+            (101.5, Some(101.5)),
+            (101.0, None),
+        ].into_iter().for_each(|(radius, expected)| assert_eq!(tracker.update(radius, 1.0), expected, "{radius}"));
+
+        tracker.invalidate();
+        assert_eq!(tracker.update(101.0, 1.0), Some(101.0));
+    }
+}
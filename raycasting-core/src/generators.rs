@@ -0,0 +1,288 @@
+use std::f32::consts::TAU;
+use glam::*;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use crate::scene::Scene;
+
+/// How a map is generated, see [generate]. Each one produces another kind of geometry: many short, scattered
+/// walls, long straight corridors, narrow winding passages, irregular caves or a wide open space with a few pillars.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub enum MapGenerator {
+    /// Every tile is a wall with the wall density as probability
+    #[default]
+    Noise,
+    /// Rectangular rooms connected by corridors one tile wide
+    Dungeon,
+    /// A maze with corridors one tile wide and a single path between any two of them
+    Maze,
+    /// Caves grown from noise with the wall density by a cellular automaton
+    Caves,
+    /// A round arena with rings of pillars
+    Arena,
+}
+
+impl MapGenerator {
+    pub const ALL: [MapGenerator; 5] = [MapGenerator::Noise, MapGenerator::Dungeon, MapGenerator::Maze, MapGenerator::Caves, MapGenerator::Arena];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MapGenerator::Noise => "noise",
+            MapGenerator::Dungeon => "dungeon",
+            MapGenerator::Maze => "maze",
+            MapGenerator::Caves => "caves",
+            MapGenerator::Arena => "arena"
+        }
+    }
+
+    /// Return the generator with the given name, see [MapGenerator::name].
+    pub fn from_name(name: &str) -> Option<Self> {
+        MapGenerator::ALL.into_iter().find(|generator| generator.name() == name)
+    }
+
+    /// Return the generator following this one, to cycle through all of them.
+    pub fn next(&self) -> Self {
+        let index = MapGenerator::ALL.iter().position(|generator| generator == self).unwrap_or(0);
+        MapGenerator::ALL[(index + 1) % MapGenerator::ALL.len()]
+    }
+}
+
+/// Number of steps the cellular automaton of [MapGenerator::Caves] smooths the noise
+const CAVE_STEPS: usize = 5;
+
+/// Generate a scene of the given size. The wall density is only used by noise and caves.
+/// The same random generator state always generates the same scene.
+pub fn generate(
+    generator: MapGenerator,
+    width: usize,
+    height: usize,
+    wall_density: f64,
+    rng: &mut impl Rng,
+) -> Scene {
+    let walls = match generator {
+        MapGenerator::Noise => noise(width, height, wall_density, rng),
+        MapGenerator::Dungeon => dungeon(width, height, rng),
+        MapGenerator::Maze => maze(width, height, rng),
+        MapGenerator::Caves => caves(width, height, wall_density, rng),
+        MapGenerator::Arena => arena(width, height, rng)
+    };
+
+    Scene::from_fn(width, height, |x, y| walls[y * width + x])
+}
+
+fn noise(
+    width: usize,
+    height: usize,
+    wall_density: f64,
+    rng: &mut impl Rng,
+) -> Vec<bool> {
+    (0..width * height).map(|_| rng.gen_bool(wall_density.clamp(0.0, 1.0))).collect()
+}
+
+fn dungeon(
+    width: usize,
+    height: usize,
+    rng: &mut impl Rng,
+) -> Vec<bool> {
+    let mut walls = vec![true; width * height];
+    let mut rooms: Vec<(usize, usize, usize, usize)> = Vec::new();
+
+    if width < 5 || height < 5 {
+        return walls;
+    }
+
+    for _ in 0..width * height / 20 {
+        let (room_width, room_height) = (rng.gen_range(3..=8.min(width - 2)), rng.gen_range(3..=8.min(height - 2)));
+        let (x, y) = (rng.gen_range(1..=width - 1 - room_width), rng.gen_range(1..=height - 1 - room_height));
+        // keep a wall between rooms
+        let overlaps = rooms.iter().any(|(x0, y0, w0, h0)| x <= x0 + w0 && *x0 <= x + room_width && y <= y0 + h0 && *y0 <= y + room_height);
+
+        if overlaps {
+            continue;
+        }
+
+        for ty in y..y + room_height {
+            walls[ty * width + x..ty * width + x + room_width].fill(false);
+        }
+
+        // connect the center of the new room with the one of the previous room by an L-shaped corridor
+        if let Some((x0, y0, w0, h0)) = rooms.last() {
+            let (from, to) = ((x0 + w0 / 2, y0 + h0 / 2), (x + room_width / 2, y + room_height / 2));
+            let corner = if rng.gen_bool(0.5) { (to.0, from.1) } else { (from.0, to.1) };
+
+            for (a, b) in [(from, corner), (corner, to)] {
+                for ty in a.1.min(b.1)..=a.1.max(b.1) {
+                    for tx in a.0.min(b.0)..=a.0.max(b.0) {
+                        walls[ty * width + tx] = false;
+                    }
+                }
+            }
+        }
+
+        rooms.push((x, y, room_width, room_height));
+    }
+
+    walls
+}
+
+fn maze(
+    width: usize,
+    height: usize,
+    rng: &mut impl Rng,
+) -> Vec<bool> {
+    let mut walls = vec![true; width * height];
+
+    if width < 3 || height < 3 {
+        return walls;
+    }
+
+    // corridors run through the tiles at odd coordinates, carved by a randomized depth first search
+    let mut stack = vec![(1, 1)];
+    walls[width + 1] = false;
+
+    while let Some(&(x, y)) = stack.last() {
+        let mut neighbours = [(2, 0), (-2, 0), (0, 2), (0, -2)]
+            .into_iter()
+            .map(|(dx, dy)| (x as i32 + dx, y as i32 + dy))
+            .filter(|(nx, ny)| *nx > 0 && *ny > 0 && *nx < width as i32 - 1 && *ny < height as i32 - 1)
+            .map(|(nx, ny)| (nx as usize, ny as usize))
+            .filter(|(nx, ny)| walls[ny * width + nx])
+            .collect::<Vec<_>>();
+        neighbours.shuffle(rng);
+
+        match neighbours.first() {
+            Some(&(nx, ny)) => {
+                walls[(y + ny) / 2 * width + (x + nx) / 2] = false;
+                walls[ny * width + nx] = false;
+                stack.push((nx, ny));
+            }
+            None => {
+                stack.pop();
+            }
+        }
+    }
+
+    walls
+}
+
+fn caves(
+    width: usize,
+    height: usize,
+    wall_density: f64,
+    rng: &mut impl Rng,
+) -> Vec<bool> {
+    let mut walls = noise(width, height, wall_density, rng);
+
+    for _ in 0..CAVE_STEPS {
+        // tiles outside the map count as walls, so the caves close at the border
+        let is_wall = |walls: &[bool], x: i32, y: i32| x < 0 || y < 0 || x >= width as i32 || y >= height as i32 || walls[y as usize * width + x as usize];
+
+        walls = (0..height as i32)
+            .flat_map(|y| (0..width as i32).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let neighbours = (-1..=1)
+                    .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                    .filter(|(dx, dy)| (*dx, *dy) != (0, 0) && is_wall(&walls, x + dx, y + dy))
+                    .count();
+
+                match neighbours {
+                    0..=3 => false,
+                    4 => is_wall(&walls, x, y),
+                    _ => true
+                }
+            })
+            .collect();
+    }
+
+    walls
+}
+
+fn arena(
+    width: usize,
+    height: usize,
+    rng: &mut impl Rng,
+) -> Vec<bool> {
+    let center = Vec2::new(width as f32 - 1.0, height as f32 - 1.0) / 2.0;
+    let radius = (width.min(height) as f32 - 1.0) / 2.0;
+    let mut walls = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| Vec2::new(x as f32, y as f32).distance(center) > radius)
+        .collect::<Vec<_>>();
+
+    for ring in [0.3, 0.6] {
+        let ring_radius = radius * ring;
+        // a pillar every four tiles along the ring, turned by a random angle
+        let pillars = (TAU * ring_radius / 4.0) as usize;
+        let offset = rng.gen_range(0.0..TAU);
+
+        for i in 0..pillars {
+            let position = center + Vec2::from_angle(offset + TAU * i as f32 / pillars as f32) * ring_radius;
+            let (x, y) = (position.x.round() as usize, position.y.round() as usize);
+
+            if x < width && y < height {
+                walls[y * width + x] = true;
+            }
+        }
+    }
+
+    walls
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use crate::generators::{generate, MapGenerator};
+    use crate::scene::Scene;
+
+    /// Return the number of floor tiles reachable from the first one.
+    fn reachable_floors(scene: &Scene) -> usize {
+        let floors = (0..scene.height())
+            .flat_map(|y| (0..scene.width()).map(move |x| (x, y)))
+            .filter(|(x, y)| !scene.is_wall(*x, *y))
+            .collect::<Vec<_>>();
+        let mut reached = floors.first().copied().into_iter().collect::<Vec<_>>();
+        let mut i = 0;
+
+        while let Some(&(x, y)) = reached.get(i) {
+            for neighbour in [(x + 1, y), (x.wrapping_sub(1), y), (x, y + 1), (x, y.wrapping_sub(1))] {
+                if floors.contains(&neighbour) && !reached.contains(&neighbour) {
+                    reached.push(neighbour);
+                }
+            }
+
+            i += 1;
+        }
+
+        reached.len()
+    }
+
+    #[test]
+    fn generators_are_deterministic() {
+        for generator in MapGenerator::ALL {
+            let scene = generate(generator, 31, 21, 0.45, &mut StdRng::seed_from_u64(7));
+
+            assert_eq!((scene.width(), scene.height()), (31, 21), "{generator:?}");
+            assert_eq!(scene, generate(generator, 31, 21, 0.45, &mut StdRng::seed_from_u64(7)), "{generator:?}");
+            assert_eq!(MapGenerator::from_name(generator.name()), Some(generator));
+        }
+
+        assert_eq!(MapGenerator::Arena.next(), MapGenerator::Noise);
+    }
+
+    #[test]
+    fn dungeons_and_mazes_are_connected() {
+        for generator in [MapGenerator::Dungeon, MapGenerator::Maze] {
+            let scene = generate(generator, 31, 21, 0.0, &mut StdRng::seed_from_u64(3));
+            let floors = (0..scene.height()).flat_map(|y| (0..scene.width()).map(move |x| (x, y))).filter(|(x, y)| !scene.is_wall(*x, *y)).count();
+
+            assert!(floors > 31 * 21 / 4, "{generator:?}: {floors}");
+            assert_eq!(reachable_floors(&scene), floors, "{generator:?}");
+            // the border is closed
+            assert!((0..31).all(|x| scene.is_wall(x, 0) && scene.is_wall(x, 20)), "{generator:?}");
+        }
+
+        let arena = generate(MapGenerator::Arena, 21, 21, 0.0, &mut StdRng::seed_from_u64(3));
+        assert!(arena.is_wall(0, 0) && !arena.is_wall(10, 10));
+    }
+}
@@ -0,0 +1,89 @@
+use std::fs;
+use std::path::PathBuf;
+use glam::*;
+use crate::config::{Algorithm, RaycastConfig};
+use crate::raycasting::calculate_intersection_points;
+use crate::scene::Scene;
+
+const TILE_SIZE: f32 = 32.0;
+
+/// Maximum distance between a computed vertex and its golden counterpart
+const TOLERANCE: f32 = 0.01;
+
+/// Algorithms whose output is pinned, and the suffix of their golden files
+const ALGORITHMS: &[(Algorithm, &str)] = &[(Algorithm::Naive, ""), (Algorithm::Sweep, "_sweep")];
+
+/// Fixture scenes and the origins (in tiles) to raycast them from
+const FIXTURES: &[(&str, &[(f32, f32)])] = &[
+    ("example", &[(2.5, 2.5), (15.5, 15.5), (27.5, 27.5), (11.5, 13.5), (6.5, 20.5)]),
+    ("pillars", &[(1.5, 1.5), (5.5, 4.5), (6.0, 3.0), (10.5, 7.5)]),
+    ("rooms", &[(2.5, 2.5), (3.5, 4.5), (9.5, 4.5), (11.5, 7.5)]),
+];
+
+fn fixtures_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures")
+}
+
+fn format_points(points: &[Vec2]) -> String {
+    points
+        .iter()
+        .map(|point| format!("{} {}\n", point.x, point.y))
+        .collect()
+}
+
+fn parse_points(source: &str) -> Vec<Vec2> {
+    source
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let mut coordinates = line.split(' ').map(|c| c.parse::<f32>().expect("invalid coordinate"));
+            Vec2::new(coordinates.next().unwrap(), coordinates.next().unwrap())
+        })
+        .collect()
+}
+
+/// Raycast every fixture scene from fixtures/scenes from a few origins with every algorithm and compare the resulting
+/// intersection points with the vertex lists stored in fixtures/golden. This catches behavioral changes like extra
+/// slivers or missing corners, which tests of single intersections cannot.
+///
+/// After an intended change of the output, regenerate the golden files with
+/// UPDATE_GOLDEN=1 cargo test golden
+/// and review the diff.
+#[test]
+fn visibility_polygons_match_golden_files() {
+    let update = std::env::var("UPDATE_GOLDEN").is_ok();
+    let mut failures = vec![];
+
+    for (name, origins) in FIXTURES {
+        let source = fs::read_to_string(fixtures_dir().join("scenes").join(format!("{name}.map"))).unwrap();
+        let occluders = Scene::parse(&source).unwrap().occluders(TILE_SIZE);
+
+        for ((index, (x, y)), (algorithm, suffix)) in origins.iter().enumerate().flat_map(|origin| ALGORITHMS.iter().map(move |algorithm| (origin, algorithm))) {
+            let origin = Vec2::new(*x, *y) * TILE_SIZE;
+            let points = calculate_intersection_points(origin, &occluders, &RaycastConfig::default().with_algorithm(*algorithm));
+            let golden_path = fixtures_dir().join("golden").join(format!("{name}_{index}{suffix}.txt"));
+
+            if update {
+                fs::write(&golden_path, format_points(&points)).unwrap();
+                continue;
+            }
+
+            let golden = parse_points(&fs::read_to_string(&golden_path).unwrap_or_default());
+
+            if golden.len() != points.len() {
+                failures.push(format!("{}: expected {} vertices, got {}", golden_path.display(), golden.len(), points.len()));
+                continue;
+            }
+
+            if let Some((i, (expected, actual))) = golden
+                .iter()
+                .zip(points.iter())
+                .enumerate()
+                .find(|(_, (expected, actual))| expected.distance(**actual) > TOLERANCE) {
+                failures.push(format!("{}: vertex {i} should be {expected}, got {actual}", golden_path.display()));
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "visibility polygons differ from the golden files:\n{}", failures.join("\n"))
+}
@@ -0,0 +1,149 @@
+use glam::*;
+
+/// The most groups a single [OccluderGroups] can hold
+pub const MAX_GROUPS: usize = 64;
+
+/// Identifies a group of an [OccluderGroups].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub struct GroupId(pub(crate) u8);
+
+impl GroupId {
+    /// Return the bit of this group in a group mask.
+    pub fn bit(&self) -> u64 {
+        1 << self.0
+    }
+}
+
+/// Named groups of occluders like "doors" or "furniture", which can be switched off as a whole without
+/// removing their occluders.
+///
+/// Groups can be nested: switching off a group also switches off all groups inside it. A query ignores
+/// every group in its [crate::config::RaycastConfig::disabled_groups], so to apply the groups switched off here,
+/// pass [OccluderGroups::disabled_mask] to [crate::config::RaycastConfig::without_groups].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OccluderGroups {
+    groups: Vec<Group>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Group {
+    name: String,
+    parent: Option<GroupId>,
+    enabled: bool,
+}
+
+impl OccluderGroups {
+    /// Add a new, enabled group inside the given parent group. Return None if there are already [MAX_GROUPS] groups.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        parent: Option<GroupId>,
+    ) -> Option<GroupId> {
+        if self.groups.len() == MAX_GROUPS {
+            return None;
+        }
+
+        self.groups.push(Group {
+            name: name.into(),
+            parent,
+            enabled: true,
+        });
+
+        Some(GroupId(self.groups.len() as u8 - 1))
+    }
+
+    /// Return the group with the given name, if there is one.
+    pub fn find(&self, name: &str) -> Option<GroupId> {
+        self.groups
+            .iter()
+            .position(|group| group.name == name)
+            .map(|index| GroupId(index as u8))
+    }
+
+    pub fn name(&self, id: GroupId) -> &str {
+        &self.groups[id.0 as usize].name
+    }
+
+    pub fn set_enabled(&mut self, id: GroupId, enabled: bool) {
+        self.groups[id.0 as usize].enabled = enabled
+    }
+
+    /// Return true if neither the group nor any group containing it is switched off.
+    pub fn is_enabled(&self, id: GroupId) -> bool {
+        let group = &self.groups[id.0 as usize];
+        group.enabled && group.parent.map(|parent| self.is_enabled(parent)).unwrap_or(true)
+    }
+
+    /// Return the mask of the given group and all groups inside it, to switch them off for a single query.
+    pub fn mask(&self, id: GroupId) -> u64 {
+        (0..self.groups.len())
+            .map(|index| GroupId(index as u8))
+            .filter(|group| self.contains(id, *group))
+            .fold(0, |mask, group| mask | group.bit())
+    }
+
+    /// Return the mask of all groups which are switched off, directly or through a group containing them.
+    pub fn disabled_mask(&self) -> u64 {
+        (0..self.groups.len())
+            .map(|index| GroupId(index as u8))
+            .filter(|group| !self.is_enabled(*group))
+            .fold(0, |mask, group| mask | group.bit())
+    }
+
+    /// Return true if the inner group is the outer group or lies somewhere inside it.
+    fn contains(&self, outer: GroupId, inner: GroupId) -> bool {
+        inner == outer || self.groups[inner.0 as usize].parent.map(|parent| self.contains(outer, parent)).unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use crate::config::RaycastConfig;
+    use crate::groups::{MAX_GROUPS, OccluderGroups};
+    use crate::occluder::Occluder;
+    use crate::raycasting::{calculate_intersection_points, Segment};
+
+    #[test]
+    fn nested_groups_work() {
+        let mut groups = OccluderGroups::default();
+        let furniture = groups.register("furniture", None).unwrap();
+        let chairs = groups.register("chairs", Some(furniture)).unwrap();
+        let doors = groups.register("doors", None).unwrap();
+
+        assert_eq!(groups.find("chairs"), Some(chairs));
+        assert_eq!(groups.mask(furniture), furniture.bit() | chairs.bit());
+        assert_eq!(groups.mask(chairs), chairs.bit());
+
+        groups.set_enabled(furniture, false);
+        assert!(!groups.is_enabled(chairs));
+        assert!(groups.is_enabled(doors));
+        assert_eq!(groups.disabled_mask(), furniture.bit() | chairs.bit());
+
+        for i in groups.groups.len()..MAX_GROUPS {
+            assert!(groups.register(format!("group {i}"), None).is_some());
+        }
+        assert_eq!(groups.register("one too many", None), None);
+    }
+
+    #[test]
+    fn disabled_groups_do_not_block_rays() {
+        let mut groups = OccluderGroups::default();
+        let doors = groups.register("doors", None).unwrap();
+        let occluders = [
+            Occluder::new(Segment::from_coords(-10.0, 10.0, 10.0, 10.0)).with_group(doors),
+            Occluder::new(Segment::from_coords(-10.0, 20.0, 10.0, 20.0)),
+        ];
+        let nearest_y = |config: &RaycastConfig| calculate_intersection_points(Vec2::ZERO, &occluders, config)
+            .into_iter()
+            .map(|point| point.y)
+            .fold(f32::INFINITY, f32::min);
+
+        assert_eq!(nearest_y(&RaycastConfig::default()), 10.0);
+        assert_eq!(nearest_y(&RaycastConfig::default().without_groups(groups.mask(doors))), 20.0);
+
+        groups.set_enabled(doors, false);
+        assert_eq!(nearest_y(&RaycastConfig::default().without_groups(groups.disabled_mask())), 20.0);
+    }
+}
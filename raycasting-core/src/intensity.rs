@@ -0,0 +1,143 @@
+use std::f32::consts::{PI, TAU};
+use glam::*;
+use crate::raycasting::Triangle;
+use crate::view_distance::limit_view_distance;
+
+/// The intensity of a light depending on the angle to the direction it faces, like the IES profile of a
+/// flashlight with a hot center and dimmer edges. The first intensity applies along the facing direction,
+/// the last one at the given half angle (radians), evenly spread in between and interpolated linearly.
+/// Beyond the half angle, the light is off.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub struct IntensityProfile {
+    intensities: Vec<f32>,
+    half_angle: f32,
+}
+
+impl IntensityProfile {
+    pub fn new(
+        intensities: Vec<f32>,
+        half_angle: f32,
+    ) -> Self {
+        IntensityProfile { intensities, half_angle }
+    }
+
+    /// Return the intensity at the given angle (radians) to the facing direction, to either side.
+    pub fn intensity(&self, angle: f32) -> f32 {
+        let angle = angle.abs();
+
+        if self.intensities.is_empty() || angle > self.half_angle {
+            return 0.0;
+        }
+
+        if self.intensities.len() == 1 || self.half_angle == 0.0 {
+            return self.intensities[0];
+        }
+
+        let position = angle / self.half_angle * (self.intensities.len() - 1) as f32;
+        let index = (position.floor() as usize).min(self.intensities.len() - 2);
+        let fraction = position - index as f32;
+
+        self.intensities[index] * (1.0 - fraction) + self.intensities[index + 1] * fraction
+    }
+}
+
+/// A triangle of a light, with an intensity at each of its points, like vertex colors.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LitTriangle {
+    pub triangle: Triangle,
+    /// The intensities at a, b and c
+    pub intensities: [f32; 3],
+}
+
+/// Return the visibility polygon around the origin (with points ordered by angle, like the result of
+/// [crate::raycasting::calculate_intersection_points]) as a triangle fan, with the intensity of a light at the origin
+/// at every point. The intensity is given as a function of the angle (radians) to the facing direction, from -PI to PI,
+/// like [IntensityProfile::intensity].
+///
+/// As the intensity is interpolated linearly across the triangles, the edges of the polygon are split into steps of
+/// at most max_step radians, so a narrow hot center is not lost between two far apart points. The point at the
+/// origin gets the intensity in the middle of its triangle.
+pub fn light_triangles(
+    origin: Vec2,
+    points: &[Vec2],
+    facing: f32,
+    intensity: impl Fn(f32) -> f32,
+    max_step: f32,
+) -> Vec<LitTriangle> {
+    let points = limit_view_distance(origin, points, |_| f32::INFINITY, max_step);
+    let intensity_towards = |direction: Vec2| {
+        let angle = (direction.y.atan2(direction.x) - facing + PI).rem_euclid(TAU) - PI;
+        intensity(angle)
+    };
+
+    (0..points.len())
+        .map(|i| {
+            let (b, c) = (points[i], points[(i + 1) % points.len()]);
+
+            LitTriangle {
+                triangle: Triangle::new(origin, b, c),
+                intensities: [
+                    intensity_towards((b - origin).normalize_or_zero() + (c - origin).normalize_or_zero()),
+                    intensity_towards(b - origin),
+                    intensity_towards(c - origin),
+                ],
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+    use glam::*;
+    use crate::config::RaycastConfig;
+    use crate::intensity::{IntensityProfile, light_triangles};
+    use crate::occluder::Occluder;
+    use crate::raycasting::{calculate_intersection_points, Segment};
+
+    #[test]
+    fn profile_interpolates_from_the_center_to_the_edge() {
+        let profile = IntensityProfile::new(vec![1.0, 0.5, 0.0], PI / 4.0);
+
+        [
+            (0.0, 1.0),
+            (PI / 16.0, 0.75),
+            (-PI / 16.0, 0.75),
+            (PI / 8.0, 0.5),
+            (PI / 4.0, 0.0),
+            (PI / 2.0, 0.0),
+        ].into_iter().for_each(|(angle, expected)| assert!((profile.intensity(angle) - expected).abs() < 0.0001, "{angle}"));
+
+        assert_eq!(IntensityProfile::new(vec![0.8], PI / 4.0).intensity(0.1), 0.8);
+        assert_eq!(IntensityProfile::new(vec![], PI / 4.0).intensity(0.0), 0.0);
+    }
+
+    #[test]
+    fn light_triangles_are_brightest_in_the_facing_direction() {
+        let occluders = [
+            Segment::from_coords(-100.0, -100.0, 100.0, -100.0),
+            Segment::from_coords(100.0, -100.0, 100.0, 100.0),
+            Segment::from_coords(100.0, 100.0, -100.0, 100.0),
+            Segment::from_coords(-100.0, 100.0, -100.0, -100.0),
+        ].into_iter().map(Occluder::new).collect::<Vec<_>>();
+        let points = calculate_intersection_points(Vec2::ZERO, &occluders, &RaycastConfig::default());
+        let profile = IntensityProfile::new(vec![1.0, 0.2], PI / 4.0);
+
+        // facing up, where the polygon has no point of its own
+        let triangles = light_triangles(Vec2::ZERO, &points, PI / 2.0, |angle| profile.intensity(angle), 0.05);
+
+        let at = |point: Vec2| triangles
+            .iter()
+            .flat_map(|lit| [(lit.triangle.b, lit.intensities[1]), (lit.triangle.c, lit.intensities[2])])
+            .filter(|(vertex, _)| vertex.normalize().dot(point.normalize()) > 0.9999)
+            .map(|(_, intensity)| intensity)
+            .next();
+
+        assert!(at(Vec2::new(0.0, 1.0)).unwrap() > 0.95);
+        assert!(at(Vec2::new(1.0, 1.0)).unwrap() < 0.25);
+        assert_eq!(at(Vec2::new(0.0, -1.0)), Some(0.0));
+        assert!(triangles.iter().all(|lit| lit.triangle.area() > 0.0));
+        assert!(triangles.iter().all(|lit| lit.intensities.iter().all(|intensity| (0.0..=1.0).contains(intensity))));
+    }
+}
@@ -0,0 +1,53 @@
+pub mod aabb;
+pub mod arena;
+pub mod bands;
+pub mod batch;
+pub mod chunks;
+#[cfg(feature = "parry")]
+pub mod collider;
+pub mod cone;
+pub mod config;
+pub mod directional;
+pub mod distance_field;
+pub mod error;
+pub mod exposure;
+pub mod far_field;
+pub mod flicker;
+#[cfg(feature = "generators")]
+pub mod generators;
+#[cfg(test)]
+mod golden;
+pub mod groups;
+pub mod intensity;
+pub mod lidar;
+pub mod linear_light;
+pub mod mirror;
+#[cfg(feature = "nalgebra")]
+pub mod nalgebra_interop;
+pub mod occluder;
+pub mod occluder_set;
+#[cfg(feature = "lyon")]
+pub mod outline;
+pub mod pathological;
+pub mod penumbra;
+pub mod predicates;
+pub mod radiosity;
+pub mod raster;
+pub mod raycasting;
+pub mod replay;
+#[cfg(feature = "rerun")]
+pub mod rerun_log;
+pub mod sanitize;
+pub mod scene;
+pub mod shadowcasting;
+pub mod shape_cast;
+pub mod sound;
+pub mod stats;
+pub mod sweep;
+pub mod tiles;
+pub mod tint;
+pub mod transform;
+pub mod view_distance;
+pub mod visibility_events;
+pub mod visibility_graph;
+pub mod visibility_polygon;
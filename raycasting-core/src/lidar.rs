@@ -0,0 +1,181 @@
+use std::f32::consts::TAU;
+use glam::*;
+use crate::config::RaycastConfig;
+use crate::occluder::Occluder;
+use crate::raycasting::{find_nearest_intersection, Ray};
+use crate::stats::RaycastStats;
+
+/// Settings of [lidar_scan].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LidarSettings {
+    /// Number of beams per scan
+    pub beams: usize,
+    /// Angle (radians) covered by the beams, centered on the heading. A full turn spreads the beams evenly
+    /// around the sensor, without a duplicated beam behind it.
+    pub field_of_view: f32,
+    /// Hits further away than this are not reported
+    pub max_range: f32,
+}
+
+impl Default for LidarSettings {
+    fn default() -> Self {
+        LidarSettings {
+            beams: 360,
+            field_of_view: TAU,
+            max_range: 1000.0,
+        }
+    }
+}
+
+impl LidarSettings {
+    /// Return the angle (radians) of every beam relative to the heading, counterclockwise, from the clockwise
+    /// border of the field of view to the counterclockwise one.
+    pub fn beam_angles(&self) -> Vec<f32> {
+        let step = match self.beams {
+            0 | 1 => 0.0,
+            beams if self.field_of_view >= TAU => TAU / beams as f32,
+            beams => self.field_of_view / (beams - 1) as f32
+        };
+        let start = if self.beams == 1 { 0.0 } else { -self.field_of_view.min(TAU) / 2.0 };
+
+        (0..self.beams).map(|i| start + step * i as f32).collect()
+    }
+}
+
+/// The directions of the beams of a lidar relative to its heading. They depend neither on the origin nor on the
+/// heading, so a table computed once per [LidarSettings] can be shared by every scan with them, instead of
+/// computing a sine and cosine per beam and scan.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BeamTable {
+    settings: LidarSettings,
+    directions: Vec<Vec2>,
+}
+
+impl BeamTable {
+    pub fn new(settings: LidarSettings) -> Self {
+        BeamTable {
+            settings,
+            directions: settings.beam_angles().into_iter().map(Vec2::from_angle).collect(),
+        }
+    }
+
+    pub fn settings(&self) -> &LidarSettings {
+        &self.settings
+    }
+
+    /// Return the unit direction of every beam relative to a heading along the x axis, in the order of
+    /// [LidarSettings::beam_angles].
+    pub fn directions(&self) -> &[Vec2] {
+        &self.directions
+    }
+
+    /// Like [lidar_scan], with the settings of the table.
+    pub fn scan(
+        &self,
+        origin: impl Into<Vec2>,
+        heading: impl Into<Vec2>,
+        occluders: &[Occluder],
+        config: &RaycastConfig,
+    ) -> Vec<Option<f32>> {
+        let origin = origin.into();
+        let heading = heading.into().normalize_or_zero();
+        let mut stats = RaycastStats::default();
+
+        self.directions
+            .iter()
+            .map(|direction| {
+                let ray = Ray::new(origin, direction.rotate(heading));
+
+                find_nearest_intersection(ray, occluders, config, &mut stats)
+                    .map(|(point, _)| point.distance(origin))
+                    .filter(|distance| *distance <= self.settings.max_range)
+            })
+            .collect()
+    }
+}
+
+/// Simulate a 2D lidar at the origin, facing the heading: return the distance to the nearest occluder along every beam
+/// (see [LidarSettings::beam_angles]), or None if a beam hits nothing within the max range.
+///
+/// The origin and heading accept anything convertible into a Vec2, like the points and vectors of nalgebra
+/// with the nalgebra feature.
+///
+/// This computes the beam directions for every scan. Agents scanning repeatedly should keep a [BeamTable] instead.
+pub fn lidar_scan(
+    origin: impl Into<Vec2>,
+    heading: impl Into<Vec2>,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+    settings: &LidarSettings,
+) -> Vec<Option<f32>> {
+    BeamTable::new(*settings).scan(origin, heading, occluders, config)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_PI_2, PI, TAU};
+    use glam::*;
+    use crate::config::RaycastConfig;
+    use crate::lidar::{BeamTable, lidar_scan, LidarSettings};
+    use crate::occluder::Occluder;
+    use crate::raycasting::Segment;
+
+    #[test]
+    fn beams_are_spread_over_the_field_of_view() {
+        [
+            (LidarSettings { beams: 4, field_of_view: TAU, ..Default::default() }, vec![-PI, -FRAC_PI_2, 0.0, FRAC_PI_2]),
+            (LidarSettings { beams: 3, field_of_view: PI, ..Default::default() }, vec![-FRAC_PI_2, 0.0, FRAC_PI_2]),
+            (LidarSettings { beams: 1, field_of_view: PI, ..Default::default() }, vec![0.0]),
+            (LidarSettings { beams: 0, ..Default::default() }, vec![]),
+        ].into_iter().for_each(|(settings, expected)| {
+            let angles = settings.beam_angles();
+            assert_eq!(angles.len(), expected.len(), "{settings:?}");
+            assert!(angles.iter().zip(&expected).all(|(angle, expected)| (angle - expected).abs() < 0.0001), "{settings:?}: {angles:?}");
+        });
+    }
+
+    #[test]
+    fn scan_reports_the_distance_along_every_beam() {
+        let occluders = [
+            Segment::from_coords(10.0, -50.0, 10.0, 50.0),
+            Segment::from_coords(-50.0, 20.0, 50.0, 20.0),
+        ].into_iter().map(Occluder::new).collect::<Vec<_>>();
+        let settings = LidarSettings { beams: 4, field_of_view: TAU, max_range: 15.0 };
+
+        let ranges = lidar_scan(Vec2::ZERO, Vec2::Y, &occluders, &RaycastConfig::default(), &settings);
+
+        // behind, to the right, ahead (beyond the max range) and to the left
+        assert_eq!(ranges.len(), 4);
+        assert_eq!(ranges[0], None);
+        assert!((ranges[1].unwrap() - 10.0).abs() < 0.001);
+        assert_eq!(ranges[2], None);
+        assert_eq!(ranges[3], None);
+
+        let ranges = lidar_scan(Vec2::ZERO, Vec2::Y, &occluders, &RaycastConfig::default(), &LidarSettings { max_range: 30.0, ..settings });
+        assert!((ranges[2].unwrap() - 20.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn beam_tables_can_be_shared_between_scans() {
+        let occluders = [
+            Segment::from_coords(10.0, -50.0, 10.0, 50.0),
+            Segment::from_coords(-50.0, 20.0, 50.0, 20.0),
+            Segment::from_coords(-30.0, -30.0, -20.0, 40.0),
+        ].into_iter().map(Occluder::new).collect::<Vec<_>>();
+        let settings = LidarSettings { beams: 90, field_of_view: PI, max_range: 100.0 };
+        let table = BeamTable::new(settings);
+
+        assert_eq!(table.directions().len(), 90);
+        assert!(table.directions().iter().all(|direction| (direction.length() - 1.0).abs() < 0.0001));
+
+        [
+            (Vec2::ZERO, Vec2::Y),
+            (Vec2::new(-5.0, 3.0), Vec2::new(-1.0, 1.0)),
+            (Vec2::new(2.0, -10.0), Vec2::X),
+        ].into_iter().for_each(|(origin, heading)| assert_eq!(
+            table.scan(origin, heading, &occluders, &RaycastConfig::default()),
+            lidar_scan(origin, heading, &occluders, &RaycastConfig::default(), &settings),
+            "{origin} {heading}"
+        ));
+    }
+}
@@ -0,0 +1,89 @@
+use glam::*;
+use crate::config::RaycastConfig;
+use crate::occluder::{Occluder, OccluderId};
+use crate::raycasting::{raycast_with_config, Segment, Triangle};
+
+/// Return the points a light along the segment is approximated with: its center for a single sample,
+/// otherwise evenly spread from one end to the other.
+pub fn light_samples(segment: Segment, samples: usize) -> Vec<Vec2> {
+    let [a, b] = segment.points();
+
+    match samples {
+        0 => Vec::new(),
+        1 => vec![a.lerp(b, 0.5)],
+        _ => (0..samples)
+            .map(|i| a.lerp(b, i as f32 / (samples - 1) as f32))
+            .collect()
+    }
+}
+
+/// Return the visibility triangles of every sample point of a light along the segment, like a fluorescent tube.
+///
+/// The union of all triangles is the area lit by the light. They overlap, so render them with a blend mode
+/// which does not add up, or rasterize them into a [crate::raster::CoverageGrid].
+pub fn raycast_linear_light(
+    segment: Segment,
+    samples: usize,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+) -> Vec<Triangle> {
+    light_samples(segment, samples)
+        .into_iter()
+        .flat_map(|origin| raycast_with_config(origin, occluders, config))
+        .collect()
+}
+
+/// Return true if the point can be seen from any sample point of the segment, like checking whether
+/// anything behind a window is visible.
+pub fn visible_from_segment(
+    segment: Segment,
+    samples: usize,
+    point: Vec2,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+) -> bool {
+    light_samples(segment, samples)
+        .into_iter()
+        .any(|origin| {
+            let sight = Segment::new(origin, point);
+
+            occluders
+                .iter()
+                .enumerate()
+                .filter(|(id, occluder)| config.considers(OccluderId::new(*id), occluder))
+                .all(|(_, occluder)| !sight.intersects(occluder.segment))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use crate::config::RaycastConfig;
+    use crate::linear_light::{light_samples, raycast_linear_light, visible_from_segment};
+    use crate::pathological::pathological_scenes;
+    use crate::raycasting::Segment;
+
+    #[test]
+    fn light_samples_works() {
+        let segment = Segment::from_coords(0.0, 0.0, 10.0, 0.0);
+
+        [
+            (0, vec![]),
+            (1, vec![Vec2::new(5.0, 0.0)]),
+            (3, vec![Vec2::new(0.0, 0.0), Vec2::new(5.0, 0.0), Vec2::new(10.0, 0.0)]),
+        ].into_iter().for_each(|(samples, expected)| assert_eq!(light_samples(segment, samples), expected))
+    }
+
+    #[test]
+    fn visible_from_segment_works() {
+        let scene = pathological_scenes().into_iter().find(|scene| scene.name == "t_junction").unwrap();
+        let config = RaycastConfig::default();
+        // the vertical wall of the T goes from (0, 20) down to (0, -10)
+        let window = Segment::from_coords(-20.0, 30.0, -20.0, -30.0);
+        let behind = Vec2::new(20.0, 0.0);
+
+        assert!(!visible_from_segment(window, 1, behind, &scene.occluders, &config));
+        assert!(visible_from_segment(window, 5, behind, &scene.occluders, &config));
+        assert!(!raycast_linear_light(window, 5, &scene.occluders, &config).is_empty());
+    }
+}
@@ -0,0 +1,282 @@
+use glam::*;
+use crate::cone::{cone_intersection_points, ViewCone};
+use crate::config::{PolygonFormat, RaycastConfig};
+use crate::occluder::{Material, Occluder, OccluderId};
+use crate::predicates::cross_sign;
+use crate::raycasting::{intersection_parameter, Segment, visible_occluders};
+
+/// The region seen in a mirror or through a refractive occluder.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReflectedView {
+    /// The mirror or refractive occluder which bent the view last
+    pub mirror: OccluderId,
+    /// 1 for a mirror seen directly, 2 for a mirror seen in a mirror, and so on (refractive occluders count alike)
+    pub depth: usize,
+    /// The outline of the region, starting and ending at the ends of the visible part of the mirror.
+    /// Like a visibility polygon, the points are ordered by angle around the virtual viewer and follow the
+    /// [PolygonFormat] of the config. A resolution resamples the points between the two ends.
+    pub points: Vec<Vec2>,
+}
+
+/// Return the regions the origin sees in the mirrors of the scene (occluders with [Material::Mirror]) and through
+/// refractive occluders ([Material::Refractive]), following them up to the given depth. Together with the
+/// visibility polygon of the origin, they form everything it can see.
+///
+/// The view in a mirror is the view from the mirror image of the origin, through the visible part of the mirror,
+/// considering only the occluders on the side of the mirror facing the origin. The view through a refractive
+/// occluder is the view from the apparent position of the origin as seen from behind the surface, considering only
+/// the occluders behind it. This position lies on the perpendicular through the origin, at its distance to the
+/// surface times the index of refraction, which is exact for rays hitting the surface head-on and good enough
+/// for the visual effect otherwise. Mirrors seen in a mirror are handled the same way, limited to the part of
+/// them visible through the first mirror.
+pub fn reflected_visibility(
+    origin: Vec2,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+    max_depth: usize,
+) -> Vec<ReflectedView> {
+    let mut reflector = Reflector {
+        occluders,
+        config,
+        max_depth,
+        views: Vec::new(),
+    };
+
+    reflector.reflect_mirrors(origin, None, occluders, 1);
+    reflector.views
+}
+
+/// Collects the views of [reflected_visibility] while following the reflections.
+struct Reflector<'a> {
+    occluders: &'a [Occluder],
+    config: &'a RaycastConfig,
+    max_depth: usize,
+    views: Vec<ReflectedView>,
+}
+
+impl Reflector<'_> {
+    /// Add the views in every mirror the eye sees among the visible occluders, limited to the cone if there is one.
+    fn reflect_mirrors(
+        &mut self,
+        eye: Vec2,
+        cone: Option<&ViewCone>,
+        visible: &[Occluder],
+        depth: usize,
+    ) {
+        if depth > self.max_depth {
+            return;
+        }
+
+        for (id, range) in visible_occluders(eye, visible, self.config) {
+            let occluder = visible[id.index];
+
+            if occluder.material == Material::Opaque {
+                continue;
+            }
+
+            let [a, b] = occluder.segment.points();
+            let (start, end) = (a.lerp(b, range.start), a.lerp(b, range.end));
+            let window = match cone {
+                Some(cone) => clip_to_cone(start, end, cone),
+                None => Some(Segment::new(start, end))
+            };
+
+            if let Some(window) = window {
+                self.reflect(eye, window, id, depth)
+            }
+        }
+    }
+
+    /// Add the view of the eye through the window, a part of the given mirror or refractive occluder, and the views
+    /// in the mirrors seen through it.
+    fn reflect(
+        &mut self,
+        eye: Vec2,
+        window: Segment,
+        mirror: OccluderId,
+        depth: usize,
+    ) {
+        let [a, b] = self.occluders[mirror.index].segment.points();
+        let [w0, w1] = window.points();
+
+        if w0.distance(w1) < 0.001 || a == b {
+            return;
+        }
+
+        // reflected rays stay on the side of the eye and refracted ones pass to the other side,
+        // so the occluders on the remaining side take no part
+        let (virtual_eye, side) = match self.occluders[mirror.index].material {
+            Material::Refractive(index) => (refract_point(eye, a, b, index), -cross_sign(a, b, a, eye)),
+            _ => (reflect_point(eye, a, b), cross_sign(a, b, a, eye))
+        };
+        let front = self.occluders
+            .iter()
+            .enumerate()
+            .map(|(index, occluder)| match clip_to_side(occluder.segment, a, b, side) {
+                Some(segment) if index != mirror.index => Occluder { segment, ..*occluder },
+                _ => occluder.with_layers(0)
+            })
+            .collect::<Vec<_>>();
+
+        let coordinate_system = self.config.coordinate_system;
+        let (start, end) = if coordinate_system.to_y_up(w0 - virtual_eye).perp_dot(coordinate_system.to_y_up(w1 - virtual_eye)) >= 0.0 {
+            (w0, w1)
+        } else {
+            (w1, w0)
+        };
+        let (to_start, to_end) = ((start - virtual_eye).normalize(), (end - virtual_eye).normalize());
+        let cone = ViewCone::new(virtual_eye, to_start + to_end, to_start.angle_to(to_end).abs() / 2.0);
+        // the view is the cone without its origin, closed by the mirror, so only the resolution applies to the cone
+        let format = self.config.format;
+        let points = cone_intersection_points(&cone, &front, &self.config.clone().with_format(PolygonFormat { resolution: format.resolution, ..PolygonFormat::default() }));
+        let mut points = [start].into_iter()
+            .chain(points.into_iter().skip(1))
+            .chain([end])
+            .collect::<Vec<_>>();
+        PolygonFormat { resolution: None, ..format }.apply(virtual_eye, &mut points);
+
+        self.views.push(ReflectedView {
+            mirror,
+            depth,
+            points,
+        });
+
+        self.reflect_mirrors(virtual_eye, Some(&cone), &front, depth + 1)
+    }
+}
+
+/// Mirror the point at the line through a and b.
+fn reflect_point(point: Vec2, a: Vec2, b: Vec2) -> Vec2 {
+    let direction = (b - a).normalize();
+    let offset = point - a;
+    a + direction * offset.dot(direction) * 2.0 - offset
+}
+
+/// Return the apparent position of the point seen from behind the line through a and b, with the given index of
+/// refraction: the distance to the line is multiplied with the index.
+fn refract_point(point: Vec2, a: Vec2, b: Vec2, index: f32) -> Vec2 {
+    let direction = (b - a).normalize();
+    let foot = a + direction * (point - a).dot(direction);
+    foot + (point - foot) * index
+}
+
+/// Return the part of the segment on the given side of the line through a and b (points on the line count as
+/// on every side), keeping the direction of the segment. Segments on the line are dropped.
+fn clip_to_side(
+    segment: Segment,
+    a: Vec2,
+    b: Vec2,
+    side: i8,
+) -> Option<Segment> {
+    let [p, q] = segment.points();
+    let (side_p, side_q) = (cross_sign(a, b, a, p), cross_sign(a, b, a, q));
+
+    match (side_p, side_q) {
+        (0, 0) => None,
+        _ if side_p != -side && side_q != -side => Some(segment),
+        _ if side_p != side && side_q != side => None,
+        _ => {
+            let crossing = p.lerp(q, intersection_parameter(p, q - p, a, b - a));
+
+            if side_p == side {
+                Some(Segment::new(p, crossing))
+            } else {
+                Some(Segment::new(crossing, q))
+            }
+        }
+    }
+}
+
+/// Return the part of the segment from p to q inside the cone, if any.
+fn clip_to_cone(p: Vec2, q: Vec2, cone: &ViewCone) -> Option<Segment> {
+    let widened = ViewCone::new(cone.origin, cone.direction, cone.half_angle + 0.0001);
+    let borders = [-cone.half_angle, cone.half_angle].map(|angle| Vec2::from_angle(angle).rotate(cone.direction));
+    let inside = [0.0, 1.0].into_iter()
+        .chain(borders.into_iter().map(|border| intersection_parameter(p, q - p, cone.origin, border)))
+        .filter(|t| (0.0..=1.0).contains(t) && widened.contains(p.lerp(q, *t)))
+        .collect::<Vec<_>>();
+
+    let start = inside.iter().copied().reduce(f32::min)?;
+    let end = inside.iter().copied().reduce(f32::max)?;
+
+    (end - start > 0.0001).then(|| Segment::new(p.lerp(q, start), p.lerp(q, end)))
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use crate::config::RaycastConfig;
+    use crate::mirror::{ReflectedView, reflect_point, reflected_visibility, refract_point};
+    use crate::occluder::{Material, Occluder, OccluderId};
+    use crate::pathological::room;
+    use crate::raycasting::Segment;
+
+    #[test]
+    fn reflect_point_works() {
+        assert!(reflect_point(Vec2::new(0.0, 0.0), Vec2::new(10.0, -5.0), Vec2::new(10.0, 5.0)).distance(Vec2::new(20.0, 0.0)) < 0.0001);
+        assert!(reflect_point(Vec2::new(2.0, 0.0), Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0)).distance(Vec2::new(0.0, 2.0)) < 0.0001);
+        assert!(refract_point(Vec2::new(0.0, 3.0), Vec2::new(10.0, 5.0), Vec2::new(10.0, -5.0), 2.0).distance(Vec2::new(-10.0, 3.0)) < 0.0001);
+    }
+
+    #[test]
+    fn mirror_shows_what_lies_behind_the_viewer() {
+        let mut occluders = room(20.0);
+        occluders.push(Occluder::new(Segment::from_coords(10.0, -5.0, 10.0, 5.0)).with_material(Material::Mirror));
+        // a pillar behind the viewer
+        occluders.push(Occluder::new(Segment::from_coords(-10.0, -1.0, -10.0, 1.0)));
+
+        let views = reflected_visibility(Vec2::ZERO, &occluders, &RaycastConfig::default(), 3);
+
+        assert_eq!(views.len(), 1);
+        let view = &views[0];
+        assert_eq!(view.mirror, OccluderId::new(4));
+        assert_eq!(view.depth, 1);
+        assert!([view.points[0], view.points[view.points.len() - 1]].iter().all(|point| (point.x - 10.0).abs() < 0.001));
+        assert!(view.points.iter().all(|point| point.x <= 10.001), "{:?}", view.points);
+        // the pillar is seen in the mirror and hides a part of the wall behind it
+        assert!(view.points.iter().any(|point| (point.x + 10.0).abs() < 0.001 && point.y.abs() <= 1.001), "{:?}", view.points);
+        assert!(view.points.iter().any(|point| (point.x + 20.0).abs() < 0.001));
+
+        assert!(reflected_visibility(Vec2::ZERO, &occluders, &RaycastConfig::default(), 0).is_empty());
+    }
+
+    #[test]
+    fn facing_mirrors_reflect_up_to_the_depth() {
+        let mut occluders = room(20.0);
+        occluders.push(Occluder::new(Segment::from_coords(10.0, -5.0, 10.0, 5.0)).with_material(Material::Mirror));
+        occluders.push(Occluder::new(Segment::from_coords(-10.0, -5.0, -10.0, 5.0)).with_material(Material::Mirror));
+
+        let views = reflected_visibility(Vec2::ZERO, &occluders, &RaycastConfig::default(), 3);
+
+        for depth in 1..=3 {
+            assert_eq!(views.iter().filter(|view| view.depth == depth).count(), 2, "depth {depth}");
+        }
+
+        assert!(views.iter().all(|view| view.points.len() >= 3));
+    }
+
+    #[test]
+    fn refraction_shows_what_lies_behind_the_surface() {
+        let mut occluders = room(20.0);
+        occluders.push(Occluder::new(Segment::from_coords(10.0, -5.0, 10.0, 5.0)).with_material(Material::Refractive(1.5)));
+        // a pillar behind the glass
+        occluders.push(Occluder::new(Segment::from_coords(15.0, -1.0, 15.0, 1.0)));
+
+        let views = reflected_visibility(Vec2::ZERO, &occluders, &RaycastConfig::default(), 1);
+
+        assert_eq!(views.len(), 1);
+        let view = &views[0];
+        assert_eq!(view.mirror, OccluderId::new(4));
+        assert!([view.points[0], view.points[view.points.len() - 1]].iter().all(|point| (point.x - 10.0).abs() < 0.001));
+        assert!(view.points.iter().all(|point| point.x >= 9.999), "{:?}", view.points);
+        assert!(view.points.iter().any(|point| (point.x - 15.0).abs() < 0.001 && point.y.abs() <= 1.001), "{:?}", view.points);
+
+        // the apparent viewer is farther away than the real one, so less of the wall behind the surface is seen
+        let wall_span = |views: &[ReflectedView]| {
+            let wall = views[0].points.iter().filter(|point| (point.x - 20.0).abs() < 0.001).map(|point| point.y).collect::<Vec<_>>();
+            wall.iter().copied().fold(f32::MIN, f32::max) - wall.iter().copied().fold(f32::MAX, f32::min)
+        };
+        occluders[4].material = Material::Refractive(1.0);
+        assert!(wall_span(&views) < wall_span(&reflected_visibility(Vec2::ZERO, &occluders, &RaycastConfig::default(), 1)));
+    }
+}
@@ -0,0 +1,70 @@
+use glam::*;
+use nalgebra::{Point2, Vector2};
+use crate::raycasting::{Ray, Segment};
+
+// nalgebra converts its points and vectors into Vec2 and back itself (with its convert-glam feature),
+// so functions taking impl Into<Vec2>, like crate::lidar::lidar_scan, accept them directly.
+
+impl From<[Point2<f32>; 2]> for Segment {
+    fn from([a, b]: [Point2<f32>; 2]) -> Self {
+        Segment::new(Vec2::from(a), Vec2::from(b))
+    }
+}
+
+impl From<Segment> for [Point2<f32>; 2] {
+    fn from(segment: Segment) -> Self {
+        segment.points().map(Point2::from)
+    }
+}
+
+/// Create a ray from its origin and direction.
+impl From<(Point2<f32>, Vector2<f32>)> for Ray {
+    fn from((origin, direction): (Point2<f32>, Vector2<f32>)) -> Self {
+        Ray::new(Vec2::from(origin), Vec2::from(direction))
+    }
+}
+
+impl From<Ray> for (Point2<f32>, Vector2<f32>) {
+    fn from(ray: Ray) -> Self {
+        (Point2::from(ray.origin()), Vector2::from(ray.direction()))
+    }
+}
+
+/// Convert points, like a visibility polygon, into nalgebra points.
+pub fn to_points(points: &[Vec2]) -> Vec<Point2<f32>> {
+    points.iter().map(|point| Point2::from(*point)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::TAU;
+    use glam::*;
+    use nalgebra::{Point2, Vector2};
+    use crate::config::RaycastConfig;
+    use crate::lidar::{lidar_scan, LidarSettings};
+    use crate::occluder::Occluder;
+    use crate::raycasting::{Ray, Segment};
+
+    #[test]
+    fn nalgebra_types_convert_to_and_from_the_geometry() {
+        let segment = Segment::from([Point2::new(1.0, 2.0), Point2::new(3.0, 4.0)]);
+        assert_eq!(segment, Segment::from_coords(1.0, 2.0, 3.0, 4.0));
+        assert_eq!(<[Point2<f32>; 2]>::from(segment), [Point2::new(1.0, 2.0), Point2::new(3.0, 4.0)]);
+
+        let ray = Ray::from((Point2::new(1.0, 2.0), Vector2::new(0.0, 1.0)));
+        assert_eq!(ray, Ray::new(Vec2::new(1.0, 2.0), Vec2::Y));
+        assert_eq!(<(Point2<f32>, Vector2<f32>)>::from(ray), (Point2::new(1.0, 2.0), Vector2::new(0.0, 1.0)));
+    }
+
+    #[test]
+    fn lidar_scans_accept_nalgebra_types() {
+        let occluders = [Occluder::new(Segment::from_coords(10.0, -50.0, 10.0, 50.0))];
+        let settings = LidarSettings { beams: 8, field_of_view: TAU, max_range: 100.0 };
+        let config = RaycastConfig::default();
+
+        assert_eq!(
+            lidar_scan(Point2::new(1.0, 2.0), Vector2::new(1.0, 0.0), &occluders, &config, &settings),
+            lidar_scan(Vec2::new(1.0, 2.0), Vec2::X, &occluders, &config, &settings),
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use glam::*;
+use crate::config::CoordinateSystem;
+use crate::groups::GroupId;
+use crate::predicates::cross_sign;
+use crate::raycasting::Segment;
+use crate::transform::Transform2;
+
+/// Layer mask matching every layer.
+pub const ALL_LAYERS: u32 = u32::MAX;
+
+/// Identifies an occluder by its index in the occluders passed to a query.
+///
+/// An [crate::occluder_set::OccluderSet] gives the slot of a removed occluder to the next added one. Every
+/// reuse increases the generation of the slot, so an id kept from before refers to no occluder instead of the
+/// new one. Queries over slices only know indices and return ids of generation 0, [crate::occluder_set::OccluderSet::id]
+/// turns an index into the current id of its slot.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub struct OccluderId {
+    pub index: usize,
+    pub generation: u32,
+}
+
+impl OccluderId {
+    /// Create the id of generation 0 for the given index, like the ids returned by queries over slices.
+    pub const fn new(index: usize) -> Self {
+        OccluderId { index, generation: 0 }
+    }
+}
+
+/// A segment which blocks rays, together with the layers it lives on.
+///
+/// A query only considers occluders which share at least one layer with the query's mask. This way,
+/// glass can block bullets but not sight, or the collider of a guard can be excluded from its own vision.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub struct Occluder {
+    pub segment: Segment,
+    pub layers: u32,
+    /// How much this occluder dampens what passes through it, from 0 (nothing) to 1 (as much as possible).
+    /// Visibility rays stop at every occluder regardless; only queries which let things pass through
+    /// occluders, like sound or [crate::tint::tinted_visibility], use it.
+    pub opacity: f32,
+    /// The color (RGB, from 0 to 1) of the light passing through the opaque part of this occluder, like the
+    /// glass of a stained-glass window. Black by default, so only the transparent part lets light through.
+    pub tint: Vec3,
+    pub kind: OccluderKind,
+    /// Whether this occluder gives full or only half cover, see [crate::exposure::evaluate_cover].
+    /// Visibility rays stop at low occluders as well.
+    pub height: OccluderHeight,
+    /// The lowest and highest elevation at which this occluder blocks rays, see [crate::config::RaycastConfig::with_height].
+    /// Unbounded by default, so it blocks at every elevation.
+    pub z_min: f32,
+    pub z_max: f32,
+    pub material: Material,
+    /// The group this occluder belongs to, see [crate::groups::OccluderGroups]
+    pub group: Option<GroupId>,
+}
+
+impl Occluder {
+    /// Create an occluder on all layers with the default properties. This is a const fn, so occluders
+    /// can be defined in constants and statics, see [crate::occluders!].
+    pub const fn new(segment: Segment) -> Self {
+        Self {
+            segment,
+            layers: ALL_LAYERS,
+            opacity: 1.0,
+            tint: Vec3::ZERO,
+            kind: OccluderKind::Open,
+            height: OccluderHeight::Full,
+            z_min: f32::NEG_INFINITY,
+            z_max: f32::INFINITY,
+            material: Material::Opaque,
+            group: None,
+        }
+    }
+
+    /// Like [Occluder::polygon], but at compile time: return the edges of the closed polygon through the points.
+    /// The points must already be ordered counterclockwise, as a const fn cannot check their winding.
+    pub const fn const_polygon<const N: usize>(points: [Vec2; N]) -> [Occluder; N] {
+        let mut edges = [Occluder::new(Segment::new(Vec2::ZERO, Vec2::ZERO)); N];
+        let mut i = 0;
+
+        while i < N {
+            edges[i] = Occluder::new(Segment::new(points[i], points[(i + 1) % N])).with_kind(OccluderKind::ClosedPolygon);
+            i += 1;
+        }
+
+        edges
+    }
+
+    /// Return the edges of the closed polygon through the given points. The edges are counterclockwise
+    /// regardless of the order of the points, so their back faces can be culled.
+    pub fn polygon(points: &[Vec2]) -> Vec<Occluder> {
+        let signed_area = (0..points.len())
+            .map(|i| points[i].perp_dot(points[(i + 1) % points.len()]))
+            .sum::<f32>();
+        let mut points = points.to_vec();
+
+        if signed_area < 0.0 {
+            points.reverse();
+        }
+
+        (0..points.len())
+            .map(|i| Occluder::new(Segment::new(points[i], points[(i + 1) % points.len()])).with_kind(OccluderKind::ClosedPolygon))
+            .collect()
+    }
+
+    pub const fn with_layers(mut self, layers: u32) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_tint(mut self, tint: Vec3) -> Self {
+        self.tint = tint.clamp(Vec3::ZERO, Vec3::ONE);
+        self
+    }
+
+    pub const fn with_kind(mut self, kind: OccluderKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub const fn with_height(mut self, height: OccluderHeight) -> Self {
+        self.height = height;
+        self
+    }
+
+    /// Only block rays between the given elevations, like the railing of a catwalk above the floor.
+    pub fn with_z_range(mut self, z_min: f32, z_max: f32) -> Self {
+        self.z_min = z_min;
+        self.z_max = z_max;
+        self
+    }
+
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = material;
+        self
+    }
+
+    pub fn with_group(mut self, group: GroupId) -> Self {
+        self.group = Some(group);
+        self
+    }
+
+    /// Return true if this occluder is in none of the groups of the given mask.
+    pub fn is_enabled(&self, disabled_groups: u64) -> bool {
+        match self.group {
+            Some(group) => disabled_groups & group.bit() == 0,
+            None => true
+        }
+    }
+
+    /// Return false if this occluder is the back face of a closed polygon as seen from the origin,
+    /// i.e. the origin lies on the inner side of it. A ray from outside the polygon always hits a front face first,
+    /// so back faces can be skipped. Seen from inside the polygon, all of its edges are back faces.
+    pub fn is_facing(&self, origin: Vec2) -> bool {
+        let [a, b] = self.segment.points();
+
+        match self.kind {
+            OccluderKind::Open => true,
+            OccluderKind::ClosedPolygon => cross_sign(a, b, a, origin) <= 0
+        }
+    }
+
+    /// Convert this occluder between the given coordinate system and [CoordinateSystem::YUp]. Mirroring reverses
+    /// the winding, so the endpoints are swapped to keep the edges of closed polygons counterclockwise.
+    pub fn to_y_up(self, coordinate_system: CoordinateSystem) -> Occluder {
+        match coordinate_system {
+            CoordinateSystem::YUp => self,
+            CoordinateSystem::YDown => Transform2::from_scale(Vec2::new(1.0, -1.0)).apply_to_occluder(self)
+        }
+    }
+
+    /// Return the fraction of each color channel of the light passing through this occluder: the transparent
+    /// part lets everything through, the opaque part only its tint.
+    pub fn transmittance(&self) -> Vec3 {
+        Vec3::splat(1.0 - self.opacity) + self.tint * self.opacity
+    }
+
+    /// Return true if this occluder blocks rays at the given elevation.
+    pub fn blocks_at(&self, z: f32) -> bool {
+        self.z_min <= z && z <= self.z_max
+    }
+
+    /// Return true if this occluder lives on at least one of the layers of the given mask.
+    pub fn is_on_layers(&self, mask: u32) -> bool {
+        self.layers & mask != 0
+    }
+}
+
+/// Where an occluder comes from, which decides whether it can be culled.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub enum OccluderKind {
+    /// A standalone segment, visible from both sides
+    #[default]
+    Open,
+    /// A counterclockwise edge of a closed polygon, visible only from outside the polygon
+    ClosedPolygon,
+}
+
+/// How tall an occluder is, compared to the things hiding behind it.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub enum OccluderHeight {
+    /// A wall, which hides everything behind it
+    #[default]
+    Full,
+    /// A low wall, fence or crate, which hides only the lower half of things behind it
+    Low,
+}
+
+/// How an occluder affects the rays which hit it.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub enum Material {
+    /// Rays stop at the occluder.
+    #[default]
+    Opaque,
+    /// Rays stop at the occluder, but the view continues in the mirror, see [crate::mirror::reflected_visibility].
+    Mirror,
+    /// Rays stop at the occluder, but the view continues through it, bent at the surface like when looking into
+    /// water or through thick glass. The value is the index of refraction of the material behind the surface
+    /// relative to the one in front of it, like 1.33 for water seen from air.
+    Refractive(f32),
+}
+
+/// Define an array of occluders at compile time, one per segment given by its coordinates `(x0, y0, x1, y1)`,
+/// like a static level layout:
+///
+/// ```ignore
+/// static WALLS: [Occluder; 2] = occluders![(0.0, 0.0, 100.0, 0.0), (100.0, 0.0, 100.0, 50.0)];
+/// ```
+#[macro_export]
+macro_rules! occluders {
+    ($(($x0:expr, $y0:expr, $x1:expr, $y1:expr)),* $(,)?) => {
+        [$($crate::occluder::Occluder::new($crate::raycasting::Segment::from_coords($x0, $y0, $x1, $y1))),*]
+    };
+}
+
+impl From<Segment> for Occluder {
+    fn from(segment: Segment) -> Self {
+        Occluder::new(segment)
+    }
+}
@@ -0,0 +1,702 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use glam::*;
+use crate::aabb::Aabb;
+use crate::cone::{cone_intersection_points, ViewCone};
+use crate::config::{RaycastConfig, SelfHitPolicy};
+use crate::occluder::{Occluder, OccluderId};
+use crate::raycasting::Segment;
+use crate::sanitize::{sanitize, SanitizeReport};
+
+/// Occluders together with a uniform grid over them, for queries which only care about the occluders
+/// near some point.
+///
+/// The index of an id is the index of its occluder in [OccluderSet::occluders], just like in the slices passed
+/// to the other queries. A removed occluder stays in that slice on no layers, so queries skip it, until the next
+/// added occluder reuses its slot with a new generation (see [OccluderId]).
+///
+/// The occluders and the grid are shared copy-on-write, so a [OccluderSet::snapshot] is cheap and only
+/// the first change after it copies them. A rollback networking game can keep a snapshot per tick and
+/// [OccluderSet::restore] an old one to rerun its queries.
+///
+/// Every state of a set has a [OccluderSet::revision], so data computed from it can be cached and checked
+/// against the revision instead of tracking changes separately.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect), reflect(opaque, Debug))]
+pub struct OccluderSet {
+    occluders: Arc<Vec<Occluder>>,
+    slots: Arc<Slots>,
+    index: Arc<SegmentIndex>,
+    revision: u64,
+}
+
+/// The state of every slot of an occluder set, and the slots of removed occluders, which are reused first
+#[derive(Clone, Debug)]
+struct Slots {
+    entries: Vec<Slot>,
+    free: Vec<usize>,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Slot {
+    /// The generation of the current occluder, or of the next one if the slot is free
+    generation: u32,
+    occupied: bool,
+}
+
+/// The last revision given to any occluder set
+static LAST_REVISION: AtomicU64 = AtomicU64::new(0);
+
+fn next_revision() -> u64 {
+    LAST_REVISION.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+impl OccluderSet {
+    /// Create a set from the given occluders, indexed by a grid of cells with the given size.
+    /// The cell size should be in the order of the typical occluder length, like the tile size of a map.
+    pub fn new(
+        occluders: Vec<Occluder>,
+        cell_size: f32,
+    ) -> Self {
+        let index = SegmentIndex::build(&occluders, cell_size);
+        let slots = Slots {
+            entries: vec![Slot { generation: 0, occupied: true }; occluders.len()],
+            free: Vec::new(),
+        };
+
+        OccluderSet {
+            occluders: Arc::new(occluders),
+            slots: Arc::new(slots),
+            index: Arc::new(index),
+            revision: next_revision(),
+        }
+    }
+
+    /// Add an occluder and return its id. The slot of a removed occluder is reused if there is one.
+    pub fn push(&mut self, occluder: Occluder) -> OccluderId {
+        let slots = Arc::make_mut(&mut self.slots);
+        let occluders = Arc::make_mut(&mut self.occluders);
+        let index = match slots.free.pop() {
+            Some(index) => {
+                occluders[index] = occluder;
+                index
+            }
+            None => {
+                occluders.push(occluder);
+                slots.entries.push(Slot { generation: 0, occupied: false });
+                occluders.len() - 1
+            }
+        };
+        slots.entries[index].occupied = true;
+        self.revision = next_revision();
+
+        if !Arc::make_mut(&mut self.index).insert(index, occluder.segment) {
+            self.rebuild_index()
+        }
+
+        self.current_id(index)
+    }
+
+    /// Replace the occluder with the given id and return the old one. If the id is stale, nothing changes and None is returned.
+    pub fn set(&mut self, id: OccluderId, occluder: Occluder) -> Option<Occluder> {
+        if !self.contains(id) {
+            return None;
+        }
+
+        let old = std::mem::replace(&mut Arc::make_mut(&mut self.occluders)[id.index], occluder);
+        self.revision = next_revision();
+        let index = Arc::make_mut(&mut self.index);
+        index.remove(id.index, old.segment);
+
+        if !index.insert(id.index, occluder.segment) {
+            self.rebuild_index()
+        }
+
+        Some(old)
+    }
+
+    /// Remove the occluder with the given id and return it, or return None if the id is stale.
+    /// The id and every copy of it are stale afterwards, even once another occluder reuses the slot.
+    pub fn remove(&mut self, id: OccluderId) -> Option<Occluder> {
+        if !self.contains(id) {
+            return None;
+        }
+
+        let slots = Arc::make_mut(&mut self.slots);
+        let slot = &mut slots.entries[id.index];
+        slot.generation = slot.generation.wrapping_add(1);
+        slot.occupied = false;
+        slots.free.push(id.index);
+
+        // the removed occluder stays in its slot on no layers, so the indices of the other occluders do not change
+        let occluder = &mut Arc::make_mut(&mut self.occluders)[id.index];
+        let removed = *occluder;
+        *occluder = removed.with_layers(0);
+        Arc::make_mut(&mut self.index).remove(id.index, removed.segment);
+        self.revision = next_revision();
+
+        Some(removed)
+    }
+
+    /// Return a copy of the current state of this set, which shares all data until either of them changes.
+    pub fn snapshot(&self) -> OccluderSet {
+        self.clone()
+    }
+
+    /// Go back to the state of the given snapshot, including its revision.
+    pub fn restore(&mut self, snapshot: &OccluderSet) {
+        *self = snapshot.clone()
+    }
+
+    /// Fix messy occluders in place (see [crate::sanitize::sanitize]) and return what was fixed.
+    /// This changes the ids of the occluders, so every id from before is stale afterwards.
+    pub fn sanitize(&mut self, tolerance: f32) -> SanitizeReport {
+        let live = self.ids().map(|id| self.occluders[id.index]).collect::<Vec<_>>();
+        let (occluders, report) = sanitize(&live, tolerance);
+        let generation = self.slots.entries
+            .iter()
+            .map(|slot| slot.generation.wrapping_add(1))
+            .max()
+            .unwrap_or(0);
+
+        *self = OccluderSet::new(occluders, self.index.cell_size);
+        Arc::make_mut(&mut self.slots).entries.iter_mut().for_each(|slot| slot.generation = generation);
+        report
+    }
+
+    /// Build the grid from scratch, for occluders outside the current one.
+    fn rebuild_index(&mut self) {
+        let mut index = SegmentIndex::build(&self.occluders, self.index.cell_size);
+        self.slots.free.iter().for_each(|free| index.remove(*free, self.occluders[*free].segment));
+        self.index = Arc::new(index)
+    }
+
+    /// Return the revision of the current state of this set. Every change gives the set a new revision, larger than
+    /// every revision before, and no two sets share a revision unless one is a snapshot of the other. So data
+    /// computed from a set is still valid as long as the revision of the set is the same as back then.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Return the occluders of every slot, including the removed ones on no layers.
+    pub fn occluders(&self) -> &[Occluder] {
+        &self.occluders
+    }
+
+    /// Return the occluder with the given id, or None if the id is stale.
+    pub fn get(&self, id: OccluderId) -> Option<&Occluder> {
+        self.contains(id).then(|| &self.occluders[id.index])
+    }
+
+    /// Return true if the id refers to an occluder of this set, so it was neither removed nor made stale by [OccluderSet::sanitize].
+    pub fn contains(&self, id: OccluderId) -> bool {
+        self.slots.entries
+            .get(id.index)
+            .map(|slot| slot.occupied && slot.generation == id.generation)
+            .unwrap_or(false)
+    }
+
+    /// Return the current id of the occluder at the given index of [OccluderSet::occluders], or None if the slot is free.
+    /// This turns the ids returned by queries over the occluders of this set, which have generation 0, into ids of the set.
+    pub fn id(&self, index: usize) -> Option<OccluderId> {
+        self.slots.entries
+            .get(index)
+            .filter(|slot| slot.occupied)
+            .map(|_| self.current_id(index))
+    }
+
+    /// Return the ids of all occluders in this set, in the order of their indices.
+    pub fn ids(&self) -> impl Iterator<Item=OccluderId> + '_ {
+        (0..self.occluders.len()).filter_map(|index| self.id(index))
+    }
+
+    fn current_id(&self, index: usize) -> OccluderId {
+        OccluderId { index, generation: self.slots.entries[index].generation }
+    }
+
+    /// Return the number of occluders in this set, without the removed ones.
+    pub fn len(&self) -> usize {
+        self.occluders.len() - self.slots.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return true if the segment from one point to the other does not touch any occluder.
+    /// Grazing the endpoint of an occluder blocks the line of sight as well.
+    pub fn has_line_of_sight(&self, from: Vec2, to: Vec2) -> bool {
+        let sight = Segment::new(from, to);
+
+        self.index
+            .ids_in(Aabb::new(from.min(to), from.max(to)))
+            .into_iter()
+            .all(|id| !sight.intersects(self.occluders[id].segment))
+    }
+
+    /// Return the ids of all occluders touched by the segment from one point to the other, in no particular order.
+    pub fn occluders_between(&self, from: Vec2, to: Vec2) -> Vec<OccluderId> {
+        let sight = Segment::new(from, to);
+
+        self.index
+            .ids_in(Aabb::new(from.min(to), from.max(to)))
+            .into_iter()
+            .filter(|id| sight.intersects(self.occluders[*id].segment))
+            .map(|id| self.current_id(id))
+            .collect()
+    }
+
+    /// Return the ids of all occluders which overlap the cone (see [ViewCone::overlaps_segment]), in the order of their ids.
+    pub fn occluders_in_cone(&self, cone: &ViewCone) -> Vec<OccluderId> {
+        self.index
+            .query_cone(&self.occluders, cone.origin, cone.direction, cone.half_angle)
+            .into_iter()
+            .map(|id| self.current_id(id))
+            .collect()
+    }
+
+    /// Return the visibility polygon of the cone, like [crate::cone::cone_intersection_points], but only consider
+    /// the occluders overlapping the cone. The others cannot change the polygon, so a narrow cone in a large
+    /// scene does not have to test every occluder.
+    ///
+    /// A [SelfHitPolicy::ExcludeOccluder] in the config takes an id of this set. Stale ids exclude nothing,
+    /// not even the occluder which reused their slot.
+    pub fn cone_intersection_points(
+        &self,
+        cone: &ViewCone,
+        config: &RaycastConfig,
+    ) -> Vec<Vec2> {
+        let ids = self.index.query_cone(&self.occluders, cone.origin, cone.direction, cone.half_angle);
+        let occluders = ids.iter().map(|id| self.occluders[*id]).collect::<Vec<_>>();
+
+        // the ids in the config refer to this set, not to the occluders in the cone, and stale ones exclude nothing
+        let self_hit_policy = match config.self_hit_policy {
+            SelfHitPolicy::ExcludeOccluder(excluded) if self.contains(excluded) => ids
+                .binary_search(&excluded.index)
+                .map(|index| SelfHitPolicy::ExcludeOccluder(OccluderId::new(index)))
+                .unwrap_or(SelfHitPolicy::None),
+            SelfHitPolicy::ExcludeOccluder(_) => SelfHitPolicy::None,
+            policy => policy
+        };
+
+        cone_intersection_points(cone, &occluders, &config.clone().with_self_hit_policy(self_hit_policy))
+    }
+
+    /// Return the point on any occluder which is nearest to the given point, or None if the set is empty.
+    ///
+    /// Searches the grid in rings around the cell of the point and stops as soon as no unvisited
+    /// cell can contain anything closer.
+    pub fn closest_point(&self, point: Vec2) -> Option<ClosestPoint> {
+        let index = &self.index;
+        let (cx, cy) = index.cell_of(point);
+        let (last_x, last_y) = (index.width as isize - 1, index.height as isize - 1);
+        // rings closer than the grid do not contain any cell, so start at the first one which does
+        let first_ring = (-cx).max(cx - last_x).max((-cy).max(cy - last_y)).max(0);
+        let last_ring = [cx, last_x - cx, cy, last_y - cy]
+            .into_iter()
+            .map(isize::abs)
+            .max()
+            .unwrap_or(0);
+        let mut closest: Option<ClosestPoint> = None;
+
+        for ring in first_ring..=last_ring {
+            for (x, y) in index.ring(cx, cy, ring) {
+                for id in index.cell(x, y) {
+                    let candidate = self.occluders[*id].segment.closest_point(point);
+                    let distance = candidate.distance(point);
+
+                    if closest.map(|c| distance < c.distance).unwrap_or(true) {
+                        closest = Some(ClosestPoint {
+                            point: candidate,
+                            distance,
+                            occluder: self.current_id(*id),
+                        })
+                    }
+                }
+            }
+
+            // every cell outside this ring is at least this far away from the point
+            if let Some(closest) = closest {
+                if closest.distance <= ring as f32 * index.cell_size {
+                    break;
+                }
+            }
+        }
+
+        closest
+    }
+}
+
+/// The result of [OccluderSet::closest_point]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ClosestPoint {
+    pub point: Vec2,
+    pub distance: f32,
+    pub occluder: OccluderId,
+}
+
+/// A uniform grid over the bounding box of some occluders. Every cell holds the ids of all occluders
+/// whose bounding box overlaps it.
+#[derive(Clone, Debug)]
+struct SegmentIndex {
+    /// World position of the lower left corner of the cell (0, 0)
+    origin: Vec2,
+    cell_size: f32,
+    width: usize,
+    height: usize,
+    cells: Vec<Vec<usize>>,
+}
+
+impl SegmentIndex {
+    fn build(
+        occluders: &[Occluder],
+        cell_size: f32,
+    ) -> Self {
+        let bounds = occluders
+            .iter()
+            .flat_map(|occluder| occluder.segment.points())
+            .fold(None, |bounds: Option<Aabb>, point| match bounds {
+                Some(bounds) => Some(Aabb::new(bounds.min.min(point), bounds.max.max(point))),
+                None => Some(Aabb::new(point, point))
+            })
+            .unwrap_or(Aabb::new(Vec2::ZERO, Vec2::ZERO));
+
+        let size = bounds.max - bounds.min;
+        let width = ((size.x / cell_size).floor() as usize + 1).max(1);
+        let height = ((size.y / cell_size).floor() as usize + 1).max(1);
+        let mut index = SegmentIndex {
+            origin: bounds.min,
+            cell_size,
+            width,
+            height,
+            cells: vec![Vec::new(); width * height],
+        };
+
+        for (id, occluder) in occluders.iter().enumerate() {
+            index.insert(id, occluder.segment);
+        }
+
+        index
+    }
+
+    /// Add the id to every cell overlapping the bounding box of the segment.
+    /// Return false if the segment does not fit into the grid, which is left unchanged then.
+    fn insert(&mut self, id: usize, segment: Segment) -> bool {
+        let cells = match self.cells_of(segment) {
+            Some(cells) => cells,
+            None => return false
+        };
+
+        for cell in cells {
+            self.cells[cell].push(id)
+        }
+
+        true
+    }
+
+    /// Remove the id from every cell overlapping the bounding box of the segment.
+    fn remove(&mut self, id: usize, segment: Segment) {
+        for cell in self.cells_of(segment).unwrap_or_default() {
+            self.cells[cell].retain(|other| *other != id)
+        }
+    }
+
+    /// Return the indices of all cells overlapping the bounding box of the segment, or None if it does not fit into the grid.
+    fn cells_of(&self, segment: Segment) -> Option<Vec<usize>> {
+        let [a, b] = segment.points();
+        let (x0, y0) = self.cell_of(a.min(b));
+        let (x1, y1) = self.cell_of(a.max(b));
+
+        if x0 < 0 || y0 < 0 || x1 as usize >= self.width || y1 as usize >= self.height {
+            return None;
+        }
+
+        Some((y0..=y1)
+            .flat_map(|y| (x0..=x1).map(move |x| y as usize * self.width + x as usize))
+            .collect())
+    }
+
+    /// Return the ids of all occluders in the cells overlapping the given box, without duplicates.
+    fn ids_in(&self, aabb: Aabb) -> Vec<usize> {
+        let (x0, y0) = self.cell_of(aabb.min);
+        let (x1, y1) = self.cell_of(aabb.max);
+        let mut ids = Vec::new();
+
+        for y in y0.max(0)..=y1.min(self.height as isize - 1) {
+            for x in x0.max(0)..=x1.min(self.width as isize - 1) {
+                ids.extend_from_slice(self.cell(x, y));
+            }
+        }
+
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    /// Return the ids of all occluders overlapping the cone with the given origin, direction and half angle (radians),
+    /// ordered and without duplicates. Only the occluders in cells overlapping the cone are tested.
+    fn query_cone(
+        &self,
+        occluders: &[Occluder],
+        origin: Vec2,
+        direction: Vec2,
+        half_angle: f32,
+    ) -> Vec<usize> {
+        let cone = ViewCone::new(origin, direction, half_angle);
+        let mut ids = Vec::new();
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let min = self.origin + Vec2::new(x as f32, y as f32) * self.cell_size;
+
+                if cone.overlaps_aabb(&Aabb::new(min, min + Vec2::splat(self.cell_size))) {
+                    ids.extend_from_slice(&self.cells[y * self.width + x]);
+                }
+            }
+        }
+
+        ids.sort_unstable();
+        ids.dedup();
+        ids.retain(|id| cone.overlaps_segment(occluders[*id].segment));
+        ids
+    }
+
+    /// Return the coordinates of the cell containing the point. They lie outside the grid if the point does.
+    fn cell_of(&self, point: Vec2) -> (isize, isize) {
+        let cell = ((point - self.origin) / self.cell_size).floor();
+        (cell.x as isize, cell.y as isize)
+    }
+
+    /// Return the coordinates of the cells inside the grid which lie on the border of the square
+    /// with the given radius (in cells) around the given cell.
+    fn ring(&self, cx: isize, cy: isize, ring: isize) -> impl Iterator<Item=(isize, isize)> {
+        let (last_x, last_y) = (self.width as isize - 1, self.height as isize - 1);
+        let xs = (cx - ring).max(0)..=(cx + ring).min(last_x);
+        let ys = (cy - ring + 1).max(0)..=(cy + ring - 1).min(last_y);
+        let rows = [cy - ring, cy + ring]
+            .into_iter()
+            .take(if ring == 0 { 1 } else { 2 })
+            .filter(move |y| (0..=last_y).contains(y))
+            .flat_map(move |y| xs.clone().map(move |x| (x, y)));
+        let columns = [cx - ring, cx + ring]
+            .into_iter()
+            .take(if ring == 0 { 0 } else { 2 })
+            .filter(move |x| (0..=last_x).contains(x))
+            .flat_map(move |x| ys.clone().map(move |y| (x, y)));
+
+        rows.chain(columns)
+    }
+
+    /// Return the ids in the given cell. Cells outside the grid are empty.
+    fn cell(&self, x: isize, y: isize) -> &[usize] {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return &[];
+        }
+
+        &self.cells[y as usize * self.width + x as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+    use std::fs;
+    use std::path::PathBuf;
+    use glam::*;
+    use crate::cone::{cone_intersection_points, ViewCone};
+    use crate::config::{RaycastConfig, SelfHitPolicy};
+    use crate::occluder::{Occluder, OccluderId};
+    use crate::occluder_set::OccluderSet;
+    use crate::raycasting::{calculate_intersection_points, Segment};
+    use crate::scene::Scene;
+
+    #[test]
+    fn closest_point_matches_brute_force() {
+        let scene = Scene::parse("######\n#....#\n#.#..#\n#...##\n######\n").unwrap();
+        let set = OccluderSet::new(scene.occluders(10.0), 10.0);
+
+        for y in -5..60 {
+            for x in -5..70 {
+                let point = Vec2::new(x as f32 * 1.3, y as f32 * 0.9);
+                let expected = set
+                    .occluders()
+                    .iter()
+                    .map(|occluder| occluder.segment.distance_to(point))
+                    .fold(f32::INFINITY, f32::min);
+                let closest = set.closest_point(point).unwrap();
+
+                assert!((closest.distance - expected).abs() < 0.0001, "{point}: {} != {expected}", closest.distance);
+                assert_eq!(closest.point.distance(point), closest.distance);
+                assert!(set.get(closest.occluder).unwrap().segment.distance_to(closest.point) < 0.0001);
+            }
+        }
+    }
+
+    #[test]
+    fn closest_point_is_found_far_away_from_the_grid() {
+        let scene = Scene::parse("#####\n#...#\n#.#.#\n#...#\n#####\n").unwrap();
+        let set = OccluderSet::new(scene.occluders(10.0), 10.0);
+
+        // a million cells away, which takes forever if every ring on the way is searched
+        [Vec2::new(1.0e7, 20.0), Vec2::new(-3.0e4, -5.0e6), Vec2::new(25.0, 4.0e4)]
+            .into_iter()
+            .for_each(|point| {
+                let expected = set
+                    .occluders()
+                    .iter()
+                    .map(|occluder| occluder.segment.distance_to(point))
+                    .fold(f32::INFINITY, f32::min);
+
+                let distance = set.closest_point(point).unwrap().distance;
+
+                assert!((distance - expected).abs() <= expected * 0.000001, "{point}: {distance} != {expected}");
+            })
+    }
+
+    #[test]
+    fn has_line_of_sight_works() {
+        let scene = Scene::parse("#####\n#...#\n#.#.#\n#...#\n#####\n").unwrap();
+        let set = OccluderSet::new(scene.occluders(10.0), 10.0);
+
+        [
+            (Vec2::new(15.0, 15.0), Vec2::new(35.0, 15.0), true),
+            (Vec2::new(15.0, 15.0), Vec2::new(15.0, 35.0), true),
+            (Vec2::new(15.0, 15.0), Vec2::new(35.0, 35.0), false),
+            (Vec2::new(15.0, 25.0), Vec2::new(35.0, 25.0), false),
+            // grazing the corner of the pillar
+            (Vec2::new(12.0, 22.0), Vec2::new(28.0, 38.0), false),
+            (Vec2::new(15.0, 15.0), Vec2::new(60.0, 15.0), false),
+        ].into_iter().for_each(|(from, to, expected)| assert_eq!(set.has_line_of_sight(from, to), expected, "{from} -> {to}"))
+    }
+
+    #[test]
+    fn closest_point_of_an_empty_set_is_none() {
+        let set = OccluderSet::new(Vec::new(), 10.0);
+
+        assert_eq!(set.closest_point(Vec2::ZERO), None);
+        assert!(set.get(OccluderId::new(0)).is_none());
+    }
+
+    #[test]
+    fn every_change_gives_a_new_revision() {
+        let wall = |x: f32| Occluder::new(Segment::from_coords(x, -10.0, x, 10.0));
+        let mut set = OccluderSet::new(vec![wall(10.0)], 10.0);
+        let other = OccluderSet::new(vec![wall(10.0)], 10.0);
+        let initial = set.revision();
+
+        assert_ne!(initial, other.revision());
+        assert_eq!(set.snapshot().revision(), initial);
+
+        set.push(wall(20.0));
+        let pushed = set.revision();
+        set.set(OccluderId::new(0), wall(30.0));
+
+        assert!(initial < pushed && pushed < set.revision());
+    }
+
+    #[test]
+    fn snapshots_are_restored() {
+        let wall = |x: f32| Occluder::new(Segment::from_coords(x, -10.0, x, 10.0));
+        let mut set = OccluderSet::new(vec![wall(10.0), wall(20.0)], 10.0);
+        let snapshot = set.snapshot();
+
+        set.set(OccluderId::new(0), wall(30.0));
+        let pushed = set.push(wall(-100.0));
+
+        assert_eq!(pushed, OccluderId::new(2));
+        assert_eq!(set.closest_point(Vec2::ZERO).map(|closest| closest.distance), Some(20.0));
+        assert_eq!(set.closest_point(Vec2::new(-90.0, 0.0)).map(|closest| closest.occluder), Some(pushed));
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot.closest_point(Vec2::ZERO).map(|closest| closest.distance), Some(10.0));
+
+        set.restore(&snapshot);
+        assert_eq!(set.revision(), snapshot.revision());
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.closest_point(Vec2::ZERO).map(|closest| closest.distance), Some(10.0));
+    }
+
+    #[test]
+    fn removed_ids_become_stale() {
+        let wall = |x: f32| Occluder::new(Segment::from_coords(x, -10.0, x, 10.0));
+        let mut set = OccluderSet::new(vec![wall(10.0), wall(20.0), wall(-20.0)], 10.0);
+        let removed = set.id(0).unwrap();
+
+        assert_eq!(set.remove(removed), Some(wall(10.0)));
+        assert_eq!(set.remove(removed), None);
+        assert_eq!(set.len(), 2);
+        assert!(set.has_line_of_sight(Vec2::ZERO, Vec2::new(15.0, 0.0)));
+        assert_eq!(
+            calculate_intersection_points(Vec2::ZERO, set.occluders(), &RaycastConfig::default()),
+            calculate_intersection_points(Vec2::ZERO, &[wall(20.0), wall(-20.0)], &RaycastConfig::default())
+        );
+
+        // the next occluder reuses the slot, but the removed id does not refer to it
+        let pushed = set.push(wall(-30.0));
+
+        assert_eq!(pushed.index, removed.index);
+        assert_eq!(set.get(removed), None);
+        assert_eq!(set.set(removed, wall(40.0)), None);
+        assert_eq!(set.get(pushed), Some(&wall(-30.0)));
+        assert_eq!(set.occluders_between(Vec2::new(-40.0, 0.0), Vec2::new(-25.0, 0.0)), vec![pushed]);
+        assert_eq!(set.ids().collect::<Vec<_>>(), vec![pushed, OccluderId::new(1), OccluderId::new(2)]);
+
+        let cone = ViewCone::new(Vec2::new(-40.0, 0.0), Vec2::X, PI / 4.0);
+        assert_eq!(
+            set.cone_intersection_points(&cone, &RaycastConfig::default().with_self_hit_policy(SelfHitPolicy::ExcludeOccluder(removed))),
+            set.cone_intersection_points(&cone, &RaycastConfig::default())
+        );
+        assert_ne!(
+            set.cone_intersection_points(&cone, &RaycastConfig::default().with_self_hit_policy(SelfHitPolicy::ExcludeOccluder(pushed))),
+            set.cone_intersection_points(&cone, &RaycastConfig::default())
+        );
+
+        set.sanitize(0.01);
+        assert_eq!(set.len(), 3);
+        assert!(!set.contains(pushed) && !set.contains(OccluderId::new(1)));
+    }
+
+    #[test]
+    fn sanitizing_rebuilds_the_set() {
+        let mut set = OccluderSet::new(vec![
+            Occluder::new(Segment::from_coords(-10.0, 0.0, 10.0, 0.0)),
+            Occluder::new(Segment::from_coords(0.0, -10.0, 0.0, 10.0)),
+            Occluder::new(Segment::from_coords(10.0, 0.0, -10.0, 0.0)),
+        ], 10.0);
+        let revision = set.revision();
+
+        let report = set.sanitize(0.01);
+
+        assert_eq!((report.crossings_split, report.duplicates_removed), (2, 2));
+        assert_eq!(set.len(), 4);
+        assert!(set.revision() > revision);
+        assert!(!set.has_line_of_sight(Vec2::new(5.0, 5.0), Vec2::new(5.0, -5.0)));
+    }
+
+    #[test]
+    fn cone_queries_only_consider_occluders_in_the_cone() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures").join("scenes").join("example.map");
+        let occluders = Scene::parse(&fs::read_to_string(path).unwrap()).unwrap().occluders(32.0);
+        let set = OccluderSet::new(occluders.clone(), 32.0);
+        let origin = Vec2::new(300.0, 300.0);
+
+        (0..16).for_each(|step| {
+            let cone = ViewCone::new(origin, Vec2::from_angle(step as f32 * PI / 8.0), PI / 8.0);
+            let ids = set.occluders_in_cone(&cone);
+            let expected = (0..occluders.len())
+                .filter(|id| cone.overlaps_segment(occluders[*id].segment))
+                .map(OccluderId::new)
+                .collect::<Vec<_>>();
+
+            assert_eq!(ids, expected, "step {step}");
+            assert!(ids.len() < occluders.len() / 2, "step {step}");
+
+            [RaycastConfig::default(), RaycastConfig::default().with_self_hit_policy(SelfHitPolicy::ExcludeOccluder(ids[ids.len() / 2]))]
+                .into_iter()
+                .for_each(|config| assert_eq!(
+                    set.cone_intersection_points(&cone, &config),
+                    cone_intersection_points(&cone, &occluders, &config),
+                    "step {step}"
+                ));
+        });
+    }
+}
@@ -0,0 +1,111 @@
+use glam::*;
+use lyon_tessellation::{BuffersBuilder, LineJoin, StrokeOptions, StrokeTessellator, StrokeVertex, VertexBuffers};
+use lyon_tessellation::math::point;
+use lyon_tessellation::path::Path;
+use crate::error::RaycastError;
+use crate::raycasting::IndexedTriangles;
+use crate::visibility_polygon::VisibilityPolygon;
+
+/// How two edges of a stroked outline are joined at a corner.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum OutlineJoin {
+    /// Extend the edges until they meet, up to the miter limit
+    Miter,
+    /// Round the corner
+    #[default]
+    Round,
+    /// Cut the corner off
+    Bevel,
+}
+
+/// Settings of [VisibilityPolygon::stroke_outline].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OutlineSettings {
+    /// Width of the outline, centered on the border of the polygon
+    pub width: f32,
+    pub join: OutlineJoin,
+    /// Largest length of a miter join, relative to the width. Longer ones are beveled. Must be at least 1.
+    pub miter_limit: f32,
+    /// Largest distance between a round join and the triangles approximating it
+    pub tolerance: f32,
+}
+
+impl Default for OutlineSettings {
+    fn default() -> Self {
+        OutlineSettings {
+            width: 2.0,
+            join: OutlineJoin::default(),
+            miter_limit: 4.0,
+            tolerance: 0.1,
+        }
+    }
+}
+
+impl VisibilityPolygon {
+    /// Tessellate a stroke along the border of this polygon with lyon, like to render a rim light at the edge
+    /// of vision. The triangles lie in the same coordinates as the points of the polygon. An empty polygon has
+    /// an empty outline.
+    pub fn stroke_outline(&self, settings: &OutlineSettings) -> Result<IndexedTriangles, RaycastError> {
+        let mut outline = IndexedTriangles::default();
+        let Some((first, rest)) = self.points().split_first() else {
+            return Ok(outline);
+        };
+
+        let mut builder = Path::builder();
+        builder.begin(point(first.x, first.y));
+        rest.iter().for_each(|p| { builder.line_to(point(p.x, p.y)); });
+        builder.end(true);
+        let path = builder.build();
+
+        let options = StrokeOptions::tolerance(settings.tolerance)
+            .with_line_width(settings.width)
+            .with_miter_limit(settings.miter_limit)
+            .with_line_join(match settings.join {
+                OutlineJoin::Miter => LineJoin::Miter,
+                OutlineJoin::Round => LineJoin::Round,
+                OutlineJoin::Bevel => LineJoin::Bevel
+            });
+        let mut buffers: VertexBuffers<Vec2, u32> = VertexBuffers::new();
+
+        StrokeTessellator::new().tessellate_path(
+            &path,
+            &options,
+            &mut BuffersBuilder::new(&mut buffers, |vertex: StrokeVertex| Vec2::new(vertex.position().x, vertex.position().y)),
+        )?;
+
+        outline.vertices = buffers.vertices;
+        outline.indices = buffers.indices;
+        Ok(outline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use crate::outline::{OutlineJoin, OutlineSettings};
+    use crate::visibility_polygon::VisibilityPolygon;
+
+    #[test]
+    fn outline_follows_the_border() {
+        let polygon = VisibilityPolygon::new(Vec2::ZERO, vec![
+            Vec2::new(-10.0, -10.0),
+            Vec2::new(10.0, -10.0),
+            Vec2::new(10.0, 10.0),
+            Vec2::new(-10.0, 10.0),
+        ]);
+
+        [OutlineJoin::Miter, OutlineJoin::Round, OutlineJoin::Bevel].into_iter().for_each(|join| {
+            let settings = OutlineSettings { width: 2.0, join, ..Default::default() };
+            let outline = polygon.stroke_outline(&settings).unwrap();
+            let area = outline.triangles().map(|triangle| triangle.area()).sum::<f32>();
+
+            assert!(!outline.indices.is_empty(), "{join:?}");
+            // every vertex lies within half the width of the border
+            assert!(outline.vertices.iter().all(|vertex| (vertex.abs().max_element() - 10.0).abs() <= 1.0 + 0.001), "{join:?}");
+            // a ring from 9 to 11 around the origin, with the outer corners cut off a bit unless mitered
+            assert!((157.5..=161.0).contains(&area), "{join:?}: {area}");
+        });
+
+        assert!(VisibilityPolygon::default().stroke_outline(&OutlineSettings::default()).unwrap().indices.is_empty());
+    }
+}
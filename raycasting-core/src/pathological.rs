@@ -0,0 +1,156 @@
+use glam::*;
+use crate::occluder::Occluder;
+use crate::raycasting::Segment;
+
+/// Half the size of the box enclosing every pathological scene
+pub const PATHOLOGICAL_BOUNDS: f32 = 100.0;
+
+/// A hand-built scene known to break naive sweeps.
+pub struct PathologicalScene {
+    pub name: &'static str,
+    pub origin: Vec2,
+    pub occluders: Vec<Occluder>,
+}
+
+impl PathologicalScene {
+    fn new(
+        name: &'static str,
+        origin: Vec2,
+        segments: impl IntoIterator<Item=Segment>,
+    ) -> Self {
+        let b = PATHOLOGICAL_BOUNDS;
+
+        let occluders = segments
+            .into_iter()
+            .chain([
+                Segment::from_coords(-b, -b, b, -b),
+                Segment::from_coords(b, -b, b, b),
+                Segment::from_coords(b, b, -b, b),
+                Segment::from_coords(-b, b, -b, -b),
+            ])
+            .map(Occluder::new)
+            .collect();
+
+        PathologicalScene {
+            name,
+            origin,
+            occluders,
+        }
+    }
+}
+
+/// Return every pathological scene. Each scene is enclosed by a box from -[PATHOLOGICAL_BOUNDS] to
+/// [PATHOLOGICAL_BOUNDS], so every ray must hit something.
+///
+/// Every algorithm variant should produce well-formed output for all of them.
+pub fn pathological_scenes() -> Vec<PathologicalScene> {
+    vec![
+        PathologicalScene::new(
+            "shared_corners",
+            Vec2::new(-30.0, -30.0),
+            square(0.0, 0.0, 10.0).into_iter().chain(square(10.0, 10.0, 10.0)),
+        ),
+        PathologicalScene::new(
+            "collinear_walls_through_origin",
+            Vec2::new(0.0, 0.0),
+            [
+                Segment::from_coords(10.0, 0.0, 20.0, 0.0),
+                Segment::from_coords(30.0, 0.0, 40.0, 0.0),
+                Segment::from_coords(-20.0, 0.0, -10.0, 0.0),
+                Segment::from_coords(0.0, 15.0, 0.0, 25.0),
+            ],
+        ),
+        PathologicalScene::new(
+            "t_junction",
+            Vec2::new(-30.0, 0.0),
+            [
+                Segment::from_coords(-50.0, 20.0, 50.0, 20.0),
+                Segment::from_coords(0.0, 20.0, 0.0, -10.0),
+            ],
+        ),
+        PathologicalScene::new(
+            "zero_length_segments",
+            Vec2::new(0.0, 0.0),
+            [
+                Segment::from_coords(10.0, 10.0, 10.0, 10.0),
+                Segment::from_coords(-20.0, 5.0, -20.0, 5.0),
+                Segment::from_coords(-20.0, 30.0, 20.0, 30.0),
+            ],
+        ),
+        PathologicalScene::new(
+            "non_finite_coordinates",
+            Vec2::new(0.0, 0.0),
+            [
+                Segment::from_coords(f32::NAN, 10.0, 20.0, 10.0),
+                Segment::from_coords(-20.0, f32::INFINITY, -20.0, 5.0),
+                Segment::from_coords(-20.0, 30.0, 20.0, 30.0),
+            ],
+        ),
+        PathologicalScene::new(
+            "crossing_segments",
+            Vec2::new(0.0, -20.0),
+            [
+                Segment::from_coords(-20.0, 10.0, 20.0, 50.0),
+                Segment::from_coords(-20.0, 50.0, 20.0, 10.0),
+            ],
+        ),
+        PathologicalScene::new(
+            "origin_on_vertex",
+            Vec2::new(0.0, 0.0),
+            square(0.0, 0.0, 10.0),
+        ),
+        PathologicalScene::new(
+            "origin_on_segment",
+            Vec2::new(5.0, 0.0),
+            square(0.0, 0.0, 10.0),
+        ),
+    ]
+}
+
+/// Return the occluders of a square room centered on the origin, with the given half size
+#[cfg(test)]
+pub(crate) fn room(half_size: f32) -> Vec<Occluder> {
+    square(-half_size, -half_size, 2.0 * half_size).into_iter().map(Occluder::new).collect()
+}
+
+/// Return the clockwise edges of the square with the given lower left corner and size
+fn square(x: f32, y: f32, size: f32) -> [Segment; 4] {
+    [
+        Segment::from_coords(x, y + size, x + size, y + size),
+        Segment::from_coords(x + size, y + size, x + size, y),
+        Segment::from_coords(x + size, y, x, y),
+        Segment::from_coords(x, y, x, y + size),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::RaycastConfig;
+    use crate::pathological::{PATHOLOGICAL_BOUNDS, pathological_scenes};
+    use crate::raycasting::calculate_intersection_points;
+
+    #[test]
+    fn output_is_well_formed_for_pathological_scenes() {
+        for scene in pathological_scenes() {
+            let points = calculate_intersection_points(scene.origin, &scene.occluders, &RaycastConfig::default());
+            let name = scene.name;
+
+            assert!(!points.is_empty(), "{name}: no intersection points");
+
+            for point in &points {
+                assert!(point.is_finite(), "{name}: {point} is not finite");
+                assert!(point.abs().max_element() <= PATHOLOGICAL_BOUNDS + 0.001, "{name}: {point} is outside the bounds");
+                assert!(
+                    scene.occluders.iter().any(|occluder| occluder.segment.distance_to(*point) < 0.001),
+                    "{name}: {point} does not lie on an occluder"
+                );
+            }
+
+            let angles = points
+                .iter()
+                .map(|point| (*point - scene.origin).y.atan2((*point - scene.origin).x))
+                .collect::<Vec<_>>();
+            assert!(angles.windows(2).all(|pair| pair[0] <= pair[1]), "{name}: points are not ordered by angle");
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use glam::*;
+use crate::occluder::{Occluder, OccluderId};
+
+/// The soft shadow a single occluder casts from a disc shaped light.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SoftShadow {
+    pub occluder: OccluderId,
+    /// The region where the whole light is hidden. Either a quad or, if the light is wider than the occluder,
+    /// a triangle ending where the umbra closes.
+    pub umbra: Vec<Vec2>,
+    /// The regions at both endpoints where the light is partially hidden, as triangles starting at the endpoint.
+    /// The second point lies on the outer edge (fully lit), the third on the inner edge (fully shadowed),
+    /// so shading across the wedge from the second to the third point gives a smooth transition.
+    pub penumbrae: [[Vec2; 3]; 2],
+}
+
+/// Return the umbra and penumbra wedges of every occluder for a disc light, computed from the tangents
+/// of the disc through the occluder endpoints. The shadows are cut off at the given distance from the endpoints.
+///
+/// Every occluder is handled on its own, so the shadows of several occluders can overlap. Occluders
+/// touching the light cast no shadow.
+pub fn soft_shadows(
+    light: Vec2,
+    radius: f32,
+    occluders: &[Occluder],
+    far: f32,
+) -> Vec<SoftShadow> {
+    occluders
+        .iter()
+        .enumerate()
+        .filter_map(|(id, occluder)| {
+            let [a, b] = occluder.segment.points();
+            let (inner_a, outer_a) = tangents(light, radius, a, b)?;
+            let (inner_b, outer_b) = tangents(light, radius, b, a)?;
+
+            let umbra = match ray_intersection(a, inner_a, b, inner_b) {
+                Some(apex) if apex.distance(a) < far => vec![a, b, apex],
+                _ => vec![a, b, b + inner_b * far, a + inner_a * far]
+            };
+
+            Some(SoftShadow {
+                occluder: OccluderId::new(id),
+                umbra,
+                penumbrae: [
+                    [a, a + outer_a * far, a + inner_a * far],
+                    [b, b + outer_b * far, b + inner_b * far],
+                ],
+            })
+        })
+        .collect()
+}
+
+/// Return the directions of the inner and outer tangent rays leaving the endpoint, i.e. the borders of the
+/// shadow of the endpoint. The inner one leans towards the other endpoint.
+fn tangents(
+    light: Vec2,
+    radius: f32,
+    endpoint: Vec2,
+    other: Vec2,
+) -> Option<(Vec2, Vec2)> {
+    let to_endpoint = endpoint - light;
+    let distance = to_endpoint.length();
+
+    if distance <= radius {
+        return None;
+    }
+
+    let direction = to_endpoint / distance;
+    let angle = (radius / distance).asin();
+    let side = if direction.perp_dot(other - endpoint) < 0.0 { -1.0 } else { 1.0 };
+
+    Some((
+        Vec2::from_angle(side * angle).rotate(direction),
+        Vec2::from_angle(-side * angle).rotate(direction),
+    ))
+}
+
+/// Return the point where the ray from p in direction r crosses the ray from q in direction s, if they do.
+fn ray_intersection(p: Vec2, r: Vec2, q: Vec2, s: Vec2) -> Option<Vec2> {
+    let r_cross_s = r.perp_dot(s);
+
+    if r_cross_s == 0.0 {
+        return None;
+    }
+
+    let t = (q - p).perp_dot(s) / r_cross_s;
+    let u = (q - p).perp_dot(r) / r_cross_s;
+
+    (t > 0.0 && u > 0.0).then(|| p + r * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use crate::occluder::Occluder;
+    use crate::penumbra::soft_shadows;
+    use crate::raycasting::Segment;
+
+    /// Return the distance from the point to the line through a and b
+    fn line_distance(point: Vec2, a: Vec2, b: Vec2) -> f32 {
+        ((b - a).perp_dot(point - a) / (b - a).length()).abs()
+    }
+
+    #[test]
+    fn soft_shadows_works() {
+        let occluders = [
+            Occluder::new(Segment::from_coords(-1.0, 5.0, 1.0, 5.0)),
+            Occluder::new(Segment::from_coords(-10.0, -5.0, 10.0, -5.0)),
+            Occluder::new(Segment::from_coords(1.0, 0.0, 1.0, 0.5)),
+        ];
+
+        let shadows = soft_shadows(Vec2::ZERO, 1.5, &occluders, 100.0);
+
+        // the light touches the last occluder
+        assert_eq!(shadows.len(), 2);
+
+        // the light is wider than the first occluder, so its umbra closes behind it
+        let closing = &shadows[0];
+        assert_eq!(closing.umbra.len(), 3);
+        assert!(closing.umbra[2].x.abs() < 0.0001);
+        assert!(closing.umbra[2].y > 5.0);
+
+        // the light is narrower than the second occluder, so its umbra gets wider
+        let widening = &shadows[1];
+        assert_eq!(widening.umbra.len(), 4);
+        assert!(widening.umbra[2].x - widening.umbra[3].x > 20.0);
+
+        // every border of the shadows is tangent to the light
+        for shadow in &shadows {
+            for [endpoint, outer, inner] in shadow.penumbrae {
+                assert!((line_distance(Vec2::ZERO, endpoint, outer) - 1.5).abs() < 0.0001);
+                assert!((line_distance(Vec2::ZERO, endpoint, inner) - 1.5).abs() < 0.0001);
+            }
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use glam::*;
+
+/// Maximum relative rounding error of the fast cross product evaluation, taken from
+/// Shewchuk's "Adaptive Precision Floating-Point Arithmetic and Fast Robust Geometric Predicates"
+/// (ccwerrboundA): https://www.cs.cmu.edu/~quake/robust.html
+const CROSS_ERROR_BOUND: f64 = (3.0 + 16.0 * f64::EPSILON / 2.0) * (f64::EPSILON / 2.0);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Orientation {
+    CounterClockwise,
+    Clockwise,
+    Collinear,
+}
+
+impl Orientation {
+    fn from_sign(sign: i8) -> Self {
+        match sign {
+            1 => Orientation::CounterClockwise,
+            -1 => Orientation::Clockwise,
+            _ => Orientation::Collinear
+        }
+    }
+}
+
+/// Return the orientation of point c relative to the directed line from a to b.
+pub fn orientation(a: Vec2, b: Vec2, c: Vec2) -> Orientation {
+    Orientation::from_sign(cross_sign(a, b, a, c))
+}
+
+/// Return the sign (-1, 0 or 1) of the cross product (b - a) × (d - c).
+///
+/// The product is first evaluated with f64 arithmetic. If the result is larger than the worst case
+/// rounding error, its sign is certain and returned right away. Otherwise, the product is expanded
+/// into a sum of coordinate products and evaluated exactly. As all inputs are f32, every such product
+/// is exact in f64, so only the summation has to be done without error (see [exact_cross_sign]).
+///
+/// A direction vector r can be used by passing Vec2::ZERO and r as one of the point pairs.
+pub fn cross_sign(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> i8 {
+    let left = (b.x as f64 - a.x as f64) * (d.y as f64 - c.y as f64);
+    let right = (b.y as f64 - a.y as f64) * (d.x as f64 - c.x as f64);
+    let det = left - right;
+    let error_bound = CROSS_ERROR_BOUND * (left.abs() + right.abs());
+
+    if det > error_bound {
+        1
+    } else if -det > error_bound {
+        -1
+    } else {
+        exact_cross_sign(a, b, c, d)
+    }
+}
+
+/// Evaluate the sign of (b - a) × (d - c) exactly.
+///
+/// The cross product equals
+/// bx·dy − bx·cy − ax·dy + ax·cy − by·dx + by·cx + ay·dx − ay·cx
+///
+/// These terms are summed into a nonoverlapping floating point expansion (Shewchuk's Grow-Expansion).
+/// The sign of such an expansion is the sign of its largest nonzero component.
+fn exact_cross_sign(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> i8 {
+    let (ax, ay) = (a.x as f64, a.y as f64);
+    let (bx, by) = (b.x as f64, b.y as f64);
+    let (cx, cy) = (c.x as f64, c.y as f64);
+    let (dx, dy) = (d.x as f64, d.y as f64);
+
+    let terms = [
+        bx * dy,
+        -(bx * cy),
+        -(ax * dy),
+        ax * cy,
+        -(by * dx),
+        by * cx,
+        ay * dx,
+        -(ay * cx)
+    ];
+
+    let mut expansion = [0.0; 8];
+
+    for (len, term) in terms.into_iter().enumerate() {
+        let mut q = term;
+
+        for component in expansion.iter_mut().take(len) {
+            let (sum, error) = two_sum(q, *component);
+            *component = error;
+            q = sum;
+        }
+
+        expansion[len] = q;
+    }
+
+    expansion
+        .iter()
+        .rev()
+        .find(|component| **component != 0.0)
+        .map_or(0, |component| component.signum() as i8)
+}
+
+/// Sum a and b and return the rounded result together with the rounding error, so that
+/// a + b = sum + error holds exactly.
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    let b_virtual = sum - a;
+    let a_virtual = sum - b_virtual;
+    let b_roundoff = b - b_virtual;
+    let a_roundoff = a - a_virtual;
+    (sum, a_roundoff + b_roundoff)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use crate::predicates::{cross_sign, orientation, Orientation::*};
+
+    #[test]
+    fn orientation_works() {
+        [
+            (Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.5, 1.0), CounterClockwise),
+            (Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.5, -1.0), Clockwise),
+            (Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(7.0, 0.0), Collinear),
+            (Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0), Vec2::new(-3.0, -3.0), Collinear),
+        ].into_iter().for_each(|(a, b, c, expected)| assert_eq!(orientation(a, b, c), expected))
+    }
+
+    #[test]
+    fn nearly_collinear_points_are_classified_exactly() {
+        // The fast path cannot decide these cases, so they must be resolved by the exact evaluation.
+        let a = Vec2::new(0.1, 0.1);
+        let b = Vec2::new(12.3, 12.3);
+
+        let mut c = Vec2::new(24.6, 24.6);
+        assert_eq!(orientation(a, b, c), Collinear);
+
+        // the smallest possible step away from the line must change the orientation
+        c.y = f32::from_bits(c.y.to_bits() + 1);
+        assert_eq!(orientation(a, b, c), CounterClockwise);
+        c.y = f32::from_bits(c.y.to_bits() - 2);
+        assert_eq!(orientation(a, b, c), Clockwise);
+    }
+
+    #[test]
+    fn cross_sign_handles_large_magnitude_differences() {
+        let a = Vec2::new(1.0e-20, 0.0);
+        let b = Vec2::new(1.0e20, 1.0);
+        let c = Vec2::new(0.0, 0.0);
+        let d = Vec2::new(1.0e20, 1.0);
+
+        assert_eq!(cross_sign(a, b, c, d), -cross_sign(c, d, a, b));
+        assert_eq!(cross_sign(Vec2::ZERO, Vec2::X, Vec2::ZERO, Vec2::Y), 1);
+    }
+}
@@ -0,0 +1,116 @@
+use glam::*;
+use crate::config::RaycastConfig;
+use crate::occluder_set::OccluderSet;
+use crate::raster::Grid;
+use crate::raycasting::calculate_intersection_points;
+
+/// Light intensities on a grid of square cells, sampled at the cell centers.
+pub type LightGrid = Grid<f32>;
+
+/// Settings of [single_bounce].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BounceSettings {
+    /// Light at the light source
+    pub intensity: f32,
+    /// Distance at which the light has fallen off to half its intensity
+    pub range: f32,
+    /// Fraction of the incoming light an occluder reflects
+    pub albedo: f32,
+    /// Distance between two secondary emitters on the lit occluders
+    pub sample_spacing: f32,
+}
+
+impl Default for BounceSettings {
+    fn default() -> Self {
+        BounceSettings {
+            intensity: 1.0,
+            range: 100.0,
+            albedo: 0.5,
+            sample_spacing: 8.0,
+        }
+    }
+}
+
+/// Experimental: add the direct light of the light source and a single bounce of indirect light to the grid.
+///
+/// The parts of the visibility polygon's boundary which lie on occluders are the lit surfaces. They are
+/// sampled as secondary emitters, each of which sends the light it receives (times the albedo) to every cell it can see.
+/// This is far from physically correct, but lightens up the shadows in a plausible way.
+pub fn single_bounce(
+    light: Vec2,
+    occluders: &OccluderSet,
+    settings: &BounceSettings,
+    grid: &mut LightGrid,
+) {
+    let falloff = |distance: f32| 1.0 / (1.0 + (distance / settings.range).powi(2));
+    let points = calculate_intersection_points(light, occluders.occluders(), &RaycastConfig::default());
+
+    let mut emitters = Vec::new();
+
+    for i in 0..points.len() {
+        let (start, end) = (points[i], points[(i + 1) % points.len()]);
+        let on_occluder = occluders
+            .closest_point((start + end) / 2.0)
+            .map(|closest| closest.distance < 0.01)
+            .unwrap_or(false);
+
+        if !on_occluder {
+            continue;
+        }
+
+        let samples = ((start.distance(end) / settings.sample_spacing).ceil() as usize).max(1);
+        let power = settings.albedo * start.distance(end) / samples as f32;
+
+        for sample in 0..samples {
+            let position = start.lerp(end, (sample as f32 + 0.5) / samples as f32);
+            // move the emitter off the occluder, otherwise it could not see anything
+            let position = position + (light - position).normalize_or_zero() * 0.01;
+            let received = settings.intensity * falloff(light.distance(position));
+            emitters.push((position, received * power / settings.sample_spacing));
+        }
+    }
+
+    grid.update(|center, value| {
+        if occluders.has_line_of_sight(light, center) {
+            *value += settings.intensity * falloff(light.distance(center));
+        }
+
+        for (position, power) in &emitters {
+            if occluders.has_line_of_sight(*position, center) {
+                *value += power * falloff(position.distance(center));
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use crate::occluder_set::OccluderSet;
+    use crate::radiosity::{BounceSettings, LightGrid, single_bounce};
+    use crate::scene::Scene;
+
+    #[test]
+    fn single_bounce_lights_shadows() {
+        let set = OccluderSet::new(Scene::parse("#######\n#.....#\n#.#...#\n#.....#\n#######\n").unwrap().occluders(10.0), 10.0);
+        let mut direct = LightGrid::new(Vec2::ZERO, 10.0, 7, 5);
+        let mut bounced = direct.clone();
+        let light = Vec2::new(55.0, 25.0);
+
+        single_bounce(light, &set, &BounceSettings { albedo: 0.0, ..Default::default() }, &mut direct);
+        single_bounce(light, &set, &BounceSettings::default(), &mut bounced);
+
+        // behind the pillar
+        assert_eq!(direct.get(1, 2), Some(0.0));
+        assert!(bounced.get(1, 2).unwrap() > 0.0);
+        // inside the walls
+        assert_eq!(bounced.get(0, 0), Some(0.0));
+        assert_eq!(bounced.get(2, 2), Some(0.0));
+
+        for y in 0..5 {
+            for x in 0..7 {
+                assert!(bounced.get(x, y) >= direct.get(x, y), "cell ({x}, {y})")
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use glam::*;
+use crate::raycasting::Triangle;
+
+/// A grid of square cells with a value each, like whether the cell is covered or the light in it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid<T> {
+    /// World position of the lower left corner of the cell (0, 0)
+    origin: Vec2,
+    cell_size: f32,
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T: Copy + Default> Grid<T> {
+    /// Create a grid whose cells hold the default value.
+    pub fn new(
+        origin: Vec2,
+        cell_size: f32,
+        width: usize,
+        height: usize,
+    ) -> Self {
+        Grid {
+            origin,
+            cell_size,
+            width,
+            height,
+            cells: vec![T::default(); width * height],
+        }
+    }
+
+    /// Return the world position of the lower left corner of the cell (0, 0).
+    pub fn origin(&self) -> Vec2 {
+        self.origin
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Return the value of the given cell, or None if it lies outside the grid.
+    pub fn get(&self, x: usize, y: usize) -> Option<T> {
+        (x < self.width && y < self.height).then(|| self.cells[y * self.width + x])
+    }
+
+    /// Return the world position of the center of the given cell.
+    pub fn cell_center(&self, x: usize, y: usize) -> Vec2 {
+        self.origin + Vec2::new(x as f32 + 0.5, y as f32 + 0.5) * self.cell_size
+    }
+
+    /// Call the function with the center and the value of every cell, row by row from the bottom.
+    pub fn update(&mut self, mut f: impl FnMut(Vec2, &mut T)) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let center = self.cell_center(x, y);
+                f(center, &mut self.cells[y * self.width + x]);
+            }
+        }
+    }
+
+    /// Reset every cell to the default value.
+    pub fn clear(&mut self) {
+        self.cells.fill(T::default())
+    }
+}
+
+/// A grid whose cells are either covered by some shape or not.
+///
+/// Used to rasterize visibility polygons, for example to render them without a GPU
+/// or to track which parts of a map were seen.
+pub type CoverageGrid = Grid<bool>;
+
+impl CoverageGrid {
+    /// Return true if the cell at the given position is covered. Cells outside the grid are never covered.
+    pub fn is_covered(&self, x: usize, y: usize) -> bool {
+        self.get(x, y).unwrap_or(false)
+    }
+
+    /// Return the fraction of covered cells in [0, 1].
+    pub fn coverage(&self) -> f32 {
+        if self.cells.is_empty() {
+            return 0.0;
+        }
+
+        self.cells.iter().filter(|covered| **covered).count() as f32 / self.cells.len() as f32
+    }
+
+    /// Mark every cell whose center lies inside the triangle (or on its edges) as covered.
+    pub fn rasterize_triangle(&mut self, triangle: &Triangle) {
+        let (a, b, c) = (triangle.a, triangle.b, triangle.c);
+
+        let min = (a.min(b).min(c) - self.origin) / self.cell_size;
+        let max = (a.max(b).max(c) - self.origin) / self.cell_size;
+
+        if self.cells.is_empty() || max.x < 0.0 || max.y < 0.0 || min.x >= self.width as f32 || min.y >= self.height as f32 {
+            return;
+        }
+
+        let x_range = (min.x.max(0.0) as usize)..=(max.x as usize).min(self.width - 1);
+        let y_range = (min.y.max(0.0) as usize)..=(max.y as usize).min(self.height - 1);
+
+        for y in y_range {
+            for x in x_range.clone() {
+                if triangle.contains(self.cell_center(x, y)) {
+                    self.cells[y * self.width + x] = true;
+                }
+            }
+        }
+    }
+
+    pub fn rasterize_triangles<'a>(&mut self, triangles: impl IntoIterator<Item=&'a Triangle>) {
+        triangles
+            .into_iter()
+            .for_each(|triangle| self.rasterize_triangle(triangle))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use crate::raster::CoverageGrid;
+    use crate::raycasting::Triangle;
+
+    #[test]
+    fn rasterize_triangle_works() {
+        let mut grid = CoverageGrid::new(Vec2::ZERO, 1.0, 4, 4);
+        let triangle = Triangle::new(Vec2::new(0.0, 0.0), Vec2::new(4.0, 0.0), Vec2::new(0.0, 4.0));
+
+        grid.rasterize_triangle(&triangle);
+
+        // the centers of all cells with x + y <= 3 are inside the triangle or on its edge
+        for y in 0..4 {
+            for x in 0..4 {
+                assert_eq!(grid.is_covered(x, y), x + y <= 3, "cell ({x}, {y})")
+            }
+        }
+        assert_eq!(grid.coverage(), 10.0 / 16.0);
+    }
+
+    #[test]
+    fn triangles_outside_the_grid_are_ignored() {
+        let mut grid = CoverageGrid::new(Vec2::new(10.0, 10.0), 2.0, 3, 3);
+
+        grid.rasterize_triangles(&[
+            Triangle::new(Vec2::new(0.0, 0.0), Vec2::new(5.0, 0.0), Vec2::new(0.0, 5.0)),
+            Triangle::new(Vec2::new(100.0, 100.0), Vec2::new(120.0, 100.0), Vec2::new(100.0, 120.0)),
+        ]);
+
+        assert_eq!(grid.coverage(), 0.0);
+    }
+}
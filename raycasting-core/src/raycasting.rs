@@ -0,0 +1,1896 @@
+use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::time::Instant;
+use glam::*;
+use thiserror::Error;
+
+#[cfg(feature = "tracing")]
+use crate::config::MissPolicy;
+use crate::arena::QueryArena;
+use crate::config::{Algorithm, CoordinateSystem, PeekPolicy, PolygonFormat, RaycastConfig};
+use crate::occluder::{Occluder, OccluderId};
+use crate::predicates::cross_sign;
+use crate::stats::{phase_span, RaycastStats};
+use crate::sweep::sweep_intersection_points;
+use crate::raycasting::IntersectionStatus::*;
+
+/// A line segment from a to b.
+///
+/// Segments are equal if they have the same endpoints in the same order, so a segment and its reverse are
+/// different. The coordinates are compared by their bits, with 0.0 and -0.0 being the same, so a segment with
+/// NaN coordinates is equal to itself as well and every segment can be used as the key of a map.
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub struct Segment {
+    a: Vec2,
+    b: Vec2,
+}
+
+impl PartialEq for Segment {
+    fn eq(&self, other: &Self) -> bool {
+        self.bits() == other.bits()
+    }
+}
+
+impl Eq for Segment {}
+
+impl Display for Segment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -> {}", self.a, self.b)
+    }
+}
+
+impl Hash for Segment {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.bits().hash(state)
+    }
+}
+
+impl Segment {
+    pub const fn new(a: Vec2, b: Vec2) -> Self {
+        Self { a, b }
+    }
+
+    pub const fn from_coords(x0: f32, y0: f32, x1: f32, y1: f32) -> Self {
+        Self::new(Vec2::new(x0, y0), Vec2::new(x1, y1))
+    }
+
+    /// Return the bits of the coordinates, which [Segment::eq] and [Segment::hash] compare.
+    /// Adding 0.0 turns -0.0 into 0.0, so both have the same bits.
+    fn bits(&self) -> [u32; 4] {
+        [self.a.x, self.a.y, self.b.x, self.b.y].map(|value| (value + 0.0).to_bits())
+    }
+
+    /// Create a segment, or return an error if it is degenerate, see [Segment::validate].
+    pub fn try_new(a: Vec2, b: Vec2) -> Result<Self, SegmentError> {
+        let segment = Self::new(a, b);
+        segment.validate().map(|_| segment)
+    }
+
+    /// Return an error if a coordinate of this segment is NaN or infinite, or if both endpoints are the same.
+    /// Queries skip such segments, see [RaycastConfig::considers].
+    pub fn validate(&self) -> Result<(), SegmentError> {
+        if !self.a.is_finite() || !self.b.is_finite() {
+            Err(SegmentError::NotFinite)
+        } else if self.a == self.b {
+            Err(SegmentError::ZeroLength)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn a(&self) -> Vec2 {
+        self.a
+    }
+
+    pub fn b(&self) -> Vec2 {
+        self.b
+    }
+
+    pub fn points(&self) -> [Vec2; 2] {
+        [self.a, self.b]
+    }
+
+    /// Return the segment from b to a.
+    pub fn reversed(&self) -> Segment {
+        Segment::new(self.b, self.a)
+    }
+
+    pub fn length(&self) -> f32 {
+        self.a.distance(self.b)
+    }
+
+    /// Return true if the endpoints of both segments, in the same order, differ by at most epsilon in each coordinate.
+    pub fn approx_eq(&self, other: &Segment, epsilon: f32) -> bool {
+        self.a.abs_diff_eq(other.a, epsilon) && self.b.abs_diff_eq(other.b, epsilon)
+    }
+
+    /// Return the distance from the given point to the nearest point of this segment.
+    pub fn distance_to(&self, point: Vec2) -> f32 {
+        point.distance(self.closest_point(point))
+    }
+
+    /// Return the point of this segment which is nearest to the given point.
+    pub fn closest_point(&self, point: Vec2) -> Vec2 {
+        let ab = self.b - self.a;
+        let length_squared = ab.length_squared();
+
+        if length_squared == 0.0 {
+            return self.a;
+        }
+
+        let t = ((point - self.a).dot(ab) / length_squared).clamp(0.0, 1.0);
+        self.a + ab * t
+    }
+
+    /// Return true if the point lies exactly on this segment, including its endpoints.
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        cross_sign(self.a, self.b, self.a, point) == 0 && (point - self.a).dot(point - self.b) <= 0.0
+    }
+
+    /// Return true if this segment touches the other one anywhere, including their endpoints.
+    pub fn intersects(&self, other: Segment) -> bool {
+        matches!(self.calculate_intersection(other), Intersecting(_) | CollinearIntersecting)
+    }
+
+    /// Calculate the intersection between this line segment and another one.
+    /// Based on this answer on stack overflow: https://stackoverflow.com/a/565282
+    ///
+    /// Basically, there are 4 cases
+    ///
+    /// 1. The segments are collinear (r × s = 0 and (q − p) × r = 0)
+    ///    If the segments are collinear, tow sub-cases could happen
+    ///
+    /// 1.1 The segments intersect and the intersection is another segment
+    /// This is checked by calculating two values
+    /// t0 = (q − p) · r / (r · r)
+    /// t1 = t0 + s · r / (r · r)
+    ///
+    /// and check if they intersect with the interval [0,1]. If true, the segments are collinear and intersecting
+    ///
+    /// 1.2 The segments don't intersect
+    /// If the check from 1.1 is false, the segments are collinear but don't intersect
+    ///
+    /// 2. The segments are parallel but don't intersect (r × s = 0 and (q − p) × r ≠ 0)
+    ///
+    /// 3. The segments are intersecting (r × s ≠ 0 and 0 ≤ t ≤ 1 and 0 ≤ u ≤ 1)
+    ///    t = (q − p) × s / (r × s)
+    ///    u = (p − q) × r / (s × r)
+    ///
+    /// Then the intersection is p + t r = q + u s
+    ///
+    /// 4. The segments are neither collinear nor parallel. They just dont intersect
+    ///
+    /// Which case applies is decided with the exact predicates from [crate::predicates], so
+    /// nearly parallel or touching segments are never misclassified due to rounding errors.
+    fn calculate_intersection(&self, other: Segment) -> IntersectionStatus {
+        let p = self.a;
+        let q = other.a;
+        let r = self.b - self.a;
+        let s = other.b - other.a;
+
+        let r_cross_s = cross_sign(self.a, self.b, other.a, other.b);
+        let q_minus_p_cross_r = cross_sign(p, q, self.a, self.b);
+
+        if r_cross_s == 0 && q_minus_p_cross_r == 0 {
+            let q_minus_p = q - p;
+            let t0 = q_minus_p.dot(r) / (r.dot(r));
+            let t1 = t0 + ((s.dot(r)) / (r.dot(r)));
+
+            let interval = 0.0..=1.0;
+
+            if interval.contains(&t0) || interval.contains(&t1) || (t0 <= 0.0 && t1 >= 1.0) {
+                return CollinearIntersecting;
+            } else {
+                return CollinearNotIntersecting;
+            }
+        }
+
+        if r_cross_s == 0 && q_minus_p_cross_r != 0 {
+            return NotIntersecting;
+        }
+
+        // 0 ≤ t ≤ 1 and 0 ≤ u ≤ 1 hold exactly if the endpoints of each segment are not
+        // strictly on the same side of the other segment
+        let other_straddles_self = cross_sign(self.a, self.b, self.a, other.a) * cross_sign(self.a, self.b, self.a, other.b) <= 0;
+        let self_straddles_other = cross_sign(other.a, other.b, other.a, self.a) * cross_sign(other.a, other.b, other.a, self.b) <= 0;
+
+        if other_straddles_self && self_straddles_other {
+            let t = intersection_parameter(p, r, q, s);
+            return Intersecting(p + r * t.clamp(0.0, 1.0));
+        }
+
+        NotIntersecting
+    }
+}
+
+/// Why a segment is degenerate, see [Segment::validate].
+#[derive(Copy, Clone, Debug, Eq, Error, PartialEq)]
+pub enum SegmentError {
+    /// A coordinate is NaN or infinite
+    #[error("the segment has a coordinate which is NaN or infinite")]
+    NotFinite,
+    /// Both endpoints are the same
+    #[error("the endpoints of the segment are the same")]
+    ZeroLength,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ray {
+    origin: Vec2,
+    direction: Vec2,
+}
+
+impl Ray {
+    pub fn new(
+        origin: Vec2,
+        direction: Vec2,
+    ) -> Self {
+        Ray {
+            origin,
+            direction,
+        }
+    }
+
+    pub fn origin(&self) -> Vec2 {
+        self.origin
+    }
+
+    pub fn direction(&self) -> Vec2 {
+        self.direction
+    }
+
+    fn calculate_intersection(&self, segment: Segment) -> IntersectionStatus {
+        let p = self.origin;
+        let q = segment.a;
+        let r = self.direction;
+        let s = segment.b - segment.a;
+
+        let r_cross_s = cross_sign(Vec2::ZERO, r, segment.a, segment.b);
+        let q_minus_p_cross_r = cross_sign(p, q, Vec2::ZERO, r);
+
+        if r_cross_s == 0 && q_minus_p_cross_r == 0 {
+            let q_minus_p = q - p;
+            let t0 = q_minus_p.dot(r) / (r.dot(r));
+            let t1 = t0 + ((s.dot(r)) / (r.dot(r)));
+
+            let interval = 0.0..=1.0;
+
+            if interval.contains(&t0) || interval.contains(&t1) || (t0 <= 0.0 && t1 >= 1.0) {
+                return CollinearIntersecting;
+            } else {
+                return CollinearNotIntersecting;
+            }
+        }
+
+        if r_cross_s == 0 && q_minus_p_cross_r != 0 {
+            return NotIntersecting;
+        }
+
+        // 0 ≤ u ≤ 1 holds exactly if the segment endpoints are not strictly on the same side of the ray,
+        // t ≥ 0 holds exactly if (q − p) × s and r × s do not have opposite signs
+        let segment_straddles_ray = cross_sign(Vec2::ZERO, r, p, segment.a) * cross_sign(Vec2::ZERO, r, p, segment.b) <= 0;
+        let in_ray_direction = cross_sign(p, q, segment.a, segment.b) * r_cross_s >= 0;
+
+        if segment_straddles_ray && in_ray_direction {
+            let t = intersection_parameter(p, r, q, s);
+            return Intersecting(p + r * t.max(0.0));
+        }
+
+        NotIntersecting
+    }
+
+    fn rotate(&self, radians: f32) -> Self {
+        let rotated_direction = Vec2::new(
+            self.direction.x * radians.cos() - self.direction.y * radians.sin(),
+            self.direction.x * radians.sin() + self.direction.y * radians.cos(),
+        );
+
+        Ray {
+            origin: self.origin,
+            direction: rotated_direction,
+        }
+    }
+
+    /// Calculate when and where a segment moving with the given velocity (units per time unit)
+    /// first touches this ray, if this happens within max_time.
+    ///
+    /// Relative to the segment, the ray moves by -velocity. As both are convex, the first contact happens either
+    /// 1. at time 0, if they already intersect,
+    /// 2. when an endpoint of the segment crosses the ray, or
+    /// 3. when the ray origin crosses the segment.
+    ///
+    /// All three cases are checked and the earliest one is returned.
+    pub fn toi_moving_segment(
+        &self,
+        segment: Segment,
+        segment_velocity: Vec2,
+        max_time: f32,
+    ) -> Option<TimeOfImpact> {
+        if let Intersecting(point) = self.calculate_intersection(segment) {
+            return Some(TimeOfImpact { time: 0.0, point });
+        }
+
+        let valid_time = |time: f32| time.is_finite() && (0.0..=max_time).contains(&time);
+        let mut earliest: Option<TimeOfImpact> = None;
+        let mut consider = |candidate: TimeOfImpact| match earliest {
+            Some(earliest) if earliest.time <= candidate.time => {}
+            _ => earliest = Some(candidate)
+        };
+
+        // the endpoint e + v t lies on the ray line when r × (e + v t − p) = 0
+        let r_cross_v = self.direction.cross_product(segment_velocity);
+
+        for endpoint in segment.points() {
+            let time = -self.direction.cross_product(endpoint - self.origin) / r_cross_v;
+
+            if !valid_time(time) {
+                continue;
+            }
+
+            let point = endpoint + segment_velocity * time;
+
+            if (point - self.origin).dot(self.direction) >= 0.0 {
+                consider(TimeOfImpact { time, point })
+            }
+        }
+
+        // the ray origin lies on the segment line when (b − a) × (p − a − v t) = 0
+        let ab = segment.b - segment.a;
+        let time = ab.cross_product(self.origin - segment.a) / ab.cross_product(segment_velocity);
+
+        if valid_time(time) {
+            let u = (self.origin - segment.a - segment_velocity * time).dot(ab) / ab.length_squared();
+
+            if (0.0..=1.0).contains(&u) {
+                consider(TimeOfImpact { time, point: self.origin })
+            }
+        }
+
+        earliest
+    }
+}
+
+/// The first contact between a ray and a moving object.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TimeOfImpact {
+    /// Time until the contact happens, in the time unit of the velocity
+    pub time: f32,
+    /// Position of the contact at that time
+    pub point: Vec2,
+}
+
+/// The part of an occluder which is visible from some origin, as parameters along its segment from a (0) to b (1).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct VisibleRange {
+    pub start: f32,
+    pub end: f32,
+}
+
+#[derive(PartialEq, Debug)]
+enum IntersectionStatus {
+    Intersecting(Vec2),
+    CollinearIntersecting,
+    CollinearNotIntersecting,
+    NotIntersecting,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Triangle {
+    pub a: Vec2,
+    pub b: Vec2,
+    pub c: Vec2,
+}
+
+impl Triangle {
+    pub fn new(a: Vec2, b: Vec2, c: Vec2) -> Self {
+        Triangle { a, b, c }
+    }
+
+    /// Return the area of this triangle, regardless of its winding.
+    pub fn area(&self) -> f32 {
+        ((self.b - self.a).perp_dot(self.c - self.a) / 2.0).abs()
+    }
+
+    /// Return true if the point lies inside this triangle or on its edges, regardless of its winding.
+    pub fn contains(&self, point: Vec2) -> bool {
+        let ab = (self.b - self.a).perp_dot(point - self.a);
+        let bc = (self.c - self.b).perp_dot(point - self.b);
+        let ca = (self.a - self.c).perp_dot(point - self.c);
+
+        (ab >= 0.0 && bc >= 0.0 && ca >= 0.0) || (ab <= 0.0 && bc <= 0.0 && ca <= 0.0)
+    }
+
+    pub fn centroid(&self) -> Vec2 {
+        (self.a + self.b + self.c) / 3.0
+    }
+
+    /// Return the edges ab, bc and ca.
+    pub fn edges(&self) -> [Segment; 3] {
+        [Segment::new(self.a, self.b), Segment::new(self.b, self.c), Segment::new(self.c, self.a)]
+    }
+
+    /// Return true if the points of both triangles, in the same order, differ by at most epsilon in each coordinate.
+    pub fn approx_eq(&self, other: &Triangle, epsilon: f32) -> bool {
+        self.a.abs_diff_eq(other.a, epsilon) && self.b.abs_diff_eq(other.b, epsilon) && self.c.abs_diff_eq(other.c, epsilon)
+    }
+}
+
+impl Display for Triangle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}, {}, {}", self.a, self.b, self.c)
+    }
+}
+
+/// Return true if both polygons have the same number of points and their points differ by at most epsilon in each
+/// coordinate. The polygons may start at different points, as long as the points follow each other in the same order,
+/// because the first point of a visibility polygon easily changes with tiny differences of the input.
+pub fn polygon_approx_eq(
+    polygon: &[Vec2],
+    other: &[Vec2],
+    epsilon: f32,
+) -> bool {
+    if polygon.len() != other.len() {
+        return false;
+    }
+
+    polygon.is_empty() || (0..other.len()).any(|start| {
+        polygon
+            .iter()
+            .enumerate()
+            .all(|(i, point)| point.abs_diff_eq(other[(start + i) % other.len()], epsilon))
+    })
+}
+
+pub fn raycast(
+    origin: Vec2,
+    segments: Vec<Segment>,
+) -> Vec<Triangle> {
+    let occluders = segments
+        .into_iter()
+        .map(Occluder::new)
+        .collect::<Vec<_>>();
+
+    raycast_with_config(origin, &occluders, &RaycastConfig::default())
+}
+
+pub fn raycast_with_config(
+    origin: Vec2,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+) -> Vec<Triangle> {
+    let intersection_points = polygon_points(origin, occluders, config);
+
+    if intersection_points.is_empty() {
+        return Vec::new();
+    }
+
+    let mut triangles = intersection_points
+        .windows(2)
+        .map(|nodes| Triangle::new(origin, nodes[0], nodes[1]))
+        .collect::<Vec<_>>();
+
+    let first = intersection_points.first().unwrap();
+    let last = intersection_points.last().unwrap();
+
+    triangles.push(Triangle::new(origin, *first, *last));
+
+    triangles
+}
+
+/// The visibility polygon as a triangle fan without duplicated vertices, ready for a vertex and an index buffer.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IndexedTriangles {
+    /// The origin, followed by the points of the polygon
+    pub vertices: Vec<Vec2>,
+    /// Three indices into the vertices per triangle
+    pub indices: Vec<u32>,
+}
+
+impl IndexedTriangles {
+    pub fn triangles(&self) -> impl Iterator<Item=Triangle> + '_ {
+        self.indices
+            .chunks_exact(3)
+            .map(|indices| Triangle::new(self.vertices[indices[0] as usize], self.vertices[indices[1] as usize], self.vertices[indices[2] as usize]))
+    }
+}
+
+/// Like [raycast_with_config], but return the triangles indexed. They are the same triangles in the same order.
+pub fn raycast_indexed(
+    origin: Vec2,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+) -> IndexedTriangles {
+    let intersection_points = polygon_points(origin, occluders, config);
+
+    if intersection_points.is_empty() {
+        return IndexedTriangles::default();
+    }
+
+    let last = intersection_points.len() as u32;
+    let indices = (1..last)
+        .flat_map(|i| [0, i, i + 1])
+        .chain([0, 1, last])
+        .collect();
+
+    IndexedTriangles {
+        vertices: [origin].into_iter().chain(intersection_points).collect(),
+        indices,
+    }
+}
+
+/// Return every intersection point of rays from origin to every point of the occluders and the occluders itself.
+/// The intersection points are ordered by angle.
+///
+/// Occluders which are not considered by the config (see [RaycastConfig::considers]) are skipped during the sweep.
+///
+/// The sweep is made of public stages, so a custom pipeline can run them one by one and change the data in between:
+/// [collect_endpoints], [sort_by_angle], [endpoint_rays], [resolve_hits], [grazing_rays], [resolve_grazing_hits]
+/// and [assemble_polygon].
+pub fn calculate_intersection_points(
+    origin: Vec2,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+) -> Vec<Vec2> {
+    calculate_intersection_points_with_stats(origin, occluders, config, &mut RaycastStats::default())
+}
+
+/// Like [calculate_intersection_points], but always return the points ordered by ascending angle and not closed,
+/// regardless of the [PolygonFormat] of the config, for queries which build on the polygon.
+pub(crate) fn polygon_points(
+    origin: Vec2,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+) -> Vec<Vec2> {
+    calculate_intersection_points(origin, occluders, &config.clone().with_format(PolygonFormat::default()))
+}
+
+/// Like [calculate_intersection_points], but records what the query did in the given stats.
+pub fn calculate_intersection_points_with_stats(
+    origin: Vec2,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+    stats: &mut RaycastStats,
+) -> Vec<Vec2> {
+    intersection_points(origin, occluders, config, &mut QueryArena::default(), stats)
+}
+
+/// Like [calculate_intersection_points], but keep the temporaries of the query in the arena, so a caller running
+/// many queries reuses their memory instead of allocating it again for each of them.
+pub fn calculate_intersection_points_in(
+    origin: Vec2,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+    arena: &mut QueryArena,
+) -> Vec<Vec2> {
+    intersection_points(origin, occluders, config, arena, &mut RaycastStats::default())
+}
+
+fn intersection_points(
+    origin: Vec2,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+    arena: &mut QueryArena,
+    stats: &mut RaycastStats,
+) -> Vec<Vec2> {
+    polygon_in(origin, occluders, config, arena, stats);
+    std::mem::take(&mut arena.polygon)
+}
+
+/// Compute the points of the visibility polygon into the polygon buffer of the arena. Only the default
+/// format, coordinate system and the naive algorithm reuse its memory, the others allocate the result.
+pub(crate) fn polygon_in(
+    origin: Vec2,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+    arena: &mut QueryArena,
+    stats: &mut RaycastStats,
+) {
+    phase_span!("calculate_intersection_points");
+
+    if config.format != PolygonFormat::default() {
+        let mut points = intersection_points(origin, occluders, &config.clone().with_format(PolygonFormat::default()), arena, stats);
+        config.format.apply(origin, &mut points);
+        arena.polygon = points;
+        return;
+    }
+
+    if config.coordinate_system != CoordinateSystem::YUp {
+        let coordinate_system = config.coordinate_system;
+        let occluders = occluders.iter().map(|occluder| occluder.to_y_up(coordinate_system)).collect::<Vec<_>>();
+        let config = config.clone().with_coordinate_system(CoordinateSystem::YUp);
+
+        polygon_in(coordinate_system.to_y_up(origin), &occluders, &config, arena, stats);
+        arena.polygon.iter_mut().for_each(|point| *point = coordinate_system.to_y_up(*point));
+        return;
+    }
+
+    if config.resolve_algorithm(occluders.len()) == Algorithm::Sweep {
+        let start = Instant::now();
+        let points = {
+            phase_span!("sweep");
+            sweep_intersection_points(origin, occluders, config, stats)
+        };
+        stats.ray_casting_time += start.elapsed();
+
+        #[cfg(feature = "tracing")]
+        report_anomalies(origin, occluders, config, &points);
+
+        arena.polygon = points;
+        return;
+    }
+
+    // the same stages as the public functions, but filling the buffers of the arena
+    arena.clear();
+    let QueryArena { endpoints, hits, grazing_rays, grazing_hits, polygon } = arena;
+
+    let start = Instant::now();
+    {
+        phase_span!("collect_endpoints");
+        endpoints.extend(considered_endpoints(origin, occluders, config));
+    }
+    stats.endpoint_collection_time += start.elapsed();
+
+    let start = Instant::now();
+    {
+        phase_span!("sort_endpoints");
+        sort_points_by_angle(origin, endpoints);
+        endpoints.dedup();
+
+        if let Some(max_rays) = config.max_rays {
+            let kept = limit_sorted_endpoints(origin, endpoints, max_rays);
+            endpoints.truncate(kept);
+        }
+    }
+    stats.sorting_time += start.elapsed();
+
+    let start = Instant::now();
+    {
+        phase_span!("cast_endpoint_rays");
+        hits.extend(endpoints.iter().filter_map(|endpoint| resolve_hit(EndpointRay::new(origin, *endpoint), occluders, config, stats)));
+    }
+    {
+        phase_span!("cast_extra_rays");
+        grazing_rays.extend(hits.iter().flat_map(|hit| grazing_rays_of(hit, occluders, config)));
+        grazing_rays.extend(uniform_rays_beside(origin, config.uniform_rays, endpoints));
+        grazing_hits.extend(grazing_rays.iter().filter_map(|ray| resolve_grazing_hit(*ray, occluders, config, stats)));
+    }
+    stats.ray_casting_time += start.elapsed();
+
+    let start = Instant::now();
+    {
+        phase_span!("sort_intersections");
+        assemble_polygon_into(origin, hits, grazing_hits.iter().copied(), polygon);
+    }
+    stats.sorting_time += start.elapsed();
+
+    #[cfg(feature = "tracing")]
+    report_anomalies(origin, occluders, config, polygon);
+}
+
+/// Emit a debug event for everything suspicious about a query, which usually points to broken input.
+#[cfg(feature = "tracing")]
+fn report_anomalies(
+    origin: Vec2,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+    points: &[Vec2],
+) {
+    if !origin.is_finite() {
+        tracing::debug!(%origin, "query from a non-finite origin");
+    }
+
+    let degenerate = occluders.iter().filter(|occluder| occluder.segment.validate().is_err()).count();
+
+    if degenerate > 0 {
+        tracing::debug!(degenerate, "skipped occluders with non-finite coordinates or without length");
+    }
+
+    if let Some(point) = points.iter().find(|point| !point.is_finite()) {
+        tracing::debug!(%origin, %point, "non-finite point in the visibility polygon");
+    }
+
+    // unless misses are dropped, the polygon surrounds the origin, so no two neighbouring points are half a turn apart
+    let unclosed = config.miss_policy != MissPolicy::Drop && (points.is_empty() || (0..points.len()).any(|i| {
+        let (a, b) = (points[i] - origin, points[(i + 1) % points.len()] - origin);
+        a.perp_dot(b) < 0.0 || (a.perp_dot(b) == 0.0 && a.dot(b) < 0.0)
+    }));
+
+    if unclosed {
+        tracing::debug!(%origin, points = points.len(), "the visibility polygon does not surround the origin");
+    }
+}
+
+/// A ray cast towards an endpoint of an occluder.
+#[derive(Copy, Clone, Debug)]
+pub struct EndpointRay {
+    pub ray: Ray,
+    pub endpoint: Vec2,
+}
+
+impl EndpointRay {
+    pub fn new(
+        origin: Vec2,
+        endpoint: Vec2,
+    ) -> Self {
+        EndpointRay {
+            ray: Ray::new(origin, endpoint - origin),
+            endpoint,
+        }
+    }
+}
+
+/// The nearest hit of an [EndpointRay].
+#[derive(Copy, Clone)]
+pub struct EndpointHit {
+    pub ray: EndpointRay,
+    pub point: Vec2,
+    /// The hit segment, or None if the ray hit nothing and the point was placed by the [crate::config::MissPolicy].
+    pub segment: Option<Segment>,
+}
+
+impl EndpointHit {
+    /// Return true if the ray reached its endpoint, i.e. it was not blocked before.
+    /// Rays slightly beside such a hit may pass the occluder and hit something behind it.
+    pub fn reached_endpoint(&self) -> bool {
+        self.point == self.ray.endpoint
+    }
+}
+
+/// Stage 1: Return the endpoints of all occluders which take part in a query from the origin with the given config.
+pub fn collect_endpoints(
+    origin: Vec2,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+) -> Vec<Vec2> {
+    considered_endpoints(origin, occluders, config).collect()
+}
+
+fn considered_endpoints<'a>(
+    origin: Vec2,
+    occluders: &'a [Occluder],
+    config: &'a RaycastConfig,
+) -> impl Iterator<Item=Vec2> + 'a {
+    occluders
+        .iter()
+        .enumerate()
+        .filter(move |(index, occluder)| config.considers(OccluderId::new(*index), occluder) && occluder.is_facing(origin))
+        .flat_map(|(_, occluder)| occluder.segment.points())
+}
+
+/// Stage 2: Sort the points by their angle around the origin and remove duplicates.
+pub fn sort_by_angle(
+    origin: Vec2,
+    points: &mut Vec<Vec2>,
+) {
+    sort_points_by_angle(origin, points);
+    points.dedup();
+}
+
+fn sort_points_by_angle(
+    origin: Vec2,
+    points: &mut [Vec2],
+) {
+    points.sort_by(|p1, p2| {
+        let angle_0 = calculate_angle(origin, *p1);
+        let angle_1 = calculate_angle(origin, *p2);
+        angle_0.total_cmp(&angle_1)
+    });
+}
+
+/// Stage 2b: Keep at most max_rays of the endpoints sorted by angle, for a query with a ray budget
+/// (see [RaycastConfig::max_rays]).
+///
+/// The full turn around the origin is split into max_rays sectors of equal angle, and only the endpoint nearest
+/// to the origin is kept of each sector: near occluders cast the widest shadows, so their corners shape the
+/// polygon the most, while the sectors keep the rays spread over all directions. Shadows of dropped endpoints
+/// are missed or cut short, and the rays beside a dropped corner may reach past it, so the polygon gets coarser
+/// and can cover parts of the scene the exact polygon does not.
+pub fn limit_endpoints(
+    origin: Vec2,
+    endpoints: &mut Vec<Vec2>,
+    max_rays: usize,
+) {
+    let kept = limit_sorted_endpoints(origin, endpoints, max_rays);
+    endpoints.truncate(kept);
+}
+
+/// Move the endpoints kept by [limit_endpoints] to the front and return their number.
+fn limit_sorted_endpoints(
+    origin: Vec2,
+    endpoints: &mut [Vec2],
+    max_rays: usize,
+) -> usize {
+    if endpoints.len() <= max_rays {
+        return endpoints.len();
+    }
+
+    if max_rays == 0 {
+        return 0;
+    }
+
+    // angles lie in [-180, 180], the last sector includes both ends
+    let sector_of = |endpoint: Vec2| (((calculate_angle(origin, endpoint) + 180.0) / 360.0 * max_rays as f32) as usize).min(max_rays - 1);
+    let mut kept = 0;
+    let mut last_sector = None;
+
+    for index in 0..endpoints.len() {
+        let (endpoint, sector) = (endpoints[index], sector_of(endpoints[index]));
+
+        if last_sector == Some(sector) {
+            let nearest = &mut endpoints[kept - 1];
+
+            if endpoint.distance_squared(origin) < nearest.distance_squared(origin) {
+                *nearest = endpoint;
+            }
+        } else {
+            endpoints[kept] = endpoint;
+            kept += 1;
+            last_sector = Some(sector);
+        }
+    }
+
+    kept
+}
+
+/// Stage 3: Return a ray from the origin towards every endpoint.
+pub fn endpoint_rays(
+    origin: Vec2,
+    endpoints: &[Vec2],
+) -> Vec<EndpointRay> {
+    endpoints
+        .iter()
+        .map(|endpoint| EndpointRay::new(origin, *endpoint))
+        .collect()
+}
+
+/// Stage 4: Return the nearest hit of every ray. Rays which hit nothing are handled by the [crate::config::MissPolicy].
+pub fn resolve_hits(
+    rays: Vec<EndpointRay>,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+    stats: &mut RaycastStats,
+) -> Vec<EndpointHit> {
+    rays
+        .into_iter()
+        .filter_map(|ray| resolve_hit(ray, occluders, config, stats))
+        .collect()
+}
+
+fn resolve_hit(
+    ray: EndpointRay,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+    stats: &mut RaycastStats,
+) -> Option<EndpointHit> {
+    match nearest_intersection_where(ray.ray, occluders, config, stats, Some(ray.endpoint), |_| true) {
+        Some((point, segment)) => Some(EndpointHit { ray, point, segment: Some(segment) }),
+        None => config.miss_policy
+            .far_point(ray.ray.origin, ray.ray.direction)
+            .map(|point| EndpointHit { ray, point, segment: None })
+    }
+}
+
+/// How the occluders touching an endpoint lie relative to the ray towards it, see [classify_endpoint].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EndpointKind {
+    /// All occluders lie counterclockwise of the ray, so the view continues clockwise past the endpoint.
+    Begin,
+    /// All occluders lie clockwise of the ray, so the view continues counterclockwise past the endpoint.
+    End,
+    /// The occluders lie on both sides of the ray, so the view stops at the endpoint, unless the config allows
+    /// to peek through it (see [PeekPolicy]).
+    Both,
+}
+
+/// Classify the endpoint by the considered occluders touching it, seen from the origin. Return None if none of
+/// them leaves the ray (like an occluder pointing right at the origin), so the view continues on both sides.
+pub fn classify_endpoint(
+    origin: Vec2,
+    endpoint: Vec2,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+) -> Option<EndpointKind> {
+    let mut clockwise = false;
+    let mut counterclockwise = false;
+
+    for (index, occluder) in occluders.iter().enumerate() {
+        if !config.considers(OccluderId::new(index), occluder) || !occluder.is_facing(origin) || !occluder.segment.contains_point(endpoint) {
+            continue;
+        }
+
+        for point in occluder.segment.points() {
+            match cross_sign(origin, endpoint, origin, point) {
+                1 => counterclockwise = true,
+                -1 => clockwise = true,
+                _ => {}
+            }
+        }
+    }
+
+    match (clockwise, counterclockwise) {
+        (true, true) => Some(EndpointKind::Both),
+        (false, true) => Some(EndpointKind::Begin),
+        (true, false) => Some(EndpointKind::End),
+        (false, false) => None
+    }
+}
+
+/// A ray which continues past the endpoint of an [EndpointHit].
+#[derive(Copy, Clone, Debug)]
+pub struct GrazingRay {
+    pub ray: Ray,
+    /// If set, the ray goes right through this corner and ignores the occluders touching it.
+    pub corner: Option<Vec2>,
+}
+
+/// Stage 5: Return the rays which continue past the endpoints reached by the hits. Depending on the
+/// [classify_endpoint] of an endpoint, these are rays slightly beside it on every side without occluders,
+/// or, if both sides are blocked, nothing or the ray itself (see [PeekPolicy]).
+pub fn grazing_rays(
+    hits: &[EndpointHit],
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+) -> Vec<GrazingRay> {
+    hits
+        .iter()
+        .flat_map(|hit| grazing_rays_of(hit, occluders, config))
+        .collect()
+}
+
+/// Return the grazing rays of a single hit, without allocating.
+fn grazing_rays_of(
+    hit: &EndpointHit,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+) -> impl Iterator<Item=GrazingRay> {
+    let beside = |ray: Ray, radians: f32| Some(GrazingRay { ray: ray.rotate(radians), corner: None });
+    let ray = hit.ray.ray;
+
+    let rays = match hit.reached_endpoint() {
+        false => [None, None],
+        true => match (classify_endpoint(ray.origin, hit.point, occluders, config), config.peek_policy) {
+            (Some(EndpointKind::Begin), _) => [beside(ray, -0.01), None],
+            (Some(EndpointKind::End), _) => [beside(ray, 0.01), None],
+            (Some(EndpointKind::Both), PeekPolicy::Block) => [None, None],
+            (Some(EndpointKind::Both), PeekPolicy::Peek) => [Some(GrazingRay { ray, corner: Some(hit.point) }), None],
+            (None, _) => [beside(ray, -0.01), beside(ray, 0.01)]
+        }
+    };
+
+    rays.into_iter().flatten()
+}
+
+/// Angle (degrees) within which a uniform ray is dropped in favour of an endpoint ray, as it would only add
+/// a near duplicate of its point
+const UNIFORM_RAY_MERGE_ANGLE: f32 = 0.01;
+
+/// Stage 5b: Return count rays at uniform angles around the origin, for the hybrid mode (see
+/// [RaycastConfig::uniform_rays]). They are resolved like [GrazingRay]s, so they only add their hit point.
+///
+/// The endpoints must be sorted by angle (see [sort_by_angle]). Rays right beside the ray towards one of them
+/// are left out.
+pub fn uniform_rays(
+    origin: Vec2,
+    count: usize,
+    endpoints: &[Vec2],
+) -> Vec<GrazingRay> {
+    uniform_rays_beside(origin, count, endpoints).collect()
+}
+
+fn uniform_rays_beside(
+    origin: Vec2,
+    count: usize,
+    endpoints: &[Vec2],
+) -> impl Iterator<Item=GrazingRay> + '_ {
+    let near_endpoint = move |angle: f32| {
+        let index = endpoints.partition_point(|endpoint| calculate_angle(origin, *endpoint) < angle);
+        [index.checked_sub(1), Some(index)]
+            .into_iter()
+            .flatten()
+            .filter_map(|index| endpoints.get(index))
+            .any(|endpoint| (calculate_angle(origin, *endpoint) - angle).abs() < UNIFORM_RAY_MERGE_ANGLE)
+    };
+
+    (0..count)
+        .map(move |i| -180.0 + 360.0 * i as f32 / count as f32)
+        .filter(move |angle| !near_endpoint(*angle))
+        .map(move |angle| GrazingRay { ray: Ray::new(origin, Vec2::from_angle(angle.to_radians())), corner: None })
+}
+
+/// Stage 6: Return the nearest hit of every grazing ray. Rays which hit nothing are handled by the [crate::config::MissPolicy].
+pub fn resolve_grazing_hits(
+    rays: Vec<GrazingRay>,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+    stats: &mut RaycastStats,
+) -> Vec<Vec2> {
+    rays
+        .into_iter()
+        .filter_map(|grazing| resolve_grazing_hit(grazing, occluders, config, stats))
+        .collect()
+}
+
+fn resolve_grazing_hit(
+    grazing: GrazingRay,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+    stats: &mut RaycastStats,
+) -> Option<Vec2> {
+    let hit = match grazing.corner {
+        Some(corner) => nearest_intersection_where(grazing.ray, occluders, config, stats, None, |occluder| !occluder.segment.contains_point(corner)),
+        None => find_nearest_intersection(grazing.ray, occluders, config, stats)
+    };
+
+    match hit {
+        Some((intersection, _)) => Some(intersection),
+        None => config.miss_policy.far_point(grazing.ray.origin, grazing.ray.direction)
+    }
+}
+
+/// Stage 7: Return all hit points ordered by angle around the origin, which are the points of the visibility polygon.
+pub fn assemble_polygon(
+    origin: Vec2,
+    hits: &[EndpointHit],
+    grazing_hits: impl IntoIterator<Item=Vec2>,
+) -> Vec<Vec2> {
+    let mut points = Vec::new();
+    assemble_polygon_into(origin, hits, grazing_hits, &mut points);
+    points
+}
+
+/// Like [assemble_polygon], but replace the content of the given vector with the points.
+fn assemble_polygon_into(
+    origin: Vec2,
+    hits: &[EndpointHit],
+    grazing_hits: impl IntoIterator<Item=Vec2>,
+    points: &mut Vec<Vec2>,
+) {
+    points.clear();
+    points.extend(hits.iter().map(|hit| hit.point).chain(grazing_hits));
+    sort_points_by_angle(origin, points);
+}
+
+/// Distance, relative to the largest coordinate of a query, within which [visible_occluders] considers a point to lie
+/// on an occluder and joins the visible parts of an occluder. The rounding errors of the points grow with the
+/// coordinates, so a fixed distance would miss occluders far from zero and join the parts of small ones.
+const VISIBLE_OCCLUDER_TOLERANCE: f32 = 0.00001;
+
+/// Return the occluders which appear on the boundary of the visibility polygon around the origin, together with
+/// the visible part of each. An occluder which is visible in several disjoint parts appears once for every part.
+///
+/// The result is ordered by occluder id and start of the range.
+pub fn visible_occluders(
+    origin: Vec2,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+) -> Vec<(OccluderId, VisibleRange)> {
+    let points = polygon_points(origin, occluders, config);
+    let tolerance = points
+        .iter()
+        .chain([&origin])
+        .map(|point| point.abs().max_element())
+        .fold(0.0, f32::max) * VISIBLE_OCCLUDER_TOLERANCE;
+    let mut ranges = Vec::new();
+
+    for i in 0..points.len() {
+        let (start, end) = (points[i], points[(i + 1) % points.len()]);
+        let middle = (start + end) / 2.0;
+
+        let visible = occluders
+            .iter()
+            .enumerate()
+            .filter(|(index, occluder)| config.considers(OccluderId::new(*index), occluder))
+            .find(|(_, occluder)| [start, middle, end].into_iter().all(|point| occluder.segment.distance_to(point) <= tolerance));
+
+        if let Some((index, occluder)) = visible {
+            let ab = occluder.segment.b - occluder.segment.a;
+
+            if ab.length_squared() == 0.0 {
+                continue;
+            }
+
+            let t0 = (start - occluder.segment.a).dot(ab) / ab.length_squared();
+            let t1 = (end - occluder.segment.a).dot(ab) / ab.length_squared();
+            ranges.push((OccluderId::new(index), VisibleRange { start: t0.min(t1).clamp(0.0, 1.0), end: t0.max(t1).clamp(0.0, 1.0) }));
+        }
+    }
+
+    ranges.sort_by(|(id_0, range_0), (id_1, range_1)| id_0.index.cmp(&id_1.index).then(range_0.start.total_cmp(&range_1.start)));
+
+    // merge the pieces between neighbouring boundary points into contiguous ranges
+    let mut merged: Vec<(OccluderId, VisibleRange)> = Vec::with_capacity(ranges.len());
+
+    for (id, range) in ranges {
+        match merged.last_mut() {
+            Some((last_id, last)) if *last_id == id && range.start <= last.end + tolerance / occluders[id.index].segment.length() => last.end = last.end.max(range.end),
+            _ => merged.push((id, range))
+        }
+    }
+
+    merged
+}
+
+/// Return the intersection of the ray with the given occluders which is nearest to the ray origin,
+/// together with the segment it lies on. Occluders and hits rejected by the config are skipped.
+pub(crate) fn find_nearest_intersection(
+    ray: Ray,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+    stats: &mut RaycastStats,
+) -> Option<(Vec2, Segment)> {
+    nearest_intersection_where(ray, occluders, config, stats, None, |_| true)
+}
+
+/// Like [find_nearest_intersection], but only tests the occluders matching the filter.
+///
+/// If the ray is cast towards an endpoint, every occluder containing the endpoint is hit exactly there. The direction
+/// of the ray is rounded, so if the origin is not exactly representable, the endpoint can lie strictly beside the ray,
+/// which would then miss all occluders meeting at the endpoint.
+fn nearest_intersection_where(
+    ray: Ray,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+    stats: &mut RaycastStats,
+    endpoint: Option<Vec2>,
+    filter: impl Fn(&Occluder) -> bool,
+) -> Option<(Vec2, Segment)> {
+    let mut nearest_intersection = None;
+    let mut nearest_distance = f32::MAX;
+    stats.rays_cast += 1;
+
+    for (index, occluder) in occluders.iter().enumerate() {
+        if !config.considers(OccluderId::new(index), occluder) || !occluder.is_facing(ray.origin) || !filter(occluder) {
+            continue;
+        }
+
+        stats.segment_tests += 1;
+
+        // occluders along the ray do not block it, just like in the intersection test
+        let intersection = match endpoint {
+            Some(endpoint) if occluder.segment.contains_point(endpoint) && cross_sign(ray.origin, endpoint, occluder.segment.a, occluder.segment.b) != 0 => Intersecting(endpoint),
+            _ => ray.calculate_intersection(occluder.segment)
+        };
+
+        if let Intersecting(intersection) = intersection {
+            let distance_to_intersection = calculate_distance(ray.origin, intersection);
+
+            if distance_to_intersection < nearest_distance && config.self_hit_policy.allows_distance(distance_to_intersection) {
+                nearest_intersection = Some((intersection, occluder.segment));
+                nearest_distance = distance_to_intersection;
+            }
+        }
+    }
+
+    nearest_intersection
+}
+
+/// Calculate t = (q − p) × s / (r × s), so that p + t r is the intersection of the lines through
+/// p with direction r and through q with direction s. The lines must not be parallel.
+///
+/// The division is done in f64, as r × s might still be very small for nearly parallel lines.
+pub(crate) fn intersection_parameter(
+    p: Vec2,
+    r: Vec2,
+    q: Vec2,
+    s: Vec2,
+) -> f32 {
+    let cross = |v: Vec2, w: Vec2| v.x as f64 * w.y as f64 - v.y as f64 * w.x as f64;
+    let t = cross(q - p, s) / cross(r, s);
+
+    if t.is_finite() {
+        t as f32
+    } else {
+        0.0
+    }
+}
+
+fn calculate_distance(
+    p1: Vec2,
+    p2: Vec2,
+) -> f32 {
+    ((p2.x - p1.x).powi(2) + (p2.y - p1.y).powi(2)).sqrt()
+}
+
+pub(crate) fn calculate_angle(
+    p1: Vec2,
+    p2: Vec2,
+) -> f32 {
+    let angle_rad = (p2.y - p1.y).atan2(p2.x - p1.x);
+    angle_rad.to_degrees()
+}
+
+/// Enables Vec2 to implement cross product.
+trait CrossProduct {
+    fn cross_product(&self, other: Self) -> f32;
+}
+
+impl CrossProduct for Vec2 {
+    fn cross_product(&self, other: Self) -> f32 {
+        self.x * other.y - self.y * other.x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use glam::*;
+    use crate::aabb::Aabb;
+    use crate::arena::QueryArena;
+    use crate::cone::{cone_intersection_points, ViewCone};
+    use crate::config::{Algorithm, CoordinateSystem, MissPolicy, PeekPolicy, PolygonFormat, RaycastConfig, SelfHitPolicy, SWEEP_THRESHOLD, Winding};
+    use crate::raycasting::IntersectionStatus::*;
+    use crate::occluder::{Occluder, OccluderId, OccluderKind};
+    use crate::occluders;
+    use crate::pathological::pathological_scenes;
+    use crate::raycasting::{assemble_polygon, calculate_intersection_points, calculate_intersection_points_in, calculate_intersection_points_with_stats, classify_endpoint, collect_endpoints, endpoint_rays, EndpointKind, IndexedTriangles, raycast_indexed, raycast_with_config, grazing_rays, limit_endpoints, polygon_approx_eq, Ray, resolve_grazing_hits, resolve_hits, Segment, SegmentError, sort_by_angle, TimeOfImpact, Triangle, uniform_rays, visible_occluders};
+    use crate::stats::RaycastStats;
+    use crate::visibility_polygon::VisibilityPolygon;
+
+    #[test]
+    fn segment_segment_intersection_works() {
+        let line = Segment::from_coords(0.0, 0.0, 5.0, 0.0);
+
+        [
+            (
+                Segment::from_coords(3.0, 3.0, 3.0, -3.0),
+                Intersecting(Vec2::new(3.0, 0.0))
+            ),
+            (
+                Segment::from_coords(0.0, 3.0, 0.0, -3.0),
+                Intersecting(Vec2::new(0.0, 0.0))
+            ),
+            (
+                Segment::from_coords(5.0, 3.0, 5.0, -3.0),
+                Intersecting(Vec2::new(5.0, 0.0))
+            ),
+            (
+                Segment::from_coords(0.0, -1.0, 5.0, 1.0),
+                Intersecting(Vec2::new(2.5, 0.0))
+            ),
+            (
+                Segment::from_coords(0.0, 0.0, 3.0, 0.0),
+                CollinearIntersecting
+            ),
+            (
+                Segment::from_coords(3.0, 0.0, 0.0, 0.0),
+                CollinearIntersecting
+            ),
+            (
+                Segment::from_coords(6.0, 0.0, 10.0, 0.0),
+                CollinearNotIntersecting
+            ),
+            (
+                Segment::from_coords(10.0, 0.0, 6.0, 0.0),
+                CollinearNotIntersecting
+            ),
+            (
+                Segment::from_coords(0.0, 1.0, 5.0, 1.0),
+                NotIntersecting
+            )
+        ].into_iter().for_each(|(l, intersection)| assert_eq!(line.calculate_intersection(l), intersection))
+    }
+
+    #[test]
+    fn endpoint_rays_from_unrepresentable_origins_hit_their_endpoint() {
+        let occluders = [
+            (15.0, -1.0, 15.0, 1.0),
+            (15.0, 1.0, 17.0, 1.0),
+            (17.0, 1.0, 17.0, -1.0),
+            (17.0, -1.0, 15.0, -1.0),
+            (-50.0, -50.0, 50.0, -50.0),
+            (50.0, -50.0, 50.0, 50.0),
+            (50.0, 50.0, -50.0, 50.0),
+            (-50.0, 50.0, -50.0, -50.0),
+        ].into_iter().map(|(x0, y0, x1, y1)| Occluder::new(Segment::from_coords(x0, y0, x1, y1))).collect::<Vec<_>>();
+
+        [
+            Vec2::new(-3.3, 0.0),
+            Vec2::new(-3.3, 0.1),
+            Vec2::new(0.7, -0.3),
+            Vec2::new(-12.9, 0.45),
+        ].into_iter().for_each(|origin| {
+            let points = calculate_intersection_points(origin, &occluders, &RaycastConfig::default());
+
+            assert!(points.contains(&Vec2::new(15.0, 1.0)), "{origin}: {points:?}");
+            assert!(points.contains(&Vec2::new(15.0, -1.0)), "{origin}: {points:?}");
+        })
+    }
+
+    #[test]
+    fn ray_segment_intersection_works() {
+        let ray = Ray {
+            origin: Vec2::new(0.0, 0.0),
+            direction: Vec2::new(5.0, 0.0),
+        };
+
+        [
+            (
+                Segment::from_coords(7.0, 3.0, 7.0, -3.0),
+                Intersecting(Vec2::new(7.0, 0.0))
+            ),
+            (
+                Segment::from_coords(7000.0, 3.0, 7000.0, -3.0),
+                Intersecting(Vec2::new(7000.0, 0.0))
+            ),
+            (
+                Segment::from_coords(-1.0, 3.0, -1.0, -3.0),
+                NotIntersecting
+            ),
+            (
+                Segment::from_coords(0.0, 3.0, -3.0, 0.0),
+                NotIntersecting
+            ),
+            (
+                Segment::from_coords(0.0, 3.0, 5.0, 3.0),
+                NotIntersecting
+            ),
+            (
+                Segment::from_coords(-5.0, 0.0, -3.0, 0.0),
+                CollinearNotIntersecting
+            ),
+            (
+                Segment::from_coords(0.0, 0.0, 3.0, 0.0),
+                CollinearIntersecting
+            )
+        ].into_iter().for_each(|(segment, intersection)| assert_eq!(ray.calculate_intersection(segment), intersection))
+    }
+
+    #[test]
+    fn self_hit_policy_prevents_collapsing_on_walls() {
+        let origin = Vec2::new(0.0, 0.0);
+        let occluders = [
+            Segment::from_coords(-5.0, 0.0, 5.0, 0.0),
+            Segment::from_coords(-10.0, -10.0, 10.0, -10.0),
+            Segment::from_coords(10.0, -10.0, 10.0, 10.0),
+            Segment::from_coords(10.0, 10.0, -10.0, 10.0),
+            Segment::from_coords(-10.0, 10.0, -10.0, -10.0),
+        ].map(Occluder::new);
+
+        let collapsed = calculate_intersection_points(origin, &occluders, &RaycastConfig::default());
+        assert!(collapsed.contains(&origin));
+
+        [
+            SelfHitPolicy::IgnoreNear(0.001),
+            SelfHitPolicy::ExcludeOccluder(OccluderId::new(0)),
+        ].into_iter().for_each(|policy| {
+            let config = RaycastConfig::default().with_self_hit_policy(policy);
+            let points = calculate_intersection_points(origin, &occluders, &config);
+            assert!(!points.is_empty());
+            assert!(points.iter().all(|point| point.distance(origin) >= 5.0));
+        })
+    }
+
+    #[test]
+    fn occluders_on_other_layers_are_ignored() {
+        const SIGHT: u32 = 0b01;
+        const BULLETS: u32 = 0b10;
+
+        let origin = Vec2::new(0.0, 0.0);
+        let glass = Occluder::new(Segment::from_coords(5.0, -20.0, 5.0, 20.0)).with_layers(BULLETS);
+        let occluders = [
+            glass,
+            Occluder::new(Segment::from_coords(-10.0, -10.0, 10.0, -10.0)),
+            Occluder::new(Segment::from_coords(10.0, -10.0, 10.0, 10.0)),
+            Occluder::new(Segment::from_coords(10.0, 10.0, -10.0, 10.0)),
+            Occluder::new(Segment::from_coords(-10.0, 10.0, -10.0, -10.0)),
+        ];
+
+        let reaches_far_wall = |mask: u32| calculate_intersection_points(origin, &occluders, &RaycastConfig::layers(mask))
+            .iter()
+            .any(|point| point.x == 10.0);
+
+        assert!(reaches_far_wall(SIGHT));
+        assert!(!reaches_far_wall(BULLETS));
+    }
+
+    #[test]
+    fn stats_count_rays_and_segment_tests() {
+        let origin = Vec2::new(0.0, 0.0);
+        let occluders = [
+            Segment::from_coords(-10.0, -10.0, 10.0, -10.0),
+            Segment::from_coords(10.0, -10.0, 10.0, 10.0),
+            Segment::from_coords(10.0, 10.0, -10.0, 10.0),
+            Segment::from_coords(-10.0, 10.0, -10.0, -10.0),
+        ].map(Occluder::new);
+
+        let mut stats = RaycastStats::default();
+        calculate_intersection_points_with_stats(origin, &occluders, &RaycastConfig::default(), &mut stats);
+
+        // one ray per corner. Every corner has walls on both sides, so no extra rays pass by them
+        assert_eq!(stats.rays_cast, 4);
+        assert_eq!(stats.segment_tests, 4 * occluders.len());
+
+        calculate_intersection_points_with_stats(origin, &occluders, &RaycastConfig::default(), &mut stats);
+        assert_eq!(stats.rays_cast, 8);
+    }
+
+    #[test]
+    fn toi_moving_segment_works() {
+        let ray = Ray::new(Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0));
+        let door = Segment::from_coords(5.0, 5.0, 5.0, 8.0);
+
+        [
+            // the lower end of the door reaches the ray after 5 time units
+            (door, Vec2::new(0.0, -1.0), 10.0, Some(TimeOfImpact { time: 5.0, point: Vec2::new(5.0, 0.0) })),
+            (door, Vec2::new(0.0, -1.0), 3.0, None),
+            // moving away or parallel
+            (door, Vec2::new(0.0, 1.0), 10.0, None),
+            (door, Vec2::new(1.0, 0.0), 10.0, None),
+            // behind the ray origin
+            (Segment::from_coords(-5.0, 5.0, -5.0, 8.0), Vec2::new(0.0, -1.0), 10.0, None),
+            // already intersecting
+            (Segment::from_coords(5.0, -5.0, 5.0, 5.0), Vec2::new(0.0, -1.0), 10.0, Some(TimeOfImpact { time: 0.0, point: Vec2::new(5.0, 0.0) })),
+            // the segment sweeps over the ray origin
+            (Segment::from_coords(-2.0, 2.0, 2.0, 4.0), Vec2::new(0.0, -1.0), 10.0, Some(TimeOfImpact { time: 3.0, point: Vec2::new(0.0, 0.0) })),
+        ].into_iter().for_each(|(segment, velocity, max_time, expected)| assert_eq!(ray.toi_moving_segment(segment, velocity, max_time), expected))
+    }
+
+    #[test]
+    fn visible_occluders_works() {
+        let occluders = [
+            Segment::from_coords(-10.0, 10.0, 10.0, 10.0),
+            Segment::from_coords(-2.0, 5.0, 2.0, 5.0),
+            Segment::from_coords(-100.0, -100.0, 100.0, -100.0),
+            Segment::from_coords(100.0, -100.0, 100.0, 100.0),
+            Segment::from_coords(100.0, 100.0, -100.0, 100.0),
+            Segment::from_coords(-100.0, 100.0, -100.0, -100.0),
+        ].map(Occluder::new);
+
+        let visible = visible_occluders(Vec2::ZERO, &occluders, &RaycastConfig::default());
+        let ranges = |id: usize| visible
+            .iter()
+            .filter(|(occluder, _)| *occluder == OccluderId::new(id))
+            .map(|(_, range)| (range.start, range.end))
+            .collect::<Vec<_>>();
+
+        // the second wall hides the middle of the first one, roughly from x = -4 to x = 4
+        let first = ranges(0);
+        assert_eq!(first.len(), 2);
+        [(0.0, 0.3), (0.7, 1.0)].into_iter().zip(first).for_each(|(expected, actual)| {
+            assert!((expected.0 - actual.0).abs() < 0.01 && (expected.1 - actual.1).abs() < 0.01, "{expected:?} != {actual:?}")
+        });
+        assert_eq!(ranges(1), vec![(0.0, 1.0)]);
+        // the first wall hides the upper bound
+        assert!(ranges(4).is_empty());
+        assert_eq!(ranges(2), vec![(0.0, 1.0)]);
+    }
+
+    #[test]
+    fn visible_occluders_do_not_depend_on_the_scale() {
+        let segments = [
+            Segment::from_coords(-10.0, 10.0, 10.0, 10.0),
+            Segment::from_coords(-2.0, 5.0, 2.0, 5.0),
+            Segment::from_coords(3.0, -4.0, 3.0, 4.0),
+            Segment::from_coords(-100.0, -100.0, 100.0, -100.0),
+            Segment::from_coords(100.0, -100.0, 100.0, 100.0),
+            Segment::from_coords(100.0, 100.0, -100.0, 100.0),
+            Segment::from_coords(-100.0, 100.0, -100.0, -100.0),
+        ];
+        let origin = Vec2::new(0.5, -0.25);
+        let visible = |scale: f32| {
+            let occluders = segments.map(|segment| Occluder::new(Segment::new(segment.a * scale, segment.b * scale)));
+            visible_occluders(origin * scale, &occluders, &RaycastConfig::default())
+        };
+        let expected = visible(1.0);
+
+        [0.0001, 0.01, 100.0, 10000.0].into_iter().for_each(|scale| {
+            let actual = visible(scale);
+
+            assert_eq!(actual.len(), expected.len(), "{scale}: {actual:?}");
+            actual.iter().zip(&expected).for_each(|((id, range), (expected_id, expected_range))| {
+                assert_eq!(id, expected_id, "{scale}");
+                assert!((range.start - expected_range.start).abs() < 0.001 && (range.end - expected_range.end).abs() < 0.001, "{scale}: {range:?} != {expected_range:?}");
+            })
+        })
+    }
+
+    #[test]
+    fn back_faces_of_closed_polygons_are_culled() {
+        let square = [Vec2::new(10.0, 10.0), Vec2::new(10.0, 20.0), Vec2::new(20.0, 20.0), Vec2::new(20.0, 10.0)];
+        let bounds = [
+            Segment::from_coords(-50.0, -50.0, 50.0, -50.0),
+            Segment::from_coords(50.0, -50.0, 50.0, 50.0),
+            Segment::from_coords(50.0, 50.0, -50.0, 50.0),
+            Segment::from_coords(-50.0, 50.0, -50.0, -50.0),
+        ].map(Occluder::new);
+        let culled = Occluder::polygon(&square).into_iter().chain(bounds).collect::<Vec<_>>();
+        let open = culled.iter().map(|occluder| occluder.with_kind(OccluderKind::Open)).collect::<Vec<_>>();
+        let (mut culled_stats, mut open_stats) = (RaycastStats::default(), RaycastStats::default());
+
+        let culled_points = calculate_intersection_points_with_stats(Vec2::ZERO, &culled, &RaycastConfig::default(), &mut culled_stats);
+        let open_points = calculate_intersection_points_with_stats(Vec2::ZERO, &open, &RaycastConfig::default(), &mut open_stats);
+        let area = |points: &[Vec2]| (0..points.len()).map(|i| points[i].perp_dot(points[(i + 1) % points.len()])).sum::<f32>() / 2.0;
+
+        // the upper and right edge of the square face away from the origin. Without them, a few redundant points
+        // on the lower and left edge are missing, but the polygon stays the same
+        assert!(culled_points.iter().all(|point| open_points.contains(point)));
+        assert!((area(&culled_points) - area(&open_points)).abs() < 0.01);
+        assert!(culled_stats.segment_tests < open_stats.segment_tests);
+    }
+
+    #[test]
+    fn occluders_can_be_defined_at_compile_time() {
+        static WALLS: [Occluder; 2] = occluders![(0.0, 0.0, 100.0, 0.0), (100.0, 0.0, 100.0, 50.0)];
+        const SQUARE: [Occluder; 4] = Occluder::const_polygon([
+            Vec2::new(10.0, 10.0),
+            Vec2::new(20.0, 10.0),
+            Vec2::new(20.0, 20.0),
+            Vec2::new(10.0, 20.0),
+        ]);
+
+        assert_eq!(WALLS.to_vec(), vec![
+            Occluder::new(Segment::new(Vec2::new(0.0, 0.0), Vec2::new(100.0, 0.0))),
+            Occluder::new(Segment::new(Vec2::new(100.0, 0.0), Vec2::new(100.0, 50.0))),
+        ]);
+        assert_eq!(SQUARE.to_vec(), Occluder::polygon(&SQUARE.map(|occluder| occluder.segment.a())));
+        let none: [Occluder; 0] = occluders![];
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn ray_budget_keeps_the_nearest_endpoint_per_sector() {
+        let origin = Vec2::ZERO;
+        let mut endpoints = vec![
+            // both in the sector from 0 to 90 degrees
+            Vec2::new(10.0, 1.0),
+            Vec2::new(2.0, 2.0),
+            Vec2::new(-5.0, 5.0),
+            Vec2::new(-10.0, -1.0),
+            Vec2::new(1.0, -10.0),
+        ];
+        sort_by_angle(origin, &mut endpoints);
+
+        let limited = |max_rays: usize| {
+            let mut endpoints = endpoints.clone();
+            limit_endpoints(origin, &mut endpoints, max_rays);
+            endpoints
+        };
+
+        assert_eq!(limited(5), endpoints);
+        assert_eq!(limited(4), vec![Vec2::new(-10.0, -1.0), Vec2::new(1.0, -10.0), Vec2::new(2.0, 2.0), Vec2::new(-5.0, 5.0)]);
+        assert_eq!(limited(0), vec![]);
+
+        // a budget bounds the rays cast over a huge scene, but still encloses the origin
+        let occluders = (0..200)
+            .map(|i| Vec2::from_angle(i as f32 * 0.0314) * (50.0 + (i % 7) as f32))
+            .map(|point| Occluder::new(Segment::new(point, point * 1.02)))
+            .chain(occluders![(-100.0, -100.0, 100.0, -100.0), (100.0, -100.0, 100.0, 100.0), (100.0, 100.0, -100.0, 100.0), (-100.0, 100.0, -100.0, -100.0)])
+            .collect::<Vec<_>>();
+        assert!(occluders.len() >= SWEEP_THRESHOLD);
+
+        // the automatic choice must not switch to the sweep, which does not know the budget
+        [Algorithm::Naive, Algorithm::Auto].into_iter().for_each(|algorithm| {
+            let config = RaycastConfig::default().with_algorithm(algorithm).with_max_rays(32);
+            let mut stats = RaycastStats::default();
+            let points = calculate_intersection_points_with_stats(origin, &occluders, &config, &mut stats);
+
+            assert!(stats.rays_cast <= 3 * 32, "{algorithm:?}: {}", stats.rays_cast);
+            assert!(points.iter().all(|point| point.x.abs() < 100.001 && point.y.abs() < 100.001));
+            assert!(VisibilityPolygon::new(origin, points).contains(Vec2::new(1.0, 1.0)));
+        });
+    }
+
+    #[test]
+    fn uniform_rays_are_merged_with_endpoint_rays() {
+        let origin = Vec2::ZERO;
+        // a room with a wall right of the origin, which has a thin gap uniform rays miss
+        let occluders = occluders![
+            (-100.0, -100.0, 100.0, -100.0),
+            (100.0, -100.0, 100.0, 100.0),
+            (100.0, 100.0, -100.0, 100.0),
+            (-100.0, 100.0, -100.0, -100.0),
+            (50.0, -50.0, 50.0, -2.0),
+            (50.0, 2.0, 50.0, 50.0),
+        ];
+        let points = |config: RaycastConfig| calculate_intersection_points(origin, &occluders, &config.with_algorithm(Algorithm::Naive));
+        let sees_through_gap = |points: &[Vec2]| points.iter().any(|point| point.x == 100.0 && point.y.abs() < 5.0);
+
+        // an odd count, so no ray points straight through the gap
+        let uniform = points(RaycastConfig::default().with_max_rays(0).with_uniform_rays(15));
+        assert!(!uniform.is_empty() && uniform.len() <= 15);
+        assert!(!sees_through_gap(&uniform));
+
+        let hybrid = points(RaycastConfig::default().with_uniform_rays(15));
+        assert!(sees_through_gap(&hybrid));
+
+        // rays towards the corners of the room are already cast towards their endpoints
+        let mut endpoints = collect_endpoints(origin, &occluders, &RaycastConfig::default());
+        sort_by_angle(origin, &mut endpoints);
+        assert_eq!(uniform_rays(origin, 8, &endpoints).len(), 4);
+
+        // the automatic choice must not switch to the sweep in large scenes, which does not cast uniform rays
+        let crowded = (0..SWEEP_THRESHOLD)
+            .map(|i| Vec2::new(-90.0 + (i % 16) as f32 * 2.0, -90.0 + (i / 16) as f32 * 2.0))
+            .map(|point| Occluder::new(Segment::new(point, point + Vec2::new(1.0, 0.5))))
+            .chain(occluders.iter().copied())
+            .collect::<Vec<_>>();
+        let config = RaycastConfig::default().with_uniform_rays(15);
+
+        assert_eq!(
+            calculate_intersection_points(origin, &crowded, &config),
+            calculate_intersection_points(origin, &crowded, &config.clone().with_algorithm(Algorithm::Naive))
+        );
+    }
+
+    #[test]
+    fn queries_in_a_reused_arena_match_fresh_queries() {
+        let mut arena = QueryArena::default();
+
+        for scene in pathological_scenes() {
+            for config in [
+                RaycastConfig::default().with_algorithm(Algorithm::Naive),
+                RaycastConfig::default().with_peek_policy(PeekPolicy::Peek),
+                RaycastConfig::default().with_coordinate_system(CoordinateSystem::YDown),
+                RaycastConfig::default().with_max_rays(8).with_uniform_rays(12),
+            ] {
+                let expected = calculate_intersection_points(scene.origin, &scene.occluders, &config);
+                assert_eq!(calculate_intersection_points_in(scene.origin, &scene.occluders, &config, &mut arena), expected, "{}", scene.name);
+            }
+        }
+    }
+
+    #[test]
+    fn endpoints_are_classified_by_their_occluders() {
+        // two squares touching at the origin, seen from the upper left
+        let lower_left = [Vec2::new(-10.0, -10.0), Vec2::new(0.0, -10.0), Vec2::new(0.0, 0.0), Vec2::new(-10.0, 0.0)];
+        let upper_right = [Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0), Vec2::new(10.0, 10.0), Vec2::new(0.0, 10.0)];
+        let occluders = Occluder::polygon(&lower_left)
+            .into_iter()
+            .chain(Occluder::polygon(&upper_right))
+            .chain([Occluder::new(Segment::from_coords(-20.0, 50.0, -20.0, 80.0))])
+            .collect::<Vec<_>>();
+        let origin = Vec2::new(-20.0, 30.0);
+
+        [
+            (Vec2::new(-10.0, -10.0), Some(EndpointKind::Begin)),
+            (Vec2::new(10.0, 10.0), Some(EndpointKind::End)),
+            (Vec2::new(-10.0, 0.0), Some(EndpointKind::Both)),
+            (Vec2::new(0.0, 0.0), Some(EndpointKind::Both)),
+            (Vec2::new(-20.0, 50.0), None),
+        ].into_iter().for_each(|(endpoint, expected)| assert_eq!(classify_endpoint(origin, endpoint, &occluders, &RaycastConfig::default()), expected, "{endpoint}"))
+    }
+
+    #[test]
+    fn the_view_can_peek_through_touching_corners() {
+        let lower_left = [Vec2::new(-10.0, -10.0), Vec2::new(0.0, -10.0), Vec2::new(0.0, 0.0), Vec2::new(-10.0, 0.0)];
+        let upper_right = [Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0), Vec2::new(10.0, 10.0), Vec2::new(0.0, 10.0)];
+        let bounds = [
+            Segment::from_coords(-100.0, -100.0, 100.0, -100.0),
+            Segment::from_coords(100.0, -100.0, 100.0, 100.0),
+            Segment::from_coords(100.0, 100.0, -100.0, 100.0),
+            Segment::from_coords(-100.0, 100.0, -100.0, -100.0),
+        ].map(Occluder::new);
+        let occluders = Occluder::polygon(&lower_left)
+            .into_iter()
+            .chain(Occluder::polygon(&upper_right))
+            .chain(bounds)
+            .collect::<Vec<_>>();
+        let origin = Vec2::new(-20.0, 30.0);
+        // the continuation of the ray from the origin through the touching corners
+        let behind = Vec2::new(200.0 / 3.0, -100.0);
+        let reaches_behind = |peek_policy: PeekPolicy| calculate_intersection_points(origin, &occluders, &RaycastConfig::default().with_peek_policy(peek_policy))
+            .iter()
+            .any(|point| point.distance(behind) < 0.01);
+
+        assert!(!reaches_behind(PeekPolicy::Block));
+        assert!(reaches_behind(PeekPolicy::Peek));
+    }
+
+    #[test]
+    fn triangle_helpers_work() {
+        let triangle = Triangle::new(Vec2::new(0.0, 0.0), Vec2::new(0.0, 6.0), Vec2::new(6.0, 0.0));
+
+        assert_eq!(triangle.area(), 18.0);
+        assert_eq!(triangle.centroid(), Vec2::new(2.0, 2.0));
+        assert!(triangle.edges() == [
+            Segment::from_coords(0.0, 0.0, 0.0, 6.0),
+            Segment::from_coords(0.0, 6.0, 6.0, 0.0),
+            Segment::from_coords(6.0, 0.0, 0.0, 0.0),
+        ]);
+
+        [
+            (Vec2::new(1.0, 1.0), true),
+            (Vec2::new(3.0, 3.0), true),
+            (Vec2::new(0.0, 6.0), true),
+            (Vec2::new(4.0, 4.0), false),
+            (Vec2::new(-1.0, 1.0), false),
+        ].into_iter().for_each(|(point, expected)| assert_eq!(triangle.contains(point), expected, "{point}"))
+    }
+
+    #[test]
+    fn indexed_triangles_match_the_triangle_fan() {
+        for scene in pathological_scenes() {
+            let indexed = raycast_indexed(scene.origin, &scene.occluders, &RaycastConfig::default());
+            let triangles = raycast_with_config(scene.origin, &scene.occluders, &RaycastConfig::default());
+
+            assert_eq!(indexed.vertices.len(), triangles.len() + 1, "{}", scene.name);
+            assert_eq!(indexed.triangles().collect::<Vec<_>>(), triangles, "{}", scene.name);
+        }
+    }
+
+    #[test]
+    fn nothing_around_the_origin_gives_no_triangles() {
+        let wall = occluders![(-10.0, 10.0, 10.0, 10.0)];
+
+        [
+            (Vec::new(), RaycastConfig::default()),
+            (wall.to_vec(), RaycastConfig::layers(0)),
+        ].into_iter().for_each(|(occluders, config)| {
+            assert_eq!(raycast_with_config(Vec2::ZERO, &occluders, &config), vec![]);
+            assert_eq!(raycast_indexed(Vec2::ZERO, &occluders, &config), IndexedTriangles::default());
+        })
+    }
+
+    #[test]
+    fn y_down_coordinates_mirror_the_results() {
+        let y_down = RaycastConfig::default().with_coordinate_system(CoordinateSystem::YDown);
+        let mirror = |point: Vec2| Vec2::new(point.x, -point.y);
+
+        for scene in pathological_scenes() {
+            let mirrored = scene.occluders.iter().map(|occluder| occluder.to_y_up(CoordinateSystem::YDown)).collect::<Vec<_>>();
+            let expected = calculate_intersection_points(scene.origin, &scene.occluders, &RaycastConfig::default())
+                .into_iter()
+                .map(mirror)
+                .collect::<Vec<_>>();
+
+            assert_eq!(calculate_intersection_points(mirror(scene.origin), &mirrored, &y_down), expected, "{}", scene.name);
+        }
+    }
+
+    #[test]
+    fn results_follow_the_polygon_format() {
+        for scene in pathological_scenes() {
+            let points = calculate_intersection_points(scene.origin, &scene.occluders, &RaycastConfig::default());
+            let formatted_with = |format: PolygonFormat| calculate_intersection_points(scene.origin, &scene.occluders, &RaycastConfig::default().with_format(format));
+            let formatted = |winding: Winding, closed: bool| formatted_with(PolygonFormat::new(winding, closed));
+
+            let mut clockwise = points.clone();
+            clockwise.reverse();
+            let closed = points.iter().chain(points.first()).copied().collect::<Vec<_>>();
+
+            assert_eq!(formatted(Winding::Clockwise, false), clockwise, "{}", scene.name);
+            assert_eq!(formatted(Winding::CounterClockwise, true), closed, "{}", scene.name);
+            assert_eq!(formatted(Winding::Clockwise, true), clockwise.iter().chain(clockwise.first()).copied().collect::<Vec<_>>(), "{}", scene.name);
+            // queries building on the polygon are not affected
+            let resampled = formatted_with(PolygonFormat::new(Winding::CounterClockwise, true).with_resolution(16));
+            assert_eq!(resampled.len(), 17, "{}", scene.name);
+            assert_eq!(resampled.first(), resampled.last(), "{}", scene.name);
+            let config = RaycastConfig::default().with_format(PolygonFormat::new(Winding::Clockwise, true));
+            assert_eq!(raycast_with_config(scene.origin, &scene.occluders, &config), raycast_with_config(scene.origin, &scene.occluders, &RaycastConfig::default()));
+
+            // cones keep their origin first
+            let cone = ViewCone::new(scene.origin, Vec2::new(1.0, 0.5), 1.0);
+            let cone_with = |format: PolygonFormat| cone_intersection_points(&cone, &scene.occluders, &RaycastConfig::default().with_format(format));
+            let points = cone_with(PolygonFormat::default());
+            let clockwise = points[..1].iter().chain(points[1..].iter().rev()).copied().collect::<Vec<_>>();
+
+            assert_eq!(cone_with(PolygonFormat::new(Winding::Clockwise, false)), clockwise, "{}", scene.name);
+            assert_eq!(cone_with(PolygonFormat::new(Winding::Clockwise, true)), clockwise.iter().chain([&scene.origin]).copied().collect::<Vec<_>>(), "{}", scene.name);
+            let resampled = cone_with(PolygonFormat::default().with_resolution(16));
+            assert_eq!(resampled.len(), 17, "{}", scene.name);
+            assert_eq!(resampled[0], scene.origin, "{}", scene.name);
+            // the first and the last sample lie on the borders, like the hits of the borders
+            assert!(resampled[1].distance(points[1]) < 0.01 && resampled[16].distance(points[points.len() - 1]) < 0.01, "{}: {resampled:?} {points:?}", scene.name);
+        }
+    }
+
+    #[test]
+    fn misses_follow_the_miss_policy() {
+        // a single wall in front of the origin, everything else is open
+        let occluders = [Occluder::new(Segment::from_coords(-10.0, 10.0, 10.0, 10.0))];
+        let bounds = Aabb::new(Vec2::new(-50.0, -50.0), Vec2::new(50.0, 50.0));
+
+        for algorithm in [Algorithm::Naive, Algorithm::Sweep] {
+            let points = |miss_policy: MissPolicy| calculate_intersection_points(Vec2::ZERO, &occluders, &RaycastConfig::default()
+                .with_algorithm(algorithm)
+                .with_miss_policy(miss_policy));
+
+            assert!(points(MissPolicy::Drop).iter().all(|point| point.y == 10.0), "{algorithm:?}");
+
+            let clamped = points(MissPolicy::MaxDistance(100.0));
+            assert!(clamped.iter().any(|point| (point.length() - 100.0).abs() < 0.01), "{algorithm:?}");
+            assert!(clamped.iter().all(|point| point.y == 10.0 || (point.length() - 100.0).abs() < 0.01), "{algorithm:?}");
+
+            let clipped = points(MissPolicy::Bounds(bounds));
+            assert!(clipped.iter().any(|point| point.x.abs() == 50.0 || point.y.abs() == 50.0), "{algorithm:?}");
+            assert!(clipped.iter().all(|point| point.y == 10.0 || point.x.abs() == 50.0 || point.y.abs() == 50.0), "{algorithm:?}");
+
+            let unbounded = points(MissPolicy::Unbounded);
+            assert!(unbounded.iter().any(|point| MissPolicy::is_unbounded(Vec2::ZERO, *point)), "{algorithm:?}");
+        }
+    }
+
+    #[test]
+    fn occluders_only_block_at_their_elevation() {
+        let occluders = [
+            Occluder::new(Segment::from_coords(-100.0, 100.0, 100.0, 100.0)),
+            // the railing of a catwalk between the floor and the wall
+            Occluder::new(Segment::from_coords(-100.0, 10.0, 100.0, 10.0)).with_z_range(5.0, 6.0),
+        ];
+
+        for algorithm in [Algorithm::Naive, Algorithm::Sweep] {
+            let points = |config: RaycastConfig| calculate_intersection_points(Vec2::ZERO, &occluders, &config.with_algorithm(algorithm));
+
+            assert!(points(RaycastConfig::default()).iter().all(|point| point.y == 10.0), "{algorithm:?}");
+            assert!(points(RaycastConfig::default().with_height(5.5)).iter().all(|point| point.y == 10.0), "{algorithm:?}");
+            assert!(points(RaycastConfig::default().with_height(0.0)).iter().all(|point| point.y == 100.0), "{algorithm:?}");
+        }
+    }
+
+    #[test]
+    fn stages_compose_to_the_full_sweep() {
+        let config = RaycastConfig::default();
+
+        for scene in pathological_scenes() {
+            let mut stats = RaycastStats::default();
+            let mut endpoints = collect_endpoints(scene.origin, &scene.occluders, &config);
+            sort_by_angle(scene.origin, &mut endpoints);
+            let hits = resolve_hits(endpoint_rays(scene.origin, &endpoints), &scene.occluders, &config, &mut stats);
+            let grazing_hits = resolve_grazing_hits(grazing_rays(&hits, &scene.occluders, &config), &scene.occluders, &config, &mut stats);
+
+            assert_eq!(
+                assemble_polygon(scene.origin, &hits, grazing_hits),
+                calculate_intersection_points(scene.origin, &scene.occluders, &config),
+                "{}", scene.name
+            );
+        }
+    }
+
+    #[test]
+    fn geometry_is_compared_approximately_and_displayed() {
+        let segment = Segment::from_coords(0.0, 0.0, 1.0, 2.0);
+        let triangle = Triangle::new(Vec2::ZERO, Vec2::X, Vec2::Y);
+
+        assert!(segment.approx_eq(&Segment::from_coords(0.0001, 0.0, 1.0, 1.9999), 0.001));
+        assert!(!segment.approx_eq(&segment.reversed(), 0.001));
+        assert!(triangle.approx_eq(&Triangle::new(Vec2::splat(0.0005), Vec2::X, Vec2::Y), 0.001));
+        assert!(!triangle.approx_eq(&Triangle::new(Vec2::X, Vec2::Y, Vec2::ZERO), 0.001));
+
+        let polygon = [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y];
+        [
+            (vec![Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y], true),
+            (vec![Vec2::ONE, Vec2::new(0.0, 1.0005), Vec2::ZERO, Vec2::X], true),
+            (vec![Vec2::Y, Vec2::ONE, Vec2::X, Vec2::ZERO], false),
+            (vec![Vec2::ZERO, Vec2::X, Vec2::ONE], false),
+            (vec![Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::new(0.0, 1.1)], false),
+        ].into_iter().for_each(|(other, expected)| assert_eq!(polygon_approx_eq(&polygon, &other, 0.001), expected, "{other:?}"));
+
+        assert_eq!(segment.to_string(), "[0, 0] -> [1, 2]");
+        assert_eq!(triangle.to_string(), "[0, 0], [1, 0], [0, 1]");
+    }
+
+    #[test]
+    fn degenerate_segments_are_rejected() {
+        [
+            (Vec2::ZERO, Vec2::X, Ok(Segment::new(Vec2::ZERO, Vec2::X))),
+            (Vec2::new(f32::NAN, 0.0), Vec2::X, Err(SegmentError::NotFinite)),
+            (Vec2::ZERO, Vec2::new(0.0, f32::NEG_INFINITY), Err(SegmentError::NotFinite)),
+            (Vec2::ONE, Vec2::ONE, Err(SegmentError::ZeroLength)),
+        ].into_iter().for_each(|(a, b, expected)| assert_eq!(Segment::try_new(a, b), expected, "{a} -> {b}"));
+    }
+
+    #[test]
+    fn segments_can_be_deduplicated() {
+        let segments = [
+            Segment::from_coords(0.0, 0.0, 1.0, 0.0),
+            Segment::from_coords(0.0, 0.0, 1.0, 0.0),
+            Segment::from_coords(-0.0, 0.0, 1.0, -0.0),
+            Segment::from_coords(0.0, 0.0, 1.0, 0.0).reversed(),
+            Segment::from_coords(0.0, 0.0, 0.0, 1.0),
+        ];
+
+        let unique = segments.into_iter().collect::<HashSet<_>>();
+
+        assert_eq!(unique.len(), 3);
+        assert!(unique.contains(&Segment::new(Vec2::X, Vec2::ZERO)));
+        assert_eq!(segments[3].a(), Vec2::X);
+        assert_eq!(segments[3].b(), Vec2::ZERO);
+        assert_eq!(segments[4].length(), 1.0);
+
+        // NaN is not equal to itself, but a segment with NaN coordinates must be, to be found in a set
+        let invalid = Segment::from_coords(f32::NAN, 10.0, 20.0, 10.0);
+        assert_eq!(invalid, invalid);
+        assert!([invalid, invalid].into_iter().collect::<HashSet<_>>().contains(&invalid));
+    }
+}
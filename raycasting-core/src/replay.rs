@@ -0,0 +1,469 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use glam::*;
+use crate::aabb::Aabb;
+use crate::config::{Algorithm, CoordinateSystem, MissPolicy, PeekPolicy, PolygonFormat, RaycastConfig, SelfHitPolicy, SymmetryPolicy, Winding};
+use crate::error::RaycastError;
+use crate::groups::GroupId;
+use crate::occluder::{Material, Occluder, OccluderHeight, OccluderId, OccluderKind};
+use crate::raycasting::{calculate_intersection_points, Segment};
+
+/// The first bytes of every recording, followed by its [FORMAT_VERSION]
+const MAGIC: [u8; 4] = *b"RCRP";
+const OCCLUDERS_TAG: u8 = 0;
+const QUERY_TAG: u8 = 1;
+const NO_GROUP: u8 = u8::MAX;
+
+/// The version of the recordings written by a [QueryRecorder]. It must be increased whenever the layout of
+/// a record changes, and [replay] must keep reading the older versions, filling new fields with their defaults.
+///
+/// Version 0 are the recordings from before the header, which start with their first record. Their layout
+/// is the one of version 1, older recordings without a header can not be replayed.
+///
+/// Version 2 added the generation of the occluder excluded by [SelfHitPolicy::ExcludeOccluder],
+/// which is 0 in older recordings.
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Writes queries to a compact binary recording, to replay them later with [replay].
+///
+/// Every query refers to a revision of the occluders, which must be recorded with [QueryRecorder::record_occluders]
+/// before (usually whenever the occluders change). This way, a recording of a whole play session stays small.
+pub struct QueryRecorder<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> QueryRecorder<W> {
+    /// Start a recording by writing its header.
+    pub fn new(mut writer: W) -> Result<Self, RaycastError> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        Ok(QueryRecorder { writer })
+    }
+
+    pub fn record_occluders(
+        &mut self,
+        revision: u64,
+        occluders: &[Occluder],
+    ) -> Result<(), RaycastError> {
+        let mut bytes = vec![OCCLUDERS_TAG];
+        bytes.extend(revision.to_le_bytes());
+        bytes.extend((occluders.len() as u32).to_le_bytes());
+
+        for occluder in occluders {
+            let [a, b] = occluder.segment.points();
+            [a.x, a.y, b.x, b.y, occluder.opacity].into_iter().for_each(|value| bytes.extend(value.to_le_bytes()));
+            occluder.tint.to_array().into_iter().for_each(|value| bytes.extend(value.to_le_bytes()));
+            bytes.extend(occluder.layers.to_le_bytes());
+            bytes.push(match occluder.kind {
+                OccluderKind::Open => 0,
+                OccluderKind::ClosedPolygon => 1
+            });
+            bytes.push(match occluder.height {
+                OccluderHeight::Full => 0,
+                OccluderHeight::Low => 1
+            });
+            [occluder.z_min, occluder.z_max].into_iter().for_each(|value| bytes.extend(value.to_le_bytes()));
+            match occluder.material {
+                Material::Opaque => bytes.push(0),
+                Material::Mirror => bytes.push(1),
+                Material::Refractive(index) => {
+                    bytes.push(2);
+                    bytes.extend(index.to_le_bytes());
+                }
+            }
+            bytes.push(occluder.group.map(|group| group.0).unwrap_or(NO_GROUP));
+        }
+
+        self.writer.write_all(&bytes)?;
+        Ok(())
+    }
+
+    pub fn record_query(
+        &mut self,
+        origin: Vec2,
+        config: &RaycastConfig,
+        revision: u64,
+    ) -> Result<(), RaycastError> {
+        let mut bytes = vec![QUERY_TAG];
+        bytes.extend(origin.x.to_le_bytes());
+        bytes.extend(origin.y.to_le_bytes());
+        bytes.extend(revision.to_le_bytes());
+        bytes.extend(config.layers.to_le_bytes());
+        bytes.extend(config.disabled_groups.to_le_bytes());
+        bytes.push(match config.algorithm {
+            Algorithm::Auto => 0,
+            Algorithm::Naive => 1,
+            Algorithm::Sweep => 2
+        });
+        bytes.push(match config.peek_policy {
+            PeekPolicy::Block => 0,
+            PeekPolicy::Peek => 1
+        });
+
+        bytes.push(match config.coordinate_system {
+            CoordinateSystem::YUp => 0,
+            CoordinateSystem::YDown => 1
+        });
+        bytes.push(match config.format.winding {
+            Winding::CounterClockwise => 0,
+            Winding::Clockwise => 1
+        });
+        bytes.push(config.format.closed as u8);
+
+        match config.format.resolution {
+            None => bytes.push(0),
+            Some(resolution) => {
+                bytes.push(1);
+                bytes.extend((resolution as u64).to_le_bytes());
+            }
+        }
+
+        bytes.push(match config.symmetry_policy {
+            SymmetryPolicy::Asymmetric => 0,
+            SymmetryPolicy::Symmetric => 1
+        });
+
+        match config.miss_policy {
+            MissPolicy::Drop => bytes.push(0),
+            MissPolicy::MaxDistance(distance) => {
+                bytes.push(1);
+                bytes.extend(distance.to_le_bytes());
+            }
+            MissPolicy::Bounds(bounds) => {
+                bytes.push(2);
+                [bounds.min.x, bounds.min.y, bounds.max.x, bounds.max.y].into_iter().for_each(|value| bytes.extend(value.to_le_bytes()));
+            }
+            MissPolicy::Unbounded => bytes.push(3)
+        }
+
+        match config.self_hit_policy {
+            SelfHitPolicy::None => bytes.push(0),
+            SelfHitPolicy::IgnoreNear(epsilon) => {
+                bytes.push(1);
+                bytes.extend(epsilon.to_le_bytes());
+            }
+            SelfHitPolicy::ExcludeOccluder(id) => {
+                bytes.push(2);
+                bytes.extend((id.index as u64).to_le_bytes());
+                bytes.extend(id.generation.to_le_bytes());
+            }
+        }
+
+        match config.height {
+            None => bytes.push(0),
+            Some(height) => {
+                bytes.push(1);
+                bytes.extend(height.to_le_bytes());
+            }
+        }
+
+        match config.max_rays {
+            None => bytes.push(0),
+            Some(max_rays) => {
+                bytes.push(1);
+                bytes.extend((max_rays as u64).to_le_bytes());
+            }
+        }
+
+        bytes.extend((config.uniform_rays as u64).to_le_bytes());
+
+        self.writer.write_all(&bytes)?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// A recorded query, together with the result of running it again.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayedQuery {
+    pub origin: Vec2,
+    pub config: RaycastConfig,
+    pub revision: u64,
+    pub points: Vec<Vec2>,
+}
+
+/// Read a recording written by a [QueryRecorder] of any version up to [FORMAT_VERSION] and run every query
+/// in it again against the occluders of its revision, without any app.
+pub fn replay(mut reader: impl Read) -> Result<Vec<ReplayedQuery>, RaycastError> {
+    let mut revisions: HashMap<u64, Vec<Occluder>> = HashMap::new();
+    let mut queries = Vec::new();
+    let mut byte = [0];
+
+    // recordings of version 0 have no header, so their first byte is already the tag of the first record
+    let (version, mut pending_tag) = match reader.read(&mut byte)? {
+        0 => return Ok(queries),
+        _ if byte[0] == MAGIC[0] => (read_header(&mut reader)?, None),
+        _ => (0, Some(byte[0]))
+    };
+
+    loop {
+        let tag = match pending_tag.take() {
+            Some(tag) => tag,
+            None if reader.read(&mut byte)? == 0 => return Ok(queries),
+            None => byte[0]
+        };
+
+        match tag {
+            OCCLUDERS_TAG => {
+                let revision = read_u64(&mut reader)?;
+                let count = read_u32(&mut reader)?;
+                let occluders = (0..count)
+                    .map(|_| read_occluder(&mut reader))
+                    .collect::<Result<Vec<_>, RaycastError>>()?;
+                revisions.insert(revision, occluders);
+            }
+            QUERY_TAG => {
+                let origin = Vec2::new(read_f32(&mut reader)?, read_f32(&mut reader)?);
+                let revision = read_u64(&mut reader)?;
+                let config = RaycastConfig::layers(read_u32(&mut reader)?)
+                    .without_groups(read_u64(&mut reader)?)
+                    .with_algorithm(match read_u8(&mut reader)? {
+                        0 => Algorithm::Auto,
+                        1 => Algorithm::Naive,
+                        2 => Algorithm::Sweep,
+                        algorithm => return Err(invalid(format!("unknown algorithm {algorithm}")))
+                    })
+                    .with_peek_policy(match read_u8(&mut reader)? {
+                        0 => PeekPolicy::Block,
+                        1 => PeekPolicy::Peek,
+                        policy => return Err(invalid(format!("unknown peek policy {policy}")))
+                    })
+                    .with_coordinate_system(match read_u8(&mut reader)? {
+                        0 => CoordinateSystem::YUp,
+                        1 => CoordinateSystem::YDown,
+                        system => return Err(invalid(format!("unknown coordinate system {system}")))
+                    })
+                    .with_format(read_format(&mut reader)?)
+                    .with_symmetry_policy(match read_u8(&mut reader)? {
+                        0 => SymmetryPolicy::Asymmetric,
+                        1 => SymmetryPolicy::Symmetric,
+                        policy => return Err(invalid(format!("unknown symmetry policy {policy}")))
+                    })
+                    .with_miss_policy(match read_u8(&mut reader)? {
+                        0 => MissPolicy::Drop,
+                        1 => MissPolicy::MaxDistance(read_f32(&mut reader)?),
+                        2 => MissPolicy::Bounds(Aabb::new(
+                            Vec2::new(read_f32(&mut reader)?, read_f32(&mut reader)?),
+                            Vec2::new(read_f32(&mut reader)?, read_f32(&mut reader)?),
+                        )),
+                        3 => MissPolicy::Unbounded,
+                        policy => return Err(invalid(format!("unknown miss policy {policy}")))
+                    })
+                    .with_self_hit_policy(match read_u8(&mut reader)? {
+                        0 => SelfHitPolicy::None,
+                        1 => SelfHitPolicy::IgnoreNear(read_f32(&mut reader)?),
+                        2 => SelfHitPolicy::ExcludeOccluder(OccluderId {
+                            index: read_u64(&mut reader)? as usize,
+                            generation: if version >= 2 { read_u32(&mut reader)? } else { 0 },
+                        }),
+                        policy => return Err(invalid(format!("unknown self hit policy {policy}")))
+                    });
+                let config = match read_u8(&mut reader)? {
+                    0 => config,
+                    1 => config.with_height(read_f32(&mut reader)?),
+                    height => return Err(invalid(format!("unknown height {height}")))
+                };
+                let config = match read_u8(&mut reader)? {
+                    0 => config,
+                    1 => config.with_max_rays(read_u64(&mut reader)? as usize),
+                    max_rays => return Err(invalid(format!("unknown ray budget {max_rays}")))
+                }.with_uniform_rays(read_u64(&mut reader)? as usize);
+                let occluders = revisions
+                    .get(&revision)
+                    .ok_or_else(|| invalid(format!("the occluders of revision {revision} were not recorded")))?;
+
+                queries.push(ReplayedQuery {
+                    origin,
+                    points: calculate_intersection_points(origin, occluders, &config),
+                    config,
+                    revision,
+                })
+            }
+            tag => return Err(invalid(format!("unknown record {tag}")))
+        }
+    }
+}
+
+/// Read the rest of the header after the first byte of the magic and return the version, if it can be read.
+fn read_header(reader: &mut impl Read) -> Result<u32, RaycastError> {
+    let mut magic = [0; 3];
+    reader.read_exact(&mut magic)?;
+
+    if magic != MAGIC[1..] {
+        return Err(invalid("the recording does not start with the magic bytes".to_string()));
+    }
+
+    match read_u32(reader)? {
+        version if version > FORMAT_VERSION => Err(invalid(format!("version {version} is newer than the supported version {FORMAT_VERSION}"))),
+        version => Ok(version)
+    }
+}
+
+fn read_occluder(reader: &mut impl Read) -> Result<Occluder, RaycastError> {
+    let segment = Segment::from_coords(read_f32(reader)?, read_f32(reader)?, read_f32(reader)?, read_f32(reader)?);
+    let opacity = read_f32(reader)?;
+    let tint = Vec3::new(read_f32(reader)?, read_f32(reader)?, read_f32(reader)?);
+    let layers = read_u32(reader)?;
+    let kind = match read_u8(reader)? {
+        0 => OccluderKind::Open,
+        1 => OccluderKind::ClosedPolygon,
+        kind => return Err(invalid(format!("unknown occluder kind {kind}")))
+    };
+    let height = match read_u8(reader)? {
+        0 => OccluderHeight::Full,
+        1 => OccluderHeight::Low,
+        height => return Err(invalid(format!("unknown occluder height {height}")))
+    };
+    let (z_min, z_max) = (read_f32(reader)?, read_f32(reader)?);
+    let material = match read_u8(reader)? {
+        0 => Material::Opaque,
+        1 => Material::Mirror,
+        2 => Material::Refractive(read_f32(reader)?),
+        material => return Err(invalid(format!("unknown material {material}")))
+    };
+    let occluder = Occluder::new(segment)
+        .with_opacity(opacity)
+        .with_tint(tint)
+        .with_layers(layers)
+        .with_kind(kind)
+        .with_height(height)
+        .with_z_range(z_min, z_max)
+        .with_material(material);
+
+    Ok(match read_u8(reader)? {
+        NO_GROUP => occluder,
+        group => occluder.with_group(GroupId(group))
+    })
+}
+
+fn read_format(reader: &mut impl Read) -> Result<PolygonFormat, RaycastError> {
+    let format = PolygonFormat::new(
+        match read_u8(reader)? {
+            0 => Winding::CounterClockwise,
+            1 => Winding::Clockwise,
+            winding => return Err(invalid(format!("unknown winding {winding}")))
+        },
+        read_u8(reader)? != 0,
+    );
+
+    match read_u8(reader)? {
+        0 => Ok(format),
+        1 => Ok(format.with_resolution(read_u64(reader)? as usize)),
+        resolution => Err(invalid(format!("unknown resolution {resolution}")))
+    }
+}
+
+fn invalid(reason: String) -> RaycastError {
+    RaycastError::InvalidRecording { reason }
+}
+
+fn read_u8(reader: &mut impl Read) -> Result<u8, RaycastError> {
+    let mut bytes = [0; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32, RaycastError> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64, RaycastError> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f32(reader: &mut impl Read) -> Result<f32, RaycastError> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use crate::config::{Algorithm, CoordinateSystem, MissPolicy, PeekPolicy, PolygonFormat, RaycastConfig, SelfHitPolicy, SymmetryPolicy, Winding};
+    use crate::error::RaycastError;
+    use crate::occluder::OccluderId;
+    use crate::pathological::pathological_scenes;
+    use crate::raycasting::calculate_intersection_points;
+    use crate::replay::{FORMAT_VERSION, QUERY_TAG, QueryRecorder, replay};
+
+    #[test]
+    fn replay_reproduces_recorded_queries() {
+        let scenes = pathological_scenes();
+        let mut recorder = QueryRecorder::new(Vec::new()).unwrap();
+        let mut expected = Vec::new();
+
+        for (revision, scene) in scenes.iter().enumerate() {
+            recorder.record_occluders(revision as u64, &scene.occluders).unwrap();
+
+            for config in [
+                RaycastConfig::default(),
+                RaycastConfig::layers(3).with_self_hit_policy(SelfHitPolicy::IgnoreNear(0.5)),
+                RaycastConfig::default().without_groups(4).with_self_hit_policy(SelfHitPolicy::ExcludeOccluder(OccluderId::new(1))),
+                RaycastConfig::default().with_self_hit_policy(SelfHitPolicy::ExcludeOccluder(OccluderId { index: 1, generation: 3 })),
+                RaycastConfig::default().with_algorithm(Algorithm::Sweep),
+                RaycastConfig::default().with_peek_policy(PeekPolicy::Peek),
+                RaycastConfig::default().with_miss_policy(MissPolicy::MaxDistance(500.0)),
+                RaycastConfig::default().with_coordinate_system(CoordinateSystem::YDown),
+                RaycastConfig::default().with_format(PolygonFormat::new(Winding::Clockwise, true)),
+                RaycastConfig::default().with_format(PolygonFormat::default().with_resolution(32)),
+                RaycastConfig::default().with_symmetry_policy(SymmetryPolicy::Symmetric),
+                RaycastConfig::default().with_height(2.0),
+                RaycastConfig::default().with_max_rays(4),
+                RaycastConfig::default().with_max_rays(0).with_uniform_rays(16),
+            ] {
+                let origin = scene.origin + Vec2::new(0.5, 0.25);
+                recorder.record_query(origin, &config, revision as u64).unwrap();
+                expected.push((origin, config.clone(), calculate_intersection_points(origin, &scene.occluders, &config)));
+            }
+        }
+
+        let recording = recorder.into_inner();
+        let replayed = replay(recording.as_slice()).unwrap();
+
+        assert_eq!(replayed.len(), expected.len());
+        replayed.into_iter().zip(expected).for_each(|(query, (origin, config, points))| {
+            assert_eq!(query.origin, origin);
+            assert_eq!(query.config, config);
+            assert_eq!(query.points, points);
+        });
+    }
+
+    #[test]
+    fn replay_rejects_queries_of_unknown_revisions() {
+        let mut recorder = QueryRecorder::new(Vec::new()).unwrap();
+        recorder.record_query(Vec2::ZERO, &RaycastConfig::default(), 7).unwrap();
+
+        assert!(matches!(replay(recorder.into_inner().as_slice()), Err(RaycastError::InvalidRecording { .. })));
+        assert!(matches!(replay([QUERY_TAG].as_slice()), Err(RaycastError::Io(_))));
+    }
+    #[test]
+    fn replay_reads_every_version_up_to_the_current_one() {
+        let scene = &pathological_scenes()[0];
+        let mut recorder = QueryRecorder::new(Vec::new()).unwrap();
+        recorder.record_occluders(0, &scene.occluders).unwrap();
+        recorder.record_query(scene.origin, &RaycastConfig::default(), 0).unwrap();
+        let recording = recorder.into_inner();
+        let replayed = replay(recording.as_slice()).unwrap();
+
+        assert_eq!(replayed.len(), 1);
+        // version 0 has no header
+        assert_eq!(replay(&recording[8..]).unwrap(), replayed);
+        assert!(replay([].as_slice()).unwrap().is_empty());
+
+        let mut newer = recording.clone();
+        newer[4..8].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert!(matches!(replay(newer.as_slice()), Err(RaycastError::InvalidRecording { .. })));
+
+        let mut foreign = recording;
+        foreign[1] = b'X';
+        assert!(matches!(replay(foreign.as_slice()), Err(RaycastError::InvalidRecording { .. })));
+    }
+}
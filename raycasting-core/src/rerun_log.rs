@@ -0,0 +1,90 @@
+use std::error::Error;
+use glam::*;
+use rerun::{Color, LineStrips2D, Points2D, RecordingStream};
+use crate::config::RaycastConfig;
+use crate::occluder::Occluder;
+use crate::raycasting::calculate_intersection_points;
+
+/// Logs the occluders, the cast rays and the visibility polygon of every query to a rerun.io recording,
+/// one step of the "query" timeline per query, so a session can be scrubbed through query by query.
+///
+/// Create the stream with rerun, like `rerun::RecordingStreamBuilder::new("raycasting").save("queries.rrd")`.
+pub struct RerunLogger {
+    stream: RecordingStream,
+    query: i64,
+}
+
+impl RerunLogger {
+    pub fn new(stream: RecordingStream) -> Self {
+        RerunLogger { stream, query: 0 }
+    }
+
+    /// Compute the visibility polygon of the origin (like [calculate_intersection_points]) and log the query.
+    pub fn intersection_points(
+        &mut self,
+        origin: Vec2,
+        occluders: &[Occluder],
+        config: &RaycastConfig,
+    ) -> Result<Vec<Vec2>, Box<dyn Error>> {
+        let points = calculate_intersection_points(origin, occluders, config);
+        self.log_query(origin, occluders, &points)?;
+        Ok(points)
+    }
+
+    /// Log a query from the origin, with the points of its visibility polygon ordered by angle, and move on to the next step.
+    /// Every point of the polygon was hit by a ray from the origin, so the rays are logged as lines to them.
+    pub fn log_query(
+        &mut self,
+        origin: Vec2,
+        occluders: &[Occluder],
+        points: &[Vec2],
+    ) -> Result<(), Box<dyn Error>> {
+        let strip = |points: &[Vec2]| points.iter().map(|point| [point.x, point.y]).collect::<Vec<_>>();
+
+        self.stream.set_time_sequence("query", self.query);
+
+        self.stream.log(
+            "query/occluders",
+            &LineStrips2D::new(occluders.iter().map(|occluder| strip(&occluder.segment.points()))).with_colors([Color::from_rgb(200, 200, 200)]),
+        )?;
+
+        self.stream.log(
+            "query/rays",
+            &LineStrips2D::new(points.iter().map(|point| strip(&[origin, *point]))).with_colors([Color::from_rgb(255, 200, 0)]),
+        )?;
+
+        self.stream.log(
+            "query/polygon",
+            &LineStrips2D::new([strip(&points.iter().chain(points.first()).copied().collect::<Vec<_>>())]).with_colors([Color::from_rgb(0, 200, 255)]),
+        )?;
+
+        self.stream.log(
+            "query/origin",
+            &Points2D::new([[origin.x, origin.y]]).with_radii([2.0]),
+        )?;
+
+        self.query += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rerun::RecordingStream;
+    use crate::config::RaycastConfig;
+    use crate::pathological::pathological_scenes;
+    use crate::raycasting::calculate_intersection_points;
+    use crate::rerun_log::RerunLogger;
+
+    #[test]
+    fn logged_queries_return_the_polygon() {
+        let mut logger = RerunLogger::new(RecordingStream::disabled());
+
+        for scene in pathological_scenes() {
+            let points = logger.intersection_points(scene.origin, &scene.occluders, &RaycastConfig::default()).unwrap();
+            assert_eq!(points, calculate_intersection_points(scene.origin, &scene.occluders, &RaycastConfig::default()), "{}", scene.name);
+        }
+
+        assert_eq!(logger.query, pathological_scenes().len() as i64);
+    }
+}
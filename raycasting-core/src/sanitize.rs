@@ -0,0 +1,551 @@
+use std::collections::HashMap;
+use glam::*;
+use crate::occluder::{Occluder, OccluderKind};
+use crate::predicates::cross_sign;
+use crate::raycasting::{intersection_parameter, Segment};
+
+/// What [sanitize] fixed.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct SanitizeReport {
+    /// Occluders removed because they had NaN or infinite coordinates or no length, also after welding
+    pub degenerate_removed: usize,
+    /// Endpoints moved onto a nearby endpoint
+    pub endpoints_welded: usize,
+    /// Points where two occluders crossed each other and were split
+    pub crossings_split: usize,
+    /// Points where the endpoint of an occluder lay inside another one, which was split there
+    pub t_junctions_split: usize,
+    /// Occluders removed because another one was the same
+    pub duplicates_removed: usize,
+}
+
+impl SanitizeReport {
+    /// Return true if nothing had to be fixed.
+    pub fn is_clean(&self) -> bool {
+        *self == SanitizeReport::default()
+    }
+}
+
+/// Clean up messy level data, like overlapping tiles or crossing walls, and report what was fixed:
+///
+/// 1. Occluders with NaN or infinite coordinates or without length are removed.
+/// 2. Endpoints closer than the tolerance to an earlier endpoint are moved onto it, so corners which almost
+///    meet do meet. Occluders which lose their length this way are removed as well.
+/// 3. Occluders crossing each other are split at the crossing, and occluders with the endpoint of another one
+///    inside of them (closer than the tolerance) are split at that endpoint, so occluders only meet at their endpoints.
+/// 4. Occluders which are the same as an earlier one are removed. Open occluders are the same in both directions,
+///    edges of closed polygons only in the same direction, as their direction decides which side can see them.
+///
+/// The order of the remaining occluders is kept, with the pieces of a split occluder in its place.
+pub fn sanitize(
+    occluders: &[Occluder],
+    tolerance: f32,
+) -> (Vec<Occluder>, SanitizeReport) {
+    let mut report = SanitizeReport::default();
+
+    let occluders = remove_degenerate(occluders.to_vec(), &mut report);
+    let occluders = weld_endpoints(occluders, tolerance, &mut report);
+    let occluders = remove_degenerate(occluders, &mut report);
+    let occluders = split_crossings(occluders, &mut report);
+    let occluders = split_occluders_at_t_junctions(occluders, tolerance, &mut report);
+    let occluders = remove_duplicates(occluders, &mut report);
+
+    (occluders, report)
+}
+
+fn remove_degenerate(
+    mut occluders: Vec<Occluder>,
+    report: &mut SanitizeReport,
+) -> Vec<Occluder> {
+    let count = occluders.len();
+    occluders.retain(|occluder| occluder.segment.validate().is_ok());
+    report.degenerate_removed += count - occluders.len();
+    occluders
+}
+
+fn weld_endpoints(
+    occluders: Vec<Occluder>,
+    tolerance: f32,
+    report: &mut SanitizeReport,
+) -> Vec<Occluder> {
+    let mut anchors: Vec<Vec2> = Vec::new();
+    let mut weld = |point: Vec2| match anchors.iter().find(|anchor| anchor.distance(point) <= tolerance) {
+        Some(anchor) => {
+            if *anchor != point {
+                report.endpoints_welded += 1;
+            }
+            *anchor
+        }
+        None => {
+            anchors.push(point);
+            point
+        }
+    };
+
+    occluders
+        .into_iter()
+        .map(|occluder| {
+            let [a, b] = occluder.segment.points();
+            Occluder { segment: Segment::new(weld(a), weld(b)), ..occluder }
+        })
+        .collect()
+}
+
+/// Split segments crossing each other at their crossing points, so segments only meet at their endpoints.
+/// Segments only touching each other, at an endpoint or along a shared line, are kept as they are.
+///
+/// The sweep assumes that occluders only meet at their endpoints, so it can leak light through the crossing of
+/// an X-shaped wall. Planarizing the walls first fixes this. The pieces are returned in the order of the segments
+/// they come from, each from the start to the end of its segment, and both pieces of a crossing share the exact
+/// same point.
+pub fn planarize(segments: &[Segment]) -> Vec<Segment> {
+    split_at_crossings(segments)
+        .0
+        .into_iter()
+        .flat_map(|points| pieces(&points).collect::<Vec<_>>())
+        .collect()
+}
+
+fn split_crossings(
+    occluders: Vec<Occluder>,
+    report: &mut SanitizeReport,
+) -> Vec<Occluder> {
+    let segments = occluders.iter().map(|occluder| occluder.segment).collect::<Vec<_>>();
+    let (points, crossings) = split_at_crossings(&segments);
+    report.crossings_split += crossings;
+
+    occluders
+        .into_iter()
+        .zip(points)
+        .flat_map(|(occluder, points)| pieces(&points).map(|segment| Occluder { segment, ..occluder }).collect::<Vec<_>>())
+        .collect()
+}
+
+/// Return the points every segment is split at, including its endpoints and ordered from its start to its end,
+/// and the number of crossings.
+fn split_at_crossings(segments: &[Segment]) -> (Vec<Vec<Vec2>>, usize) {
+    let mut splits = vec![Vec::new(); segments.len()];
+    let mut crossings = 0;
+
+    for i in 0..segments.len() {
+        for j in (i + 1)..segments.len() {
+            let [a, b] = segments[i].points();
+            let [c, d] = segments[j].points();
+            let crossing = cross_sign(a, b, a, c) * cross_sign(a, b, a, d) < 0 && cross_sign(c, d, c, a) * cross_sign(c, d, c, b) < 0;
+
+            if crossing {
+                let t = intersection_parameter(a, b - a, c, d - c);
+                let point = a + (b - a) * t;
+                splits[i].push((t, point));
+                splits[j].push(((point - c).dot(d - c) / (d - c).length_squared(), point));
+                crossings += 1;
+            }
+        }
+    }
+
+    let points = segments
+        .iter()
+        .zip(splits)
+        .map(|(segment, splits)| split_points(*segment, splits))
+        .collect();
+
+    (points, crossings)
+}
+
+/// Split segments at the endpoints of other segments which lie inside of them, closer than the tolerance,
+/// like where a short wall meets a long one in its middle after merging the edges of tiles.
+///
+/// The sweep only stops at endpoints, so it misses the corners of such T-junctions, unless the long segment
+/// is split there. The pieces meet exactly at the endpoint of the other segment, even if it was a bit off the
+/// long segment. The pieces are returned in the order of the segments they come from, each from the start
+/// to the end of its segment.
+pub fn split_t_junctions(
+    segments: &[Segment],
+    tolerance: f32,
+) -> Vec<Segment> {
+    split_at_t_junctions(segments, tolerance)
+        .0
+        .into_iter()
+        .flat_map(|points| pieces(&points).collect::<Vec<_>>())
+        .collect()
+}
+
+fn split_occluders_at_t_junctions(
+    occluders: Vec<Occluder>,
+    tolerance: f32,
+    report: &mut SanitizeReport,
+) -> Vec<Occluder> {
+    let segments = occluders.iter().map(|occluder| occluder.segment).collect::<Vec<_>>();
+    let (points, junctions) = split_at_t_junctions(&segments, tolerance);
+    report.t_junctions_split += junctions;
+
+    occluders
+        .into_iter()
+        .zip(points)
+        .flat_map(|(occluder, points)| pieces(&points).map(|segment| Occluder { segment, ..occluder }).collect::<Vec<_>>())
+        .collect()
+}
+
+/// Return the points every segment is split at, like [split_at_crossings], and the number of splits.
+fn split_at_t_junctions(
+    segments: &[Segment],
+    tolerance: f32,
+) -> (Vec<Vec<Vec2>>, usize) {
+    let endpoints = segments.iter().flat_map(Segment::points).collect::<Vec<_>>();
+    let mut junctions = 0;
+
+    let points = segments
+        .iter()
+        .map(|segment| {
+            let [a, b] = segment.points();
+            let mut splits = endpoints
+                .iter()
+                .filter(|point| point.distance(a) > tolerance && point.distance(b) > tolerance)
+                .filter(|point| segment.closest_point(**point).distance(**point) <= tolerance)
+                .map(|point| ((*point - a).dot(b - a) / (b - a).length_squared(), *point))
+                .collect::<Vec<_>>();
+            splits.sort_by(|s, t| s.0.total_cmp(&t.0));
+            splits.dedup_by(|s, t| s.1 == t.1);
+            junctions += splits.len();
+
+            split_points(*segment, splits)
+        })
+        .collect();
+
+    (points, junctions)
+}
+
+/// Return the start of the segment, the given split points ordered by their parameter along it, and its end.
+fn split_points(
+    segment: Segment,
+    mut splits: Vec<(f32, Vec2)>,
+) -> Vec<Vec2> {
+    let [a, b] = segment.points();
+    splits.sort_by(|s, t| s.0.total_cmp(&t.0));
+    [a].into_iter().chain(splits.into_iter().map(|(_, point)| point)).chain([b]).collect()
+}
+
+fn pieces(points: &[Vec2]) -> impl Iterator<Item=Segment> + '_ {
+    points
+        .windows(2)
+        .filter(|pair| pair[0] != pair[1])
+        .map(|pair| Segment::new(pair[0], pair[1]))
+}
+
+fn remove_duplicates(
+    occluders: Vec<Occluder>,
+    report: &mut SanitizeReport,
+) -> Vec<Occluder> {
+    let mut seen = HashMap::<Segment, Vec<Occluder>>::new();
+    let count = occluders.len();
+    let key = |occluder: &Occluder| {
+        let [a, b] = occluder.segment.points();
+
+        match occluder.kind {
+            OccluderKind::Open if (b.x, b.y) < (a.x, a.y) => Occluder { segment: occluder.segment.reversed(), ..*occluder },
+            _ => *occluder
+        }
+    };
+
+    let unique = occluders
+        .into_iter()
+        .filter(|occluder| {
+            let key = key(occluder);
+            let same_place = seen.entry(key.segment).or_default();
+
+            if same_place.contains(&key) {
+                return false;
+            }
+
+            same_place.push(key);
+            true
+        })
+        .collect::<Vec<_>>();
+
+    report.duplicates_removed += count - unique.len();
+    unique
+}
+
+/// Merge chains of occluders into as few occluders as possible and drop tiny ones, like for the thousands of short,
+/// collinear segments of auto-traced level art, which slow down queries without changing their result.
+///
+/// Occluders are chained where exactly two of them meet at an endpoint and they differ in nothing but their segment.
+/// Edges of closed polygons are only chained in their direction. Every chain is simplified with Douglas-Peucker,
+/// so the simplified chain strays no further than the tolerance from the original one: collinear pieces are merged
+/// and zigzags smaller than the tolerance are straightened. Chains which touch nothing else and are no longer than
+/// the tolerance are dropped, as well as degenerate occluders.
+///
+/// Only endpoints which are exactly the same are chained, so [sanitize] messy data first. Endpoints where three or
+/// more occluders meet are kept, so no T-junctions are created.
+pub fn simplify_occluders(
+    occluders: &[Occluder],
+    tolerance: f32,
+) -> Vec<Occluder> {
+    let occluders = occluders.iter().filter(|occluder| occluder.segment.validate().is_ok()).copied().collect::<Vec<_>>();
+    let key = |point: Vec2| (point.x.to_bits(), point.y.to_bits());
+    let mut incident = HashMap::<(u32, u32), Vec<usize>>::new();
+
+    for (i, occluder) in occluders.iter().enumerate() {
+        occluder.segment.points().into_iter().for_each(|point| incident.entry(key(point)).or_default().push(i));
+    }
+
+    let other_end = |i: usize, point: Vec2| {
+        let [a, b] = occluders[i].segment.points();
+        if a == point { b } else { a }
+    };
+    // the occluder continuing the chain of occluder i through its endpoint
+    let next = |i: usize, point: Vec2| {
+        let j = match incident[&key(point)][..] {
+            [j, k] if j == i => k,
+            [j, k] if k == i => j,
+            _ => return None
+        };
+        let (current, other) = (occluders[i], occluders[j]);
+        let directed = current.kind == OccluderKind::ClosedPolygon
+            && !(current.segment.b() == point && other.segment.a() == point)
+            && !(current.segment.a() == point && other.segment.b() == point);
+
+        (j != i && Occluder { segment: other.segment, ..current } == other && !directed).then_some(j)
+    };
+
+    let mut visited = vec![false; occluders.len()];
+    let mut simplified = Vec::new();
+
+    for start in 0..occluders.len() {
+        if visited[start] {
+            continue;
+        }
+
+        // walk back to the start of the chain. A loop starts where it was found
+        let (mut first, mut point) = (start, occluders[start].segment.a());
+        while let Some(previous) = next(first, point) {
+            if previous == start {
+                (first, point) = (start, occluders[start].segment.a());
+                break;
+            }
+
+            point = other_end(previous, point);
+            first = previous;
+        }
+
+        let mut chain = vec![point];
+        let mut current = first;
+        visited[first] = true;
+        chain.push(other_end(first, point));
+
+        while let Some(following) = next(current, chain[chain.len() - 1]).filter(|following| !visited[*following]) {
+            visited[following] = true;
+            chain.push(other_end(following, chain[chain.len() - 1]));
+            current = following;
+        }
+
+        let free = |point: Vec2| incident[&key(point)].len() == 1;
+        let length = chain.windows(2).map(|pair| pair[0].distance(pair[1])).sum::<f32>();
+
+        if free(chain[0]) && free(chain[chain.len() - 1]) && length <= tolerance {
+            continue;
+        }
+
+        simplified.extend(pieces(&simplify_chain(&chain, tolerance)).map(|segment| Occluder { segment, ..occluders[first] }));
+    }
+
+    simplified
+}
+
+/// Simplify a polyline with Douglas-Peucker. A closed polyline, which ends where it starts, stays closed, and its
+/// start is dropped as well if it lies on the line between its neighbours.
+fn simplify_chain(
+    points: &[Vec2],
+    tolerance: f32,
+) -> Vec<Vec2> {
+    let mut kept = douglas_peucker(points, tolerance);
+    let closed = points[0] == points[points.len() - 1];
+
+    if closed && kept.len() > 4 && Segment::new(kept[kept.len() - 2], kept[1]).closest_point(kept[0]).distance(kept[0]) <= tolerance {
+        kept.pop();
+        kept.remove(0);
+        kept.push(kept[0]);
+    }
+
+    kept
+}
+
+fn douglas_peucker(
+    points: &[Vec2],
+    tolerance: f32,
+) -> Vec<Vec2> {
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let chord = Segment::new(first, last);
+    let farthest = (1..points.len() - 1)
+        .map(|i| (i, chord.closest_point(points[i]).distance(points[i])))
+        .max_by(|s, t| s.1.total_cmp(&t.1));
+
+    match farthest {
+        Some((i, distance)) if distance > tolerance => {
+            let mut kept = douglas_peucker(&points[..=i], tolerance);
+            kept.pop();
+            kept.extend(douglas_peucker(&points[i..], tolerance));
+            kept
+        }
+        _ => vec![first, last]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use crate::occluder::{Occluder, OccluderKind};
+    use crate::raycasting::Segment;
+    use crate::sanitize::{planarize, sanitize, SanitizeReport, simplify_occluders, split_t_junctions};
+
+    fn points(occluders: &[Occluder]) -> Vec<[Vec2; 2]> {
+        occluders.iter().map(|occluder| occluder.segment.points()).collect()
+    }
+
+    #[test]
+    fn sanitize_fixes_messy_occluders() {
+        let occluders = [
+            // an X, split into four pieces
+            Segment::from_coords(-10.0, -10.0, 10.0, 10.0),
+            Segment::from_coords(-10.0, 10.0, 10.0, -10.0),
+            // a corner which almost meets, welded onto the end of the X
+            Segment::from_coords(10.001, 10.0, 20.0, 10.0),
+            // the same wall in the other direction
+            Segment::from_coords(20.0, 10.0, 10.0, 10.0),
+            Segment::from_coords(5.0, f32::NAN, 5.0, 20.0),
+            Segment::from_coords(30.0, 0.0, 30.0, 0.001),
+        ].into_iter().map(Occluder::new).collect::<Vec<_>>();
+
+        let (sanitized, report) = sanitize(&occluders, 0.01);
+
+        assert_eq!(report, SanitizeReport {
+            degenerate_removed: 2,
+            endpoints_welded: 2,
+            crossings_split: 1,
+            t_junctions_split: 0,
+            duplicates_removed: 1,
+        });
+        assert_eq!(points(&sanitized), vec![
+            [Vec2::new(-10.0, -10.0), Vec2::ZERO],
+            [Vec2::ZERO, Vec2::new(10.0, 10.0)],
+            [Vec2::new(-10.0, 10.0), Vec2::ZERO],
+            [Vec2::ZERO, Vec2::new(10.0, -10.0)],
+            [Vec2::new(10.0, 10.0), Vec2::new(20.0, 10.0)],
+        ]);
+    }
+
+    #[test]
+    fn sanitize_keeps_clean_and_distinct_occluders() {
+        let wall = Segment::from_coords(0.0, 0.0, 10.0, 0.0);
+        let occluders = vec![
+            Occluder::new(wall),
+            // on another layer, so it is not the same
+            Occluder::new(wall).with_layers(2),
+            // edges of closed polygons only see one side
+            Occluder::new(wall).with_kind(OccluderKind::ClosedPolygon),
+            Occluder::new(wall.reversed()).with_kind(OccluderKind::ClosedPolygon),
+            // touching at an endpoint is no crossing
+            Occluder::new(Segment::from_coords(10.0, 0.0, 5.0, 10.0)),
+        ];
+
+        let (sanitized, report) = sanitize(&occluders, 0.01);
+
+        assert!(report.is_clean(), "{report:?}");
+        assert_eq!(sanitized, occluders);
+    }
+
+    #[test]
+    fn planarize_splits_segments_only_at_crossings() {
+        let segments = [
+            Segment::from_coords(-10.0, -10.0, 10.0, 10.0),
+            Segment::from_coords(-10.0, 10.0, 10.0, -10.0),
+            Segment::from_coords(-10.0, 5.0, 10.0, 5.0),
+            // touches the first one at its end
+            Segment::from_coords(10.0, 10.0, 20.0, 0.0),
+        ];
+
+        let planar = planarize(&segments);
+
+        assert_eq!(planar.len(), 10);
+        assert_eq!(planar[..3], [
+            Segment::new(Vec2::new(-10.0, -10.0), Vec2::ZERO),
+            Segment::new(Vec2::ZERO, Vec2::new(5.0, 5.0)),
+            Segment::new(Vec2::new(5.0, 5.0), Vec2::new(10.0, 10.0)),
+        ]);
+        assert_eq!(planar[9], segments[3]);
+        assert!(planar.iter().all(|segment| planar.iter().all(|other| {
+            let [a, b] = segment.points();
+            let [c, d] = other.points();
+            !segment.intersects(*other) || [a, b].contains(&c) || [a, b].contains(&d)
+        })));
+        assert_eq!(planarize(&planar), planar);
+    }
+
+    #[test]
+    fn t_junctions_are_split() {
+        let segments = [
+            Segment::from_coords(-50.0, 20.0, 50.0, 20.0),
+            Segment::from_coords(0.0, 20.0, 0.0, -10.0),
+            // a bit off the long wall, but within the tolerance
+            Segment::from_coords(30.0, 19.995, 30.0, 0.0),
+            // too far off
+            Segment::from_coords(-30.0, 19.9, -30.0, 0.0),
+        ];
+
+        let split = split_t_junctions(&segments, 0.01);
+
+        assert_eq!(split, vec![
+            Segment::from_coords(-50.0, 20.0, 0.0, 20.0),
+            Segment::from_coords(0.0, 20.0, 30.0, 19.995),
+            Segment::from_coords(30.0, 19.995, 50.0, 20.0),
+            segments[1],
+            segments[2],
+            segments[3],
+        ]);
+        assert_eq!(split_t_junctions(&split, 0.01), split);
+
+        let occluders = segments.into_iter().map(Occluder::new).collect::<Vec<_>>();
+        let (sanitized, report) = sanitize(&occluders, 0.01);
+        assert_eq!(report.t_junctions_split, 2);
+        assert_eq!(sanitized.iter().map(|occluder| occluder.segment).collect::<Vec<_>>(), split);
+    }
+
+    #[test]
+    fn chains_are_simplified() {
+        // a wall traced in many collinear pieces, with a tiny bump, ending in a corner
+        let traced = (0..=10)
+            .map(|x| Vec2::new(x as f32, if x == 5 { 0.001 } else { 0.0 }))
+            .chain([Vec2::new(10.0, 10.0)])
+            .collect::<Vec<_>>();
+        let traced = traced.windows(2).map(|pair| Occluder::new(Segment::new(pair[0], pair[1])));
+        // a square traced as a closed polygon, with a collinear point where the loop starts
+        let square = Occluder::polygon(&[
+            Vec2::new(20.0, 5.0),
+            Vec2::new(20.0, 0.0),
+            Vec2::new(30.0, 0.0),
+            Vec2::new(30.0, 10.0),
+            Vec2::new(20.0, 10.0),
+        ]);
+        // a speck of dust, and a wall on another layer which is not merged with the first one
+        let others = [
+            Occluder::new(Segment::from_coords(50.0, 50.0, 50.0, 50.005)),
+            Occluder::new(Segment::from_coords(10.0, 10.0, 0.0, 10.0)).with_layers(2),
+        ];
+        let occluders = traced.chain(square).chain(others).collect::<Vec<_>>();
+
+        let simplified = simplify_occluders(&occluders, 0.01);
+
+        assert_eq!(points(&simplified), vec![
+            [Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0)],
+            [Vec2::new(10.0, 0.0), Vec2::new(10.0, 10.0)],
+            [Vec2::new(20.0, 0.0), Vec2::new(30.0, 0.0)],
+            [Vec2::new(30.0, 0.0), Vec2::new(30.0, 10.0)],
+            [Vec2::new(30.0, 10.0), Vec2::new(20.0, 10.0)],
+            [Vec2::new(20.0, 10.0), Vec2::new(20.0, 0.0)],
+            [Vec2::new(10.0, 10.0), Vec2::new(0.0, 10.0)],
+        ]);
+        assert!(simplified[2..6].iter().all(|occluder| occluder.kind == OccluderKind::ClosedPolygon));
+        assert_eq!(simplified[6].layers, 2);
+        assert_eq!(simplify_occluders(&simplified, 0.01), simplified);
+    }
+}
@@ -0,0 +1,168 @@
+use thiserror::Error;
+use crate::occluder::Occluder;
+use crate::raycasting::Segment;
+use crate::tiles::{tile_edges, TileGrid};
+
+/// A tile based scene, parsed from a plain text file.
+///
+/// Every non-empty line of the file is a row of tiles, where '#' is a wall and '.' is a floor.
+/// The first line is the top row of the scene, so the tile at (0, 0) is the first character of the last line.
+///
+/// ```text
+/// #####
+/// #...#
+/// #.#.#
+/// #...#
+/// #####
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scene {
+    width: usize,
+    height: usize,
+    walls: Vec<bool>,
+}
+
+impl Scene {
+    pub fn parse(source: &str) -> Result<Self, SceneError> {
+        let rows = source
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+
+        let width = rows.first().ok_or(SceneError::Empty)?.chars().count();
+        let height = rows.len();
+        let mut walls = vec![false; width * height];
+
+        for (row, line) in rows.iter().enumerate() {
+            if line.chars().count() != width {
+                return Err(SceneError::UnevenRow { row });
+            }
+
+            let y = height - 1 - row;
+
+            for (x, character) in line.chars().enumerate() {
+                walls[y * width + x] = match character {
+                    '#' => true,
+                    '.' => false,
+                    _ => return Err(SceneError::UnknownTile { row, column: x, character })
+                }
+            }
+        }
+
+        Ok(Scene {
+            width,
+            height,
+            walls,
+        })
+    }
+
+    /// Return a scene of the given size, with walls where the function returns true for the tile at (x, y).
+    pub fn from_fn(
+        width: usize,
+        height: usize,
+        is_wall: impl Fn(usize, usize) -> bool,
+    ) -> Self {
+        Scene {
+            width,
+            height,
+            walls: (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).map(|(x, y)| is_wall(x, y)).collect(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Return true if the tile at the given position is a wall. Positions outside the scene are no walls.
+    pub fn is_wall(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.walls[y * self.width + x]
+    }
+
+    /// Return the occluders of this scene: the edges of every wall and the bounds of the scene.
+    /// The tile at (x, y) covers the square from (x, y) * tile_size to (x + 1, y + 1) * tile_size.
+    pub fn occluders(&self, tile_size: f32) -> Vec<Occluder> {
+        let mut segments = Vec::new();
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if !self.is_wall(x, y) {
+                    continue;
+                }
+
+                segments.extend(tile_edges(x as i32, y as i32, tile_size));
+            }
+        }
+
+        let (width, height) = (self.width as f32 * tile_size, self.height as f32 * tile_size);
+
+        segments.extend([
+            Segment::from_coords(0.0, 0.0, width, 0.0),
+            Segment::from_coords(width, 0.0, width, height),
+            Segment::from_coords(width, height, 0.0, height),
+            Segment::from_coords(0.0, height, 0.0, 0.0),
+        ]);
+
+        segments
+            .into_iter()
+            .map(Occluder::new)
+            .collect()
+    }
+}
+
+impl TileGrid for Scene {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn is_opaque(&self, x: usize, y: usize) -> bool {
+        self.is_wall(x, y)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, Error, PartialEq)]
+pub enum SceneError {
+    /// The source does not contain a single row
+    #[error("the scene is empty")]
+    Empty,
+    /// The row (counted from the top, starting at 0) has a different length than the first one
+    #[error("row {row} has a different length than the first row")]
+    UnevenRow { row: usize },
+    /// The character at the given row and column is neither a wall nor a floor
+    #[error("unknown tile '{character}' at row {row}, column {column}")]
+    UnknownTile { row: usize, column: usize, character: char },
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scene::{Scene, SceneError};
+
+    #[test]
+    fn parse_works() {
+        let scene = Scene::parse("###\n#..\n").unwrap();
+
+        assert_eq!(scene.width(), 3);
+        assert_eq!(scene.height(), 2);
+        assert!(scene.is_wall(0, 0));
+        assert!(!scene.is_wall(1, 0));
+        assert!(scene.is_wall(2, 1));
+        assert!(!scene.is_wall(3, 1));
+    }
+
+    #[test]
+    fn parse_rejects_invalid_scenes() {
+        [
+            ("", SceneError::Empty),
+            ("###\n##\n", SceneError::UnevenRow { row: 1 }),
+            ("#.#\n#x#\n", SceneError::UnknownTile { row: 1, column: 1, character: 'x' }),
+        ].into_iter().for_each(|(source, error)| assert_eq!(Scene::parse(source), Err(error)))
+    }
+}
@@ -0,0 +1,184 @@
+use crate::tiles::{TileGrid, TileVisibility};
+
+/// Return which tiles can be seen from the origin tile, computed with symmetric shadowcasting.
+///
+/// Unlike [crate::tiles::tile_visibility], this scans the grid row by row in each of the four quadrants and
+/// narrows the visible slopes at every opaque tile, so every tile is visited once. A floor tile is visible if
+/// the center of the origin can see its center, which makes the result symmetric between floor tiles: if a
+/// sees b, then b sees a. Opaque tiles are visible if any part of them can be seen, so the walls around a room
+/// are seen. Tiles outside the grid count as opaque.
+pub fn shadowcast(
+    origin_tile: (usize, usize),
+    grid: &impl TileGrid,
+) -> TileVisibility {
+    let mut visibility = TileVisibility::hidden(grid.width(), grid.height());
+    visibility.reveal(origin_tile.0, origin_tile.1);
+
+    for quadrant in [Quadrant::North, Quadrant::East, Quadrant::South, Quadrant::West] {
+        let mut rows = vec![Row { depth: 1, start: Slope::new(-1, 1), end: Slope::new(1, 1) }];
+
+        while let Some(mut row) = rows.pop() {
+            let mut previous_opaque = None;
+
+            for column in row.columns() {
+                let tile = quadrant.tile(origin_tile, row.depth, column);
+                let opaque = is_opaque(grid, tile);
+
+                if opaque || row.is_symmetric(column) {
+                    if let Some((x, y)) = tile {
+                        visibility.reveal(x, y)
+                    }
+                }
+
+                if previous_opaque == Some(true) && !opaque {
+                    row.start = Slope::of_tile(row.depth, column);
+                }
+
+                if previous_opaque == Some(false) && opaque {
+                    let mut next = row.next();
+                    next.end = Slope::of_tile(row.depth, column);
+                    rows.push(next);
+                }
+
+                previous_opaque = Some(opaque);
+            }
+
+            if previous_opaque == Some(false) {
+                rows.push(row.next());
+            }
+        }
+    }
+
+    visibility
+}
+
+fn is_opaque(grid: &impl TileGrid, tile: Option<(usize, usize)>) -> bool {
+    match tile {
+        Some((x, y)) => x >= grid.width() || y >= grid.height() || grid.is_opaque(x, y),
+        None => true
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Quadrant {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Quadrant {
+    /// Return the tile at the given depth and column of this quadrant, or None if it lies left of or below the grid.
+    fn tile(
+        &self,
+        origin: (usize, usize),
+        depth: i64,
+        column: i64,
+    ) -> Option<(usize, usize)> {
+        let (x, y) = (origin.0 as i64, origin.1 as i64);
+        let (x, y) = match self {
+            Quadrant::North => (x + column, y + depth),
+            Quadrant::East => (x + depth, y + column),
+            Quadrant::South => (x + column, y - depth),
+            Quadrant::West => (x - depth, y + column)
+        };
+
+        (x >= 0 && y >= 0).then_some((x as usize, y as usize))
+    }
+}
+
+/// An exact slope as a fraction, so the rounding at tile borders does not depend on floating point errors.
+#[derive(Copy, Clone)]
+struct Slope {
+    numerator: i64,
+    denominator: i64,
+}
+
+impl Slope {
+    fn new(numerator: i64, denominator: i64) -> Self {
+        Slope { numerator, denominator }
+    }
+
+    /// The slope of the line from the origin to the clockwise corner of the tile.
+    fn of_tile(depth: i64, column: i64) -> Self {
+        Slope::new(2 * column - 1, 2 * depth)
+    }
+}
+
+/// All tiles at one depth of a quadrant which lie between two slopes.
+struct Row {
+    depth: i64,
+    start: Slope,
+    end: Slope,
+}
+
+impl Row {
+    /// Return the columns of the tiles whose centers lie between the slopes, rounding half tiles outwards.
+    fn columns(&self) -> std::ops::RangeInclusive<i64> {
+        let (start, end) = (self.start, self.end);
+        let min = (2 * self.depth * start.numerator + start.denominator).div_euclid(2 * start.denominator);
+        let max = -(end.denominator - 2 * self.depth * end.numerator).div_euclid(2 * end.denominator);
+        min..=max
+    }
+
+    /// Return true if the center of the tile in the given column lies between the slopes.
+    fn is_symmetric(&self, column: i64) -> bool {
+        column * self.start.denominator >= self.depth * self.start.numerator
+            && column * self.end.denominator <= self.depth * self.end.numerator
+    }
+
+    fn next(&self) -> Row {
+        Row {
+            depth: self.depth + 1,
+            start: self.start,
+            end: self.end,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use crate::scene::Scene;
+    use crate::shadowcasting::shadowcast;
+
+    #[test]
+    fn open_room_is_fully_visible() {
+        let scene = Scene::parse("#####\n#...#\n#...#\n#...#\n#####\n").unwrap();
+
+        for (x, y) in [(1, 1), (2, 2), (3, 1)] {
+            let visibility = shadowcast((x, y), &scene);
+            assert_eq!(visibility.visible_count(), 25, "({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn pillars_cast_shadows() {
+        let scene = Scene::parse("#######\n#.....#\n#..#..#\n#.....#\n#######\n").unwrap();
+        let visibility = shadowcast((1, 2), &scene);
+
+        assert!(visibility.is_visible(3, 2));
+        assert!(visibility.is_visible(2, 1));
+        assert!(!visibility.is_visible(4, 2));
+        assert!(!visibility.is_visible(5, 2));
+        assert!(visibility.is_visible(5, 1));
+    }
+
+    #[test]
+    fn floor_tiles_see_each_other() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures").join("scenes").join("pillars.map");
+        let scene = Scene::parse(&fs::read_to_string(path).unwrap()).unwrap();
+        let floors = (0..scene.height())
+            .flat_map(|y| (0..scene.width()).map(move |x| (x, y)))
+            .filter(|(x, y)| !scene.is_wall(*x, *y))
+            .collect::<Vec<_>>();
+        let visibilities = floors.iter().map(|tile| shadowcast(*tile, &scene)).collect::<Vec<_>>();
+
+        for (a, visibility_a) in floors.iter().zip(&visibilities) {
+            for (b, visibility_b) in floors.iter().zip(&visibilities) {
+                assert_eq!(visibility_a.is_visible(b.0, b.1), visibility_b.is_visible(a.0, a.1), "{a:?} and {b:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use glam::*;
+use crate::aabb::Aabb;
+use crate::occluder::{Occluder, OccluderId};
+use crate::raycasting::Segment;
+
+/// A shape which can be swept through the occluders, centered on its position.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Shape {
+    Circle { radius: f32 },
+    Aabb { half_extents: Vec2 },
+}
+
+/// The first contact of a shape moving through the occluders.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ShapeHit {
+    /// Fraction of the movement after which the contact happens, in [0, 1]
+    pub time: f32,
+    /// Center of the shape at the time of contact
+    pub position: Vec2,
+    /// Point where the shape touches the occluder
+    pub point: Vec2,
+    /// Unit normal of the contact, pointing from the occluder towards the shape.
+    /// If the shape already overlaps the occluder at the start, this is the reversed movement direction.
+    pub normal: Vec2,
+    pub occluder: OccluderId,
+}
+
+/// A contact of a shape with a single segment
+struct Contact {
+    time: f32,
+    point: Vec2,
+    normal: Vec2,
+}
+
+/// Move the shape from origin by direction (the full movement, not a unit vector) and return the first contact
+/// with any of the occluders, if there is one. This answers questions like "can a unit with radius r move here".
+pub fn cast_shape(
+    shape: Shape,
+    origin: Vec2,
+    direction: Vec2,
+    occluders: &[Occluder],
+) -> Option<ShapeHit> {
+    occluders
+        .iter()
+        .enumerate()
+        .filter_map(|(index, occluder)| {
+            let contact = match shape {
+                Shape::Circle { radius } => cast_circle(radius, origin, direction, occluder.segment),
+                Shape::Aabb { half_extents } => cast_aabb(half_extents, origin, direction, occluder.segment)
+            }?;
+
+            Some(ShapeHit {
+                time: contact.time,
+                position: origin + direction * contact.time,
+                point: contact.point,
+                normal: contact.normal,
+                occluder: OccluderId::new(index),
+            })
+        })
+        .min_by(|hit_0, hit_1| hit_0.time.total_cmp(&hit_1.time))
+}
+
+/// A moving circle first touches a segment either with its side on the segment's interior,
+/// or somewhere on its boundary at one of the segment's endpoints.
+fn cast_circle(
+    radius: f32,
+    center: Vec2,
+    direction: Vec2,
+    segment: Segment,
+) -> Option<Contact> {
+    let [a, b] = segment.points();
+    let closest = segment.closest_point(center);
+
+    if center.distance(closest) < radius {
+        let normal = (center - closest).try_normalize().unwrap_or(-direction.normalize_or_zero());
+        return Some(Contact { time: 0.0, point: closest, normal });
+    }
+
+    let mut contacts = Vec::with_capacity(3);
+    let ab = b - a;
+
+    if let Some(mut normal) = ab.perp().try_normalize() {
+        // let the normal point from the segment towards the circle
+        let mut distance = (center - a).dot(normal);
+
+        if distance < 0.0 {
+            normal = -normal;
+            distance = -distance;
+        }
+
+        let approach = -direction.dot(normal);
+
+        if approach > 0.0 {
+            let time = (distance - radius) / approach;
+            let position = center + direction * time;
+            let u = (position - a).dot(ab) / ab.length_squared();
+
+            if (0.0..=1.0).contains(&time) && (0.0..=1.0).contains(&u) {
+                contacts.push(Contact { time, point: position - normal * radius, normal })
+            }
+        }
+    }
+
+    for endpoint in [a, b] {
+        // solve |center + direction t − endpoint|² = radius² for the smaller t
+        let m = center - endpoint;
+        let qa = direction.dot(direction);
+        let qb = 2.0 * m.dot(direction);
+        let qc = m.dot(m) - radius * radius;
+        let discriminant = qb * qb - 4.0 * qa * qc;
+
+        if qa == 0.0 || discriminant < 0.0 {
+            continue;
+        }
+
+        let time = (-qb - discriminant.sqrt()) / (2.0 * qa);
+
+        if (0.0..=1.0).contains(&time) {
+            let position = center + direction * time;
+            contacts.push(Contact { time, point: endpoint, normal: (position - endpoint) / radius })
+        }
+    }
+
+    contacts
+        .into_iter()
+        .min_by(|c0, c1| c0.time.total_cmp(&c1.time))
+}
+
+/// A moving box first touches a segment either with one of its corners on the segment,
+/// or with one of its edges on an endpoint of the segment.
+fn cast_aabb(
+    half_extents: Vec2,
+    center: Vec2,
+    direction: Vec2,
+    segment: Segment,
+) -> Option<Contact> {
+    let [a, b] = segment.points();
+    let aabb = Aabb::from_center(center, half_extents);
+
+    if let Some((inside, _)) = aabb.clip_segment(a, b) {
+        return Some(Contact { time: 0.0, point: inside, normal: -direction.normalize_or_zero() });
+    }
+
+    let mut contacts = Vec::with_capacity(6);
+
+    if let Some(mut normal) = (b - a).perp().try_normalize() {
+        if normal.dot(direction) > 0.0 {
+            normal = -normal;
+        }
+
+        for corner in aabb.corners() {
+            if let Some(time) = sweep_point(corner, direction, a, b) {
+                contacts.push(Contact { time, point: corner + direction * time, normal })
+            }
+        }
+    }
+
+    // relative to the box, the endpoints move in the opposite direction
+    let corners = aabb.corners();
+
+    for i in 0..4 {
+        let (edge_start, edge_end) = (corners[i], corners[(i + 1) % 4]);
+        // the corners are counterclockwise, so the left side of an edge is the inside of the box
+        let normal = (edge_end - edge_start).perp().normalize();
+
+        for endpoint in [a, b] {
+            if let Some(time) = sweep_point(endpoint, -direction, edge_start, edge_end) {
+                contacts.push(Contact { time, point: endpoint, normal })
+            }
+        }
+    }
+
+    contacts
+        .into_iter()
+        .min_by(|c0, c1| c0.time.total_cmp(&c1.time))
+}
+
+/// Return the fraction t in [0, 1] at which the point moving by the given motion crosses the segment from a to b.
+fn sweep_point(
+    point: Vec2,
+    motion: Vec2,
+    a: Vec2,
+    b: Vec2,
+) -> Option<f32> {
+    let ab = b - a;
+    let denominator = motion.perp_dot(ab);
+
+    if denominator == 0.0 {
+        return None;
+    }
+
+    let time = (a - point).perp_dot(ab) / denominator;
+    let u = (a - point).perp_dot(motion) / denominator;
+
+    ((0.0..=1.0).contains(&time) && (0.0..=1.0).contains(&u)).then_some(time)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use crate::occluder::{Occluder, OccluderId};
+    use crate::raycasting::Segment;
+    use crate::shape_cast::{cast_shape, Shape};
+
+    fn assert_hit(
+        shape: Shape,
+        segment: Segment,
+        expected_time: f32,
+        expected_point: Vec2,
+        expected_normal: Vec2,
+    ) {
+        let occluders = [Occluder::new(segment)];
+        let hit = cast_shape(shape, Vec2::ZERO, Vec2::new(10.0, 0.0), &occluders).expect("no hit");
+
+        assert!((hit.time - expected_time).abs() < 0.0001, "time {} != {expected_time}", hit.time);
+        assert!(hit.point.distance(expected_point) < 0.0001, "point {} != {expected_point}", hit.point);
+        assert!(hit.normal.distance(expected_normal) < 0.0001, "normal {} != {expected_normal}", hit.normal);
+        assert_eq!(hit.occluder, OccluderId::new(0));
+    }
+
+    #[test]
+    fn cast_circle_works() {
+        assert_hit(
+            Shape::Circle { radius: 1.0 },
+            Segment::from_coords(5.0, -5.0, 5.0, 5.0),
+            0.4,
+            Vec2::new(5.0, 0.0),
+            Vec2::new(-1.0, 0.0),
+        );
+
+        // the circle only touches the lower end of the wall
+        let x = 5.0 - 0.75_f32.sqrt();
+        assert_hit(
+            Shape::Circle { radius: 1.0 },
+            Segment::from_coords(5.0, 0.5, 5.0, 10.0),
+            x / 10.0,
+            Vec2::new(5.0, 0.5),
+            Vec2::new(x - 5.0, -0.5),
+        );
+    }
+
+    #[test]
+    fn cast_aabb_works() {
+        let shape = Shape::Aabb { half_extents: Vec2::new(1.0, 1.0) };
+
+        assert_hit(
+            shape,
+            Segment::from_coords(5.0, -5.0, 5.0, 5.0),
+            0.4,
+            Vec2::new(5.0, -1.0),
+            Vec2::new(-1.0, 0.0),
+        );
+
+        // the right edge of the box hits the lower end of the diagonal segment
+        assert_hit(
+            shape,
+            Segment::from_coords(4.0, 0.0, 8.0, 5.0),
+            0.3,
+            Vec2::new(4.0, 0.0),
+            Vec2::new(-1.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn shapes_moving_past_occluders_do_not_hit() {
+        let occluders = [
+            Occluder::new(Segment::from_coords(5.0, 1.5, 5.0, 10.0)),
+            Occluder::new(Segment::from_coords(-5.0, -5.0, -5.0, 5.0)),
+        ];
+
+        [
+            Shape::Circle { radius: 1.0 },
+            Shape::Aabb { half_extents: Vec2::new(1.0, 1.0) },
+        ].into_iter().for_each(|shape| assert_eq!(cast_shape(shape, Vec2::ZERO, Vec2::new(10.0, 0.0), &occluders), None))
+    }
+}
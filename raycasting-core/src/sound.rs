@@ -0,0 +1,101 @@
+use glam::*;
+use crate::occluder_set::OccluderSet;
+use crate::visibility_graph::VisibilityGraph;
+
+/// Settings of the sound attenuation approximation.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SoundSettings {
+    /// Fraction of the sound passing through an occluder with opacity 1
+    pub transmission: f32,
+    /// Fraction of the sound kept at every corner it bends around
+    pub diffraction: f32,
+}
+
+impl Default for SoundSettings {
+    fn default() -> Self {
+        SoundSettings {
+            transmission: 0.25,
+            diffraction: 0.5,
+        }
+    }
+}
+
+/// Return how much of the sound of the emitter reaches the listener, from 0 (nothing) to 1 (unobstructed).
+///
+/// The sound passes through every occluder between both, which keeps `1 - opacity * (1 - transmission)` of it.
+/// Note that a wall tile has two edges in the way, so it dampens twice. If a visibility graph is given,
+/// the sound may also bend around corners along the shortest path, losing a part at every corner and
+/// getting weaker the longer the detour is. The louder of both ways wins.
+pub fn attenuation(
+    emitter: Vec2,
+    listener: Vec2,
+    occluders: &OccluderSet,
+    graph: Option<&VisibilityGraph>,
+    settings: &SoundSettings,
+) -> f32 {
+    let through = occluders
+        .occluders_between(emitter, listener)
+        .into_iter()
+        .filter_map(|id| occluders.get(id))
+        .map(|occluder| 1.0 - occluder.opacity * (1.0 - settings.transmission))
+        .product::<f32>();
+
+    let around = graph
+        .and_then(|graph| graph.find_path(occluders, emitter, listener))
+        .map(|path| {
+            let length = path.windows(2).map(|pair| pair[0].distance(pair[1])).sum::<f32>();
+            let corners = path.len() as i32 - 2;
+
+            if length == 0.0 {
+                return 1.0;
+            }
+
+            settings.diffraction.powi(corners) * emitter.distance(listener) / length
+        })
+        .unwrap_or(0.0);
+
+    through.max(around)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use crate::occluder::Occluder;
+    use crate::occluder_set::OccluderSet;
+    use crate::raycasting::Segment;
+    use crate::scene::Scene;
+    use crate::sound::{attenuation, SoundSettings};
+    use crate::visibility_graph::VisibilityGraph;
+
+    #[test]
+    fn attenuation_through_occluders_works() {
+        let set = OccluderSet::new(vec![
+            Occluder::new(Segment::from_coords(10.0, -10.0, 10.0, 10.0)),
+            Occluder::new(Segment::from_coords(20.0, -10.0, 20.0, 10.0)).with_opacity(0.5),
+        ], 10.0);
+        let settings = SoundSettings { transmission: 0.2, diffraction: 0.5 };
+
+        [
+            (Vec2::new(5.0, 0.0), 1.0),
+            (Vec2::new(15.0, 0.0), 0.2),
+            (Vec2::new(25.0, 0.0), 0.2 * 0.6),
+        ].into_iter().for_each(|(listener, expected)| {
+            let value = attenuation(Vec2::ZERO, listener, &set, None, &settings);
+            assert!((value - expected).abs() < 0.0001, "{listener}: {value} != {expected}")
+        })
+    }
+
+    #[test]
+    fn attenuation_around_corners_works() {
+        let set = OccluderSet::new(Scene::parse("#######\n#.....#\n#.###.#\n#.....#\n#######\n").unwrap().occluders(10.0), 10.0);
+        let graph = VisibilityGraph::new(&set, 1.0);
+        let settings = SoundSettings { transmission: 0.0, diffraction: 0.5 };
+        let (emitter, listener) = (Vec2::new(15.0, 25.0), Vec2::new(55.0, 25.0));
+
+        let path = graph.find_path(&set, emitter, listener).unwrap();
+        let length = path.windows(2).map(|pair| pair[0].distance(pair[1])).sum::<f32>();
+
+        assert_eq!(attenuation(emitter, listener, &set, None, &settings), 0.0);
+        assert_eq!(attenuation(emitter, listener, &set, Some(&graph), &settings), 0.25 * 40.0 / length);
+    }
+}
@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+/// Instrumentation of raycast queries.
+///
+/// All values are added up, so the same stats can be passed to several queries (e.g. all queries of a frame).
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct RaycastStats {
+    /// Number of rays cast, including the extra rays passing by corners.
+    pub rays_cast: usize,
+    /// Number of ray-segment intersection tests performed.
+    pub segment_tests: usize,
+    /// Time spent collecting the endpoints of the occluders.
+    pub endpoint_collection_time: Duration,
+    /// Time spent sorting endpoints and intersections by angle.
+    pub sorting_time: Duration,
+    /// Time spent casting rays and resolving their nearest hits.
+    pub ray_casting_time: Duration,
+}
+
+impl RaycastStats {
+    pub fn total_time(&self) -> Duration {
+        self.endpoint_collection_time + self.sorting_time + self.ray_casting_time
+    }
+}
+
+/// Enter a tracing span with the given name, which lasts until the end of the current scope.
+/// Does nothing if the tracing feature is disabled.
+macro_rules! phase_span {
+    ($name:literal) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!($name).entered();
+    };
+}
+
+pub(crate) use phase_span;
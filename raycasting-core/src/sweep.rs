@@ -0,0 +1,333 @@
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use glam::*;
+use crate::config::{RaycastConfig, SelfHitPolicy};
+use crate::occluder::{Occluder, OccluderId};
+use crate::predicates::cross_sign;
+use crate::raycasting::{calculate_angle, intersection_parameter, Segment};
+use crate::stats::RaycastStats;
+
+/// An endpoint of an occluder, where the sweeping ray starts or stops crossing it.
+struct Event {
+    point: Vec2,
+    angle: f32,
+    occluder: usize,
+    starts: bool,
+}
+
+/// Compute the same visibility polygon as [crate::raycasting::calculate_intersection_points] with a rotational plane sweep.
+///
+/// A ray rotates counterclockwise around the origin, starting and ending at the negative x axis. The occluders
+/// it currently crosses are kept ordered by their distance along the ray, which does not change while two
+/// occluders are crossed at the same time (as long as they do not cross each other). At every endpoint, the
+/// crossed occluders change, and if the nearest one changed, the polygon gets a corner on the old and the new one.
+///
+/// Occluders collinear with the origin (including those passing through it) cannot be seen and are skipped.
+/// With [SelfHitPolicy::IgnoreNear], occluders closer to the origin than the epsilon are skipped as a whole.
+pub fn sweep_intersection_points(
+    origin: Vec2,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+    stats: &mut RaycastStats,
+) -> Vec<Vec2> {
+    let segments = occluders
+        .iter()
+        .enumerate()
+        .filter(|(index, occluder)| config.considers(OccluderId::new(*index), occluder) && occluder.is_facing(origin))
+        .filter(|(_, occluder)| match config.self_hit_policy {
+            SelfHitPolicy::IgnoreNear(epsilon) => occluder.segment.distance_to(origin) >= epsilon,
+            _ => true
+        })
+        .filter_map(|(index, occluder)| {
+            // order the endpoints counterclockwise around the origin
+            let [a, b] = occluder.segment.points();
+
+            match cross_sign(origin, a, origin, b) {
+                1 => Some((index, a, b)),
+                -1 => Some((index, b, a)),
+                _ => None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut events = segments
+        .iter()
+        .flat_map(|(index, start, end)| [
+            Event { point: *start, angle: calculate_angle(origin, *start), occluder: *index, starts: true },
+            Event { point: *end, angle: calculate_angle(origin, *end), occluder: *index, starts: false },
+        ])
+        .collect::<Vec<_>>();
+    events.sort_by(|e0, e1| e0.angle.total_cmp(&e1.angle).then(e0.starts.cmp(&e1.starts)));
+
+    // the occluders crossing the negative x axis are crossed right from the start
+    let segment_tests = Cell::new(0);
+    let mut active = ActiveOccluders::new(origin, occluders, &segment_tests);
+
+    for (index, start, end) in &segments {
+        if calculate_angle(origin, *start) > calculate_angle(origin, *end) {
+            active.insert(*index);
+        }
+    }
+
+    let mut points = Vec::new();
+    let mut i = 0;
+
+    while i < events.len() {
+        // handle all endpoints on the same ray at once
+        let direction = events[i].point - origin;
+        let group_end = events[i..]
+            .iter()
+            .position(|event| cross_sign(Vec2::ZERO, direction, Vec2::ZERO, event.point - origin) != 0 || (event.point - origin).dot(direction) <= 0.0)
+            .map(|offset| i + offset)
+            .unwrap_or(events.len());
+
+        let nearest_before = active.nearest();
+
+        for event in &events[i..group_end] {
+            if !event.starts {
+                active.remove(event.occluder);
+            }
+        }
+
+        for event in &events[i..group_end] {
+            if event.starts {
+                active.insert(event.occluder);
+            }
+        }
+
+        let nearest_after = active.nearest();
+        stats.rays_cast += 1;
+
+        if nearest_before != nearest_after {
+            let hit = |occluder: Option<usize>| match occluder {
+                Some(occluder) => {
+                    let [a, b] = occluders[occluder].segment.points();
+                    Some(origin + direction * intersection_parameter(origin, direction, a, b - a))
+                }
+                None => config.miss_policy.far_point(origin, direction)
+            };
+
+            points.extend(hit(nearest_before));
+            points.extend(hit(nearest_after));
+        }
+
+        i = group_end;
+    }
+
+    stats.segment_tests += segment_tests.get();
+    points
+}
+
+/// The occluders crossed by the sweeping ray, ordered by their distance along it, so inserting, removing
+/// and finding the nearest one take O(log n).
+struct ActiveOccluders<'a> {
+    origin: Vec2,
+    occluders: &'a [Occluder],
+    segment_tests: &'a Cell<usize>,
+    ordered: BTreeSet<ActiveOccluder<'a>>,
+    contained: Vec<bool>,
+}
+
+impl<'a> ActiveOccluders<'a> {
+    fn new(
+        origin: Vec2,
+        occluders: &'a [Occluder],
+        segment_tests: &'a Cell<usize>,
+    ) -> Self {
+        ActiveOccluders {
+            origin,
+            occluders,
+            segment_tests,
+            ordered: BTreeSet::new(),
+            contained: vec![false; occluders.len()],
+        }
+    }
+
+    fn nearest(&self) -> Option<usize> {
+        self.ordered.first().map(|active| active.occluder)
+    }
+
+    fn insert(&mut self, occluder: usize) {
+        if !self.contained[occluder] {
+            self.contained[occluder] = true;
+            self.ordered.insert(self.key(occluder));
+        }
+    }
+
+    fn remove(&mut self, occluder: usize) {
+        if !self.contained[occluder] {
+            return;
+        }
+
+        self.contained[occluder] = false;
+
+        // occluders crossing each other have no consistent order, so the search may miss it
+        if !self.ordered.remove(&self.key(occluder)) {
+            self.ordered.retain(|active| active.occluder != occluder);
+        }
+    }
+
+    fn key(&self, occluder: usize) -> ActiveOccluder<'a> {
+        ActiveOccluder {
+            occluder,
+            segment: self.occluders[occluder].segment,
+            origin: self.origin,
+            segment_tests: self.segment_tests,
+        }
+    }
+}
+
+/// An occluder crossed by the sweeping ray, ordered by its distance along the ray (see [compare_distance]), then by its index.
+struct ActiveOccluder<'a> {
+    occluder: usize,
+    segment: Segment,
+    origin: Vec2,
+    segment_tests: &'a Cell<usize>,
+}
+
+impl Ord for ActiveOccluder<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.segment_tests.set(self.segment_tests.get() + 1);
+        compare_distance(self.segment, other.segment, self.origin).then(self.occluder.cmp(&other.occluder))
+    }
+}
+
+impl PartialOrd for ActiveOccluder<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for ActiveOccluder<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.occluder == other.occluder
+    }
+}
+
+impl Eq for ActiveOccluder<'_> {}
+
+/// Compare the distance of two occluders along any ray from the origin which crosses both.
+/// The occluders must not cross each other. Occluders on the same line are equal.
+fn compare_distance(
+    s0: Segment,
+    s1: Segment,
+    origin: Vec2,
+) -> Ordering {
+    let side = |segment: Segment, point: Vec2| {
+        let [a, b] = segment.points();
+        cross_sign(a, b, a, point)
+    };
+    let [a0, b0] = s0.points();
+    let [a1, b1] = s1.points();
+
+    // if s1 lies completely on one side of the line through s0, s0 is nearer exactly if s1 lies on the far side
+    let (side_a1, side_b1) = (side(s0, a1), side(s0, b1));
+
+    if side_a1 * side_b1 >= 0 {
+        let s1_side = if side_a1 != 0 { side_a1 } else { side_b1 };
+
+        return match s1_side {
+            0 => Ordering::Equal,
+            s1_side if s1_side != side(s0, origin) => Ordering::Less,
+            _ => Ordering::Greater
+        };
+    }
+
+    // otherwise, s0 lies completely on one side of the line through s1
+    let (side_a0, side_b0) = (side(s1, a0), side(s1, b0));
+    let s0_side = if side_a0 != 0 { side_a0 } else { side_b0 };
+
+    if s0_side == side(s1, origin) {
+        Ordering::Less
+    } else {
+        Ordering::Greater
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::TAU;
+    use std::fs;
+    use std::path::PathBuf;
+    use glam::*;
+    use crate::config::{Algorithm, RaycastConfig};
+    use crate::pathological::pathological_scenes;
+    use crate::raycasting::{calculate_angle, calculate_intersection_points, find_nearest_intersection, Ray};
+    use crate::scene::Scene;
+    use crate::stats::RaycastStats;
+    use crate::visibility_events::polygon_contains;
+    use crate::visibility_polygon::VisibilityPolygon;
+
+    #[test]
+    fn sweep_matches_naive_algorithm() {
+        let naive = RaycastConfig::default().with_algorithm(Algorithm::Naive);
+        let sweep = RaycastConfig::default().with_algorithm(Algorithm::Sweep);
+        let scenes = ["example", "pillars", "rooms"]
+            .map(|name| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures").join("scenes").join(format!("{name}.map")))
+            .map(|path| Scene::parse(&fs::read_to_string(path).unwrap()).unwrap());
+
+        for scene in scenes {
+            let occluders = scene.occluders(32.0);
+
+            for y in (1..scene.height()).step_by(3) {
+                for x in (1..scene.width()).step_by(3) {
+                    if scene.is_wall(x, y) {
+                        continue;
+                    }
+
+                    let origin = Vec2::new(x as f32 + 0.3, y as f32 + 0.6) * 32.0;
+                    let naive_points = calculate_intersection_points(origin, &occluders, &naive);
+                    let sweep_points = calculate_intersection_points(origin, &occluders, &sweep);
+
+                    // the naive algorithm passes corners at a small angle, so it misses parts of far walls seen through narrow gaps
+                    for point in &naive_points {
+                        assert!(
+                            sweep_points.iter().any(|corner| corner.distance(*point) < 0.01) || polygon_contains(origin, &sweep_points, origin.lerp(*point, 0.995)),
+                            "{origin}: {point} is not visible"
+                        );
+                    }
+
+                    // so instead of comparing the polygons point by point, every direction between two endpoints, where
+                    // the nearest occluder is unambiguous, must reach exactly as far as a ray cast in it
+                    let polygon = VisibilityPolygon::new(origin, sweep_points);
+                    let mut angles = occluders
+                        .iter()
+                        .flat_map(|occluder| occluder.segment.points())
+                        .map(|point| calculate_angle(origin, point))
+                        .collect::<Vec<_>>();
+                    angles.sort_by(f32::total_cmp);
+                    angles.push(angles[0] + TAU);
+
+                    for angle in angles.windows(2).filter(|pair| pair[1] - pair[0] > 0.0001).map(|pair| (pair[0] + pair[1]) / 2.0) {
+                        let ray = Ray::new(origin, Vec2::from_angle(angle));
+                        let (hit, _) = find_nearest_intersection(ray, &occluders, &sweep, &mut RaycastStats::default()).unwrap();
+                        let (expected, distance) = (hit.distance(origin), polygon.raycast(angle).unwrap());
+
+                        assert!((distance - expected).abs() <= expected * 0.001, "{origin}, {angle}: {distance} != {expected}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sweep_output_lies_on_the_occluders() {
+        let sweep = RaycastConfig::default().with_algorithm(Algorithm::Sweep);
+
+        for scene in pathological_scenes() {
+            let points = calculate_intersection_points(scene.origin, &scene.occluders, &sweep);
+            let name = scene.name;
+
+            assert!(!points.is_empty(), "{name}: no intersection points");
+
+            for point in &points {
+                assert!(point.is_finite(), "{name}: {point} is not finite");
+                assert!(
+                    scene.occluders.iter().any(|occluder| occluder.segment.distance_to(*point) < 0.001),
+                    "{name}: {point} does not lie on an occluder"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,550 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use glam::*;
+use crate::occluder::{Occluder, OccluderKind};
+use crate::raycasting::{Ray, Segment};
+
+/// A grid of square tiles, some of which block the view. The tile at (x, y) covers the square from
+/// (x, y) * size to (x + 1, y + 1) * size.
+pub trait TileGrid {
+    fn width(&self) -> usize;
+
+    fn height(&self) -> usize;
+
+    /// Return true if the tile blocks the view. Only called for tiles inside the grid.
+    fn is_opaque(&self, x: usize, y: usize) -> bool;
+}
+
+/// Return the edges of the tile at the given integer coordinates, clockwise starting with the top edge.
+pub fn tile_edges(
+    ix: i32,
+    iy: i32,
+    size: f32,
+) -> [Segment; 4] {
+    let (x0, y0) = (ix as f32 * size, iy as f32 * size);
+    let (x1, y1) = (x0 + size, y0 + size);
+
+    [
+        Segment::from_coords(x0, y1, x1, y1),
+        Segment::from_coords(x1, y1, x1, y0),
+        Segment::from_coords(x1, y0, x0, y0),
+        Segment::from_coords(x0, y0, x0, y1),
+    ]
+}
+
+/// Return the tile containing the point.
+pub fn tile_at(point: Vec2, size: f32) -> (i32, i32) {
+    ((point.x / size).floor() as i32, (point.y / size).floor() as i32)
+}
+
+/// Return the center of the tile at the given integer coordinates.
+pub fn tile_center(
+    ix: i32,
+    iy: i32,
+    size: f32,
+) -> Vec2 {
+    Vec2::new(ix as f32 + 0.5, iy as f32 + 0.5) * size
+}
+
+/// Return every tile the ray touches, in the order the ray reaches them, starting with the tile of its origin.
+///
+/// This is a supercover traversal: if the ray passes exactly through the corner of a tile, both tiles next to the
+/// corner are returned before the diagonal one, so a ray can never slip between two diagonally touching tiles.
+/// The ray is endless, so stop taking tiles at some point.
+pub fn tiles_on_ray(ray: Ray, size: f32) -> TilesOnRay {
+    let origin = ray.origin() / size;
+    let direction = ray.direction();
+    let (x, y) = tile_at(ray.origin(), size);
+    let step = |d: f32| if d > 0.0 { 1 } else if d < 0.0 { -1 } else { 0 };
+
+    // the ray parameter of the next vertical and horizontal tile border
+    let next_border = |position: f32, tile: i32, d: f32| match step(d) {
+        1 => ((tile + 1) as f32 - position) / d,
+        -1 => (tile as f32 - position) / d,
+        _ => f32::INFINITY
+    };
+
+    TilesOnRay {
+        current: (x, y),
+        step: (step(direction.x), step(direction.y)),
+        next: (next_border(origin.x, x, direction.x), next_border(origin.y, y, direction.y)),
+        delta: ((1.0 / direction.x).abs(), (1.0 / direction.y).abs()),
+        pending: Vec::new(),
+        started: false,
+    }
+}
+
+/// The iterator returned by [tiles_on_ray].
+#[derive(Clone, Debug)]
+pub struct TilesOnRay {
+    current: (i32, i32),
+    step: (i32, i32),
+    next: (f32, f32),
+    delta: (f32, f32),
+    /// Tiles touched at a corner, which are returned before moving on
+    pending: Vec<(i32, i32)>,
+    started: bool,
+}
+
+impl Iterator for TilesOnRay {
+    type Item = (i32, i32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.started {
+            self.started = true;
+            return Some(self.current);
+        }
+
+        if let Some(tile) = self.pending.pop() {
+            return Some(tile);
+        }
+
+        if self.step == (0, 0) {
+            return None;
+        }
+
+        let (x, y) = self.current;
+
+        if self.next.0 < self.next.1 {
+            self.current = (x + self.step.0, y);
+            self.next.0 += self.delta.0;
+        } else if self.next.1 < self.next.0 {
+            self.current = (x, y + self.step.1);
+            self.next.1 += self.delta.1;
+        } else {
+            // the ray passes exactly through a corner, so it touches both neighbours of the diagonal tile
+            self.current = (x + self.step.0, y + self.step.1);
+            self.next.0 += self.delta.0;
+            self.next.1 += self.delta.1;
+            self.pending.push(self.current);
+            self.pending.push((x, y + self.step.1));
+            return Some((x + self.step.0, y));
+        }
+
+        Some(self.current)
+    }
+}
+
+/// The first opaque tile a ray enters, see [cast_grid_ray].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GridHit {
+    pub tile: (i32, i32),
+    /// Where the ray enters the tile
+    pub point: Vec2,
+    /// Distance from the origin of the ray to the point
+    pub distance: f32,
+    /// True if the ray enters through the left or right side of the tile, false for the top or bottom side.
+    /// Renderers usually shade both kinds of sides differently.
+    pub vertical_side: bool,
+}
+
+/// Walk the tiles along the ray with [tiles_on_ray] and return where it enters the first opaque tile of the grid,
+/// or None if there is none within the max distance. A ray starting inside an opaque tile hits it at its origin.
+pub fn cast_grid_ray(
+    ray: Ray,
+    grid: &impl TileGrid,
+    size: f32,
+    max_distance: f32,
+) -> Option<GridHit> {
+    let (origin, direction) = (ray.origin(), ray.direction().normalize_or_zero());
+    let is_opaque = |(x, y): (i32, i32)| x >= 0 && y >= 0 && (x as usize) < grid.width() && (y as usize) < grid.height() && grid.is_opaque(x as usize, y as usize);
+    // the distance at which the ray enters the slab between both borders of a tile along one axis
+    let entry = |position: f32, min: f32, d: f32| match d {
+        d if d > 0.0 => (min - position) / d,
+        d if d < 0.0 => (min + size - position) / d,
+        _ => f32::NEG_INFINITY
+    };
+
+    if direction == Vec2::ZERO {
+        let tile = tile_at(origin, size);
+        return is_opaque(tile).then_some(GridHit { tile, point: origin, distance: 0.0, vertical_side: false });
+    }
+
+    for tile in tiles_on_ray(Ray::new(origin, direction), size) {
+        let min = Vec2::new(tile.0 as f32, tile.1 as f32) * size;
+        let (x_entry, y_entry) = (entry(origin.x, min.x, direction.x), entry(origin.y, min.y, direction.y));
+        let distance = x_entry.max(y_entry).max(0.0);
+
+        if distance > max_distance {
+            return None;
+        }
+
+        if is_opaque(tile) {
+            return Some(GridHit {
+                tile,
+                point: origin + direction * distance,
+                distance,
+                vertical_side: x_entry >= y_entry,
+            });
+        }
+    }
+
+    None
+}
+
+/// Which tiles of a [TileGrid] can be seen from a tile, see [tile_visibility].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileVisibility {
+    width: usize,
+    visible: Vec<bool>,
+}
+
+impl TileVisibility {
+    /// Return a visibility of the given size, in which no tile is visible.
+    pub(crate) fn hidden(width: usize, height: usize) -> Self {
+        TileVisibility {
+            width,
+            visible: vec![false; width * height],
+        }
+    }
+
+    /// Mark the tile as visible. Tiles outside the grid are ignored.
+    pub(crate) fn reveal(&mut self, x: usize, y: usize) {
+        if x < self.width {
+            if let Some(visible) = self.visible.get_mut(y * self.width + x) {
+                *visible = true
+            }
+        }
+    }
+
+    /// Return true if the tile is visible. Tiles outside the grid are never visible.
+    pub fn is_visible(&self, x: usize, y: usize) -> bool {
+        x < self.width && self.visible.get(y * self.width + x).copied().unwrap_or(false)
+    }
+
+    pub fn visible_count(&self) -> usize {
+        self.visible.iter().filter(|visible| **visible).count()
+    }
+}
+
+/// Return which tiles can be seen from the center of the origin tile. A tile is visible if the straight line
+/// between both centers touches no opaque tile in between, with the same corner rule as [tiles_on_ray]. Opaque
+/// tiles can be visible themselves, so the walls around a room are seen. The line is traversed with exact
+/// integer arithmetic and touches the same tiles in both directions, so the result is symmetric between floor
+/// tiles: if a sees b, then b sees a.
+pub fn tile_visibility(
+    origin_tile: (usize, usize),
+    grid: &impl TileGrid,
+) -> TileVisibility {
+    let (width, height) = (grid.width(), grid.height());
+    let origin = (origin_tile.0 as i32, origin_tile.1 as i32);
+    let is_opaque = |(x, y): (i32, i32)| x < 0 || y < 0 || x as usize >= width || y as usize >= height || grid.is_opaque(x as usize, y as usize);
+
+    let visible = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x as i32, y as i32)))
+        .map(|target| tiles_between(origin, target)
+            .into_iter()
+            .take_while(|tile| *tile == target || !is_opaque(*tile))
+            .any(|tile| tile == target))
+        .collect();
+
+    TileVisibility { width, visible }
+}
+
+/// Return the tiles touched by the line between the centers of two tiles, from the first to the second one.
+fn tiles_between(
+    from: (i32, i32),
+    to: (i32, i32),
+) -> Vec<(i32, i32)> {
+    let (nx, ny) = ((to.0 - from.0).abs(), (to.1 - from.1).abs());
+    let (sx, sy) = ((to.0 - from.0).signum(), (to.1 - from.1).signum());
+    let (mut x, mut y) = from;
+    let (mut ix, mut iy) = (0, 0);
+    let mut tiles = vec![from];
+
+    while ix < nx || iy < ny {
+        // compare the parameters of the next vertical and horizontal tile border, (ix + 0.5) / nx and (iy + 0.5) / ny
+        match ((1 + 2 * ix) * ny).cmp(&((1 + 2 * iy) * nx)) {
+            Ordering::Less => {
+                x += sx;
+                ix += 1;
+            }
+            Ordering::Greater => {
+                y += sy;
+                iy += 1;
+            }
+            Ordering::Equal => {
+                tiles.extend([(x + sx, y), (x, y + sy)]);
+                x += sx;
+                y += sy;
+                ix += 1;
+                iy += 1;
+            }
+        }
+
+        tiles.push((x, y))
+    }
+
+    tiles
+}
+
+/// The union of connected opaque tiles, as a polygon with holes, see [tile_polygons].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TilePolygon {
+    /// The corners of the outer boundary, counterclockwise
+    pub outer: Vec<Vec2>,
+    /// The corners of every hole, like an inner courtyard, clockwise
+    pub holes: Vec<Vec<Vec2>>,
+}
+
+impl TilePolygon {
+    /// Return the edges of the boundary and the holes as edges of a closed polygon, with the opaque tiles on their
+    /// left, so they are only seen from the floor and their back faces can be culled.
+    pub fn occluders(&self) -> Vec<Occluder> {
+        [&self.outer].into_iter()
+            .chain(&self.holes)
+            .flat_map(|ring| (0..ring.len()).map(|i| Segment::new(ring[i], ring[(i + 1) % ring.len()])))
+            .map(|segment| Occluder::new(segment).with_kind(OccluderKind::ClosedPolygon))
+            .collect()
+    }
+}
+
+/// Merge the opaque tiles of the grid into polygons with holes: one for every group of tiles connected by their
+/// sides, with a corner only where the boundary turns. Tiles touching only at a corner belong to different polygons.
+///
+/// Unlike the four edges of every tile, the boundaries neither overlap nor meet inside of each other, so they need
+/// no planarization, and they are much fewer. Tiles outside the grid are not opaque.
+pub fn tile_polygons(
+    grid: &impl TileGrid,
+    size: f32,
+) -> Vec<TilePolygon> {
+    let (width, height) = (grid.width() as i32, grid.height() as i32);
+    let is_opaque = |x: i32, y: i32| x >= 0 && y >= 0 && x < width && y < height && grid.is_opaque(x as usize, y as usize);
+
+    // the unit edges between opaque and other tiles, by their start, directed with the opaque tile on their left
+    let mut outgoing = BTreeMap::<(i32, i32), Vec<(i32, i32)>>::new();
+
+    for y in 0..height {
+        for x in 0..width {
+            if !is_opaque(x, y) {
+                continue;
+            }
+
+            [
+                (!is_opaque(x, y - 1), (x, y), (1, 0)),
+                (!is_opaque(x + 1, y), (x + 1, y), (0, 1)),
+                (!is_opaque(x, y + 1), (x + 1, y + 1), (-1, 0)),
+                (!is_opaque(x - 1, y), (x, y + 1), (0, -1)),
+            ].into_iter()
+                .filter(|(boundary, _, _)| *boundary)
+                .for_each(|(_, start, direction)| outgoing.entry(start).or_default().push(direction));
+        }
+    }
+
+    let mut rings = Vec::new();
+
+    while let Some((start, directions)) = outgoing.iter_mut().find(|(_, directions)| !directions.is_empty()) {
+        let (start, start_direction) = (*start, directions.pop().unwrap());
+        let mut steps = vec![(start, start_direction)];
+        let mut vertex = (start.0 + start_direction.0, start.1 + start_direction.1);
+
+        loop {
+            let (_, (dx, dy)) = steps[steps.len() - 1];
+            let directions = outgoing.entry(vertex).or_default();
+            // turn left where two tiles touch at a corner, so they stay apart
+            let Some(direction) = [(-dy, dx), (dx, dy), (dy, -dx)]
+                .into_iter()
+                .find(|direction| (vertex == start && *direction == start_direction) || directions.contains(direction)) else {
+                break;
+            };
+
+            if vertex == start && direction == start_direction {
+                break;
+            }
+
+            directions.retain(|other| *other != direction);
+            steps.push((vertex, direction));
+            vertex = (vertex.0 + direction.0, vertex.1 + direction.1);
+        }
+
+        rings.push(steps);
+    }
+
+    // the start and direction of every unit edge along a ring
+    type Ring = [((i32, i32), (i32, i32))];
+    let area = |ring: &Ring| (0..ring.len())
+        .map(|i| {
+            let ((x0, y0), (x1, y1)) = (ring[i].0, ring[(i + 1) % ring.len()].0);
+            x0 as i64 * y1 as i64 - x1 as i64 * y0 as i64
+        })
+        .sum::<i64>();
+    let corners = |ring: &Ring| (0..ring.len())
+        .filter(|i| ring[(i + ring.len() - 1) % ring.len()].1 != ring[*i].1)
+        .map(|i| Vec2::new(ring[i].0.0 as f32, ring[i].0.1 as f32) * size)
+        .collect::<Vec<_>>();
+
+    let (outers, holes): (Vec<_>, Vec<_>) = rings.into_iter().partition(|ring| area(ring) > 0);
+    let mut polygons = outers.iter().map(|ring| TilePolygon { outer: corners(ring), holes: Vec::new() }).collect::<Vec<_>>();
+
+    for hole in holes {
+        // the center of the opaque tile left of the first edge of the hole, which lies in the polygon around it
+        let ((x, y), (dx, dy)) = hole[0];
+        let inside = Vec2::new(x as f32 + (dx - dy) as f32 / 2.0, y as f32 + (dy + dx) as f32 / 2.0) * size;
+        let around = polygons
+            .iter()
+            .enumerate()
+            .filter(|(_, polygon)| ring_contains(&polygon.outer, inside))
+            .min_by_key(|(i, _)| area(&outers[*i]))
+            .map(|(i, _)| i);
+
+        if let Some(i) = around {
+            polygons[i].holes.push(corners(&hole));
+        }
+    }
+
+    polygons
+}
+
+/// Return the edges of [tile_polygons], see [TilePolygon::occluders].
+pub fn tile_occluders(
+    grid: &impl TileGrid,
+    size: f32,
+) -> Vec<Occluder> {
+    tile_polygons(grid, size).iter().flat_map(TilePolygon::occluders).collect()
+}
+
+/// Return true if the point lies inside the ring, by counting the edges crossed by a horizontal ray from it.
+fn ring_contains(
+    ring: &[Vec2],
+    point: Vec2,
+) -> bool {
+    (0..ring.len())
+        .filter(|i| {
+            let (a, b) = (ring[*i], ring[(i + 1) % ring.len()]);
+            (a.y > point.y) != (b.y > point.y) && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+        })
+        .count() % 2 == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use glam::*;
+    use crate::config::RaycastConfig;
+    use crate::raycasting::{calculate_intersection_points, Ray, Segment};
+    use crate::scene::Scene;
+    use crate::tiles::{cast_grid_ray, GridHit, tile_at, tile_edges, tile_polygons, tile_visibility, tiles_between, tiles_on_ray};
+
+    #[test]
+    fn tile_edges_work() {
+        assert!(tile_edges(-1, 2, 10.0) == [
+            Segment::from_coords(-10.0, 30.0, 0.0, 30.0),
+            Segment::from_coords(0.0, 30.0, 0.0, 20.0),
+            Segment::from_coords(0.0, 20.0, -10.0, 20.0),
+            Segment::from_coords(-10.0, 20.0, -10.0, 30.0),
+        ]);
+        assert_eq!(tile_at(Vec2::new(-0.5, 25.0), 10.0), (-1, 2));
+    }
+
+    #[test]
+    fn tiles_on_ray_is_a_supercover() {
+        [
+            (Ray::new(Vec2::new(0.5, 0.5), Vec2::new(1.0, 0.0)), vec![(0, 0), (1, 0), (2, 0), (3, 0)]),
+            (Ray::new(Vec2::new(0.5, 0.5), Vec2::new(-1.0, 0.25)), vec![(0, 0), (-1, 0), (-2, 0), (-2, 1)]),
+            // exactly through the corners
+            (Ray::new(Vec2::new(0.5, 0.5), Vec2::new(1.0, 1.0)), vec![(0, 0), (1, 0), (0, 1), (1, 1)]),
+            (Ray::new(Vec2::new(0.5, 0.5), Vec2::new(-1.0, -1.0)), vec![(0, 0), (-1, 0), (0, -1), (-1, -1)]),
+        ].into_iter().for_each(|(ray, expected)| assert_eq!(tiles_on_ray(ray, 1.0).take(expected.len()).collect::<Vec<_>>(), expected, "{ray:?}"));
+
+        assert_eq!(tiles_on_ray(Ray::new(Vec2::new(15.0, 5.0), Vec2::new(0.0, 1.0)), 10.0).take(3).collect::<Vec<_>>(), vec![(1, 0), (1, 1), (1, 2)]);
+        assert_eq!(tiles_between((0, 0), (-2, -2)), vec![(0, 0), (-1, 0), (0, -1), (-1, -1), (-2, -1), (-1, -2), (-2, -2)]);
+        assert_eq!(tiles_between((0, 0), (3, 1)), vec![(0, 0), (1, 0), (2, 0), (1, 1), (2, 1), (3, 1)]);
+    }
+
+    #[test]
+    fn grid_rays_hit_the_first_opaque_tile() {
+        let scene = Scene::parse("#####\n#...#\n#.#.#\n#...#\n#####\n").unwrap();
+
+        [
+            (Ray::new(Vec2::new(15.0, 15.0), Vec2::new(1.0, 0.0)), Some(GridHit { tile: (4, 1), point: Vec2::new(40.0, 15.0), distance: 25.0, vertical_side: true })),
+            (Ray::new(Vec2::new(15.0, 15.0), Vec2::new(0.0, 2.0)), Some(GridHit { tile: (1, 4), point: Vec2::new(15.0, 40.0), distance: 25.0, vertical_side: false })),
+            // the pillar in the middle
+            (Ray::new(Vec2::new(12.0, 15.0), Vec2::new(1.0, 1.0)), Some(GridHit { tile: (2, 2), point: Vec2::new(20.0, 23.0), distance: 8.0 * 2.0_f32.sqrt(), vertical_side: true })),
+            // inside a wall
+            (Ray::new(Vec2::new(5.0, 5.0), Vec2::new(1.0, 0.0)), Some(GridHit { tile: (0, 0), point: Vec2::new(5.0, 5.0), distance: 0.0, vertical_side: true })),
+        ].into_iter().for_each(|(ray, expected)| {
+            let hit = cast_grid_ray(ray, &scene, 10.0, 100.0);
+
+            match (hit, expected) {
+                (Some(hit), Some(expected)) => {
+                    assert_eq!((hit.tile, hit.vertical_side), (expected.tile, expected.vertical_side), "{ray:?}");
+                    assert!(hit.point.distance(expected.point) < 0.001 && (hit.distance - expected.distance).abs() < 0.001, "{ray:?}: {hit:?}");
+                }
+                (hit, expected) => assert_eq!(hit, expected, "{ray:?}")
+            }
+        });
+
+        assert_eq!(cast_grid_ray(Ray::new(Vec2::new(15.0, 15.0), Vec2::new(1.0, 0.0)), &scene, 10.0, 20.0), None);
+    }
+
+    #[test]
+    fn tile_visibility_works() {
+        let scene = Scene::parse("#######\n#.....#\n#.###.#\n#.....#\n#######\n").unwrap();
+
+        let visibility = tile_visibility((1, 1), &scene);
+
+        // the bottom and the left corridor, including their walls
+        assert!(visibility.is_visible(5, 1));
+        assert!(visibility.is_visible(1, 3));
+        assert!(visibility.is_visible(0, 1));
+        assert!(visibility.is_visible(2, 2));
+        // hidden behind the block in the middle
+        assert!(!visibility.is_visible(5, 3));
+        assert!(!visibility.is_visible(4, 3));
+        assert!(!visibility.is_visible(7, 0));
+        // the corner tile only touches the origin at a corner between two walls
+        assert!(!visibility.is_visible(0, 0));
+        assert!(visibility.visible_count() < 35);
+    }
+
+    #[test]
+    fn tile_visibility_is_symmetric() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures").join("scenes").join("rooms.map");
+        let scene = Scene::parse(&fs::read_to_string(path).unwrap()).unwrap();
+        let floors = (0..scene.height())
+            .flat_map(|y| (0..scene.width()).map(move |x| (x, y)))
+            .filter(|(x, y)| !scene.is_wall(*x, *y))
+            .collect::<Vec<_>>();
+        let visibilities = floors.iter().map(|tile| tile_visibility(*tile, &scene)).collect::<Vec<_>>();
+
+        for (a, visibility_a) in floors.iter().zip(&visibilities) {
+            for (b, visibility_b) in floors.iter().zip(&visibilities) {
+                assert_eq!(visibility_a.is_visible(b.0, b.1), visibility_b.is_visible(a.0, a.1), "{a:?} and {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn tiles_are_merged_into_polygons_with_holes() {
+        // a ring of walls around a courtyard with a pillar in it, and a tile touching the ring only at a corner
+        let scene = Scene::parse("......#\n#####..\n#...#..\n#.#.#..\n#...#..\n#####..\n").unwrap();
+
+        let polygons = tile_polygons(&scene, 10.0);
+
+        assert_eq!(polygons.len(), 3);
+        let ring = polygons.iter().find(|polygon| !polygon.holes.is_empty()).unwrap();
+        assert_eq!(ring.outer.len(), 4);
+        assert_eq!(ring.holes.len(), 1);
+        assert_eq!(ring.holes[0].len(), 4);
+        assert!(ring.holes[0].contains(&Vec2::new(10.0, 10.0)) && ring.holes[0].contains(&Vec2::new(40.0, 40.0)));
+        assert!(polygons.iter().all(|polygon| polygon.outer.len() == 4));
+
+        // every edge has a floor tile on its right and a wall on its left
+        let occluders = polygons.iter().flat_map(|polygon| polygon.occluders()).collect::<Vec<_>>();
+        assert_eq!(occluders.len(), 16);
+        assert!(occluders.iter().all(|occluder| {
+            let [a, b] = occluder.segment.points();
+            let (middle, left) = ((a + b) / 2.0, (b - a).normalize().perp() * 5.0);
+            let is_wall = |point: Vec2| scene.is_wall((point.x / 10.0) as usize, (point.y / 10.0) as usize);
+            is_wall(middle + left) && (!is_wall(middle - left) || middle.x - left.x < 0.0 || middle.y - left.y < 0.0)
+        }));
+
+        // seen from the courtyard, the polygons hide the same as the edges of every tile
+        let bounds = scene.occluders(10.0).split_off(scene.occluders(10.0).len() - 4);
+        let area = |points: &[Vec2]| (0..points.len()).map(|i| points[i].perp_dot(points[(i + 1) % points.len()])).sum::<f32>() / 2.0;
+        let merged = calculate_intersection_points(Vec2::new(15.0, 15.0), &[occluders, bounds].concat(), &RaycastConfig::default());
+        let edges = calculate_intersection_points(Vec2::new(15.0, 15.0), &scene.occluders(10.0), &RaycastConfig::default());
+        assert!((area(&merged) - area(&edges)).abs() < 0.1, "{} != {}", area(&merged), area(&edges));
+    }
+}
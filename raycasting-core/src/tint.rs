@@ -0,0 +1,171 @@
+use glam::*;
+use crate::config::RaycastConfig;
+use crate::occluder::{Occluder, OccluderId};
+use crate::predicates::cross_sign;
+use crate::raycasting::{intersection_parameter, polygon_points, Segment};
+
+/// A part of the visibility polygon lit through the same translucent occluders.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TintedSector {
+    /// A counterclockwise triangle starting at the origin, or a counterclockwise quad behind a translucent occluder
+    pub points: Vec<Vec2>,
+    /// The fraction of each color channel (RGB) of the light at the origin which reaches this sector
+    pub tint: Vec3,
+}
+
+/// Return the visibility polygon of the origin, split into sectors which are each lit through the same
+/// translucent occluders, together with the light passing through them (see [Occluder::transmittance]).
+/// Multiplying the color of a light at the origin with the tints gives colored light pools behind
+/// stained-glass windows.
+///
+/// Only occluders which let no light through bound the polygon. Every translucent occluder considered by the
+/// config splits the sectors behind it, and the tint of a sector is the product of the transmittances of all
+/// translucent occluders between it and the origin.
+pub fn tinted_visibility(
+    origin: Vec2,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+) -> Vec<TintedSector> {
+    let is_translucent = |occluder: &Occluder| occluder.transmittance() != Vec3::ZERO;
+    let opaque = occluders
+        .iter()
+        .map(|occluder| if is_translucent(occluder) { occluder.with_layers(0) } else { *occluder })
+        .collect::<Vec<_>>();
+    let translucent = occluders
+        .iter()
+        .enumerate()
+        .filter(|(id, occluder)| is_translucent(occluder) && config.considers(OccluderId::new(*id), occluder))
+        .map(|(_, occluder)| occluder)
+        .collect::<Vec<_>>();
+    let points = polygon_points(origin, &opaque, config);
+    let mut sectors = Vec::new();
+
+    for i in 0..points.len() {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+
+        if cross_sign(origin, a, origin, b) <= 0 {
+            continue;
+        }
+
+        let mut splits = translucent
+            .iter()
+            .flat_map(|occluder| occluder.segment.points())
+            .filter(|point| cross_sign(origin, a, origin, *point) > 0 && cross_sign(origin, *point, origin, b) > 0)
+            .map(|point| origin + (point - origin) * intersection_parameter(origin, point - origin, a, b - a))
+            .collect::<Vec<_>>();
+        splits.sort_by(|p, q| cross_sign(origin, *q, origin, *p).cmp(&0));
+        splits.insert(0, a);
+        splits.push(b);
+
+        for pair in splits.windows(2) {
+            split_by_translucent_occluders(origin, pair[0], pair[1], &translucent, &mut sectors);
+        }
+    }
+
+    sectors
+}
+
+/// Split the triangle from the origin to the far edge from a to b at the translucent occluders crossing it.
+/// No endpoint of a translucent occluder lies inside the triangle, so every occluder crossing it spans its
+/// whole width.
+fn split_by_translucent_occluders(
+    origin: Vec2,
+    a: Vec2,
+    b: Vec2,
+    translucent: &[&Occluder],
+    sectors: &mut Vec<TintedSector>,
+) {
+    let middle = (a + b) / 2.0;
+    let mut crossings = translucent
+        .iter()
+        .filter(|occluder| {
+            let [p, q] = occluder.segment.points();
+            cross_sign(origin, middle, p, q) != 0 && Segment::new(origin, middle).intersects(occluder.segment)
+        })
+        .map(|occluder| {
+            let [p, q] = occluder.segment.points();
+            let distance = |to: Vec2| intersection_parameter(origin, to - origin, p, q - p).clamp(0.0, 1.0);
+            (distance(middle), origin + (a - origin) * distance(a), origin + (b - origin) * distance(b), occluder.transmittance())
+        })
+        .collect::<Vec<_>>();
+    crossings.sort_by(|c, d| c.0.total_cmp(&d.0));
+
+    let mut tint = Vec3::ONE;
+    let mut near = None;
+
+    for (_, near_a, near_b, transmittance) in crossings.into_iter().chain([(1.0, a, b, Vec3::ONE)]) {
+        sectors.push(TintedSector {
+            points: match near {
+                None => vec![origin, near_a, near_b],
+                Some((previous_a, previous_b)) => vec![previous_a, near_a, near_b, previous_b]
+            },
+            tint,
+        });
+        tint *= transmittance;
+        near = Some((near_a, near_b));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use crate::config::RaycastConfig;
+    use crate::occluder::Occluder;
+    use crate::pathological::room;
+    use crate::raycasting::{calculate_intersection_points, Segment, Triangle};
+    use crate::tint::{tinted_visibility, TintedSector};
+
+    /// Return the tint at the given point, if it lies in exactly one sector
+    fn tint_at(sectors: &[TintedSector], point: Vec2) -> Option<Vec3> {
+        let containing = sectors
+            .iter()
+            .filter(|sector| (1..sector.points.len() - 1).any(|i| Triangle::new(sector.points[0], sector.points[i], sector.points[i + 1]).contains(point)))
+            .collect::<Vec<_>>();
+
+        (containing.len() == 1).then(|| containing[0].tint)
+    }
+
+    #[test]
+    fn transmittance_combines_opacity_and_tint() {
+        let segment = Segment::from_coords(0.0, 0.0, 1.0, 0.0);
+
+        [
+            (Occluder::new(segment), Vec3::ZERO),
+            (Occluder::new(segment).with_opacity(0.5), Vec3::splat(0.5)),
+            (Occluder::new(segment).with_tint(Vec3::new(1.0, 0.0, 0.0)), Vec3::new(1.0, 0.0, 0.0)),
+            (Occluder::new(segment).with_opacity(0.5).with_tint(Vec3::new(1.0, 0.0, 0.5)), Vec3::new(1.0, 0.5, 0.75)),
+        ].into_iter().for_each(|(occluder, expected)| assert_eq!(occluder.transmittance(), expected));
+    }
+
+    #[test]
+    fn tinted_visibility_colors_the_light_behind_translucent_occluders() {
+        let purple = Vec3::new(1.0, 0.0, 0.5);
+        let yellow = Vec3::new(1.0, 1.0, 0.0);
+        let mut occluders = room(100.0);
+        occluders.push(Occluder::new(Segment::from_coords(20.0, -10.0, 20.0, 10.0)).with_tint(purple));
+        occluders.push(Occluder::new(Segment::from_coords(40.0, -40.0, 40.0, -10.0)).with_tint(yellow));
+        occluders.push(Occluder::new(Segment::from_coords(-20.0, -10.0, -20.0, 10.0)));
+
+        let sectors = tinted_visibility(Vec2::ZERO, &occluders, &RaycastConfig::default());
+
+        [
+            (Vec2::new(10.0, 0.0), Some(Vec3::ONE)),
+            (Vec2::new(30.0, 5.0), Some(purple)),
+            (Vec2::new(50.0, -10.0), Some(purple)),
+            (Vec2::new(30.0, 25.0), Some(Vec3::ONE)),
+            (Vec2::new(60.0, -45.0), Some(yellow)),
+            // through both
+            (Vec2::new(60.0, -24.0), Some(Vec3::new(1.0, 0.0, 0.0))),
+            (Vec2::new(30.0, -12.0), Some(purple)),
+            (Vec2::new(-10.0, 0.0), Some(Vec3::ONE)),
+            // behind the opaque occluder
+            (Vec2::new(-30.0, 0.0), None),
+        ].into_iter().for_each(|(point, expected)| assert_eq!(tint_at(&sectors, point), expected, "{point}"));
+
+        // the sectors cover the visibility polygon of the opaque occluders
+        let area = |points: &[Vec2]| (0..points.len()).map(|i| points[i].perp_dot(points[(i + 1) % points.len()])).sum::<f32>() / 2.0;
+        let opaque = occluders.iter().filter(|occluder| occluder.transmittance() == Vec3::ZERO).copied().collect::<Vec<_>>();
+        assert!(sectors.iter().all(|sector| area(&sector.points) >= 0.0));
+        assert!((sectors.iter().map(|sector| area(&sector.points)).sum::<f32>() - area(&calculate_intersection_points(Vec2::ZERO, &opaque, &RaycastConfig::default()))).abs() < 1.0);
+    }
+}
@@ -0,0 +1,179 @@
+use std::ops::Mul;
+use glam::*;
+use crate::groups::GroupId;
+use crate::occluder::Occluder;
+use crate::raycasting::Segment;
+
+/// A 2D affine transform: a linear part (rotation, scale, shear) followed by a translation.
+///
+/// Transforms compose like matrices: (a * b).apply(point) is a.apply(b.apply(point)).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform2 {
+    pub matrix: Mat2,
+    pub translation: Vec2,
+}
+
+impl Default for Transform2 {
+    fn default() -> Self {
+        Transform2::IDENTITY
+    }
+}
+
+impl Transform2 {
+    pub const IDENTITY: Transform2 = Transform2 {
+        matrix: Mat2::IDENTITY,
+        translation: Vec2::ZERO,
+    };
+
+    pub fn new(matrix: Mat2, translation: Vec2) -> Self {
+        Transform2 { matrix, translation }
+    }
+
+    pub fn from_translation(translation: Vec2) -> Self {
+        Transform2::new(Mat2::IDENTITY, translation)
+    }
+
+    /// Rotate counterclockwise by the given angle in radians.
+    pub fn from_rotation(angle: f32) -> Self {
+        Transform2::new(Mat2::from_angle(angle), Vec2::ZERO)
+    }
+
+    pub fn from_scale(scale: Vec2) -> Self {
+        Transform2::new(Mat2::from_diagonal(scale), Vec2::ZERO)
+    }
+
+    /// Scale, then rotate, then translate.
+    pub fn from_scale_rotation_translation(
+        scale: Vec2,
+        angle: f32,
+        translation: Vec2,
+    ) -> Self {
+        Transform2::new(Mat2::from_angle(angle) * Mat2::from_diagonal(scale), translation)
+    }
+
+    /// Transform a point.
+    pub fn apply(&self, point: Vec2) -> Vec2 {
+        self.matrix * point + self.translation
+    }
+
+    /// Transform a direction, which ignores the translation.
+    pub fn apply_vector(&self, vector: Vec2) -> Vec2 {
+        self.matrix * vector
+    }
+
+    /// Return the transform which undoes this one, or None if it collapses the plane (like a scale of 0).
+    pub fn inverse(&self) -> Option<Transform2> {
+        if self.matrix.determinant() == 0.0 {
+            return None;
+        }
+
+        let matrix = self.matrix.inverse();
+        Some(Transform2::new(matrix, -(matrix * self.translation)))
+    }
+
+    /// Return true if this transform mirrors the plane, which reverses the winding of polygons.
+    pub fn is_mirroring(&self) -> bool {
+        self.matrix.determinant() < 0.0
+    }
+
+    pub fn apply_to_segment(&self, segment: Segment) -> Segment {
+        let [a, b] = segment.points();
+        Segment::new(self.apply(a), self.apply(b))
+    }
+
+    /// Transform the occluder. If this transform mirrors the plane, the endpoints are swapped, so the edges of
+    /// closed polygons stay counterclockwise.
+    pub fn apply_to_occluder(&self, occluder: Occluder) -> Occluder {
+        let [a, b] = occluder.segment.points();
+        let (a, b) = if self.is_mirroring() { (b, a) } else { (a, b) };
+
+        Occluder {
+            segment: Segment::new(self.apply(a), self.apply(b)),
+            ..occluder
+        }
+    }
+
+    /// Transform every occluder in the given group, like a door swinging open. Other occluders stay as they are.
+    pub fn apply_to_group(
+        &self,
+        occluders: &mut [Occluder],
+        group: GroupId,
+    ) {
+        occluders
+            .iter_mut()
+            .filter(|occluder| occluder.group == Some(group))
+            .for_each(|occluder| *occluder = self.apply_to_occluder(*occluder))
+    }
+
+    /// Transform the points of a query result, like a visibility polygon computed in scene space.
+    pub fn apply_to_points(&self, points: &mut [Vec2]) {
+        points
+            .iter_mut()
+            .for_each(|point| *point = self.apply(*point))
+    }
+}
+
+impl Mul for Transform2 {
+    type Output = Transform2;
+
+    fn mul(self, rhs: Transform2) -> Self::Output {
+        Transform2::new(self.matrix * rhs.matrix, self.apply(rhs.translation))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+    use glam::*;
+    use crate::groups::OccluderGroups;
+    use crate::occluder::Occluder;
+    use crate::raycasting::Segment;
+    use crate::transform::Transform2;
+
+    #[test]
+    fn transforms_compose_and_invert() {
+        let rotate = Transform2::from_rotation(PI / 2.0);
+        let translate = Transform2::from_translation(Vec2::new(10.0, 0.0));
+        let scale = Transform2::from_scale(Vec2::new(2.0, 3.0));
+
+        assert!(rotate.apply(Vec2::X).distance(Vec2::Y) < 0.0001);
+        assert!((translate * rotate).apply(Vec2::X).distance(Vec2::new(10.0, 1.0)) < 0.0001);
+        assert!((rotate * translate).apply(Vec2::X).distance(Vec2::new(0.0, 11.0)) < 0.0001);
+        assert_eq!(translate.apply_vector(Vec2::X), Vec2::X);
+
+        let combined = Transform2::from_scale_rotation_translation(Vec2::new(2.0, 3.0), PI / 2.0, Vec2::new(10.0, 0.0));
+        assert!(combined.apply(Vec2::new(1.0, 1.0)).distance((translate * rotate * scale).apply(Vec2::new(1.0, 1.0))) < 0.0001);
+
+        let point = Vec2::new(3.0, -7.0);
+        assert!(combined.inverse().unwrap().apply(combined.apply(point)).distance(point) < 0.0001);
+        assert_eq!(Transform2::from_scale(Vec2::new(0.0, 1.0)).inverse(), None);
+    }
+
+    #[test]
+    fn mirrored_polygons_keep_facing_outwards() {
+        let square = Occluder::polygon(&[Vec2::new(1.0, 1.0), Vec2::new(2.0, 1.0), Vec2::new(2.0, 2.0), Vec2::new(1.0, 2.0)]);
+        let mirror = Transform2::from_scale(Vec2::new(1.0, -1.0));
+
+        assert!(mirror.is_mirroring());
+        // the origin lies outside the square before and after mirroring, so exactly the same number of edges face it
+        let facing = |occluders: &[Occluder]| occluders.iter().filter(|occluder| occluder.is_facing(Vec2::ZERO)).count();
+        let mirrored = square.iter().map(|occluder| mirror.apply_to_occluder(*occluder)).collect::<Vec<_>>();
+        assert_eq!(facing(&mirrored), facing(&square));
+        assert_eq!(facing(&square), 2);
+    }
+
+    #[test]
+    fn only_the_group_is_transformed() {
+        let mut groups = OccluderGroups::default();
+        let door = groups.register("door", None).unwrap();
+        let mut occluders = [
+            Occluder::new(Segment::from_coords(0.0, 0.0, 1.0, 0.0)).with_group(door),
+            Occluder::new(Segment::from_coords(0.0, 1.0, 1.0, 1.0)),
+        ];
+
+        Transform2::from_translation(Vec2::new(0.0, 5.0)).apply_to_group(&mut occluders, door);
+
+        assert_eq!(occluders[0].segment.points(), [Vec2::new(0.0, 5.0), Vec2::new(1.0, 5.0)]);
+        assert_eq!(occluders[1].segment.points(), [Vec2::new(0.0, 1.0), Vec2::new(1.0, 1.0)]);
+    }
+}
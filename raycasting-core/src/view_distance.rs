@@ -0,0 +1,112 @@
+use std::f32::consts::TAU;
+use glam::*;
+use crate::raycasting::intersection_parameter;
+
+/// A view distance sampled at evenly spread directions, like a row of a falloff texture. The first sample
+/// looks along the positive x axis, the following ones counterclockwise. Between samples, the distance is
+/// interpolated linearly.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ViewDistanceProfile {
+    distances: Vec<f32>,
+}
+
+impl ViewDistanceProfile {
+    pub fn new(distances: Vec<f32>) -> Self {
+        ViewDistanceProfile { distances }
+    }
+
+    /// Return the view distance in the direction of the given angle (radians). An empty profile does not limit the view.
+    pub fn distance(&self, angle: f32) -> f32 {
+        let count = self.distances.len();
+
+        if count == 0 {
+            return f32::INFINITY;
+        }
+
+        let position = angle.rem_euclid(TAU) / TAU * count as f32;
+        let index = position.floor() as usize % count;
+        let fraction = position.fract();
+
+        self.distances[index] * (1.0 - fraction) + self.distances[(index + 1) % count] * fraction
+    }
+}
+
+/// Shorten the visibility polygon around the origin (with points ordered by angle, like the result of
+/// [crate::raycasting::calculate_intersection_points]) to a view distance which depends on the direction, given
+/// as a function of the angle (radians, counterclockwise from the positive x axis). This way, fog or darkness
+/// can shorten the sight lines in some directions only.
+///
+/// The edges of the polygon are split into steps of at most max_step radians, and every step ends at the
+/// polygon or at the view distance, whichever is nearer. Smaller steps follow the view distance more closely.
+pub fn limit_view_distance(
+    origin: Vec2,
+    points: &[Vec2],
+    view_distance: impl Fn(f32) -> f32,
+    max_step: f32,
+) -> Vec<Vec2> {
+    let limit = |direction: Vec2, distance: f32| origin + direction * distance.min(view_distance(direction.y.atan2(direction.x)));
+    let mut limited = Vec::new();
+
+    for i in 0..points.len() {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+        let (to_a, to_b) = (a - origin, b - origin);
+        let angle = to_a.angle_to(to_b);
+
+        if to_a == Vec2::ZERO || to_b == Vec2::ZERO || !angle.is_finite() || angle == 0.0 {
+            limited.push(limit(to_a.normalize_or_zero(), to_a.length()));
+            continue;
+        }
+
+        let steps = (angle.abs() / max_step).ceil().max(1.0) as usize;
+
+        for step in 0..steps {
+            let direction = Vec2::from_angle(angle * step as f32 / steps as f32).rotate(to_a).normalize();
+            let distance = if step == 0 { to_a.length() } else { intersection_parameter(origin, direction, a, b - a) };
+            limited.push(limit(direction, distance));
+        }
+    }
+
+    limited
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+    use glam::*;
+    use crate::config::RaycastConfig;
+    use crate::pathological::room;
+    use crate::raycasting::calculate_intersection_points;
+    use crate::view_distance::{limit_view_distance, ViewDistanceProfile};
+
+    #[test]
+    fn view_distance_limits_the_polygon() {
+        let points = calculate_intersection_points(Vec2::ZERO, &room(100.0), &RaycastConfig::default());
+
+        let unlimited = limit_view_distance(Vec2::ZERO, &points, |_| f32::INFINITY, 0.1);
+        assert!(unlimited.iter().all(|point| point.x.abs().max(point.y.abs()) > 99.99), "{unlimited:?}");
+        assert!(unlimited.len() > points.len());
+
+        let circle = limit_view_distance(Vec2::ZERO, &points, |_| 50.0, 0.1);
+        assert!(circle.iter().all(|point| (point.length() - 50.0).abs() < 0.001));
+
+        // fog only towards the positive x axis
+        let foggy = limit_view_distance(Vec2::ZERO, &points, |angle| if angle.abs() < PI / 4.0 { 10.0 } else { f32::INFINITY }, 0.1);
+        assert!(foggy.iter().filter(|point| point.x > 0.0 && point.y.abs() < point.x * 0.9).all(|point| point.length() <= 10.001));
+        assert!(foggy.iter().any(|point| point.x < -99.99));
+    }
+
+    #[test]
+    fn profile_interpolates_between_samples() {
+        let profile = ViewDistanceProfile::new(vec![10.0, 20.0, 30.0, 40.0]);
+
+        [
+            (0.0, 10.0),
+            (PI / 4.0, 15.0),
+            (PI, 30.0),
+            (-PI / 4.0, 25.0),
+            (2.0 * PI, 10.0),
+        ].into_iter().for_each(|(angle, expected)| assert!((profile.distance(angle) - expected).abs() < 0.001, "{angle}"));
+
+        assert_eq!(ViewDistanceProfile::new(vec![]).distance(1.0), f32::INFINITY);
+    }
+}
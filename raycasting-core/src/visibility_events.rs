@@ -0,0 +1,168 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+use glam::*;
+use crate::config::{RaycastConfig, SymmetryPolicy};
+use crate::occluder::Occluder;
+use crate::raycasting::{calculate_intersection_points, Triangle};
+
+/// Tracks which things (occluders, entities, ...) were visible in the last frame and reports which of them
+/// became visible or hidden since.
+///
+/// The sets are reused between updates, so tracking does not allocate once they reached their size.
+#[derive(Clone, Debug)]
+pub struct VisibilityTracker<T> {
+    visible: HashSet<T>,
+    previous: HashSet<T>,
+}
+
+impl<T> Default for VisibilityTracker<T> {
+    fn default() -> Self {
+        VisibilityTracker {
+            visible: HashSet::new(),
+            previous: HashSet::new(),
+        }
+    }
+}
+
+/// The changes reported by [VisibilityTracker::update]
+#[derive(Clone, Debug, PartialEq)]
+pub struct VisibilityChanges<T> {
+    pub became_visible: Vec<T>,
+    pub became_hidden: Vec<T>,
+}
+
+impl<T> VisibilityChanges<T> {
+    pub fn is_empty(&self) -> bool {
+        self.became_visible.is_empty() && self.became_hidden.is_empty()
+    }
+}
+
+impl<T: Copy + Eq + Hash> VisibilityTracker<T> {
+    /// Replace the visible things with the given ones and return what changed. Duplicates are ignored.
+    pub fn update(&mut self, visible: impl IntoIterator<Item=T>) -> VisibilityChanges<T> {
+        std::mem::swap(&mut self.visible, &mut self.previous);
+        self.visible.clear();
+        self.visible.extend(visible);
+
+        VisibilityChanges {
+            became_visible: self.visible.difference(&self.previous).copied().collect(),
+            became_hidden: self.previous.difference(&self.visible).copied().collect(),
+        }
+    }
+
+    pub fn is_visible(&self, thing: &T) -> bool {
+        self.visible.contains(thing)
+    }
+}
+
+/// Return true if the target lies inside the visibility polygon around the origin, given by its
+/// points ordered by angle (like the result of [crate::raycasting::calculate_intersection_points]).
+pub fn polygon_contains(
+    origin: Vec2,
+    points: &[Vec2],
+    target: Vec2,
+) -> bool {
+    (0..points.len()).any(|i| Triangle::new(origin, points[i], points[(i + 1) % points.len()]).contains(target))
+}
+
+/// Return true if the target can be seen from the origin, which means it lies inside the visibility polygon of the
+/// origin. With [SymmetryPolicy::Symmetric], the origin must lie inside the visibility polygon of the target as well.
+pub fn can_see(
+    origin: Vec2,
+    target: Vec2,
+    occluders: &[Occluder],
+    config: &RaycastConfig,
+) -> bool {
+    let sees = |from: Vec2, to: Vec2| polygon_contains(from, &calculate_intersection_points(from, occluders, config), to);
+
+    match config.symmetry_policy {
+        SymmetryPolicy::Asymmetric => sees(origin, target),
+        SymmetryPolicy::Symmetric => sees(origin, target) && sees(target, origin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use glam::*;
+    use crate::config::{PeekPolicy, RaycastConfig, SymmetryPolicy};
+    use crate::occluder::OccluderId;
+    use crate::pathological::pathological_scenes;
+    use crate::raycasting::{calculate_intersection_points, visible_occluders};
+    use crate::scene::Scene;
+    use crate::visibility_events::{can_see, polygon_contains, VisibilityTracker};
+
+    #[test]
+    fn tracker_reports_changes() {
+        let mut tracker = VisibilityTracker::default();
+
+        let changes = tracker.update([1, 2, 2]);
+        assert_eq!(changes.became_visible.len(), 2);
+        assert!(changes.became_hidden.is_empty());
+
+        let changes = tracker.update([2, 3]);
+        assert_eq!(changes.became_visible, vec![3]);
+        assert_eq!(changes.became_hidden, vec![1]);
+        assert!(tracker.is_visible(&3));
+
+        assert!(tracker.update([2, 3]).is_empty());
+    }
+
+    #[test]
+    fn occluders_entering_and_leaving_view_are_reported() {
+        let scene = pathological_scenes().into_iter().find(|scene| scene.name == "t_junction").unwrap();
+        let config = RaycastConfig::default();
+        let mut tracker = VisibilityTracker::default();
+        let visible_ids = |origin: Vec2| visible_occluders(origin, &scene.occluders, &config)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+
+        tracker.update(visible_ids(Vec2::new(-30.0, 0.0)));
+        // moving above the horizontal wall
+        let changes = tracker.update(visible_ids(Vec2::new(-30.0, 50.0)));
+
+        assert!(changes.became_visible.contains(&OccluderId::new(4)));
+        assert!(changes.became_hidden.contains(&OccluderId::new(2)));
+        assert!(!changes.became_hidden.contains(&OccluderId::new(0)));
+    }
+
+    #[test]
+    fn polygon_contains_works() {
+        let scene = pathological_scenes().into_iter().find(|scene| scene.name == "t_junction").unwrap();
+        let points = calculate_intersection_points(scene.origin, &scene.occluders, &RaycastConfig::default());
+
+        [
+            (Vec2::new(-10.0, 0.0), true),
+            (Vec2::new(10.0, 0.0), false),
+            (Vec2::new(10.0, -50.0), true),
+            (Vec2::new(0.0, 50.0), false),
+        ].into_iter().for_each(|(target, expected)| assert_eq!(polygon_contains(scene.origin, &points, target), expected, "{target}"))
+    }
+
+    #[test]
+    fn symmetric_visibility_works_both_ways() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures").join("scenes").join("pillars.map");
+        let scene = Scene::parse(&fs::read_to_string(path).unwrap()).unwrap();
+        let occluders = scene.occluders(32.0);
+        let points = (1..scene.height())
+            .step_by(3)
+            .flat_map(|y| (1..scene.width()).step_by(3).map(move |x| (x, y)))
+            .filter(|(x, y)| !scene.is_wall(*x, *y))
+            .map(|(x, y)| Vec2::new(x as f32 + 0.5, y as f32 + 0.5) * 32.0)
+            .collect::<Vec<_>>();
+
+        for config in [RaycastConfig::default(), RaycastConfig::default().with_peek_policy(PeekPolicy::Peek)] {
+            let symmetric = config.clone().with_symmetry_policy(SymmetryPolicy::Symmetric);
+
+            for a in &points {
+                for b in &points {
+                    let visible = can_see(*a, *b, &occluders, &symmetric);
+                    assert_eq!(visible, can_see(*b, *a, &occluders, &symmetric), "{a} and {b}");
+                    assert_eq!(visible, can_see(*a, *b, &occluders, &config) && can_see(*b, *a, &occluders, &config));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use glam::*;
+use crate::occluder_set::OccluderSet;
+
+/// A graph of mutually visible points around the corners of some occluders, used to find shortest
+/// any-angle paths between two points.
+///
+/// The nodes are the convex corners of the occluders, pushed outwards by a clearance. Without it,
+/// every path would slide along the walls and every line between two corners of the same wall would count as visible.
+pub struct VisibilityGraph {
+    nodes: Vec<Vec2>,
+    /// The visible neighbours of every node and their distance
+    edges: Vec<Vec<(usize, f32)>>,
+    /// The revision of the occluders the graph was built from
+    revision: u64,
+}
+
+impl VisibilityGraph {
+    pub fn new(
+        occluders: &OccluderSet,
+        clearance: f32,
+    ) -> Self {
+        let nodes = corner_nodes(occluders, clearance);
+        let mut edges = vec![Vec::new(); nodes.len()];
+
+        for i in 0..nodes.len() {
+            for j in (i + 1)..nodes.len() {
+                if occluders.has_line_of_sight(nodes[i], nodes[j]) {
+                    let distance = nodes[i].distance(nodes[j]);
+                    edges[i].push((j, distance));
+                    edges[j].push((i, distance));
+                }
+            }
+        }
+
+        VisibilityGraph {
+            nodes,
+            edges,
+            revision: occluders.revision(),
+        }
+    }
+
+    pub fn nodes(&self) -> &[Vec2] {
+        &self.nodes
+    }
+
+    /// Return true if the graph was built from the current state of the given occluders, so it does not need to be rebuilt.
+    pub fn is_valid_for(&self, occluders: &OccluderSet) -> bool {
+        self.revision == occluders.revision()
+    }
+
+    /// Return the shortest path from start to goal (both included) which does not touch any occluder,
+    /// or None if the goal cannot be reached.
+    ///
+    /// The occluders must be the ones the graph was built from, see [VisibilityGraph::is_valid_for].
+    pub fn find_path(
+        &self,
+        occluders: &OccluderSet,
+        start: Vec2,
+        goal: Vec2,
+    ) -> Option<Vec<Vec2>> {
+        if occluders.has_line_of_sight(start, goal) {
+            return Some(vec![start, goal]);
+        }
+
+        // start and goal are the two nodes after the corners
+        let start_node = self.nodes.len();
+        let goal_node = start_node + 1;
+        let position = |node: usize| match node {
+            n if n == start_node => start,
+            n if n == goal_node => goal,
+            n => self.nodes[n]
+        };
+
+        let visible_from = |point: Vec2| self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| occluders.has_line_of_sight(point, **node))
+            .map(|(i, node)| (i, point.distance(*node)))
+            .collect::<Vec<_>>();
+        let from_start = visible_from(start);
+        let to_goal = visible_from(goal)
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+
+        let mut distances = vec![f32::INFINITY; self.nodes.len() + 2];
+        let mut previous = vec![None; self.nodes.len() + 2];
+        let mut open = BinaryHeap::new();
+
+        distances[start_node] = 0.0;
+        open.push(OpenNode { node: start_node, estimate: start.distance(goal) });
+
+        while let Some(OpenNode { node, estimate }) = open.pop() {
+            if node == goal_node {
+                break;
+            }
+
+            // skip outdated entries
+            if estimate > distances[node] + position(node).distance(goal) {
+                continue;
+            }
+
+            let neighbours = match node {
+                n if n == start_node => from_start.clone(),
+                n => {
+                    let mut neighbours = self.edges[n].clone();
+                    if let Some(distance) = to_goal.get(&n) {
+                        neighbours.push((goal_node, *distance));
+                    }
+                    neighbours
+                }
+            };
+
+            for (neighbour, distance) in neighbours {
+                let new_distance = distances[node] + distance;
+
+                if new_distance < distances[neighbour] {
+                    distances[neighbour] = new_distance;
+                    previous[neighbour] = Some(node);
+                    open.push(OpenNode {
+                        node: neighbour,
+                        estimate: new_distance + position(neighbour).distance(goal),
+                    });
+                }
+            }
+        }
+
+        previous[goal_node]?;
+
+        let mut path = vec![goal];
+        let mut node = goal_node;
+
+        while let Some(prev) = previous[node] {
+            path.push(position(prev));
+            node = prev;
+        }
+
+        path.reverse();
+        Some(path)
+    }
+}
+
+/// Remove every waypoint of the path whose neighbours can see each other (string pulling).
+///
+/// Useful for paths from a grid or navmesh search, which zigzag along the cells. The first and last point are always kept.
+pub fn smooth_path(
+    path: &[Vec2],
+    occluders: &OccluderSet,
+) -> Vec<Vec2> {
+    let (first, last) = match (path.first(), path.last()) {
+        (Some(first), Some(last)) if path.len() > 2 => (*first, *last),
+        _ => return path.to_vec()
+    };
+
+    let mut smoothed = vec![first];
+    let mut anchor = first;
+
+    for window in path.windows(2).skip(1) {
+        let (waypoint, next) = (window[0], window[1]);
+
+        if !occluders.has_line_of_sight(anchor, next) {
+            smoothed.push(waypoint);
+            anchor = waypoint;
+        }
+    }
+
+    smoothed.push(last);
+    smoothed
+}
+
+/// A node in the open list of the A* search, ordered so the smallest estimate is popped first
+#[derive(Copy, Clone, PartialEq)]
+struct OpenNode {
+    node: usize,
+    estimate: f32,
+}
+
+impl Eq for OpenNode {}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+/// Return the convex corners of the occluders, pushed outwards by the clearance.
+///
+/// A corner is pushed away from the sum of the directions of all occluders starting or ending in it.
+/// Points where these directions cancel out (like the shared corner of two diagonal tiles) and points
+/// which end up closer to another occluder than half the clearance are dropped.
+fn corner_nodes(
+    occluders: &OccluderSet,
+    clearance: f32,
+) -> Vec<Vec2> {
+    let mut corners: Vec<(Vec2, Vec2)> = Vec::new();
+
+    for occluder in occluders.ids().filter_map(|id| occluders.get(id)) {
+        let [a, b] = occluder.segment.points();
+        let direction = match (b - a).try_normalize() {
+            Some(direction) => direction,
+            None => continue
+        };
+
+        for (point, outgoing) in [(a, direction), (b, -direction)] {
+            match corners.iter_mut().find(|(corner, _)| *corner == point) {
+                Some((_, sum)) => *sum += outgoing,
+                None => corners.push((point, outgoing))
+            }
+        }
+    }
+
+    corners
+        .into_iter()
+        .filter_map(|(corner, sum)| {
+            let outwards = (-sum).try_normalize()?;
+            (sum.length() > 0.001).then_some(corner + outwards * clearance)
+        })
+        .filter(|node| occluders
+            .closest_point(*node)
+            .map(|closest| closest.distance >= clearance / 2.0)
+            .unwrap_or(true)
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+    use crate::occluder::Occluder;
+    use crate::occluder_set::OccluderSet;
+    use crate::raycasting::Segment;
+    use crate::scene::Scene;
+    use crate::visibility_graph::{smooth_path, VisibilityGraph};
+
+    fn scene_set(source: &str) -> OccluderSet {
+        OccluderSet::new(Scene::parse(source).unwrap().occluders(10.0), 10.0)
+    }
+
+    #[test]
+    fn removed_occluders_have_no_corners() {
+        let mut set = scene_set("#######\n#.....#\n#.###.#\n#.....#\n#######\n");
+        let expected = VisibilityGraph::new(&set, 1.0).nodes().to_vec();
+
+        // a wall starting at a corner of the block changes the corner and adds one at its other end
+        let wall = set.push(Occluder::new(Segment::from_coords(20.0, 30.0, 15.0, 35.0)));
+        assert_ne!(VisibilityGraph::new(&set, 1.0).nodes(), expected.as_slice());
+
+        set.remove(wall);
+        assert_eq!(VisibilityGraph::new(&set, 1.0).nodes(), expected.as_slice());
+    }
+
+    #[test]
+    fn find_path_goes_around_walls() {
+        let set = scene_set("#######\n#.....#\n#.###.#\n#.....#\n#######\n");
+        let graph = VisibilityGraph::new(&set, 1.0);
+        let (start, goal) = (Vec2::new(15.0, 25.0), Vec2::new(55.0, 25.0));
+
+        let path = graph.find_path(&set, start, goal).unwrap();
+
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+        assert!(path.windows(2).all(|pair| set.has_line_of_sight(pair[0], pair[1])));
+
+        // around the upper or lower corners of the wall, which are pushed out diagonally by the clearance
+        let corner = Vec2::new(20.0, 30.0) + Vec2::new(-1.0, 1.0).normalize();
+        let expected_length = 2.0 * start.distance(corner) + 30.0 + 2.0 * 0.5_f32.sqrt();
+        let length = path.windows(2).map(|pair| pair[0].distance(pair[1])).sum::<f32>();
+        assert!((length - expected_length).abs() < 0.001, "{length} != {expected_length}");
+    }
+
+    #[test]
+    fn find_path_works_for_direct_sight_and_unreachable_goals() {
+        let set = scene_set("#####\n#.#.#\n#####\n");
+        let graph = VisibilityGraph::new(&set, 1.0);
+
+        assert_eq!(graph.find_path(&set, Vec2::new(15.0, 12.0), Vec2::new(15.0, 18.0)), Some(vec![Vec2::new(15.0, 12.0), Vec2::new(15.0, 18.0)]));
+        assert_eq!(graph.find_path(&set, Vec2::new(15.0, 15.0), Vec2::new(35.0, 15.0)), None);
+    }
+
+    #[test]
+    fn smooth_path_works() {
+        let set = scene_set("#######\n#.....#\n#.###.#\n#.....#\n#######\n");
+        // a path along the cell centers, from the left of the wall over the top to its right
+        let path = [(15.0, 25.0), (15.0, 35.0), (25.0, 35.0), (35.0, 35.0), (45.0, 35.0), (55.0, 35.0), (55.0, 25.0)]
+            .map(|(x, y)| Vec2::new(x, y));
+
+        assert_eq!(
+            smooth_path(&path, &set),
+            vec![Vec2::new(15.0, 25.0), Vec2::new(15.0, 35.0), Vec2::new(55.0, 35.0), Vec2::new(55.0, 25.0)]
+        );
+        assert_eq!(smooth_path(&path[..2], &set), path[..2].to_vec());
+        assert_eq!(smooth_path(&path[1..6], &set), vec![Vec2::new(15.0, 35.0), Vec2::new(55.0, 35.0)]);
+    }
+}
@@ -0,0 +1,547 @@
+use std::f32::consts::{PI, TAU};
+use glam::*;
+use crate::aabb::Aabb;
+use crate::config::RaycastConfig;
+use crate::occluder::Occluder;
+use crate::predicates::cross_sign;
+use crate::raycasting::{intersection_parameter, polygon_points, Segment};
+use crate::visibility_events::polygon_contains;
+
+/// A numerical problem of a [VisibilityPolygon], see [VisibilityPolygon::defects].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PolygonDefect {
+    /// The point is NaN or infinite
+    NonFinite { index: usize, point: Vec2 },
+    /// The triangle between the origin, the point and the next one is a sliver: not exactly flat like the edge of
+    /// a shadow, but thinner than the tolerance. Such triangles usually come from rounding errors and flicker.
+    Sliver { index: usize, height: f32 },
+    /// The point lies clockwise of the previous one, so the polygon folds back onto itself
+    AngleReversal { index: usize },
+}
+
+/// How much of a box lies inside a [VisibilityPolygon], see [VisibilityPolygon::intersects_aabb].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Overlap {
+    /// The box lies completely outside the polygon, so whatever it bounds is in darkness
+    None,
+    /// The border of the polygon passes through the box
+    Partial,
+    /// The box lies completely inside the polygon
+    Full,
+}
+
+/// A computed visibility polygon, which can be queried directly instead of intersecting the scene again,
+/// like to clamp the tracer of a projectile to the lit area.
+///
+/// Every point of the polygon can be seen from the origin, so every ray from the origin leaves it exactly once.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub struct VisibilityPolygon {
+    origin: Vec2,
+    points: Vec<Vec2>,
+}
+
+impl VisibilityPolygon {
+    /// Create a polygon from the origin and its points ordered by angle, like the result of [crate::raycasting::calculate_intersection_points].
+    pub fn new(
+        origin: Vec2,
+        points: Vec<Vec2>,
+    ) -> Self {
+        VisibilityPolygon { origin, points }
+    }
+
+    /// Compute the visibility polygon of the origin.
+    pub fn compute(
+        origin: Vec2,
+        occluders: &[Occluder],
+        config: &RaycastConfig,
+    ) -> Self {
+        VisibilityPolygon::new(origin, polygon_points(origin, occluders, config))
+    }
+
+    pub fn origin(&self) -> Vec2 {
+        self.origin
+    }
+
+    pub fn points(&self) -> &[Vec2] {
+        &self.points
+    }
+
+    /// Return true if the point lies inside the polygon or on its border.
+    pub fn contains(&self, point: Vec2) -> bool {
+        polygon_contains(self.origin, &self.points, point)
+    }
+
+    /// Return how much of the box lies inside the polygon, like to skip rendering and updating entities in
+    /// darkness, or to shade partially lit ones differently. Boxes which only touch the border count as full or none.
+    ///
+    /// Boxes beside the bounds of the polygon are rejected first, by testing the axes of the box (separating axis
+    /// test). Otherwise, the box is partial if an edge of the polygon passes through its inside. If none does, the
+    /// box is either completely inside or completely outside, which its center decides.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> Overlap {
+        let Some(bounds) = Aabb::from_points(self.points.iter().copied()) else {
+            return Overlap::None;
+        };
+
+        if !bounds.intersects(aabb) {
+            return Overlap::None;
+        }
+
+        // the middle of a part of an edge inside the box lies on the border of the box only if the whole part does
+        let crosses_inside = |(a, b): (Vec2, Vec2)| {
+            let middle = (a + b) / 2.0;
+            middle.cmpgt(aabb.min).all() && middle.cmplt(aabb.max).all()
+        };
+
+        if self.edges().filter_map(|edge| aabb.clip_segment(edge.a(), edge.b())).any(crosses_inside) {
+            return Overlap::Partial;
+        }
+
+        match self.contains(aabb.center()) {
+            true => Overlap::Full,
+            false => Overlap::None
+        }
+    }
+
+    /// Return the edges of the polygon, from every point to the next one.
+    pub fn edges(&self) -> impl Iterator<Item=Segment> + '_ {
+        (0..self.points.len()).map(|i| Segment::new(self.points[i], self.points[(i + 1) % self.points.len()]))
+    }
+
+    /// Split the polygon into convex polygons, each given by its points in counterclockwise order, which together
+    /// cover the polygon without overlapping. Physics engines usually only handle convex shapes, so this is
+    /// the base of colliders.
+    ///
+    /// The polygon is a fan of triangles around the origin. Neighbouring triangles are merged as long as the
+    /// merged polygon stays convex, which keeps the number of pieces low for open areas.
+    pub fn convex_pieces(&self) -> Vec<Vec<Vec2>> {
+        let origin = self.origin;
+        let count = self.points.len();
+        let point = |i: usize| self.points[i % count];
+        let mut pieces = Vec::new();
+        let mut start = 0;
+
+        if count < 2 {
+            return pieces;
+        }
+
+        while start < count {
+            let mut end = start + 1;
+
+            while end < count {
+                let (previous, current, next) = (point(end - 1), point(end), point(end + 1));
+                let turns_left = (current - previous).perp_dot(next - current) >= 0.0;
+                let within_half_turn = (point(start) - origin).perp_dot(next - origin) >= 0.0;
+
+                if !turns_left || !within_half_turn {
+                    break;
+                }
+
+                end += 1;
+            }
+
+            let piece = [origin].into_iter().chain((start..=end).map(point)).collect::<Vec<_>>();
+            let area = (0..piece.len()).map(|i| piece[i].perp_dot(piece[(i + 1) % piece.len()])).sum::<f32>();
+
+            if area > 0.0 {
+                pieces.push(piece);
+            }
+
+            start = end;
+        }
+
+        pieces
+    }
+
+    /// Return every numerical problem of the polygon, like NaN points or slivers thinner than the tolerance.
+    /// A polygon computed from sane input should have none, so these point at bugs or precision problems.
+    ///
+    /// A step of more than half a turn between two points counts as an angle reversal, which only happens in
+    /// polygons with very few points.
+    pub fn defects(
+        &self,
+        tolerance: f32,
+    ) -> Vec<PolygonDefect> {
+        let (origin, count) = (self.origin, self.points.len());
+        let mut defects = self.points
+            .iter()
+            .enumerate()
+            .filter(|(_, point)| !point.is_finite())
+            .map(|(index, point)| PolygonDefect::NonFinite { index, point: *point })
+            .collect::<Vec<_>>();
+
+        if count < 2 || !defects.is_empty() {
+            return defects;
+        }
+
+        for index in 0..count {
+            let (point, next) = (self.points[index], self.points[(index + 1) % count]);
+            let (previous, turn) = (self.points[(index + count - 1) % count], cross_sign(origin, point, origin, next));
+
+            if cross_sign(origin, previous, origin, point) < 0 && (previous - origin).dot(point - origin) > 0.0 {
+                defects.push(PolygonDefect::AngleReversal { index });
+            }
+
+            // the height of the triangle over its longest side
+            let longest = [origin.distance(point), point.distance(next), next.distance(origin)].into_iter().fold(0.0, f32::max);
+            let height = (point - origin).perp_dot(next - origin).abs() / longest;
+
+            if turn != 0 && point != next && height < tolerance {
+                defects.push(PolygonDefect::Sliver { index, height });
+            }
+        }
+
+        defects
+    }
+
+    /// Return the border of the polygon sampled at count uniform angles, starting at half a turn and going
+    /// counterclockwise like the points of the polygon (see [VisibilityPolygon::raycast]). Directions in which the
+    /// polygon has no border, like the open sectors of a polygon whose misses were dropped, are sampled at the origin,
+    /// so the result always has exactly count points.
+    pub fn resample(
+        &self,
+        count: usize,
+    ) -> Vec<Vec2> {
+        (0..count)
+            .map(|i| -PI + TAU * i as f32 / count as f32)
+            .map(|angle| self.origin + Vec2::from_angle(angle) * self.raycast(angle).unwrap_or(0.0))
+            .collect()
+    }
+
+    /// Interpolate between two polygons, like the ones of two successive frames: alpha 0 returns the previous one,
+    /// alpha 1 the next one. Renderers can use this to smooth jitter or to animate revealing an area gradually.
+    ///
+    /// Polygons with the same number of points, like ones with a fixed resolution
+    /// (see [crate::config::PolygonFormat::resolution]), are interpolated point by point. Otherwise, both are
+    /// resampled (see [VisibilityPolygon::resample]) at the larger number of points first, so points at the same
+    /// angle correspond.
+    pub fn lerp(
+        prev: &VisibilityPolygon,
+        next: &VisibilityPolygon,
+        alpha: f32,
+    ) -> VisibilityPolygon {
+        let (prev_points, next_points) = match prev.points.len() == next.points.len() {
+            true => (prev.points.clone(), next.points.clone()),
+            false => {
+                let count = prev.points.len().max(next.points.len());
+                (prev.resample(count), next.resample(count))
+            }
+        };
+
+        VisibilityPolygon::new(
+            prev.origin.lerp(next.origin, alpha),
+            prev_points.into_iter().zip(next_points).map(|(prev, next)| prev.lerp(next, alpha)).collect(),
+        )
+    }
+
+    /// Return the distance from the origin to the border of the polygon in the direction of the given angle
+    /// (radians, counterclockwise from the x axis), or None if the polygon is empty. Along the edge of a shadow,
+    /// which points straight away from the origin, this is the distance to its far end.
+    pub fn raycast(&self, angle: f32) -> Option<f32> {
+        let origin = self.origin;
+        let direction = Vec2::from_angle(angle);
+
+        self.edges()
+            .flat_map(|edge| {
+                let [p, q] = edge.points();
+                let (side_p, side_q) = (cross_sign(Vec2::ZERO, direction, origin, p), cross_sign(Vec2::ZERO, direction, origin, q));
+
+                if side_p == 0 && side_q == 0 {
+                    return vec![(p - origin).dot(direction), (q - origin).dot(direction)];
+                }
+
+                if side_p * side_q <= 0 {
+                    return vec![intersection_parameter(origin, direction, p, q - p)];
+                }
+
+                vec![]
+            })
+            .filter(|distance| *distance >= 0.0)
+            .max_by(f32::total_cmp)
+    }
+
+    /// Return the parts of the segment inside the polygon, ordered from its start to its end.
+    pub fn clip_segment(&self, segment: Segment) -> Vec<Segment> {
+        let [a, b] = segment.points();
+        let mut parameters = self
+            .edges()
+            .filter(|edge| {
+                let [p, q] = edge.points();
+                let (side_p, side_q) = (cross_sign(a, b, a, p), cross_sign(a, b, a, q));
+                let (side_a, side_b) = (cross_sign(p, q, p, a), cross_sign(p, q, p, b));
+
+                !(side_p == 0 && side_q == 0) && side_p * side_q <= 0 && side_a * side_b <= 0
+            })
+            .map(|edge| intersection_parameter(a, b - a, edge.a(), edge.b() - edge.a()).clamp(0.0, 1.0))
+            .chain([0.0, 1.0])
+            .collect::<Vec<_>>();
+        parameters.sort_by(f32::total_cmp);
+        parameters.dedup();
+
+        let mut parts: Vec<(f32, f32)> = Vec::new();
+
+        for pair in parameters.windows(2) {
+            if !self.contains(a + (b - a) * (pair[0] + pair[1]) / 2.0) {
+                continue;
+            }
+
+            match parts.last_mut() {
+                Some(last) if last.1 == pair[0] => last.1 = pair[1],
+                _ => parts.push((pair[0], pair[1]))
+            }
+        }
+
+        parts
+            .into_iter()
+            .map(|(start, end)| Segment::new(a + (b - a) * start, a + (b - a) * end))
+            .collect()
+    }
+}
+
+/// Return the fraction (0 to 1) of the box which lies inside any of the polygons.
+///
+/// Boxes which are completely inside or outside (see [VisibilityPolygon::intersects_aabb]) are decided
+/// exactly. Otherwise, the fraction is estimated from a grid of samples by samples points, tested against
+/// the polygons which pass through the box.
+pub fn lit_fraction(
+    aabb: &Aabb,
+    polygons: &[VisibilityPolygon],
+    samples: usize,
+) -> f32 {
+    let mut partial = Vec::new();
+
+    for polygon in polygons {
+        match polygon.intersects_aabb(aabb) {
+            Overlap::Full => return 1.0,
+            Overlap::Partial => partial.push(polygon),
+            Overlap::None => {}
+        }
+    }
+
+    if partial.is_empty() {
+        return 0.0;
+    }
+
+    let samples = samples.max(1);
+    let size = aabb.max - aabb.min;
+    let lit = (0..samples)
+        .flat_map(|y| (0..samples).map(move |x| Vec2::new(x as f32, y as f32)))
+        .map(|cell| aabb.min + size * (cell + 0.5) / samples as f32)
+        .filter(|point| partial.iter().any(|polygon| polygon.contains(*point)))
+        .count();
+
+    lit as f32 / (samples * samples) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+    use glam::*;
+    use crate::config::RaycastConfig;
+    use crate::occluder::Occluder;
+    use crate::raycasting::{find_nearest_intersection, Ray, Segment};
+    use crate::stats::RaycastStats;
+    use crate::aabb::Aabb;
+    use crate::visibility_polygon::{lit_fraction, Overlap, PolygonDefect, VisibilityPolygon};
+
+    /// A square room from -10 to 10, with a shadow behind a pillar on the right
+    fn room() -> VisibilityPolygon {
+        VisibilityPolygon::new(Vec2::ZERO, vec![
+            Vec2::new(-10.0, -10.0),
+            Vec2::new(10.0, -10.0),
+            Vec2::new(10.0, -2.0),
+            Vec2::new(5.0, -1.0),
+            Vec2::new(5.0, 1.0),
+            Vec2::new(10.0, 2.0),
+            Vec2::new(10.0, 10.0),
+            Vec2::new(-10.0, 10.0),
+        ])
+    }
+
+    #[test]
+    fn defects_are_found() {
+        let with_point = |index: usize, point: Vec2| {
+            let mut points = room().points().to_vec();
+            points[index] = point;
+            VisibilityPolygon::new(Vec2::ZERO, points)
+        };
+
+        [
+            (room(), vec![]),
+            (with_point(3, Vec2::new(f32::NAN, -1.0)), vec![PolygonDefect::NonFinite { index: 3, point: Vec2::new(f32::NAN, -1.0) }]),
+            // the shadow edge tilts a little, so the triangle along it is no longer flat
+            (with_point(3, Vec2::new(5.0, -0.9999)), vec![PolygonDefect::Sliver { index: 2, height: 0.0 }]),
+            (with_point(3, Vec2::new(5.0, -3.0)), vec![PolygonDefect::AngleReversal { index: 3 }]),
+        ].into_iter().for_each(|(polygon, expected)| {
+            let defects = polygon.defects(0.01);
+            let kinds = |defects: &[PolygonDefect]| defects.iter().map(|defect| match defect {
+                PolygonDefect::NonFinite { index, .. } => ("non finite", *index),
+                PolygonDefect::Sliver { index, .. } => ("sliver", *index),
+                PolygonDefect::AngleReversal { index } => ("angle reversal", *index)
+            }).collect::<Vec<_>>();
+
+            assert_eq!(kinds(&defects), kinds(&expected), "{:?}", polygon.points());
+        });
+    }
+
+    #[test]
+    fn overlap_of_boxes_is_found() {
+        let polygon = room();
+
+        [
+            (Aabb::new(Vec2::new(-2.0, -2.0), Vec2::new(2.0, 2.0)), Overlap::Full),
+            (Aabb::new(Vec2::new(20.0, 20.0), Vec2::new(25.0, 25.0)), Overlap::None),
+            // inside the bounds of the room, but in the shadow of the pillar
+            (Aabb::new(Vec2::new(6.0, -0.5), Vec2::new(8.0, 0.5)), Overlap::None),
+            (Aabb::new(Vec2::new(4.0, -0.5), Vec2::new(6.0, 0.5)), Overlap::Partial),
+            (Aabb::new(Vec2::new(8.0, 5.0), Vec2::new(12.0, 6.0)), Overlap::Partial),
+            (Aabb::new(Vec2::new(-20.0, -20.0), Vec2::new(20.0, 20.0)), Overlap::Partial),
+            // sharing the wall of the room with the polygon
+            (Aabb::new(Vec2::new(-10.0, 5.0), Vec2::new(-8.0, 6.0)), Overlap::Full),
+        ].into_iter().for_each(|(aabb, expected)| assert_eq!(polygon.intersects_aabb(&aabb), expected, "{aabb:?}"));
+
+        assert_eq!(VisibilityPolygon::default().intersects_aabb(&Aabb::new(Vec2::ZERO, Vec2::ONE)), Overlap::None);
+    }
+
+    #[test]
+    fn raycast_returns_the_distance_to_the_border() {
+        let polygon = room();
+
+        [
+            (0.0, Some(5.0)),
+            (FRAC_PI_2, Some(10.0)),
+            (FRAC_PI_4, Some(10.0 * 2.0f32.sqrt())),
+            (PI, Some(10.0)),
+            // just beside the shadow, which reaches the far wall
+            (0.2, Some(10.0 / 0.2f32.cos())),
+            (0.19, Some(5.0 / 0.19f32.cos())),
+        ].into_iter().for_each(|(angle, expected)| {
+            let distance = polygon.raycast(angle);
+            assert!((distance.unwrap() - expected.unwrap()).abs() < 0.001, "{angle}: {distance:?}");
+        });
+
+        assert_eq!(VisibilityPolygon::default().raycast(0.0), None);
+    }
+
+    #[test]
+    fn resampling_keeps_the_border_at_uniform_angles() {
+        let resampled = room().resample(8);
+        let expected = [(-10.0, 0.0), (-10.0, -10.0), (0.0, -10.0), (10.0, -10.0), (5.0, 0.0), (10.0, 10.0), (0.0, 10.0), (-10.0, 10.0)];
+
+        assert_eq!(resampled.len(), expected.len());
+        resampled.iter().zip(expected).for_each(|(point, (x, y))| assert!(point.distance(Vec2::new(x, y)) < 0.001, "{point} != ({x}, {y})"));
+        assert!(VisibilityPolygon::default().resample(4).iter().all(|point| *point == Vec2::ZERO));
+    }
+
+    #[test]
+    fn polygons_are_interpolated() {
+        let moved = VisibilityPolygon::new(Vec2::new(2.0, 0.0), room().points().iter().map(|point| *point + Vec2::new(2.0, 0.0)).collect());
+
+        assert_eq!(VisibilityPolygon::lerp(&room(), &moved, 0.0), room());
+        assert_eq!(VisibilityPolygon::lerp(&room(), &moved, 1.0), moved);
+        assert_eq!(VisibilityPolygon::lerp(&room(), &moved, 0.5).points()[0], Vec2::new(-9.0, -10.0));
+
+        // a square room without the pillar has fewer points, so both are resampled
+        let square = VisibilityPolygon::new(Vec2::ZERO, vec![Vec2::new(-10.0, -10.0), Vec2::new(10.0, -10.0), Vec2::new(10.0, 10.0), Vec2::new(-10.0, 10.0)]);
+        let halfway = VisibilityPolygon::lerp(&room(), &square, 0.5);
+
+        assert_eq!(halfway.points().len(), 8);
+        // the pillar is halfway gone
+        assert!((halfway.raycast(0.0).unwrap() - 7.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn clip_segment_keeps_the_parts_inside() {
+        let polygon = room();
+
+        [
+            (Segment::from_coords(-20.0, 0.0, 20.0, 0.0), vec![Segment::from_coords(-10.0, 0.0, 5.0, 0.0)]),
+            // through the shadow, which splits it
+            (Segment::from_coords(8.0, -5.0, 8.0, 5.0), vec![Segment::from_coords(8.0, -5.0, 8.0, -1.6), Segment::from_coords(8.0, 1.6, 8.0, 5.0)]),
+            (Segment::from_coords(-5.0, -5.0, 5.0, 5.0), vec![Segment::from_coords(-5.0, -5.0, 5.0, 5.0)]),
+            (Segment::from_coords(20.0, -20.0, 20.0, 20.0), vec![]),
+        ].into_iter().for_each(|(segment, expected)| {
+            let clipped = polygon.clip_segment(segment);
+            assert_eq!(clipped.len(), expected.len(), "{segment}: {clipped:?}");
+            clipped.iter().zip(&expected).for_each(|(clipped, expected)| assert!(clipped.approx_eq(expected, 0.001), "{segment}: {clipped}"));
+        });
+    }
+
+    #[test]
+    fn convex_pieces_cover_the_polygon() {
+        let area = |points: &[Vec2]| (0..points.len()).map(|i| points[i].perp_dot(points[(i + 1) % points.len()])).sum::<f32>() / 2.0;
+        let occluders = [
+            Segment::from_coords(-50.0, -50.0, 50.0, -50.0),
+            Segment::from_coords(50.0, -50.0, 50.0, 50.0),
+            Segment::from_coords(50.0, 50.0, -50.0, 50.0),
+            Segment::from_coords(-50.0, 50.0, -50.0, -50.0),
+            Segment::from_coords(10.0, -5.0, 15.0, 20.0),
+            Segment::from_coords(-20.0, 10.0, -10.0, 10.0),
+        ].into_iter().map(Occluder::new).collect::<Vec<_>>();
+
+        [
+            room(),
+            VisibilityPolygon::compute(Vec2::new(-3.0, 2.0), &occluders, &RaycastConfig::default()),
+        ].into_iter().for_each(|polygon| {
+            let pieces = polygon.convex_pieces();
+            let total = pieces.iter().map(|piece| area(piece)).sum::<f32>();
+
+            assert!((total - area(polygon.points())).abs() < 0.01, "{total} != {}", area(polygon.points()));
+            assert!(pieces.len() < polygon.points().len());
+            pieces.iter().for_each(|piece| assert!((0..piece.len()).all(|i| {
+                let (a, b, c) = (piece[i], piece[(i + 1) % piece.len()], piece[(i + 2) % piece.len()]);
+                (b - a).perp_dot(c - b) >= -0.0001
+            }), "{piece:?}"));
+        });
+
+        assert_eq!(room().convex_pieces().len(), 4);
+    }
+
+    #[test]
+    fn raycast_matches_the_scene() {
+        let occluders = [
+            Segment::from_coords(-50.0, -50.0, 50.0, -50.0),
+            Segment::from_coords(50.0, -50.0, 50.0, 50.0),
+            Segment::from_coords(50.0, 50.0, -50.0, 50.0),
+            Segment::from_coords(-50.0, 50.0, -50.0, -50.0),
+            Segment::from_coords(10.0, -5.0, 15.0, 20.0),
+        ].into_iter().map(Occluder::new).collect::<Vec<_>>();
+        let config = RaycastConfig::default();
+        let origin = Vec2::new(-3.0, 2.0);
+        let polygon = VisibilityPolygon::compute(origin, &occluders, &config);
+
+        (0..64).map(|i| i as f32 * PI / 32.0 + 0.05).for_each(|angle| {
+            let (hit, _) = find_nearest_intersection(Ray::new(origin, Vec2::from_angle(angle)), &occluders, &config, &mut RaycastStats::default()).unwrap();
+            let distance = polygon.raycast(angle).unwrap();
+            assert!((distance - hit.distance(origin)).abs() < 0.01, "{angle}: {distance} != {}", hit.distance(origin));
+        });
+    }
+
+    #[test]
+    fn lit_fraction_covers_the_union_of_the_polygons() {
+        // a square room from -10 to 10 with a shadow behind a pillar at x = 5, and the same room without it
+        let shadowed = VisibilityPolygon::new(Vec2::ZERO, vec![
+            Vec2::new(-10.0, -10.0),
+            Vec2::new(10.0, -10.0),
+            Vec2::new(10.0, -2.0),
+            Vec2::new(5.0, -1.0),
+            Vec2::new(5.0, 1.0),
+            Vec2::new(10.0, 2.0),
+            Vec2::new(10.0, 10.0),
+            Vec2::new(-10.0, 10.0),
+        ]);
+        let open = VisibilityPolygon::new(Vec2::ZERO, vec![Vec2::new(-10.0, -10.0), Vec2::new(10.0, -10.0), Vec2::new(10.0, 10.0), Vec2::new(-10.0, 10.0)]);
+        let half_shadowed = Aabb::new(Vec2::new(4.0, -0.5), Vec2::new(6.0, 0.5));
+
+        [
+            (half_shadowed, vec![shadowed.clone()], 0.5),
+            (half_shadowed, vec![shadowed.clone(), open], 1.0),
+            (half_shadowed, vec![], 0.0),
+            (Aabb::new(Vec2::new(-2.0, -2.0), Vec2::new(2.0, 2.0)), vec![shadowed.clone()], 1.0),
+            (Aabb::new(Vec2::new(6.0, -0.5), Vec2::new(8.0, 0.5)), vec![shadowed.clone()], 0.0),
+            (Aabb::new(Vec2::new(20.0, 20.0), Vec2::new(25.0, 25.0)), vec![shadowed], 0.0),
+        ].into_iter().for_each(|(aabb, polygons, expected)| {
+            assert_eq!(lit_fraction(&aabb, &polygons, 8), expected, "{aabb:?}");
+        });
+    }
+}
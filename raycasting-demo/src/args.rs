@@ -0,0 +1,57 @@
+use bevy::prelude::*;
+use raycasting_core::generators::MapGenerator;
+
+/// Command line arguments of the demo.
+#[derive(Default, Resource)]
+pub struct Args {
+    /// Path of a map file (relative to the assets folder) to load instead of generating a random map.
+    /// The map is reloaded whenever the file changes.
+    pub map: Option<String>,
+    /// Directory to write captures of the visibility overlay to. If given, the demo renders the captures
+    /// and exits instead of opening a window.
+    pub capture: Option<String>,
+    /// Number of frames to benchmark the line of sight for. If given, the origin follows a fixed path over
+    /// a fixed map instead of the cursor, and the demo prints the compute times and exits afterwards.
+    pub bench: Option<usize>,
+    /// Seed of the generated map, so a map can be generated again. Random if not given.
+    pub seed: Option<u64>,
+    /// Generator of the map, by its name, see [MapGenerator::name]. Random noise if not given.
+    pub generator: Option<MapGenerator>,
+    /// Don't log the diagnostics, like the compute time of the line of sight, to the console.
+    pub quiet: bool,
+}
+
+impl Args {
+    /// Parse the arguments of the process, or return why they are malformed. This happens before the app
+    /// and its log exist, so the caller has to report the error itself.
+    pub fn parse() -> Result<Self, String> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    fn parse_from(mut args: impl Iterator<Item=String>) -> Result<Self, String> {
+        let mut result = Args::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--map" => result.map = args.next(),
+                "--capture" => result.capture = args.next(),
+                "--bench" => match args.next().map(|frames| frames.parse()) {
+                    Some(Ok(frames)) => result.bench = Some(frames),
+                    _ => return Err("--bench expects a number of frames".to_string())
+                },
+                "--seed" => match args.next().map(|seed| seed.parse()) {
+                    Some(Ok(seed)) => result.seed = Some(seed),
+                    _ => return Err("--seed expects a number".to_string())
+                },
+                "--generator" => match args.next().and_then(|name| MapGenerator::from_name(&name)) {
+                    Some(generator) => result.generator = Some(generator),
+                    None => return Err(format!("--generator expects one of {:?}", MapGenerator::ALL.map(|generator| generator.name())))
+                },
+                "--quiet" => result.quiet = true,
+                _ => return Err(format!("unknown argument {arg}"))
+            }
+        }
+
+        Ok(result)
+    }
+}
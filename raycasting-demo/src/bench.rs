@@ -0,0 +1,99 @@
+use std::f32::consts::TAU;
+use bevy::app::AppExit;
+use bevy::diagnostic::DiagnosticsStore;
+use bevy::platform::time::Instant;
+use bevy::prelude::*;
+use raycasting_bevy::compat::{AppCompat, MessageWriter, WriteMessages};
+use raycasting_bevy::line_of_sight::LOS_COMPUTE_TIME;
+use crate::args::Args;
+use crate::map::{MapSize, Tile, TILE_SIZE};
+use crate::mouse_cursor::CursorCoordinates;
+
+/// The map every benchmark runs on, relative to the assets folder
+pub const BENCH_MAP: &str = "maps/example.map";
+
+/// Moves the origin of the line of sight along a fixed path for the number of frames given by [Args::bench],
+/// records how long each computation took and prints a report before exiting. Replaces the input plugins.
+pub struct BenchPlugin;
+
+impl Plugin for BenchPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<CursorCoordinates>()
+            .init_resource::<BenchRun>()
+            .update_systems((
+                move_origin,
+                record_compute_time
+            ))
+        ;
+    }
+}
+
+#[derive(Default, Resource)]
+struct BenchRun {
+    frame: usize,
+    /// Compute times in milliseconds
+    samples: Vec<f64>,
+    /// When the last recorded measurement was taken, so frames without a computation are not counted twice
+    last_measurement: Option<Instant>,
+}
+
+/// Move the origin along a closed loop over the map, which takes all frames of the benchmark.
+fn move_origin(
+    args: Res<Args>,
+    map_size: Res<MapSize>,
+    mut run: ResMut<BenchRun>,
+    mut cursor_position: ResMut<CursorCoordinates>,
+    tiles: Query<(), With<Tile>>,
+) {
+    let Some(frames) = args.bench else {
+        return;
+    };
+
+    // the map file is loaded in the background
+    if tiles.is_empty() {
+        return;
+    }
+
+    let t = run.frame as f32 / frames.max(1) as f32 * TAU;
+    let extent = Vec2::new(map_size.width as f32, map_size.height as f32) * TILE_SIZE;
+    **cursor_position = extent / 2.0 + Vec2::new((t * 2.0).sin(), (t * 3.0).sin()) * extent * 0.4;
+    run.frame += 1;
+}
+
+fn record_compute_time(
+    args: Res<Args>,
+    diagnostics: Res<DiagnosticsStore>,
+    mut run: ResMut<BenchRun>,
+    mut exit: MessageWriter<AppExit>,
+) {
+    let Some(frames) = args.bench else {
+        return;
+    };
+
+    if let Some(measurement) = diagnostics.get(&LOS_COMPUTE_TIME).and_then(|diagnostic| diagnostic.measurement()) {
+        if run.last_measurement != Some(measurement.time) {
+            run.last_measurement = Some(measurement.time);
+            run.samples.push(measurement.value);
+        }
+    }
+
+    if run.samples.len() < frames.max(1) {
+        return;
+    }
+
+    let mut samples = run.samples.clone();
+    samples.sort_by(f64::total_cmp);
+    let percentile = |p: f64| samples[((samples.len() - 1) as f64 * p).round() as usize];
+    let average = samples.iter().sum::<f64>() / samples.len() as f64;
+
+    println!(
+        "line of sight over {} frames: average {average:.3}ms, p50 {:.3}ms, p90 {:.3}ms, p99 {:.3}ms, max {:.3}ms",
+        samples.len(),
+        percentile(0.5),
+        percentile(0.9),
+        percentile(0.99),
+        samples[samples.len() - 1]
+    );
+    exit.emit(AppExit::Success);
+}
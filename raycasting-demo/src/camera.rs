@@ -0,0 +1,96 @@
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use raycasting_bevy::compat::{AppCompat, MessageReader, MouseButtons, ReadMessages};
+use crate::map::{MAP_HEIGHT, MAP_WIDTH};
+use crate::mouse_cursor::CursorCoordinates;
+
+/// How much one line of the scroll wheel zooms in or out.
+const ZOOM_STEP: f32 = 1.1;
+/// Pixels of a touchpad scroll which count as one line of the scroll wheel.
+const PIXELS_PER_LINE: f32 = 20.0;
+const MIN_SCALE: f32 = 0.1;
+const MAX_SCALE: f32 = 20.0;
+
+/// Spawns the camera of the demo, which is panned by dragging with the right mouse button
+/// and zoomed towards the cursor with the scroll wheel.
+pub(super) struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .startup_systems(spawn_camera)
+            .update_systems((
+                pan_camera,
+                zoom_camera
+            ))
+        ;
+    }
+}
+
+fn spawn_camera(
+    mut commands: Commands
+) {
+    commands.spawn((
+        Camera2d,
+        Projection::Orthographic(OrthographicProjection {
+            scale: 2.0,
+            ..OrthographicProjection::default_2d()
+        }),
+        Transform::from_translation(Vec3::new(
+            (MAP_WIDTH as f32 / 2.0) * 32.0,
+            (MAP_HEIGHT as f32 / 2.0) * 32.0,
+            1000.0
+        )),
+    ));
+}
+
+fn pan_camera(
+    buttons: Res<MouseButtons>,
+    mut motions: MessageReader<MouseMotion>,
+    mut cameras: Query<(&mut Transform, &Projection), With<Camera>>,
+) {
+    let delta = motions.messages().map(|motion| motion.delta).sum::<Vec2>();
+
+    if !buttons.pressed(MouseButton::Right) || delta == Vec2::ZERO {
+        return;
+    }
+
+    // the motion is in window pixels with y pointing down, so the map follows the cursor
+    for (mut transform, projection) in &mut cameras {
+        let Projection::Orthographic(projection) = projection else {
+            continue;
+        };
+        transform.translation += Vec3::new(-delta.x, delta.y, 0.0) * projection.scale;
+    }
+}
+
+fn zoom_camera(
+    mut wheels: MessageReader<MouseWheel>,
+    cursor_position: Res<CursorCoordinates>,
+    mut cameras: Query<(&mut Transform, &mut Projection), With<Camera>>,
+) {
+    let lines = wheels
+        .messages()
+        .map(|wheel| match wheel.unit {
+            MouseScrollUnit::Line => wheel.y,
+            MouseScrollUnit::Pixel => wheel.y / PIXELS_PER_LINE
+        })
+        .sum::<f32>();
+
+    if lines == 0.0 {
+        return;
+    }
+
+    for (mut transform, mut projection) in &mut cameras {
+        let Projection::Orthographic(projection) = projection.as_mut() else {
+            continue;
+        };
+        let scale = (projection.scale * ZOOM_STEP.powf(-lines)).clamp(MIN_SCALE, MAX_SCALE);
+        let center = transform.translation.truncate();
+
+        // keep the point under the cursor where it is
+        let shift = (**cursor_position - center) * (1.0 - scale / projection.scale);
+        transform.translation += shift.extend(0.0);
+        projection.scale = scale;
+    }
+}
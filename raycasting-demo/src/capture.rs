@@ -0,0 +1,99 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use bevy::prelude::*;
+use image::{Rgba, RgbaImage};
+use pad::{Position, p};
+use raycasting_core::config::RaycastConfig;
+use raycasting_core::raster::CoverageGrid;
+use raycasting_core::raycasting::raycast_with_config;
+use raycasting_core::scene::Scene;
+use crate::line_of_sight::build_occluders;
+use crate::map::{MapSize, scene_walls, TILE_SIZE, TileType};
+
+/// The scene every capture is made from
+const CAPTURE_MAP: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/maps/example.map");
+
+/// Tiles whose centers are used as origins of the captures. They must be floors of the capture map.
+const CAPTURE_TILES: [(usize, usize); 7] = [(2, 2), (15, 15), (27, 27), (6, 20), (20, 5), (11, 13), (24, 10)];
+
+/// Color of the visibility overlay, drawn on top of the tiles
+const OVERLAY_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.5];
+
+/// Render the visibility overlay of the capture map from every capture origin and write each result
+/// as PNG into the given directory. Every world unit becomes one pixel.
+///
+/// Rendering happens on the CPU, so the captures are deterministic and can be compared between runs.
+pub fn run_capture(dir: &Path) -> Result<(), Box<dyn Error>> {
+    let scene = Scene::parse(&fs::read_to_string(CAPTURE_MAP)?)?;
+    let map_size = MapSize {
+        width: scene.width(),
+        height: scene.height(),
+    };
+    let occluders = build_occluders(scene_walls(&scene), &map_size);
+
+    fs::create_dir_all(dir)?;
+
+    // tiles are centered on their position, so the map starts half a tile left of and below the origin
+    let width = (map_size.width as f32 * TILE_SIZE) as usize;
+    let height = (map_size.height as f32 * TILE_SIZE) as usize;
+    let mut coverage = CoverageGrid::new(Vec2::splat(-TILE_SIZE / 2.0), 1.0, width, height);
+
+    for (index, (x, y)) in CAPTURE_TILES.into_iter().enumerate() {
+        let origin = Vec2::new(x as f32, y as f32) * TILE_SIZE;
+        let triangles = raycast_with_config(origin, &occluders, &RaycastConfig::default());
+
+        coverage.clear();
+        coverage.rasterize_triangles(&triangles);
+
+        let path = dir.join(format!("capture_{index:03}.png"));
+        render(&scene, &coverage).save(&path)?;
+        info!("captured {}", path.display());
+    }
+
+    Ok(())
+}
+
+fn render(
+    scene: &Scene,
+    coverage: &CoverageGrid,
+) -> RgbaImage {
+    let height = coverage.height() as u32;
+
+    RgbaImage::from_fn(coverage.width() as u32, height, |column, row| {
+        // image rows go down, world coordinates go up
+        let (x, y) = (column as usize, (height - 1 - row) as usize);
+        let tile = tile_at(x, y);
+        let tile_type = if scene.is_wall(tile.x as usize, tile.y as usize) {
+            TileType::Wall
+        } else {
+            TileType::Floor
+        };
+
+        let color = tile_type.color().to_srgba().to_f32_array();
+        let color = if coverage.is_covered(x, y) {
+            blend(color, OVERLAY_COLOR)
+        } else {
+            color
+        };
+
+        Rgba(color.map(|channel| (channel * 255.0).round() as u8))
+    })
+}
+
+/// Return the tile which contains the given pixel
+fn tile_at(x: usize, y: usize) -> Position {
+    let tile_size = TILE_SIZE as usize;
+    p!(x / tile_size, y / tile_size)
+}
+
+/// Alpha blend the overlay color over the opaque base color
+fn blend(base: [f32; 4], overlay: [f32; 4]) -> [f32; 4] {
+    let alpha = overlay[3];
+    [
+        base[0] * (1.0 - alpha) + overlay[0] * alpha,
+        base[1] * (1.0 - alpha) + overlay[1] * alpha,
+        base[2] * (1.0 - alpha) + overlay[2] * alpha,
+        1.0
+    ]
+}
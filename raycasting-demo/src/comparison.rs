@@ -0,0 +1,211 @@
+use bevy::asset::RenderAssetUsages;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use raycasting_core::config::Algorithm;
+use raycasting_core::raster::CoverageGrid;
+use raycasting_core::raycasting::{calculate_intersection_points, Triangle};
+use raycasting_bevy::compat::{AppCompat, Keys, keys};
+use raycasting_bevy::line_of_sight::{LosOrigin, LosSettings};
+use raycasting_bevy::occluders::{QueryOccluders, SceneOccluders};
+use crate::map::{MapSize, TILE_SIZE};
+
+/// Switches the comparison overlay on and off
+const COMPARISON_KEY: KeyCode = keys::C;
+/// Swaps the left and the right algorithm
+const SWAP_KEY: KeyCode = keys::X;
+/// Cells of the overlay per tile along each axis
+const CELLS_PER_TILE: usize = 4;
+
+/// Visible with the left algorithm only
+const LEFT_COLOR: [u8; 4] = [255, 60, 60, 180];
+/// Visible with the right algorithm only
+const RIGHT_COLOR: [u8; 4] = [60, 120, 255, 180];
+/// Visible with both algorithms
+const SHARED_COLOR: [u8; 4] = [255, 255, 255, 60];
+const TRANSPARENT: [u8; 4] = [0, 0, 0, 0];
+
+/// Compares two visibility algorithms from the same origin in an overlay: cells both see are drawn faintly, cells only
+/// the left one sees red and cells only the right one sees blue. Press C to toggle the overlay and X to swap the
+/// algorithms, so the colors of a difference switch.
+pub struct ComparisonPlugin;
+
+impl Plugin for ComparisonPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<ComparisonSettings>()
+            .register_type::<ComparisonSettings>()
+            .startup_systems(spawn_comparison_overlay)
+            .update_systems((
+                toggle_comparison,
+                show_comparison_sprite.after(toggle_comparison),
+                fit_comparison_sprite,
+                update_comparison.after(toggle_comparison).in_set(QueryOccluders)
+            ))
+        ;
+    }
+}
+
+/// The algorithms to compare, see [ComparisonPlugin]. Changing them recomputes the comparison.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct ComparisonSettings {
+    pub enabled: bool,
+    pub left: Algorithm,
+    pub right: Algorithm,
+}
+
+impl Default for ComparisonSettings {
+    fn default() -> Self {
+        ComparisonSettings {
+            enabled: false,
+            left: Algorithm::Naive,
+            right: Algorithm::Sweep,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct ComparisonOverlay {
+    image: Handle<Image>,
+    /// Number of cells the algorithms disagreed on last time, to only log changes
+    differences: Option<usize>,
+}
+
+#[derive(Component)]
+struct ComparisonSprite;
+
+fn spawn_comparison_overlay(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let image = images.add(create_image(1, 1, TRANSPARENT.to_vec()));
+
+    commands.spawn((
+        Sprite::from_image(image.clone()),
+        Visibility::Hidden,
+        ComparisonSprite
+    ));
+    commands.insert_resource(ComparisonOverlay { image, differences: None });
+}
+
+fn toggle_comparison(
+    keys: Res<Keys>,
+    mut settings: ResMut<ComparisonSettings>,
+) {
+    if keys.just_pressed(COMPARISON_KEY) {
+        settings.enabled = !settings.enabled;
+    }
+
+    if settings.enabled && keys.just_pressed(SWAP_KEY) {
+        let settings = settings.as_mut();
+        std::mem::swap(&mut settings.left, &mut settings.right);
+    }
+}
+
+fn show_comparison_sprite(
+    settings: Res<ComparisonSettings>,
+    mut sprites: Query<&mut Visibility, With<ComparisonSprite>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for mut visibility in sprites.iter_mut() {
+        *visibility = if settings.enabled { Visibility::Visible } else { Visibility::Hidden };
+    }
+}
+
+/// Stretch the overlay over the whole map, [CELLS_PER_TILE] pixels per tile.
+fn fit_comparison_sprite(
+    map_size: Res<MapSize>,
+    mut sprites: Query<(&mut Sprite, &mut Transform), With<ComparisonSprite>>,
+) {
+    let size = Vec2::new(map_size.width as f32, map_size.height as f32) * TILE_SIZE;
+
+    for (mut sprite, mut transform) in sprites.iter_mut() {
+        if sprite.custom_size != Some(size) {
+            sprite.custom_size = Some(size);
+            transform.translation = (size / 2.0 - TILE_SIZE / 2.0).extend(3.0);
+        }
+    }
+}
+
+fn update_comparison(
+    mut images: ResMut<Assets<Image>>,
+    mut overlay: ResMut<ComparisonOverlay>,
+    settings: Res<ComparisonSettings>,
+    los_settings: Res<LosSettings>,
+    origin: Res<LosOrigin>,
+    map_size: Res<MapSize>,
+    occluders: Res<SceneOccluders>,
+) {
+    if !settings.enabled {
+        overlay.differences = None;
+        return;
+    }
+
+    if !settings.is_changed() && !los_settings.is_changed() && !origin.is_changed() && !map_size.is_changed() && !occluders.is_changed() {
+        return;
+    }
+
+    // tiles are centered on their position, so the map starts half a tile left of and below the origin
+    let grid = CoverageGrid::new(
+        Vec2::splat(-TILE_SIZE / 2.0),
+        TILE_SIZE / CELLS_PER_TILE as f32,
+        map_size.width * CELLS_PER_TILE,
+        map_size.height * CELLS_PER_TILE,
+    );
+    let [left, right] = [settings.left, settings.right].map(|algorithm| {
+        let points = calculate_intersection_points(**origin, occluders.0.occluders(), &los_settings.config.clone().with_algorithm(algorithm));
+        let mut grid = grid.clone();
+        grid.rasterize_triangles(&(0..points.len())
+            .map(|i| Triangle::new(**origin, points[i], points[(i + 1) % points.len()]))
+            .collect::<Vec<_>>());
+        grid
+    });
+
+    let (width, height) = (grid.width(), grid.height());
+    let differences = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .filter(|(x, y)| left.is_covered(*x, *y) != right.is_covered(*x, *y))
+        .count();
+
+    if overlay.differences != Some(differences) {
+        info!("{:?} and {:?} disagree on {differences} of {} cells", settings.left, settings.right, width * height);
+        overlay.differences = Some(differences);
+    }
+
+    // image rows go down, world coordinates go up
+    let data = (0..height)
+        .rev()
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .flat_map(|(x, y)| match (left.is_covered(x, y), right.is_covered(x, y)) {
+            (true, false) => LEFT_COLOR,
+            (false, true) => RIGHT_COLOR,
+            (true, true) => SHARED_COLOR,
+            (false, false) => TRANSPARENT
+        })
+        .collect();
+
+    if let Some(mut image) = images.get_mut(&overlay.image) {
+        *image = create_image(width, height, data);
+    }
+}
+
+fn create_image(
+    width: usize,
+    height: usize,
+    data: Vec<u8>,
+) -> Image {
+    Image::new(
+        Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    )
+}
@@ -0,0 +1,176 @@
+use std::f32::consts::PI;
+use bevy::asset::RenderAssetUsages;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use raycasting_core::raycasting::Ray;
+use raycasting_core::scene::Scene;
+use raycasting_core::tiles::cast_grid_ray;
+use raycasting_bevy::compat::{AppCompat, Keys, keys};
+use raycasting_bevy::line_of_sight::LosOrigin;
+use crate::map::{DoorState, MapSize, Tile, TILE_SIZE};
+
+/// Switches between the top down view and the first person view
+const FIRST_PERSON_KEY: KeyCode = keys::F;
+/// Resolution of the first person view, upscaled to the whole window
+const VIEW_WIDTH: usize = 320;
+const VIEW_HEIGHT: usize = 200;
+/// Horizontal field of view, in radians
+const FIELD_OF_VIEW: f32 = PI / 3.0;
+/// How fast the arrow keys turn the view, in radians per second
+const TURN_SPEED: f32 = PI;
+/// How many tiles a column ray may travel before it gives up
+const VIEW_DISTANCE_TILES: f32 = 64.0;
+
+const CEILING_COLOR: [u8; 4] = [40, 40, 60, 255];
+const FLOOR_COLOR: [u8; 4] = [70, 60, 50, 255];
+const WALL_COLOR: [u8; 4] = [180, 180, 180, 255];
+/// Walls hit on their left or right side, darker so corners stay visible
+const SIDE_WALL_COLOR: [u8; 4] = [120, 120, 120, 255];
+
+/// Renders the map Wolfenstein style from the line of sight origin with [cast_grid_ray], one ray per column.
+/// Press F to switch between the top down and the first person view and the arrow keys to turn.
+pub struct FirstPersonPlugin;
+
+impl Plugin for FirstPersonPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .startup_systems(spawn_first_person_view)
+            .update_systems((toggle_first_person_view, turn, render_first_person_view.after(turn)))
+        ;
+    }
+}
+
+#[derive(Resource)]
+struct FirstPersonView {
+    image: Handle<Image>,
+    enabled: bool,
+    /// Angle of the view direction, counterclockwise from the x axis
+    heading: f32,
+}
+
+#[derive(Component)]
+struct FirstPersonImage;
+
+fn spawn_first_person_view(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let image = images.add(create_image(vec![0; VIEW_WIDTH * VIEW_HEIGHT * 4]));
+
+    commands.spawn((
+        ImageNode::new(image.clone()),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+        Visibility::Hidden,
+        FirstPersonImage
+    ));
+    commands.insert_resource(FirstPersonView { image, enabled: false, heading: 0.0 });
+}
+
+fn toggle_first_person_view(
+    keys: Res<Keys>,
+    mut view: ResMut<FirstPersonView>,
+    mut query: Query<&mut Visibility, With<FirstPersonImage>>,
+) {
+    if !keys.just_pressed(FIRST_PERSON_KEY) {
+        return;
+    }
+
+    view.enabled = !view.enabled;
+
+    for mut visibility in query.iter_mut() {
+        *visibility = if view.enabled { Visibility::Visible } else { Visibility::Hidden };
+    }
+}
+
+fn turn(
+    time: Res<Time>,
+    keys: Res<Keys>,
+    mut view: ResMut<FirstPersonView>,
+) {
+    let direction = keys.pressed(keys::LEFT) as i32 - keys.pressed(keys::RIGHT) as i32;
+
+    if view.enabled && direction != 0 {
+        view.heading = (view.heading + direction as f32 * TURN_SPEED * time.delta_secs()).rem_euclid(2.0 * PI);
+    }
+}
+
+fn render_first_person_view(
+    mut images: ResMut<Assets<Image>>,
+    view: Res<FirstPersonView>,
+    origin: Res<LosOrigin>,
+    map_size: Res<MapSize>,
+    query: Query<(&Tile, Option<&DoorState>)>,
+) {
+    if !view.enabled {
+        return;
+    }
+
+    let mut walls = vec![false; map_size.width * map_size.height];
+    query
+        .iter()
+        .filter(|(tile, door)| tile.blocks_view(*door))
+        .map(|(tile, _)| (tile.pos.x as usize, tile.pos.y as usize))
+        .filter(|(x, y)| *x < map_size.width && *y < map_size.height)
+        .for_each(|(x, y)| walls[y * map_size.width + x] = true);
+    let scene = Scene::from_fn(map_size.width, map_size.height, |x, y| walls[y * map_size.width + x]);
+
+    if let Some(mut image) = images.get_mut(&view.image) {
+        // tiles are centered on their position, the grid starts half a tile left of and below it
+        *image = create_image(render_columns(&scene, **origin + Vec2::splat(TILE_SIZE / 2.0), view.heading));
+    }
+}
+
+/// Cast one ray per column and draw a vertical wall slice for each, with ceiling above and floor below.
+fn render_columns(
+    scene: &Scene,
+    origin: Vec2,
+    heading: f32,
+) -> Vec<u8> {
+    let direction = Vec2::from_angle(heading);
+    // the camera plane is perpendicular to the view direction and spans the field of view at distance 1
+    let plane = direction.perp() * -(FIELD_OF_VIEW / 2.0).tan();
+    let mut columns = vec![[CEILING_COLOR; VIEW_HEIGHT]; VIEW_WIDTH];
+
+    for (x, column) in columns.iter_mut().enumerate() {
+        let camera_x = 2.0 * (x as f32 + 0.5) / VIEW_WIDTH as f32 - 1.0;
+        let ray_direction = direction + plane * camera_x;
+        let Some(hit) = cast_grid_ray(Ray::new(origin, ray_direction), scene, TILE_SIZE, VIEW_DISTANCE_TILES * TILE_SIZE) else {
+            column[VIEW_HEIGHT / 2..].fill(FLOOR_COLOR);
+            continue;
+        };
+
+        // the distance to the camera plane instead of the euclidean distance, which would bend walls (fisheye)
+        let perpendicular = hit.distance * ray_direction.normalize().dot(direction);
+        let line_height = (VIEW_WIDTH as f32 / (2.0 * (FIELD_OF_VIEW / 2.0).tan()) * TILE_SIZE / perpendicular.max(f32::EPSILON)) as usize;
+        let top = (VIEW_HEIGHT / 2).saturating_sub(line_height / 2);
+        let bottom = (VIEW_HEIGHT / 2 + line_height / 2).min(VIEW_HEIGHT);
+        let wall_color = if hit.vertical_side { SIDE_WALL_COLOR } else { WALL_COLOR };
+
+        column[top..bottom].fill(wall_color);
+        column[bottom..].fill(FLOOR_COLOR);
+    }
+
+    (0..VIEW_HEIGHT)
+        .flat_map(|y| columns.iter().map(move |column| column[y]))
+        .flatten()
+        .collect()
+}
+
+fn create_image(data: Vec<u8>) -> Image {
+    Image::new(
+        Extent3d {
+            width: VIEW_WIDTH as u32,
+            height: VIEW_HEIGHT as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    )
+}
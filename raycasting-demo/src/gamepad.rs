@@ -0,0 +1,241 @@
+use bevy::prelude::*;
+use raycasting_core::cone::{cone_triangles, ConeTracker, ViewCone};
+use raycasting_core::stats::RaycastStats;
+use raycasting_bevy::compat::{AppCompat, colored_mesh, gamepad_sticks, mesh_handle, MeshComponent, MeshVertexAttribute, new_mesh, PrimitiveTopology, VertexFormat};
+use raycasting_bevy::line_of_sight::LosSettings;
+use raycasting_bevy::occluders::{QueryOccluders, SceneOccluders};
+use crate::map::{MapSize, TILE_SIZE};
+
+/// Tiles per second a vision source moves at with the left stick fully tilted
+const MOVE_SPEED: f32 = 8.0;
+/// Tilt of the right stick below which the cone keeps its direction, so it does not snap back when the stick is released
+const AIM_THRESHOLD: f32 = 0.3;
+/// Half of the opening angle (radians) of the cone, by default
+const HALF_ANGLE: f32 = 0.6;
+/// Largest angle (radians) between two points of the cone mesh, so the fade towards its borders stays smooth
+const CONE_STEP: f32 = 0.05;
+/// Fraction of the half angle from which the cone fades out towards its borders
+const PERIPHERAL_VISION: f32 = 0.5;
+
+/// The angular offset (radians) of a vertex of a cone mesh from the direction of the cone, for shaders which
+/// shade peripheral vision themselves. The default material fades the vertex colors instead.
+pub const ATTRIBUTE_CONE_OFFSET: MeshVertexAttribute = MeshVertexAttribute::new("Vertex_ConeOffset", 988540917, VertexFormat::Float32);
+
+/// Spawns a vision source for every connected gamepad, which is moved with the left stick and looks
+/// in the direction of the right stick, limited to a [ViewCone]. Sources without a gamepad fill up to the
+/// number of the [VisionSettings] and stand still in the center of the map.
+pub struct GamepadPlugin;
+
+impl Plugin for GamepadPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<VisionSettings>()
+            .register_type::<VisionSettings>()
+            .register_type::<VisionSource>()
+            .update_systems((
+                spawn_vision_sources,
+                move_vision_sources.after(spawn_vision_sources),
+                apply_half_angle.after(spawn_vision_sources),
+                update_vision_cones.after(move_vision_sources).after(apply_half_angle).in_set(QueryOccluders)
+            ))
+        ;
+    }
+}
+
+/// Settings of all vision sources.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct VisionSettings {
+    /// Half of the opening angle (radians) of every cone.
+    pub half_angle: f32,
+    /// Least number of vision sources. If fewer gamepads are connected, the remaining sources stand still.
+    pub sources: usize,
+}
+
+impl Default for VisionSettings {
+    fn default() -> Self {
+        VisionSettings {
+            half_angle: HALF_ANGLE,
+            sources: 0,
+        }
+    }
+}
+
+/// Something which sees in a cone, controlled by a gamepad if it has one.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct VisionSource {
+    /// The entity of the gamepad
+    pub gamepad: Option<Entity>,
+    pub direction: Vec2,
+    pub half_angle: f32,
+    #[reflect(ignore)]
+    tracker: ConeTracker,
+}
+
+impl Default for VisionSource {
+    fn default() -> Self {
+        VisionSource {
+            gamepad: None,
+            direction: Vec2::X,
+            half_angle: HALF_ANGLE,
+            tracker: ConeTracker::default(),
+        }
+    }
+}
+
+fn spawn_vision_sources(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    gamepads: Query<Entity, With<Gamepad>>,
+    map_size: Res<MapSize>,
+    settings: Res<VisionSettings>,
+    sources: Query<(Entity, &VisionSource)>,
+) {
+    for (entity, source) in &sources {
+        if source.gamepad.is_some_and(|gamepad| !gamepads.contains(gamepad)) {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    let idle = sources.iter().filter(|(_, source)| source.gamepad.is_none()).map(|(entity, _)| entity).collect::<Vec<_>>();
+    let wanted = settings.sources.saturating_sub(gamepads.iter().count());
+
+    for entity in idle.iter().skip(wanted) {
+        commands.entity(*entity).despawn();
+    }
+
+    let center = Vec2::new(map_size.width as f32, map_size.height as f32) * TILE_SIZE / 2.0;
+    let mut spawn = |source: VisionSource| {
+        commands.spawn((
+            source,
+            colored_mesh(
+                meshes.add(new_mesh(PrimitiveTopology::TriangleList)),
+                materials.add(ColorMaterial::from(Color::srgba(1.0, 0.9, 0.3, 0.5))),
+            ),
+            Transform::from_translation(center.extend(2.0)),
+        ));
+    };
+
+    for gamepad in gamepads.iter().filter(|gamepad| sources.iter().all(|(_, source)| source.gamepad != Some(*gamepad))) {
+        spawn(VisionSource {
+            gamepad: Some(gamepad),
+            half_angle: settings.half_angle,
+            ..default()
+        });
+    }
+
+    // the sources without a gamepad look in evenly spread directions
+    for i in idle.len()..wanted {
+        spawn(VisionSource {
+            direction: Vec2::from_angle(i as f32 * std::f32::consts::TAU / wanted as f32),
+            half_angle: settings.half_angle,
+            ..default()
+        });
+    }
+}
+
+fn move_vision_sources(
+    time: Res<Time>,
+    gamepads: Query<&Gamepad>,
+    mut sources: Query<(&mut VisionSource, &mut Transform)>,
+) {
+    for (mut source, mut transform) in &mut sources {
+        let Some(Ok(gamepad)) = source.gamepad.map(|gamepad| gamepads.get(gamepad)) else {
+            continue;
+        };
+        let (movement, aim) = gamepad_sticks(gamepad);
+
+        if movement != Vec2::ZERO {
+            transform.translation += (movement * MOVE_SPEED * TILE_SIZE * time.delta_secs()).extend(0.0);
+        }
+
+        if aim.length() > AIM_THRESHOLD {
+            source.direction = aim.normalize();
+        }
+    }
+}
+
+fn apply_half_angle(
+    settings: Res<VisionSettings>,
+    mut sources: Query<&mut VisionSource>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for mut source in &mut sources {
+        if source.half_angle != settings.half_angle {
+            source.half_angle = settings.half_angle;
+        }
+    }
+}
+
+/// Recompute the cone of every vision source which moved, turned or whose map changed. The tracker
+/// makes turning on the spot cheap.
+fn update_vision_cones(
+    mut meshes: ResMut<Assets<Mesh>>,
+    settings: Res<LosSettings>,
+    occluders: Res<SceneOccluders>,
+    mut sources: Query<(&mut VisionSource, Ref<Transform>, &MeshComponent)>,
+) {
+    if sources.is_empty() {
+        return;
+    }
+
+    let map_changed = occluders.is_changed();
+
+    for (mut source, transform, mesh) in &mut sources {
+        if !map_changed && !source.is_changed() && !transform.is_changed() {
+            continue;
+        }
+
+        // updating the tracker is no change of the source
+        let source = source.bypass_change_detection();
+
+        if map_changed {
+            source.tracker.invalidate();
+        }
+
+        let origin = transform.translation.truncate();
+        let cone = ViewCone::new(origin, source.direction, source.half_angle);
+        let points = source.tracker.update(&cone, occluders.0.occluders(), &settings.config, &mut RaycastStats::default());
+
+        if let Some(mut mesh) = meshes.get_mut(mesh_handle(mesh)) {
+            *mesh = create_cone_mesh(&cone, &points);
+        }
+    }
+}
+
+/// Return the triangles of the cone polygon relative to the origin, with the offset from the cone direction
+/// at every vertex and vertex colors fading out towards the borders.
+fn create_cone_mesh(
+    cone: &ViewCone,
+    points: &[Vec2],
+) -> Mesh {
+    let triangles = cone_triangles(cone, points, CONE_STEP);
+    let fade = |offset: f32| {
+        let peripheral = (offset.abs() / cone.half_angle - PERIPHERAL_VISION) / (1.0 - PERIPHERAL_VISION);
+        [1.0, 1.0, 1.0, 1.0 - peripheral.clamp(0.0, 1.0)]
+    };
+    let mut mesh = new_mesh(PrimitiveTopology::TriangleList);
+
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        triangles
+            .iter()
+            .flat_map(|cone_triangle| [cone_triangle.triangle.a, cone_triangle.triangle.b, cone_triangle.triangle.c])
+            .map(|point| (point - cone.origin).extend(0.0).to_array())
+            .collect::<Vec<_>>(),
+    );
+    mesh.insert_attribute(
+        ATTRIBUTE_CONE_OFFSET,
+        triangles.iter().flat_map(|cone_triangle| cone_triangle.offsets).collect::<Vec<_>>(),
+    );
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_COLOR,
+        triangles.iter().flat_map(|cone_triangle| cone_triangle.offsets).map(fade).collect::<Vec<_>>(),
+    );
+    mesh
+}
@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin, EguiPrimaryContextPass};
+use raycasting_core::config::{Algorithm, SelfHitPolicy};
+use raycasting_core::generators::MapGenerator;
+use raycasting_bevy::compat::AppCompat;
+use raycasting_bevy::line_of_sight::LosSettings;
+use crate::comparison::ComparisonSettings;
+use crate::gamepad::VisionSettings;
+use crate::map::{MapSettings, TILE_SIZE};
+
+/// Largest radius of the line of sight the inspector offers, in tiles
+const MAX_RADIUS: f32 = 64.0;
+/// Widest vision cone the inspector offers, in degrees, as cones must be narrower than a full circle
+const MAX_CONE_ANGLE: f32 = 350.0;
+/// Most vision sources the inspector offers
+const MAX_VISION_SOURCES: usize = 8;
+
+/// Shows a panel to tune the line of sight and map parameters at runtime.
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .plugin(EguiPlugin::default())
+            // egui may run several passes per frame, so its systems have their own schedule
+            .add_systems(EguiPrimaryContextPass, show_inspector)
+        ;
+    }
+}
+
+fn show_inspector(
+    mut contexts: EguiContexts,
+    mut los_settings: ResMut<LosSettings>,
+    mut map_settings: ResMut<MapSettings>,
+    mut comparison: ResMut<ComparisonSettings>,
+    // the vision sources only exist if the demo reads the gamepads, which benchmarks don't
+    mut vision: Option<ResMut<VisionSettings>>,
+) -> Result {
+    // work on copies, so the settings are only marked as changed if a value actually changed
+    let mut epsilon = match los_settings.config.self_hit_policy {
+        SelfHitPolicy::IgnoreNear(epsilon) => epsilon,
+        _ => 0.0
+    };
+    let mut radius = los_settings.radius.unwrap_or(0.0);
+    let mut smoothing = los_settings.smoothing.unwrap_or(0.0);
+    let mut snap_to_tile = los_settings.snap_to_grid.is_some();
+    let mut wall_density = map_settings.wall_density;
+    let mut generator = map_settings.generator;
+    let (mut compare, mut left, mut right) = (comparison.enabled, comparison.left, comparison.right);
+    let initial_cone = vision.as_ref().map(|vision| (vision.half_angle.to_degrees() * 2.0, vision.sources));
+    let mut cone = initial_cone;
+
+    egui::Window::new("Inspector").show(contexts.ctx_mut()?, |ui| {
+        ui.add(egui::Slider::new(&mut epsilon, 0.0..=TILE_SIZE).text("self hit epsilon"));
+        ui.add(egui::Slider::new(&mut radius, 0.0..=MAX_RADIUS * TILE_SIZE).text("max distance (0 is unlimited)"));
+        ui.add(egui::Slider::new(&mut smoothing, 0.0..=30.0).text("origin smoothing"));
+        ui.checkbox(&mut snap_to_tile, "snap origin to tile");
+        if let Some((fov, sources)) = &mut cone {
+            ui.add(egui::Slider::new(fov, 1.0..=MAX_CONE_ANGLE).text("vision cone angle (degrees)"));
+            ui.add(egui::Slider::new(sources, 0..=MAX_VISION_SOURCES).text("vision sources"));
+        }
+        ui.add(egui::Slider::new(&mut wall_density, 0.0..=1.0).text("wall density"));
+        egui::ComboBox::from_label("map generator")
+            .selected_text(generator.name())
+            .show_ui(ui, |ui| MapGenerator::ALL.into_iter().for_each(|option| { ui.selectable_value(&mut generator, option, option.name()); }));
+        ui.checkbox(&mut compare, "compare algorithms");
+        [(&mut left, "left algorithm"), (&mut right, "right algorithm")].into_iter().for_each(|(algorithm, label)| {
+            egui::ComboBox::from_label(label)
+                .selected_text(format!("{algorithm:?}"))
+                .show_ui(ui, |ui| [Algorithm::Naive, Algorithm::Sweep].into_iter().for_each(|option| { ui.selectable_value(algorithm, option, format!("{option:?}")); }));
+        });
+    });
+
+    let self_hit_policy = if epsilon > 0.0 {
+        SelfHitPolicy::IgnoreNear(epsilon)
+    } else {
+        SelfHitPolicy::None
+    };
+
+    if self_hit_policy != los_settings.config.self_hit_policy {
+        los_settings.config.self_hit_policy = self_hit_policy;
+    }
+
+    let radius = (radius > 0.0).then_some(radius);
+
+    if radius != los_settings.radius {
+        los_settings.radius = radius;
+    }
+
+    let smoothing = (smoothing > 0.0).then_some(smoothing);
+
+    if smoothing != los_settings.smoothing {
+        los_settings.smoothing = smoothing;
+    }
+
+    let snap_to_grid = snap_to_tile.then_some(TILE_SIZE);
+
+    if snap_to_grid != los_settings.snap_to_grid {
+        los_settings.snap_to_grid = snap_to_grid;
+    }
+
+    // compared in degrees, as converting back and forth might not give the same radians
+    if let (Some(vision), Some((fov, sources))) = (vision.as_mut(), cone) {
+        if cone != initial_cone {
+            (vision.half_angle, vision.sources) = ((fov / 2.0).to_radians(), sources);
+        }
+    }
+
+    if wall_density != map_settings.wall_density {
+        map_settings.wall_density = wall_density;
+    }
+
+    if generator != map_settings.generator {
+        map_settings.generator = generator;
+    }
+
+    if (compare, left, right) != (comparison.enabled, comparison.left, comparison.right) {
+        (comparison.enabled, comparison.left, comparison.right) = (compare, left, right);
+    }
+
+    Ok(())
+}
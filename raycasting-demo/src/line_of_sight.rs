@@ -0,0 +1,160 @@
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy::prelude::*;
+use pad::Position;
+use raycasting_core::occluder::{Occluder, OccluderId};
+use raycasting_core::occluder_set::OccluderSet;
+use raycasting_core::raycasting::Segment;
+use raycasting_bevy::compat::{AppCompat, On, Remove};
+use raycasting_bevy::line_of_sight::LosTarget;
+use raycasting_bevy::occluders::{QueryOccluders, SceneOccluders};
+use crate::map::{DoorOccluders, DoorState, MapSize, Tile, tile_edges, TileType, TILE_SIZE};
+use crate::mouse_cursor::CursorCoordinates;
+
+/// Time from opening or closing a door until the line of sight reflects it, in milliseconds
+pub const LOS_DOOR_LATENCY: DiagnosticPath = DiagnosticPath::const_new("los_door_latency");
+
+/// Measurements the door latency averages over
+const DIAGNOSTIC_HISTORY: usize = 20;
+
+/// Feeds the plugins of raycasting-bevy: the origin of the line of sight follows the cursor, and the walls of
+/// the map are part of the scene occluders.
+pub struct LosInputPlugin;
+
+impl Plugin for LosInputPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<CursorCoordinates>()
+            .insert_resource(SceneOccluders(OccluderSet::new(vec![], TILE_SIZE)))
+            .init_resource::<MapOccluders>()
+            .observer(remove_door_occluders)
+            .register_diagnostic(Diagnostic::new(LOS_DOOR_LATENCY).with_suffix("ms").with_max_history_length(DIAGNOSTIC_HISTORY))
+            .update_systems((
+                follow_cursor,
+                update_map_occluders.before(QueryOccluders),
+                update_door_occluders.before(QueryOccluders),
+                record_door_latency.after(QueryOccluders)
+            ))
+        ;
+    }
+}
+
+/// The ids of the walls of the map in the [SceneOccluders]
+#[derive(Default, Resource)]
+struct MapOccluders(Vec<OccluderId>);
+
+fn follow_cursor(
+    cursor_coordinates: Res<CursorCoordinates>,
+    mut target: ResMut<LosTarget>,
+) {
+    if **target != **cursor_coordinates {
+        **target = **cursor_coordinates
+    }
+}
+
+/// Replace the walls of the map in the scene occluders whenever the map changed. Doors and the occluders of moving
+/// entities change on their own.
+fn update_map_occluders(
+    map_size: Res<MapSize>,
+    tiles: Query<&Tile>,
+    added_tiles: Query<(), Added<Tile>>,
+    mut map_occluders: ResMut<MapOccluders>,
+    mut occluders: ResMut<SceneOccluders>,
+) {
+    if !map_size.is_changed() && added_tiles.is_empty() {
+        return;
+    }
+
+    for id in map_occluders.0.drain(..) {
+        occluders.0.remove(id);
+    }
+
+    let walls = tiles
+        .iter()
+        .filter(|tile| tile.tile_type == TileType::Wall)
+        .map(|tile| tile.pos);
+    map_occluders.0 = build_occluders(walls, &map_size)
+        .into_iter()
+        .map(|occluder| occluders.0.push(occluder))
+        .collect();
+}
+
+/// Add the edges of a door to the scene occluders when it closes and remove them when it opens.
+fn update_door_occluders(
+    mut doors: Query<(&Tile, &DoorState, &mut DoorOccluders), Changed<DoorState>>,
+    mut occluders: ResMut<SceneOccluders>,
+) {
+    for (tile, door, mut ids) in &mut doors {
+        let closed = tile.blocks_view(Some(door));
+
+        if closed && ids.0.is_empty() {
+            ids.0 = tile_edges(tile.pos).into_iter().map(|edge| occluders.0.push(Occluder::new(edge))).collect();
+        } else if !closed {
+            for id in ids.0.drain(..) {
+                occluders.0.remove(id);
+            }
+        }
+    }
+}
+
+/// Remove the edges of a door which is despawned, like when the map is regenerated.
+fn remove_door_occluders(
+    remove: On<Remove, DoorOccluders>,
+    doors: Query<&DoorOccluders>,
+    mut occluders: ResMut<SceneOccluders>,
+) {
+    if let Ok(ids) = doors.get(remove.entity) {
+        for id in &ids.0 {
+            occluders.0.remove(*id);
+        }
+    }
+}
+
+/// The line of sight is recomputed in the frame the occluders change, so the latency is the time since the
+/// last door was toggled.
+fn record_door_latency(
+    mut diagnostics: Diagnostics,
+    changed_doors: Query<&DoorState, Changed<DoorState>>,
+) {
+    if let Some(door) = changed_doors.iter().max_by_key(|door| door.toggled_at) {
+        let latency = door.toggled_at.elapsed();
+        diagnostics.add_measurement(&LOS_DOOR_LATENCY, || latency.as_secs_f64() * 1000.0);
+    }
+}
+
+/// Return the occluders of a map with the given walls and size: the edges of every wall and the map bounds.
+pub fn build_occluders(
+    walls: impl IntoIterator<Item=Position>,
+    map_size: &MapSize,
+) -> Vec<Occluder> {
+    let mut lines = walls
+        .into_iter()
+        .flat_map(tile_edges)
+        .collect::<Vec<_>>();
+
+    let width = map_size.width as f32 * TILE_SIZE;
+    let height = map_size.height as f32 * TILE_SIZE;
+
+    lines.extend([
+        Segment::new(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(width, 0.0),
+        ),
+        Segment::new(
+            Vec2::new(width, 0.0),
+            Vec2::new(width, height),
+        ),
+        Segment::new(
+            Vec2::new(width, height),
+            Vec2::new(0.0, height),
+        ),
+        Segment::new(
+            Vec2::new(0.0, height),
+            Vec2::new(0.0, 0.0),
+        ),
+    ]);
+
+    lines
+        .into_iter()
+        .map(Occluder::new)
+        .collect()
+}
@@ -0,0 +1,119 @@
+mod args;
+mod bench;
+mod camera;
+mod capture;
+mod comparison;
+mod first_person;
+mod gamepad;
+#[cfg(feature = "inspector")]
+mod inspector;
+mod line_of_sight;
+mod map;
+mod minimap;
+mod mouse_cursor;
+mod patrol;
+mod stress;
+
+use std::path::Path;
+use bevy::diagnostic::LogDiagnosticsPlugin;
+use bevy::prelude::*;
+use bevy::window::WindowMode;
+use raycasting_bevy::compat::{AppCompat, Message};
+use raycasting_bevy::detection::DetectionPlugin;
+use raycasting_bevy::line_of_sight::LineOfSightPlugin;
+use raycasting_bevy::lit::LitPlugin;
+use raycasting_bevy::reflect::ReflectTypesPlugin;
+use crate::args::Args;
+use crate::bench::{BENCH_MAP, BenchPlugin};
+use crate::camera::CameraPlugin;
+use crate::comparison::ComparisonPlugin;
+use crate::first_person::FirstPersonPlugin;
+use crate::gamepad::GamepadPlugin;
+use crate::line_of_sight::LosInputPlugin;
+use crate::map::MapPlugin;
+use crate::minimap::MinimapPlugin;
+use crate::mouse_cursor::MouseCursorPlugin;
+use crate::patrol::PatrolPlugin;
+use crate::stress::StressOverlayPlugin;
+
+fn main() {
+    let mut args = match Args::parse() {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(2);
+        }
+    };
+
+    if let Some(dir) = &args.capture {
+        capture::run_capture(Path::new(dir)).expect("failed to capture the visibility overlay");
+        return;
+    }
+
+    let bench = args.bench.is_some();
+    let quiet = args.quiet;
+
+    if bench {
+        args.map = Some(BENCH_MAP.to_string());
+    }
+
+    let mut app = App::new();
+
+    app
+        .add_plugins(DefaultPlugins
+            .set(
+                WindowPlugin {
+                    primary_window: Some(Window {
+                        resolution: (800, 600).into(),
+                        title: "raycasting".to_string(),
+                        resizable: true,
+                        mode: WindowMode::Windowed,
+                        ..default()
+                    }),
+                    ..default()
+                }
+            )
+            .set(ImagePlugin::default_nearest())
+            .set(AssetPlugin {
+                watch_for_changes_override: Some(true),
+                ..default()
+            })
+        )
+        .insert_resource(args)
+        .message::<UpdateLos>()
+        .plugin(ReflectTypesPlugin)
+        .plugin(MapPlugin)
+        .plugin(LineOfSightPlugin)
+        .plugin(LosInputPlugin)
+        .plugin(DetectionPlugin)
+        .plugin(LitPlugin)
+        .plugin(PatrolPlugin)
+        .plugin(MinimapPlugin)
+        .plugin(FirstPersonPlugin)
+        .plugin(ComparisonPlugin)
+        .plugin(StressOverlayPlugin)
+        .plugin(CameraPlugin)
+    ;
+
+    // benchmarks move the origin themselves, so input would only disturb them
+    if bench {
+        app.plugin(BenchPlugin);
+    } else {
+        app
+            .plugin(MouseCursorPlugin)
+            .plugin(GamepadPlugin)
+        ;
+    }
+
+    if !quiet {
+        app.plugin(LogDiagnosticsPlugin::default());
+    }
+
+    #[cfg(feature = "inspector")]
+    app.plugin(inspector::InspectorPlugin);
+
+    app.run();
+}
+
+#[derive(Message)]
+pub struct UpdateLos;
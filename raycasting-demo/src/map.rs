@@ -0,0 +1,398 @@
+use std::io;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::asset::io::Reader;
+use bevy::platform::time::Instant;
+use bevy::prelude::*;
+use pad::{Position, p};
+use rand::{Rng, SeedableRng, thread_rng};
+use rand::rngs::StdRng;
+use TileType::*;
+use raycasting_core::error::RaycastError;
+use raycasting_core::generators::{generate, MapGenerator};
+use raycasting_core::occluder::OccluderId;
+use raycasting_core::raycasting::Segment;
+use raycasting_core::scene::Scene;
+use raycasting_core::tiles;
+use raycasting_core::transform::Transform2;
+use raycasting_bevy::compat::{AppCompat, Keys, keys, MessageReader, ReadMessages};
+use crate::args::Args;
+
+pub const TILE_SIZE: f32 = 32.0;
+pub const MAP_WIDTH: usize = 30;
+pub const MAP_HEIGHT: usize = 30;
+
+pub struct MapPlugin;
+
+impl Plugin for MapPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<MapAsset>()
+            .init_asset_loader::<MapAssetLoader>()
+            .init_resource::<MapSettings>()
+            .init_resource::<MapSize>()
+            .register_type::<MapSettings>()
+            .register_type::<MapSize>()
+            .register_type::<MapGenerator>()
+            .startup_systems(spawn_map)
+            .update_systems((
+                regenerate_on_key,
+                regenerate_map.after(regenerate_on_key),
+                reload_map_file,
+                toggle_doors,
+                animate_doors.after(toggle_doors)
+            ))
+        ;
+    }
+}
+
+/// Key which regenerates the map with a new seed
+const REGENERATE_KEY: KeyCode = keys::R;
+/// Key which switches to the next map generator
+const GENERATOR_KEY: KeyCode = keys::G;
+/// Key which opens or closes every door at once
+const DOOR_KEY: KeyCode = keys::O;
+
+/// Probability of a doorway (a floor tile between two walls) becoming a door in a generated map
+const DOOR_CHANCE: f64 = 0.3;
+/// Seconds between a door opening and closing again
+const DOOR_INTERVAL: f32 = 3.0;
+/// Change of the opacity of a door per second while it opens or closes
+const DOOR_SPEED: f32 = 3.0;
+/// Opacity of an open door
+const OPEN_DOOR_ALPHA: f32 = 0.2;
+
+/// Settings of the map generation. Changing them regenerates the map.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct MapSettings {
+    /// Probability of a tile being a wall
+    pub wall_density: f64,
+    /// Seed of the random generator, so the same settings always generate the same map
+    pub seed: u64,
+    pub generator: MapGenerator,
+}
+
+/// Take the seed and generator from the command line if they were given, otherwise pick a random seed.
+impl FromWorld for MapSettings {
+    fn from_world(world: &mut World) -> Self {
+        let args = world.get_resource::<Args>();
+        let seed = args.and_then(|args| args.seed);
+        let generator = args.and_then(|args| args.generator);
+
+        MapSettings {
+            wall_density: 0.25,
+            seed: seed.unwrap_or_else(|| thread_rng().gen()),
+            generator: generator.unwrap_or_default(),
+        }
+    }
+}
+
+/// Size of the current map in tiles
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct MapSize {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Default for MapSize {
+    fn default() -> Self {
+        MapSize {
+            width: MAP_WIDTH,
+            height: MAP_HEIGHT,
+        }
+    }
+}
+
+/// A map loaded from a .map file, see [Scene] for the format.
+#[derive(Asset, TypePath)]
+pub struct MapAsset(pub Scene);
+
+#[derive(Default, TypePath)]
+struct MapAssetLoader;
+
+impl AssetLoader for MapAssetLoader {
+    type Asset = MapAsset;
+    type Settings = ();
+    type Error = RaycastError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<MapAsset, RaycastError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let source = String::from_utf8(bytes).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        Ok(MapAsset(Scene::parse(&source)?))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["map"]
+    }
+}
+
+/// The map file the map was loaded from, if a map file was given
+#[derive(Resource)]
+struct MapFile(Handle<MapAsset>);
+
+#[derive(Component)]
+pub struct Tile {
+    pub pos: Position,
+    pub tile_type: TileType,
+}
+
+impl Tile {
+    /// Return true if this tile blocks the view: walls always, doors while they are closed.
+    pub fn blocks_view(&self, door: Option<&DoorState>) -> bool {
+        match self.tile_type {
+            Floor => false,
+            Wall => true,
+            Door => !door.map(|door| door.open).unwrap_or(false)
+        }
+    }
+}
+
+/// Whether a door is open. It only changes when the door is opened or closed, so a changed state means
+/// the occluders of the map changed.
+#[derive(Component)]
+pub struct DoorState {
+    pub open: bool,
+    /// When the door was last opened or closed
+    pub toggled_at: Instant,
+}
+
+/// The ids of the edges of a closed door in the scene occluders, empty while the door is open
+#[derive(Component, Default)]
+pub struct DoorOccluders(pub Vec<OccluderId>);
+
+/// Opens and closes a door regularly.
+#[derive(Component)]
+struct DoorTimer(Timer);
+
+/// Return the edges of the tile at the given position. Tiles are centered on their position times the tile size.
+pub fn tile_edges(pos: Position) -> [Segment; 4] {
+    let center = Transform2::from_translation(Vec2::splat(-TILE_SIZE / 2.0));
+    tiles::tile_edges(pos.x as i32, pos.y as i32, TILE_SIZE).map(|edge| center.apply_to_segment(edge))
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum TileType {
+    Floor,
+    Wall,
+    /// A wall which opens and closes, see [DoorState]
+    Door,
+}
+
+impl TileType {
+    pub fn color(&self) -> Color {
+        match self {
+            Floor => Color::srgba_u8(196, 164, 132, 255),
+            Wall => Color::srgba_u8(101, 67, 33, 255),
+            Door => Color::srgba_u8(150, 60, 40, 255)
+        }
+    }
+}
+
+fn spawn_map(
+    mut commands: Commands,
+    settings: Res<MapSettings>,
+    args: Res<Args>,
+    asset_server: Res<AssetServer>,
+) {
+    match &args.map {
+        Some(path) => commands.insert_resource(MapFile(asset_server.load(path.clone()))),
+        None => spawn_tiles(&mut commands, &settings)
+    }
+}
+
+/// Regenerate the map with a new seed or the next generator when one of the keys is pressed.
+fn regenerate_on_key(
+    keys: Res<Keys>,
+    mut settings: ResMut<MapSettings>,
+) {
+    if keys.just_pressed(REGENERATE_KEY) {
+        settings.seed = thread_rng().gen();
+    }
+
+    if keys.just_pressed(GENERATOR_KEY) {
+        settings.generator = settings.generator.next();
+    }
+}
+
+fn regenerate_map(
+    mut commands: Commands,
+    settings: Res<MapSettings>,
+    map_file: Option<Res<MapFile>>,
+    tiles: Query<Entity, With<Tile>>,
+) {
+    if !settings.is_changed() || settings.is_added() || map_file.is_some() {
+        return;
+    }
+
+    for e in &tiles {
+        commands.entity(e).despawn();
+    }
+
+    spawn_tiles(&mut commands, &settings);
+}
+
+/// Rebuild the map whenever the map file was (re)loaded.
+fn reload_map_file(
+    mut commands: Commands,
+    mut events: MessageReader<AssetEvent<MapAsset>>,
+    mut map_size: ResMut<MapSize>,
+    map_file: Option<Res<MapFile>>,
+    maps: Res<Assets<MapAsset>>,
+    tiles: Query<Entity, With<Tile>>,
+) {
+    let map_file = match map_file {
+        Some(map_file) => map_file,
+        None => return
+    };
+
+    for event in events.messages() {
+        match event {
+            AssetEvent::Added { id } | AssetEvent::Modified { id } if *id == map_file.0.id() => {
+                if let Some(MapAsset(scene)) = maps.get(*id) {
+                    for e in &tiles {
+                        commands.entity(e).despawn();
+                    }
+
+                    spawn_scene_tiles(&mut commands, scene, &[], &[]);
+                    *map_size = MapSize {
+                        width: scene.width(),
+                        height: scene.height(),
+                    };
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn spawn_tiles(
+    commands: &mut Commands,
+    settings: &MapSettings,
+) {
+    info!(
+        "generating {} map with seed {} (pass --generator {} --seed {} to generate it again)",
+        settings.generator.name(),
+        settings.seed,
+        settings.generator.name(),
+        settings.seed
+    );
+
+    let mut rng = StdRng::seed_from_u64(settings.seed);
+    let scene = generate(settings.generator, MAP_WIDTH, MAP_HEIGHT, settings.wall_density, &mut rng);
+    let doors = scene_doorways(&scene)
+        .filter(|_| rng.gen_bool(DOOR_CHANCE))
+        .collect::<Vec<_>>();
+    let phases = doors.iter().map(|_| rng.gen_range(0.0..DOOR_INTERVAL)).collect::<Vec<_>>();
+
+    spawn_scene_tiles(commands, &scene, &doors, &phases);
+}
+
+/// Return the positions of all floor tiles with walls on two opposite sides and floors on the other two.
+fn scene_doorways(scene: &Scene) -> impl Iterator<Item=Position> + '_ {
+    p!(1,1)
+        .iter_to(p!(scene.width().max(2) - 2, scene.height().max(2) - 2))
+        .filter(|pos| {
+            let (x, y) = (pos.x as usize, pos.y as usize);
+            let [left, right, below, above] = [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)].map(|(x, y)| scene.is_wall(x, y));
+            !scene.is_wall(x, y) && (left && right && !below && !above || below && above && !left && !right)
+        })
+}
+
+/// Return the positions of all walls in the scene.
+pub fn scene_walls(scene: &Scene) -> impl Iterator<Item=Position> + '_ {
+    p!(0,0)
+        .iter_to(p!(scene.width() - 1, scene.height() - 1))
+        .filter(|pos| scene.is_wall(pos.x as usize, pos.y as usize))
+}
+
+/// Spawn the tiles of the scene, with doors at the given positions. Each door opens or closes for the first
+/// time after the given number of seconds.
+fn spawn_scene_tiles(
+    commands: &mut Commands,
+    scene: &Scene,
+    doors: &[Position],
+    phases: &[f32],
+) {
+    for pos in p!(0,0).iter_to(p!(scene.width() - 1, scene.height() - 1)) {
+        let door = doors.iter().position(|door| *door == pos);
+        let tile_type = if scene.is_wall(pos.x as usize, pos.y as usize) {
+            Wall
+        } else if door.is_some() {
+            Door
+        } else {
+            Floor
+        };
+
+        let tile = spawn_tile(commands, pos, tile_type);
+
+        if let Some(index) = door {
+            commands.entity(tile).insert((
+                DoorState {
+                    open: false,
+                    toggled_at: Instant::now(),
+                },
+                DoorOccluders::default(),
+                DoorTimer(Timer::from_seconds(phases[index], TimerMode::Once)),
+            ));
+        }
+    }
+}
+
+/// Open or close doors whose timer finished, or every door when the key is pressed.
+fn toggle_doors(
+    time: Res<Time>,
+    keys: Res<Keys>,
+    mut doors: Query<(&mut DoorState, &mut DoorTimer)>,
+) {
+    let pressed = keys.just_pressed(DOOR_KEY);
+
+    for (mut door, mut timer) in &mut doors {
+        if timer.0.tick(time.delta()).just_finished() || pressed {
+            door.open = !door.open;
+            door.toggled_at = Instant::now();
+            timer.0 = Timer::from_seconds(DOOR_INTERVAL, TimerMode::Once);
+        }
+    }
+}
+
+/// Fade doors in and out while they close and open. A door blocks the view as soon as it starts closing.
+fn animate_doors(
+    time: Res<Time>,
+    mut doors: Query<(&DoorState, &mut Sprite)>,
+) {
+    for (door, mut sprite) in &mut doors {
+        let target = if door.open { OPEN_DOOR_ALPHA } else { 1.0 };
+        let alpha = sprite.color.alpha();
+        let step = DOOR_SPEED * time.delta_secs();
+
+        if alpha != target {
+            sprite.color.set_alpha(alpha + (target - alpha).clamp(-step, step));
+        }
+    }
+}
+
+fn spawn_tile(
+    commands: &mut Commands,
+    pos: Position,
+    tile_type: TileType,
+) -> Entity {
+    commands.spawn((
+        Tile {
+            pos,
+            tile_type,
+        },
+        Sprite {
+            custom_size: Some(Vec2::splat(TILE_SIZE)),
+            color: tile_type.color(),
+            ..default()
+        },
+        Transform::from_translation(Vec3::new(pos.x as f32 * TILE_SIZE, pos.y as f32 * TILE_SIZE, 0.0)),
+    )).id()
+}
\ No newline at end of file
@@ -0,0 +1,128 @@
+use bevy::asset::RenderAssetUsages;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use raycasting_core::raster::CoverageGrid;
+use raycasting_bevy::compat::AppCompat;
+use raycasting_bevy::line_of_sight::LineOfSight;
+use crate::map::{MapSize, TILE_SIZE};
+
+/// Cells of the minimap per tile along each axis
+const CELLS_PER_TILE: usize = 2;
+/// Edge length of the minimap on screen, in pixels
+const MINIMAP_SIZE: f32 = 180.0;
+
+const UNEXPLORED_COLOR: [u8; 4] = [0, 0, 0, 200];
+const EXPLORED_COLOR: [u8; 4] = [90, 90, 90, 200];
+const VISIBLE_COLOR: [u8; 4] = [255, 255, 255, 200];
+
+/// Shows a minimap in the upper right corner with the fog of war: what is visible right now,
+/// what was seen before and what was never seen, rasterized with [CoverageGrid].
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .startup_systems(spawn_minimap)
+            .update_systems(update_minimap)
+        ;
+    }
+}
+
+#[derive(Resource)]
+struct Minimap {
+    image: Handle<Image>,
+    /// Cells which are visible right now
+    visible: CoverageGrid,
+    /// Cells which were visible at some point
+    explored: CoverageGrid,
+}
+
+impl Minimap {
+    /// Return empty grids covering the map. Tiles are centered on their position, so the map starts half a tile
+    /// left of and below the origin.
+    fn grids(map_size: &MapSize) -> (CoverageGrid, CoverageGrid) {
+        let grid = CoverageGrid::new(
+            Vec2::splat(-TILE_SIZE / 2.0),
+            TILE_SIZE / CELLS_PER_TILE as f32,
+            map_size.width * CELLS_PER_TILE,
+            map_size.height * CELLS_PER_TILE,
+        );
+
+        (grid.clone(), grid)
+    }
+}
+
+fn spawn_minimap(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    map_size: Res<MapSize>,
+) {
+    let (visible, explored) = Minimap::grids(&map_size);
+    let image = images.add(render(&visible, &explored));
+
+    commands.spawn((
+        ImageNode::new(image.clone()),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            right: Val::Px(10.0),
+            width: Val::Px(MINIMAP_SIZE),
+            height: Val::Px(MINIMAP_SIZE),
+            ..default()
+        },
+    ));
+    commands.insert_resource(Minimap { image, visible, explored });
+}
+
+fn update_minimap(
+    mut images: ResMut<Assets<Image>>,
+    mut minimap: ResMut<Minimap>,
+    line_of_sight: Res<LineOfSight>,
+    map_size: Res<MapSize>,
+) {
+    if map_size.is_changed() {
+        (minimap.visible, minimap.explored) = Minimap::grids(&map_size);
+    }
+
+    if !line_of_sight.is_changed() && !map_size.is_changed() {
+        return;
+    }
+
+    let minimap = minimap.as_mut();
+    minimap.visible.clear();
+    minimap.visible.rasterize_triangles(line_of_sight.triangles());
+    minimap.explored.rasterize_triangles(line_of_sight.triangles());
+
+    if let Some(mut image) = images.get_mut(&minimap.image) {
+        *image = render(&minimap.visible, &minimap.explored);
+    }
+}
+
+fn render(
+    visible: &CoverageGrid,
+    explored: &CoverageGrid,
+) -> Image {
+    let (width, height) = (visible.width(), visible.height());
+    // image rows go down, world coordinates go up
+    let data = (0..height)
+        .rev()
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .flat_map(|(x, y)| match (visible.is_covered(x, y), explored.is_covered(x, y)) {
+            (true, _) => VISIBLE_COLOR,
+            (false, true) => EXPLORED_COLOR,
+            (false, false) => UNEXPLORED_COLOR
+        })
+        .collect();
+
+    Image::new(
+        Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    )
+}
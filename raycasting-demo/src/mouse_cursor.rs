@@ -0,0 +1,45 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use raycasting_bevy::compat::AppCompat;
+
+pub(super) struct MouseCursorPlugin;
+
+impl Plugin for MouseCursorPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<CursorCoordinates>()
+            .register_type::<CursorCoordinates>()
+            .update_systems(update_cursor_position)
+        ;
+    }
+}
+
+/// The world position of the mouse cursor, or of the first finger touching the screen.
+#[derive(Default, Deref, DerefMut, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct CursorCoordinates(Vec2);
+
+fn update_cursor_position(
+    mut cursor_position: ResMut<CursorCoordinates>,
+    touches: Res<Touches>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+) {
+    let (Ok((camera, transform)), Ok(window)) = (cameras.single(), windows.single()) else {
+        return;
+    };
+    // touches and the cursor both start at the top left of the window
+    let touch_position = touches
+        .iter()
+        .min_by_key(|touch| touch.id())
+        .map(|touch| touch.position());
+
+    if let Some(world_pos) = touch_position
+        .or(window.cursor_position())
+        .and_then(|position| camera.viewport_to_world_2d(transform, position).ok())
+    {
+        if world_pos != **cursor_position {
+            **cursor_position = world_pos
+        }
+    }
+}
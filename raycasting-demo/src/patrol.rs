@@ -0,0 +1,102 @@
+use bevy::prelude::*;
+use raycasting_core::occluder::Occluder;
+use raycasting_bevy::compat::AppCompat;
+use raycasting_bevy::occluders::EntityOccluders;
+use crate::map::{MapSize, TILE_SIZE};
+
+/// Tiles per second a crate moves at
+const PATROL_SPEED: f32 = 2.0;
+/// Radians per second a crate turns at while it moves
+const SPIN_SPEED: f32 = 0.5;
+/// Edge length of a crate, in tiles
+const CRATE_SIZE: f32 = 0.8;
+
+/// Spawns crates which patrol along fixed paths across the map. Their [EntityOccluders] move with them.
+pub struct PatrolPlugin;
+
+impl Plugin for PatrolPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_type::<Patrol>()
+            .update_systems((
+                spawn_crates,
+                patrol.after(spawn_crates)
+            ))
+        ;
+    }
+}
+
+/// Moves an entity along a closed path of waypoints.
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Patrol {
+    pub waypoints: Vec<Vec2>,
+    /// Index of the waypoint the entity is moving towards
+    next: usize,
+}
+
+#[derive(Component)]
+struct Crate;
+
+/// Spawn the crates again whenever the size of the map changed, so their paths span the whole map.
+fn spawn_crates(
+    mut commands: Commands,
+    map_size: Res<MapSize>,
+    crates: Query<Entity, With<Crate>>,
+) {
+    if !map_size.is_changed() {
+        return;
+    }
+
+    for e in &crates {
+        commands.entity(e).despawn();
+    }
+
+    let tile = |x: f32, y: f32| Vec2::new(x, y) * TILE_SIZE;
+    let (width, height) = (map_size.width as f32, map_size.height as f32);
+    let paths = [
+        vec![tile(width * 0.25, height * 0.25), tile(width * 0.75, height * 0.25)],
+        vec![tile(width * 0.5, height * 0.3), tile(width * 0.7, height * 0.5), tile(width * 0.5, height * 0.7), tile(width * 0.3, height * 0.5)],
+        vec![tile(width * 0.8, height * 0.85), tile(width * 0.8, height * 0.6), tile(width * 0.2, height * 0.6), tile(width * 0.2, height * 0.85)],
+    ];
+    let half = CRATE_SIZE * TILE_SIZE / 2.0;
+    let local = Occluder::polygon(&[Vec2::new(-half, -half), Vec2::new(half, -half), Vec2::new(half, half), Vec2::new(-half, half)]);
+
+    for waypoints in paths {
+        let start = waypoints[0];
+
+        commands.spawn((
+            Crate,
+            Patrol { waypoints, next: 1 },
+            EntityOccluders(local.clone()),
+            Sprite {
+                custom_size: Some(Vec2::splat(CRATE_SIZE * TILE_SIZE)),
+                color: Color::srgb_u8(120, 85, 50),
+                ..default()
+            },
+            Transform::from_translation(start.extend(2.0)),
+        ));
+    }
+}
+
+fn patrol(
+    time: Res<Time>,
+    mut patrols: Query<(&mut Patrol, &mut Transform)>,
+) {
+    for (mut patrol, mut transform) in &mut patrols {
+        let Some(target) = patrol.waypoints.get(patrol.next).copied() else {
+            continue;
+        };
+        let position = transform.translation.truncate();
+        let step = PATROL_SPEED * TILE_SIZE * time.delta_secs();
+
+        if position.distance(target) <= step {
+            transform.translation = target.extend(transform.translation.z);
+            patrol.next = (patrol.next + 1) % patrol.waypoints.len();
+        } else {
+            transform.translation += ((target - position).normalize() * step).extend(0.0);
+        }
+
+        transform.rotate_z(SPIN_SPEED * time.delta_secs());
+    }
+}
@@ -0,0 +1,92 @@
+use bevy::color::palettes::css::{FUCHSIA, ORANGE, RED};
+use bevy::prelude::*;
+use raycasting_core::visibility_polygon::PolygonDefect;
+use raycasting_bevy::compat::{AppCompat, Keys, keys};
+use raycasting_bevy::line_of_sight::LineOfSight;
+
+/// Switches the stress overlay on and off
+const STRESS_KEY: KeyCode = keys::D;
+/// Triangles of the line of sight thinner than this are reported as slivers
+const SLIVER_TOLERANCE: f32 = 0.01;
+/// Edge length of a defect marker
+const MARKER_SIZE: f32 = 6.0;
+
+const NON_FINITE_COLOR: Color = Color::Srgba(FUCHSIA);
+const SLIVER_COLOR: Color = Color::Srgba(ORANGE);
+const ANGLE_REVERSAL_COLOR: Color = Color::Srgba(RED);
+
+/// Inspects the line of sight whenever it changes and reports numerical problems (see [VisibilityPolygon::defects]):
+/// each defect is logged and marked at its point. Press D to toggle the overlay.
+pub struct StressOverlayPlugin;
+
+impl Plugin for StressOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<StressOverlay>()
+            .update_systems((toggle_stress_overlay, report_defects.after(toggle_stress_overlay)))
+        ;
+    }
+}
+
+#[derive(Default, Resource)]
+struct StressOverlay {
+    enabled: bool,
+}
+
+#[derive(Component)]
+struct DefectMarker;
+
+fn toggle_stress_overlay(
+    keys: Res<Keys>,
+    mut overlay: ResMut<StressOverlay>,
+) {
+    if keys.just_pressed(STRESS_KEY) {
+        overlay.enabled = !overlay.enabled;
+        info!("numeric stress overlay {}", if overlay.enabled { "enabled" } else { "disabled" });
+    }
+}
+
+fn report_defects(
+    mut commands: Commands,
+    overlay: Res<StressOverlay>,
+    line_of_sight: Res<LineOfSight>,
+    markers: Query<Entity, With<DefectMarker>>,
+) {
+    if !overlay.is_changed() && !line_of_sight.is_changed() {
+        return;
+    }
+
+    for e in &markers {
+        commands.entity(e).despawn();
+    }
+
+    if !overlay.enabled {
+        return;
+    }
+
+    let polygon = line_of_sight.polygon();
+    let origin = polygon.origin();
+
+    for defect in polygon.defects(SLIVER_TOLERANCE) {
+        let (index, color) = match defect {
+            PolygonDefect::NonFinite { index, .. } => (index, NON_FINITE_COLOR),
+            PolygonDefect::Sliver { index, .. } => (index, SLIVER_COLOR),
+            PolygonDefect::AngleReversal { index } => (index, ANGLE_REVERSAL_COLOR)
+        };
+        let point = polygon.points()[index];
+        warn!("line of sight from {origin} has a defect at {point}: {defect:?}");
+
+        // non-finite points cannot be drawn, mark the origin instead
+        let position = if point.is_finite() { point } else { origin };
+
+        commands.spawn((
+            DefectMarker,
+            Sprite {
+                color,
+                custom_size: Some(Vec2::splat(MARKER_SIZE)),
+                ..default()
+            },
+            Transform::from_translation(position.extend(4.0)),
+        ));
+    }
+}